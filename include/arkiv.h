@@ -0,0 +1,678 @@
+/* The C FFI of Arkiv, see the `ffi` module. */
+
+#ifndef ARKIV_H
+#define ARKIV_H
+
+/* Generated by cbindgen, see cbindgen.toml: do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * The status returned by the functions of the FFI.
+ */
+typedef enum ArkivStatus {
+  /**
+   * The function succeeded.
+   */
+  ARKIV_STATUS_OK = 0,
+  /**
+   * A pointer argument is null.
+   */
+  ARKIV_STATUS_NULL_POINTER = 1,
+  /**
+   * The bytes end before the record does.
+   */
+  ARKIV_STATUS_TRUNCATED = 2,
+  /**
+   * The record cannot be located, or its content is inconsistent.
+   */
+  ARKIV_STATUS_MALFORMED = 3,
+  /**
+   * The iterator is exhausted.
+   */
+  ARKIV_STATUS_DONE = 4,
+  /**
+   * A callback of an `ArkivReader` failed, see its `last_error`.
+   */
+  ARKIV_STATUS_IO = 5,
+  /**
+   * The library panicked.
+   */
+  ARKIV_STATUS_PANICKED = 6,
+} ArkivStatus;
+
+/**
+ * The End of Central Directory of an archive, combining the End of Central
+ * Directory record with the ZIP64 End of Central Directory record, if any.
+ */
+typedef struct ArkivZipEndOfCentralDirectory {
+  /**
+   * The offset of the End of Central Directory record.
+   */
+  uint64_t offset;
+  /**
+   * Whether the archive has a ZIP64 End of Central Directory record.
+   */
+  bool is_zip64;
+  /**
+   * The offset of the ZIP64 End of Central Directory record, or 0 if none.
+   */
+  uint64_t zip64_offset;
+  /**
+   * The number of the disk of the End of Central Directory.
+   */
+  uint32_t disk;
+  /**
+   * The number of the disk where the Central Directory starts.
+   */
+  uint32_t central_directory_disk;
+  /**
+   * The number of Central Directory records on this disk.
+   */
+  uint64_t nb_local_central_directory_records;
+  /**
+   * The total number of Central Directory records.
+   */
+  uint64_t nb_central_directory_records;
+  /**
+   * The size of the Central Directory.
+   */
+  uint64_t central_directory_size;
+  /**
+   * The offset of the Central Directory.
+   */
+  uint64_t central_directory_offset;
+  /**
+   * The offset of the archive comment.
+   */
+  uint64_t comment_offset;
+  /**
+   * The size of the archive comment.
+   */
+  uint64_t comment_size;
+} ArkivZipEndOfCentralDirectory;
+
+/**
+ * Writes the size of the archive into `size`, returning 0 on success, or an
+ * error code of the consumer's choosing otherwise.
+ */
+typedef int32_t (*ArkivReaderSize)(void *user_data, uint64_t *size);
+
+/**
+ * Copies up to `length` bytes of the archive, starting at `offset`, into
+ * `buffer`, writing their number into `read`, and returning 0 on success, or
+ * an error code of the consumer's choosing otherwise.
+ *
+ * Fewer bytes than available may be copied, as long as at least one is; 0 is
+ * only read past the end of the archive.
+ */
+typedef int32_t (*ArkivReaderRead)(void *user_data,
+                                   uint64_t offset,
+                                   uint8_t *buffer,
+                                   size_t length,
+                                   size_t *read);
+
+/**
+ * An archive provided by a C consumer through callbacks, each passed the
+ * `user_data`.
+ */
+typedef struct ArkivReader {
+  /**
+   * The opaque data of the consumer.
+   */
+  void *user_data;
+  /**
+   * The callback returning the size of the archive.
+   */
+  ArkivReaderSize size;
+  /**
+   * The callback copying a range of the archive.
+   */
+  ArkivReaderRead read;
+  /**
+   * The error code returned by the callback which failed during the last
+   * call passed this reader, or 0 if none did.
+   */
+  int32_t last_error;
+} ArkivReader;
+
+/**
+ * A view of `size` bytes starting at `data`, borrowed from the bytes a
+ * record was read from.
+ */
+typedef struct ArkivBytes {
+  /**
+   * The first byte, never null.
+   */
+  const uint8_t *data;
+  /**
+   * The number of bytes.
+   */
+  size_t size;
+} ArkivBytes;
+
+/**
+ * A Central Directory File Header record.
+ */
+typedef struct ArkivZipRawCentralDirectoryFileHeader {
+  /**
+   * The signature.
+   */
+  uint32_t signature;
+  /**
+   * The version of the specification, and the compatibility, of the
+   * encoder.
+   */
+  uint16_t version_made_by;
+  /**
+   * The minimum version of the specification needed to extract.
+   */
+  uint16_t version_needed_to_extract;
+  /**
+   * The general purpose bit flag.
+   */
+  uint16_t general_purpose_bit_flag;
+  /**
+   * The compression method.
+   */
+  uint16_t compression_method;
+  /**
+   * The last modification time of the file, in MS-DOS format.
+   */
+  uint16_t file_last_modification_time;
+  /**
+   * The last modification date of the file, in MS-DOS format.
+   */
+  uint16_t file_last_modification_date;
+  /**
+   * The CRC-32 of the file.
+   */
+  uint32_t crc32;
+  /**
+   * The compressed size of the file.
+   */
+  uint32_t compressed_size;
+  /**
+   * The uncompressed size of the file.
+   */
+  uint32_t uncompressed_size;
+  /**
+   * The size of the file name.
+   */
+  uint16_t file_name_size;
+  /**
+   * The size of the extra field.
+   */
+  uint16_t extra_field_size;
+  /**
+   * The size of the file comment.
+   */
+  uint16_t file_comment_size;
+  /**
+   * The number of the disk where the file starts.
+   */
+  uint16_t file_start_disk;
+  /**
+   * The internal file attributes.
+   */
+  uint16_t internal_file_attributes;
+  /**
+   * The external file attributes.
+   */
+  uint32_t external_file_attributes;
+  /**
+   * The offset of the Local File Header record, relative to the start of
+   * the disk where the file starts.
+   */
+  uint32_t local_file_header_relative_offset;
+  /**
+   * The file name.
+   */
+  struct ArkivBytes file_name;
+  /**
+   * The extra field.
+   */
+  struct ArkivBytes extra_field;
+  /**
+   * The file comment.
+   */
+  struct ArkivBytes file_comment;
+} ArkivZipRawCentralDirectoryFileHeader;
+
+/**
+ * A Local File Header record.
+ */
+typedef struct ArkivZipRawLocalFileHeader {
+  /**
+   * The signature.
+   */
+  uint32_t signature;
+  /**
+   * The minimum version of the specification needed to extract.
+   */
+  uint16_t version_needed_to_extract;
+  /**
+   * The general purpose bit flag.
+   */
+  uint16_t general_purpose_bit_flag;
+  /**
+   * The compression method.
+   */
+  uint16_t compression_method;
+  /**
+   * The last modification time of the file, in MS-DOS format.
+   */
+  uint16_t file_last_modification_time;
+  /**
+   * The last modification date of the file, in MS-DOS format.
+   */
+  uint16_t file_last_modification_date;
+  /**
+   * The CRC-32 of the file.
+   */
+  uint32_t crc32;
+  /**
+   * The compressed size of the file.
+   */
+  uint32_t compressed_size;
+  /**
+   * The uncompressed size of the file.
+   */
+  uint32_t uncompressed_size;
+  /**
+   * The size of the file name.
+   */
+  uint16_t file_name_size;
+  /**
+   * The size of the extra field.
+   */
+  uint16_t extra_field_size;
+  /**
+   * The file name.
+   */
+  struct ArkivBytes file_name;
+  /**
+   * The extra field.
+   */
+  struct ArkivBytes extra_field;
+} ArkivZipRawLocalFileHeader;
+
+/**
+ * An iterator over a contiguous sequence of Central Directory File Header
+ * records, as `CentralDirectoryFileHeaderIterator`.
+ *
+ * The fields are only meant to be set by
+ * `arkiv_zip_central_directory_iterator_init`.
+ */
+typedef struct ArkivZipCentralDirectoryIterator {
+  /**
+   * The bytes not iterated over yet.
+   */
+  struct ArkivBytes bytes;
+  /**
+   * The maximum number of records still returned.
+   */
+  uint64_t remaining;
+} ArkivZipCentralDirectoryIterator;
+
+/**
+ * An End of Central Directory record.
+ */
+typedef struct ArkivZipRawEndOfCentralDirectory {
+  /**
+   * The signature.
+   */
+  uint32_t signature;
+  /**
+   * The number of this disk.
+   */
+  uint16_t disk;
+  /**
+   * The number of the disk where the Central Directory starts.
+   */
+  uint16_t central_directory_disk;
+  /**
+   * The number of Central Directory records on this disk.
+   */
+  uint16_t nb_local_central_directory_records;
+  /**
+   * The total number of Central Directory records.
+   */
+  uint16_t nb_central_directory_records;
+  /**
+   * The size of the Central Directory.
+   */
+  uint32_t central_directory_size;
+  /**
+   * The offset of the Central Directory.
+   */
+  uint32_t central_directory_offset;
+  /**
+   * The size of the comment.
+   */
+  uint16_t comment_size;
+  /**
+   * The comment.
+   */
+  struct ArkivBytes comment;
+} ArkivZipRawEndOfCentralDirectory;
+
+/**
+ * A ZIP64 End of Central Directory record.
+ */
+typedef struct ArkivZipRawZip64EndOfCentralDirectory {
+  /**
+   * The signature.
+   */
+  uint32_t signature;
+  /**
+   * The size of the record, excluding its first 12 bytes.
+   */
+  uint64_t record_size;
+  /**
+   * The version of the specification, and the compatibility, of the
+   * encoder.
+   */
+  uint16_t version_made_by;
+  /**
+   * The minimum version of the specification needed to extract.
+   */
+  uint16_t version_needed_to_extract;
+  /**
+   * The number of this disk.
+   */
+  uint32_t disk;
+  /**
+   * The number of the disk where the Central Directory starts.
+   */
+  uint32_t central_directory_disk;
+  /**
+   * The number of Central Directory records on this disk.
+   */
+  uint64_t nb_local_central_directory_records;
+  /**
+   * The total number of Central Directory records.
+   */
+  uint64_t nb_central_directory_records;
+  /**
+   * The size of the Central Directory.
+   */
+  uint64_t central_directory_size;
+  /**
+   * The offset of the Central Directory.
+   */
+  uint64_t central_directory_offset;
+  /**
+   * The extensible data sector.
+   */
+  struct ArkivBytes extensible_data;
+} ArkivZipRawZip64EndOfCentralDirectory;
+
+/**
+ * A ZIP64 End of Central Directory Locator record.
+ */
+typedef struct ArkivZipRawZip64EndOfCentralDirectoryLocator {
+  /**
+   * The signature.
+   */
+  uint32_t signature;
+  /**
+   * The number of the disk where the ZIP64 End of Central Directory record
+   * starts.
+   */
+  uint32_t zip64_end_of_central_directory_disk;
+  /**
+   * The offset of the ZIP64 End of Central Directory record.
+   */
+  uint64_t zip64_end_of_central_directory_offset;
+  /**
+   * The total number of disks.
+   */
+  uint32_t nb_disks;
+} ArkivZipRawZip64EndOfCentralDirectoryLocator;
+
+/**
+ * A Data Descriptor record.
+ */
+typedef struct ArkivZipRawDataDescriptor {
+  /**
+   * Whether the record starts with the optional signature.
+   */
+  bool has_signature;
+  /**
+   * The signature, or 0 if absent.
+   */
+  uint32_t signature;
+  /**
+   * The CRC-32 of the file.
+   */
+  uint32_t crc32;
+  /**
+   * The compressed size of the file.
+   */
+  uint32_t compressed_size;
+  /**
+   * The uncompressed size of the file.
+   */
+  uint32_t uncompressed_size;
+} ArkivZipRawDataDescriptor;
+
+/**
+ * A ZIP64 Data Descriptor record.
+ */
+typedef struct ArkivZipRawZip64DataDescriptor {
+  /**
+   * Whether the record starts with the optional signature.
+   */
+  bool has_signature;
+  /**
+   * The signature, or 0 if absent.
+   */
+  uint32_t signature;
+  /**
+   * The CRC-32 of the file.
+   */
+  uint32_t crc32;
+  /**
+   * The compressed size of the file.
+   */
+  uint64_t compressed_size;
+  /**
+   * The uncompressed size of the file.
+   */
+  uint64_t uncompressed_size;
+} ArkivZipRawZip64DataDescriptor;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Locates the End of Central Directory of the archive held in the `size`
+ * bytes at `data`, as `EndOfCentralDirectory::locate` does, into `eocd`.
+ *
+ * # Safety
+ *
+ * See the `ffi` module documentation.
+ */
+enum ArkivStatus arkiv_zip_locate_end_of_central_directory(const uint8_t *data,
+                                                           size_t size,
+                                                           struct ArkivZipEndOfCentralDirectory *eocd);
+
+/**
+ * Locates the End of Central Directory of the archive provided by the
+ * `reader`, as `EndOfCentralDirectory::locate` does, into `eocd`.
+ *
+ * The `scratch_size` bytes at `scratch` hold the tail of the archive, which
+ * is looked into for the End of Central Directory record: 65557 bytes cover
+ * any archive comment.
+ *
+ * # Safety
+ *
+ * See the `ffi` module documentation; the callbacks of the `reader` must
+ * abide by their contract.
+ */
+enum ArkivStatus arkiv_zip_reader_locate_end_of_central_directory(struct ArkivReader *reader,
+                                                                  uint8_t *scratch,
+                                                                  size_t scratch_size,
+                                                                  struct ArkivZipEndOfCentralDirectory *eocd);
+
+/**
+ * Reads the Central Directory File Header record at `offset` of the archive
+ * provided by the `reader`, not extending beyond `end`, into `record`, as
+ * `read_central_directory_file_header` does.
+ *
+ * The record is copied into the `scratch_size` bytes at `scratch`, which its
+ * variable fields point into; `Truncated` is returned if it does not fit.
+ *
+ * # Safety
+ *
+ * See the `ffi` module documentation; the callbacks of the `reader` must
+ * abide by their contract.
+ */
+enum ArkivStatus arkiv_zip_reader_read_central_directory_file_header(struct ArkivReader *reader,
+                                                                     uint64_t offset,
+                                                                     uint64_t end,
+                                                                     uint8_t *scratch,
+                                                                     size_t scratch_size,
+                                                                     struct ArkivZipRawCentralDirectoryFileHeader *record);
+
+/**
+ * Reads the Local File Header record at `offset` of the archive provided by
+ * the `reader`, not extending beyond `end`, into `record`, as
+ * `read_local_file_header` does.
+ *
+ * The record is copied into the `scratch_size` bytes at `scratch`, which its
+ * variable fields point into; `Truncated` is returned if it does not fit.
+ *
+ * # Safety
+ *
+ * See the `ffi` module documentation; the callbacks of the `reader` must
+ * abide by their contract.
+ */
+enum ArkivStatus arkiv_zip_reader_read_local_file_header(struct ArkivReader *reader,
+                                                         uint64_t offset,
+                                                         uint64_t end,
+                                                         uint8_t *scratch,
+                                                         size_t scratch_size,
+                                                         struct ArkivZipRawLocalFileHeader *record);
+
+/**
+ * Initializes the `iterator` over the `size` bytes at `data`, which should
+ * start exactly on the boundary of the first record, returning at most
+ * `total` records.
+ *
+ * # Safety
+ *
+ * See the `ffi` module documentation; the bytes must outlive the `iterator`.
+ */
+enum ArkivStatus arkiv_zip_central_directory_iterator_init(struct ArkivZipCentralDirectoryIterator *iterator,
+                                                           const uint8_t *data,
+                                                           size_t size,
+                                                           uint64_t total);
+
+/**
+ * Reads the next record of the `iterator` into `record`, advancing it.
+ *
+ * Returns `Done` once `total` records were returned, and `Truncated`,
+ * without advancing, if the bytes end before the next record does.
+ *
+ * # Safety
+ *
+ * See the `ffi` module documentation; the `iterator` must have been
+ * initialized by `arkiv_zip_central_directory_iterator_init`.
+ */
+enum ArkivStatus arkiv_zip_central_directory_iterator_next(struct ArkivZipCentralDirectoryIterator *iterator,
+                                                           struct ArkivZipRawCentralDirectoryFileHeader *record);
+
+/**
+ * Reads the End of Central Directory record at the start of the `size` bytes
+ * at `data` into `record`.
+ *
+ * # Safety
+ *
+ * See the `ffi` module documentation.
+ */
+enum ArkivStatus arkiv_zip_raw_read_end_of_central_directory(const uint8_t *data,
+                                                             size_t size,
+                                                             struct ArkivZipRawEndOfCentralDirectory *record);
+
+/**
+ * Reads the ZIP64 End of Central Directory record at the start of the `size`
+ * bytes at `data` into `record`.
+ *
+ * # Safety
+ *
+ * See the `ffi` module documentation.
+ */
+enum ArkivStatus arkiv_zip_raw_read_zip64_end_of_central_directory(const uint8_t *data,
+                                                                   size_t size,
+                                                                   struct ArkivZipRawZip64EndOfCentralDirectory *record);
+
+/**
+ * Reads the ZIP64 End of Central Directory Locator record at the start of the
+ * `size` bytes at `data` into `record`.
+ *
+ * # Safety
+ *
+ * See the `ffi` module documentation.
+ */
+enum ArkivStatus arkiv_zip_raw_read_zip64_end_of_central_directory_locator(const uint8_t *data,
+                                                                           size_t size,
+                                                                           struct ArkivZipRawZip64EndOfCentralDirectoryLocator *record);
+
+/**
+ * Reads the Central Directory File Header record at the start of the `size`
+ * bytes at `data` into `record`.
+ *
+ * # Safety
+ *
+ * See the `ffi` module documentation.
+ */
+enum ArkivStatus arkiv_zip_raw_read_central_directory_file_header(const uint8_t *data,
+                                                                  size_t size,
+                                                                  struct ArkivZipRawCentralDirectoryFileHeader *record);
+
+/**
+ * Reads the Local File Header record at the start of the `size` bytes at
+ * `data` into `record`.
+ *
+ * # Safety
+ *
+ * See the `ffi` module documentation.
+ */
+enum ArkivStatus arkiv_zip_raw_read_local_file_header(const uint8_t *data,
+                                                      size_t size,
+                                                      struct ArkivZipRawLocalFileHeader *record);
+
+/**
+ * Reads the Data Descriptor record spanning the `size` bytes at `data`, which
+ * must be either 12 or 16, into `record`.
+ *
+ * # Safety
+ *
+ * See the `ffi` module documentation.
+ */
+enum ArkivStatus arkiv_zip_raw_read_data_descriptor(const uint8_t *data,
+                                                    size_t size,
+                                                    struct ArkivZipRawDataDescriptor *record);
+
+/**
+ * Reads the ZIP64 Data Descriptor record spanning the `size` bytes at `data`,
+ * which must be either 20 or 24, into `record`.
+ *
+ * # Safety
+ *
+ * See the `ffi` module documentation.
+ */
+enum ArkivStatus arkiv_zip_raw_read_zip64_data_descriptor(const uint8_t *data,
+                                                          size_t size,
+                                                          struct ArkivZipRawZip64DataDescriptor *record);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ARKIV_H */
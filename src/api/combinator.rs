@@ -0,0 +1,346 @@
+//! # Combinators viewing one or several `Reader` as another
+//!
+//! Offsets are computed with checked arithmetic: a range which cannot be
+//! mapped onto the underlying readers yields an empty slice.
+
+use core::cmp::{max, min};
+use core::ops::Range;
+
+use super::Reader;
+
+/// A `Reader` over the `range` of an inner reader, such as an archive stored
+/// within another, or following a self-extracting stub.
+///
+/// The offset 0 of this reader maps to the start of the `range`, and no byte
+/// beyond its end is ever accessed.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SubReader<R> {
+    inner: R,
+    range: Range<u64>,
+}
+
+impl<R> SubReader<R> {
+    /// Returns an instance over the `range` of `inner`.
+    ///
+    /// An ill-formed range, ending before it starts, is empty.
+    pub fn new(inner: R, range: Range<u64>) -> SubReader<R> {
+        let range = range.start..max(range.start, range.end);
+
+        SubReader { inner, range }
+    }
+
+    /// Returns the range of the inner reader.
+    pub fn range(&self) -> Range<u64> { self.range.clone() }
+
+    /// Returns the inner reader.
+    pub fn into_inner(self) -> R { self.inner }
+}
+
+impl<R: Reader> Reader for SubReader<R> {
+    type Error = R::Error;
+
+    fn size(&self) -> Result<u64, R::Error> {
+        let end = min(self.inner.size()?, self.range.end);
+
+        Ok(end.saturating_sub(self.range.start))
+    }
+
+    fn get<'a>(&'a self, range: Range<u64>, scratch: &'a mut [u8])
+        -> Result<&'a [u8], R::Error>
+    {
+        let start = match self.range.start.checked_add(range.start) {
+            Some(start) => start,
+            None => return Ok(b""),
+        };
+
+        let end = self.range.start
+            .checked_add(range.end)
+            .map_or(self.range.end, |end| min(end, self.range.end));
+
+        if start >= end {
+            return Ok(b"");
+        }
+
+        self.inner.get(start..end, scratch)
+    }
+}
+
+/// A `Reader` presenting an inner reader at `offset`, such as a fragment of a
+/// larger archive: the offset 0 of the inner reader maps to `offset`.
+///
+/// The bytes before `offset` are unavailable, and read as zeroes: a range
+/// starting before it yields zeroes up to `offset`, as would a chunked reader,
+/// so that the End of Central Directory record of a fragment smaller than its
+/// search window can still be located.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct OffsetReader<R> {
+    inner: R,
+    offset: u64,
+}
+
+impl<R> OffsetReader<R> {
+    /// Returns an instance presenting `inner` at `offset`.
+    pub fn new(inner: R, offset: u64) -> OffsetReader<R> {
+        OffsetReader { inner, offset }
+    }
+
+    /// Returns the offset at which the inner reader is presented.
+    pub fn offset(&self) -> u64 { self.offset }
+
+    /// Returns the inner reader.
+    pub fn into_inner(self) -> R { self.inner }
+}
+
+impl<R: Reader> Reader for OffsetReader<R> {
+    type Error = R::Error;
+
+    fn size(&self) -> Result<u64, R::Error> {
+        Ok(self.inner.size()?.saturating_add(self.offset))
+    }
+
+    fn get<'a>(&'a self, range: Range<u64>, scratch: &'a mut [u8])
+        -> Result<&'a [u8], R::Error>
+    {
+        static ZEROES: [u8; 512] = [0; 512];
+
+        match range.start.checked_sub(self.offset) {
+            Some(start) => {
+                let end = range.end.saturating_sub(self.offset);
+                self.inner.get(start..end, scratch)
+            },
+            None => {
+                let end = min(range.end, self.offset);
+                let length = min(end.saturating_sub(range.start), 512);
+
+                Ok(ZEROES.get(..length as usize).unwrap_or(b""))
+            },
+        }
+    }
+}
+
+/// A `Reader` over the concatenation of two readers, the `second` following
+/// the `first`; longer sequences are obtained by nesting.
+///
+/// A range straddling both readers only yields the bytes of the first, as a
+/// chunked reader would.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ChainReader<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> ChainReader<A, B> {
+    /// Returns an instance over `first`, followed by `second`.
+    pub fn new(first: A, second: B) -> ChainReader<A, B> {
+        ChainReader { first, second }
+    }
+
+    /// Returns the inner readers.
+    pub fn into_inner(self) -> (A, B) { (self.first, self.second) }
+}
+
+impl<A, B> Reader for ChainReader<A, B>
+    where A: Reader,
+          B: Reader<Error = A::Error>
+{
+    type Error = A::Error;
+
+    fn size(&self) -> Result<u64, A::Error> {
+        Ok(self.first.size()?.saturating_add(self.second.size()?))
+    }
+
+    fn get<'a>(&'a self, range: Range<u64>, scratch: &'a mut [u8])
+        -> Result<&'a [u8], A::Error>
+    {
+        let boundary = self.first.size()?;
+
+        match range.start.checked_sub(boundary) {
+            Some(start) => {
+                let end = range.end.saturating_sub(boundary);
+                self.second.get(start..end, scratch)
+            },
+            None => {
+                let end = min(range.end, boundary);
+                self.first.get(range.start..end, scratch)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use api::Reader;
+    use checksum::Crc32;
+    use utils::read::read_contiguous;
+    use utils::test::{
+        CopyingReader,
+        TestWriter,
+        test_archive,
+        test_zip64_archive
+    };
+    use zip::{EntryOptions, ZipWriter};
+    use zip::low_level::{EndOfCentralDirectory, validate};
+    use super::{ChainReader, OffsetReader, SubReader};
+
+    const DATA: &[u8] = b"Hello, World!";
+
+    #[test]
+    fn sub_reader_get_within_range() {
+        let reader = SubReader::new(DATA, 7..12);
+
+        assert_eq!(reader.size(), Ok(5));
+        assert_eq!(reader.get(0..3, &mut []), Ok(&b"Wor"[..]));
+        assert_eq!(reader.get(2..10, &mut []), Ok(&b"rld"[..]));
+        assert_eq!(reader.get(5..10, &mut []), Ok(&b""[..]));
+    }
+
+    #[test]
+    fn sub_reader_get_beyond_inner() {
+        let reader = SubReader::new(DATA, 10..20);
+
+        assert_eq!(reader.size(), Ok(3));
+        assert_eq!(reader.get(0..10, &mut []), Ok(&b"ld!"[..]));
+
+        let (start, end) = (20, 10);
+        let reader = SubReader::new(DATA, start..end);
+
+        assert_eq!(reader.range(), 20..20);
+        assert_eq!(reader.size(), Ok(0));
+        assert_eq!(reader.get(0..10, &mut []), Ok(&b""[..]));
+    }
+
+    #[test]
+    fn sub_reader_get_empty_on_overflow() {
+        let reader = SubReader::new(DATA, 7..u64::MAX);
+
+        assert_eq!(reader.size(), Ok(6));
+        assert_eq!(reader.get(u64::MAX - 3..u64::MAX, &mut []), Ok(&b""[..]));
+        assert_eq!(reader.get(0..u64::MAX, &mut []), Ok(&b"World!"[..]));
+    }
+
+    #[test]
+    fn offset_reader_get_shifted() {
+        let reader = OffsetReader::new(DATA, 100);
+
+        assert_eq!(reader.size(), Ok(113));
+        assert_eq!(reader.get(107..110, &mut []), Ok(&b"Wor"[..]));
+        assert_eq!(reader.get(99..110, &mut []), Ok(&b"\0"[..]));
+        assert_eq!(reader.get(0..1000, &mut []).unwrap().len(), 100);
+        assert_eq!(reader.get(120..130, &mut []), Ok(&b""[..]));
+
+        let reader = OffsetReader::new(DATA, u64::MAX - 3);
+
+        assert_eq!(reader.size(), Ok(u64::MAX));
+        assert_eq!(
+            reader.get(u64::MAX - 3..u64::MAX, &mut []),
+            Ok(&b"Hel"[..])
+        );
+    }
+
+    #[test]
+    fn chain_reader_get_across_readers() {
+        let reader = ChainReader::new(&DATA[..5], CopyingReader {
+            data: &DATA[5..],
+        });
+
+        let mut scratch = [0; 16];
+
+        assert_eq!(reader.size(), Ok(13));
+        assert_eq!(reader.get(1..4, &mut []), Ok(&b"ell"[..]));
+        assert_eq!(reader.get(7..20, &mut scratch), Ok(&b"World!"[..]));
+
+        //  A straddling range is short, unless read whole.
+        assert_eq!(reader.get(3..9, &mut []), Ok(&b"lo"[..]));
+        assert_eq!(
+            read_contiguous(&reader, 3..9, &mut scratch),
+            Ok(&b"lo, Wo"[..])
+        );
+    }
+
+    #[test]
+    fn chain_reader_nested() {
+        let reader = ChainReader::new(
+            ChainReader::new(&DATA[..2], &DATA[2..4]),
+            &DATA[4..]
+        );
+
+        let mut scratch = [0; 16];
+
+        assert_eq!(reader.size(), Ok(13));
+        assert_eq!(read_contiguous(&reader, 0..13, &mut scratch), Ok(DATA));
+    }
+
+    #[test]
+    fn validate_nested_archive() {
+        let archive = test_archive(&[(b"a.txt", b"Hello")]);
+
+        let mut outer = b"stub".to_vec();
+        outer.extend_from_slice(&archive);
+        outer.extend_from_slice(b"trailer");
+
+        let end = 4 + archive.len() as u64;
+        let reader = SubReader::new(&outer[..], 4..end);
+
+        let expected = EndOfCentralDirectory::locate(&archive[..], &mut []);
+
+        assert_eq!(
+            EndOfCentralDirectory::locate(&reader, &mut [0; 256]),
+            expected
+        );
+
+        let mut scratch = [0; 512];
+        let mut diagnostics = vec!();
+
+        validate(&reader, &mut scratch, &mut |d| diagnostics.push(d)).unwrap();
+
+        assert_eq!(diagnostics, vec!());
+    }
+
+    #[test]
+    fn validate_chained_archive() {
+        let archive = test_zip64_archive(&[(b"a.txt", b"Hello")]);
+        let (first, second) = archive.split_at(archive.len() / 2);
+
+        let reader = ChainReader::new(first, second);
+
+        let mut scratch = [0; 512];
+        let mut diagnostics = vec!();
+
+        validate(&reader, &mut scratch, &mut |d| diagnostics.push(d)).unwrap();
+
+        assert_eq!(diagnostics, vec!());
+    }
+
+    #[test]
+    fn validate_archive_fragment() {
+        //  Of an archive following a stub of 100 bytes, only the archive is
+        //  available, and it is smaller than the search window of the End of
+        //  Central Directory record.
+        let entry = EntryOptions::new(b"a.txt");
+
+        let mut storage = [0; 64];
+        let mut zip = ZipWriter::with_offset(&mut storage, 100);
+        let mut writer = TestWriter::default();
+
+        zip.start_entry(&mut writer, &entry).unwrap();
+        zip.write_data(&mut writer, b"Hello").unwrap();
+        zip.finish_entry(&mut writer, &entry, Crc32::checksum(b"Hello"), 5)
+            .unwrap();
+        zip.finish(&mut writer, b"").unwrap();
+
+        let reader = OffsetReader::new(&writer.data[..], 100);
+
+        let mut scratch = [0; 512];
+
+        //  LFH (35), data (5), DD (16), following the stub.
+        let eocd = EndOfCentralDirectory::locate(&reader, &mut scratch);
+
+        assert_eq!(eocd.unwrap().central_directory_offset(), 156);
+
+        let mut diagnostics = vec!();
+
+        validate(&reader, &mut scratch, &mut |d| diagnostics.push(d)).unwrap();
+
+        assert_eq!(diagnostics, vec!());
+    }
+}
@@ -0,0 +1,286 @@
+//! # A `Reader` over a memory-mapped file
+//!
+//! All the unsafe code required by the `mmap` feature is confined to this
+//! module.
+//!
+//! # SIGBUS
+//!
+//! Accessing a page of a mapping beyond the end of the underlying file raises
+//! SIGBUS, which aborts the process. This happens if the file is truncated by
+//! another process while mapped, a risk which cannot be eliminated without
+//! exclusive access to the file.
+//!
+//! The size of the file is snapshot when mapping it, and no byte beyond it is
+//! ever accessed. In addition, `MmapReader::with_checked_copies` returns an
+//! instance which never touches the mapping, and instead reads the bytes from
+//! the file into the scratch buffer. It is free of SIGBUS: a truncation of the
+//! file results in an `UnexpectedEof` error, at the cost of a system call and
+//! a copy per access.
+
+use std::cmp::min;
+use std::fs::File;
+use std::io;
+use std::ops::Range;
+use std::os::raw::{c_int, c_void};
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+use std::slice;
+
+use utils::intersect_slice;
+
+use super::{Reader, to_usize};
+
+const PROT_READ: c_int = 1;
+const MAP_PRIVATE: c_int = 2;
+
+extern "C" {
+    fn mmap(
+        address: *mut c_void,
+        length: usize,
+        protection: c_int,
+        flags: c_int,
+        fd: c_int,
+        offset: i64
+    )
+        -> *mut c_void;
+
+    fn munmap(address: *mut c_void, length: usize) -> c_int;
+}
+
+/// How an `MmapReader` hands out the bytes of the file.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum MmapAccess {
+    /// The bytes are borrowed from the mapping.
+    Borrowed,
+    /// The bytes are read from the file into the scratch buffer, without
+    /// touching the mapping.
+    CheckedCopy,
+}
+
+/// A `Reader` over a read-only, private, memory-mapping of a file.
+///
+/// See the module documentation for the risk of SIGBUS, should the file be
+/// truncated while mapped.
+#[derive(Debug)]
+pub struct MmapReader {
+    file: File,
+    address: *mut c_void,
+    size: usize,
+    access: MmapAccess,
+}
+
+//  The mapping is read-only, and only unmapped on drop.
+unsafe impl Send for MmapReader {}
+unsafe impl Sync for MmapReader {}
+
+impl MmapReader {
+    /// Returns an instance mapping the `file`, handing out borrowed slices.
+    pub fn new(file: File) -> io::Result<MmapReader> {
+        MmapReader::map(file, MmapAccess::Borrowed)
+    }
+
+    /// Returns an instance mapping the `file`, reading the bytes from the file
+    /// into the scratch buffer rather than from the mapping.
+    pub fn with_checked_copies(file: File) -> io::Result<MmapReader> {
+        MmapReader::map(file, MmapAccess::CheckedCopy)
+    }
+
+    /// Returns how the bytes are handed out.
+    pub fn access(&self) -> MmapAccess { self.access }
+
+    fn map(file: File, access: MmapAccess) -> io::Result<MmapReader> {
+        let size = file.metadata()?.len();
+
+        if size > usize::MAX as u64 {
+            return Err(io::Error::other("file larger than the address space"));
+        }
+
+        let size = size as usize;
+
+        //  An empty mapping is invalid, and there is nothing to map anyway.
+        if size == 0 {
+            let address = ptr::null_mut();
+            return Ok(MmapReader { file, address, size, access });
+        }
+
+        let address = unsafe {
+            mmap(
+                ptr::null_mut(),
+                size,
+                PROT_READ,
+                MAP_PRIVATE,
+                file.as_raw_fd(),
+                0
+            )
+        };
+
+        if address as usize == usize::MAX {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(MmapReader { file, address, size, access })
+    }
+
+    /// Returns the mapped bytes, as of the snapshot size.
+    fn bytes(&self) -> &[u8] {
+        if self.address.is_null() {
+            return b"";
+        }
+
+        unsafe { slice::from_raw_parts(self.address as *const u8, self.size) }
+    }
+}
+
+impl Drop for MmapReader {
+    fn drop(&mut self) {
+        if !self.address.is_null() {
+            unsafe { munmap(self.address, self.size); }
+        }
+    }
+}
+
+impl Reader for MmapReader {
+    type Error = io::Error;
+
+    fn size(&self) -> io::Result<u64> { Ok(self.size as u64) }
+
+    fn get<'a>(&'a self, range: Range<u64>, scratch: &'a mut [u8])
+        -> io::Result<&'a [u8]>
+    {
+        let offset = range.start;
+        let range = to_usize(range.start)..to_usize(range.end);
+
+        if self.access == MmapAccess::Borrowed {
+            return Ok(intersect_slice(self.bytes(), range));
+        }
+
+        let end = min(range.end, self.size);
+        let end = min(end, range.start.saturating_add(scratch.len()));
+
+        let target = match scratch.get_mut(..end.saturating_sub(range.start)) {
+            Some(target) if !target.is_empty() => target,
+            _ => return Ok(b""),
+        };
+
+        //  A truncated file results in an `UnexpectedEof` error.
+        self.file.read_exact_at(target, offset)?;
+
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{File, OpenOptions, remove_file};
+    use std::io::{ErrorKind, Write};
+    use std::path::PathBuf;
+
+    use api::Reader;
+    use utils::test::test_zip64_archive;
+    use zip::low_level::validate;
+    use super::{MmapAccess, MmapReader};
+
+    fn temp_file(name: &str, content: &[u8]) -> PathBuf {
+        let path = ::std::env::temp_dir().join(
+            format!("arkiv-api-mmap-{}-{}", name, ::std::process::id())
+        );
+
+        File::create(&path).unwrap().write_all(content).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn get_borrowed() {
+        let path = temp_file("borrowed", b"Hello, World!");
+        let reader = MmapReader::new(File::open(&path).unwrap()).unwrap();
+
+        remove_file(&path).unwrap();
+
+        assert_eq!(reader.access(), MmapAccess::Borrowed);
+        assert_eq!(reader.size().unwrap(), 13);
+        assert_eq!(reader.get(7..20, &mut []).unwrap(), b"World!");
+    }
+
+    #[test]
+    fn get_empty_file() {
+        let path = temp_file("empty", b"");
+        let reader = MmapReader::new(File::open(&path).unwrap()).unwrap();
+
+        remove_file(&path).unwrap();
+
+        assert_eq!(reader.size().unwrap(), 0);
+        assert_eq!(reader.get(0..20, &mut []).unwrap(), b"");
+    }
+
+    #[test]
+    fn get_checked_copy() {
+        let path = temp_file("checked", b"Hello, World!");
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        let reader =
+            MmapReader::with_checked_copies(File::open(&path).unwrap())
+                .unwrap();
+
+        let mut scratch = [0; 5];
+
+        assert_eq!(reader.get(0..20, &mut []).unwrap(), b"");
+        assert_eq!(reader.get(7..20, &mut scratch).unwrap(), b"World");
+
+        //  Only the bytes still within the file may be accessed.
+        file.set_len(4).unwrap();
+
+        assert_eq!(reader.get(0..4, &mut scratch).unwrap(), b"Hell");
+        assert!(reader.get(2..6, &mut scratch).is_err());
+
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn get_checked_copy_after_truncation() {
+        let path = temp_file("truncated", &[1; 3 * 4096]);
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        let reader =
+            MmapReader::with_checked_copies(File::open(&path).unwrap())
+                .unwrap();
+
+        //  Accessing the mapping beyond the first page would raise SIGBUS.
+        file.set_len(100).unwrap();
+
+        let mut scratch = [0; 16];
+
+        assert_eq!(reader.size().unwrap(), 3 * 4096);
+        assert_eq!(reader.get(96..112, &mut scratch).unwrap_err().kind(),
+            ErrorKind::UnexpectedEof);
+        assert_eq!(reader.get(8192..8208, &mut scratch).unwrap_err().kind(),
+            ErrorKind::UnexpectedEof);
+        assert_eq!(reader.get(90..100, &mut scratch).unwrap(), &[1; 10]);
+
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn validate_mapped_archive() {
+        let archive = test_zip64_archive(&[(b"a.txt", b"Hello")]);
+        let path = temp_file("validate", &archive);
+
+        for &checked in &[false, true] {
+            let file = File::open(&path).unwrap();
+            let reader = if checked {
+                MmapReader::with_checked_copies(file).unwrap()
+            } else {
+                MmapReader::new(file).unwrap()
+            };
+
+            let mut scratch = [0; 512];
+            let mut diagnostics = vec!();
+
+            validate(&reader, &mut scratch, &mut |d| diagnostics.push(d))
+                .unwrap();
+
+            assert_eq!(diagnostics, vec!());
+        }
+
+        remove_file(&path).unwrap();
+    }
+}
@@ -1,25 +1,561 @@
 //! # Common API used by all formats
 
-use std::ops::Range;
+use core::cmp::min;
+use core::convert::Infallible;
+use core::ops::Range;
+use core::task::{Context, Poll};
 
 use utils::intersect_slice;
 
+mod combinator;
+
+#[cfg(feature = "std")]
+mod seek;
+
+pub use self::combinator::{ChainReader, OffsetReader, SubReader};
+
+#[cfg(feature = "std")]
+pub use self::seek::SeekReader;
+
+//  The declarations of `mmap` assume a 64-bits `off_t`.
+#[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+mod mmap;
+
+#[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+pub use self::mmap::{MmapAccess, MmapReader};
+
 /// A trait used to access portions of a buffer at a time, without (necessarily)
 /// having the full buffer in memory at any point in time.
+///
+/// Offsets and sizes are `u64`, so that archives larger than the address space
+/// can be accessed; the slices returned are windows of such an archive.
+///
+/// The bytes are either borrowed from the reader, for in-memory archives, or
+/// copied into a `scratch` buffer provided by the caller, so that a reader
+/// caching parts of a file need not keep them alive once served.
 pub trait Reader {
+    /// The error reported when the underlying storage cannot be accessed.
+    type Error;
+
     /// Returns the size of the archive.
-    fn size(&self) -> usize;
+    fn size(&self) -> Result<u64, Self::Error>;
 
     /// Returns the slice of bytes corresponding to the intersection of the
-    /// provided range and the archive.
+    /// provided range and the archive, either borrowed from the reader or
+    /// copied into the `scratch` buffer.
     ///
-    /// An instance of this traits juggling multiple buffers may need to use
-    /// interior mutability.
-    fn get(&self, range: Range<usize>) -> &[u8];
+    /// When copied, only the first `scratch.len()` bytes of the intersection
+    /// are returned; a caller needing more should provide a larger buffer.
+    ///
+    /// A chunked reader may return fewer bytes than available, for example at
+    /// the end of one of its chunks, as long as it returns at least one; an
+    /// empty slice is only returned past the end of the archive, or when the
+    /// `scratch` buffer is empty and the reader copies.
+    fn get<'a>(&'a self, range: Range<u64>, scratch: &'a mut [u8])
+        -> Result<&'a [u8], Self::Error>;
 }
 
 impl Reader for [u8] {
-    fn size(&self) -> usize { self.len() }
+    type Error = Infallible;
+
+    fn size(&self) -> Result<u64, Infallible> { Ok(self.len() as u64) }
+
+    fn get<'a>(&'a self, range: Range<u64>, _: &'a mut [u8])
+        -> Result<&'a [u8], Infallible>
+    {
+        Ok(intersect_slice(self, to_usize(range.start)..to_usize(range.end)))
+    }
+}
+
+impl<R: Reader + ?Sized> Reader for &R {
+    type Error = R::Error;
+
+    fn size(&self) -> Result<u64, R::Error> { (**self).size() }
+
+    fn get<'a>(&'a self, range: Range<u64>, scratch: &'a mut [u8])
+        -> Result<&'a [u8], R::Error>
+    {
+        (**self).get(range, scratch)
+    }
+}
+
+/// A trait used to access portions of an archive asynchronously, such as an
+/// archive stored remotely and fetched by ranges.
+///
+/// The trait is poll-based, and thus independent of any runtime: whenever the
+/// bytes are not available yet, the reader arranges for the waker of the
+/// `context` to be notified, and returns `Poll::Pending`; the caller then polls
+/// again, with the same arguments, once woken.
+pub trait AsyncReader {
+    /// The error reported when the underlying storage cannot be accessed.
+    type Error;
+
+    /// Polls for the size of the archive.
+    fn poll_size(&mut self, context: &mut Context)
+        -> Poll<Result<u64, Self::Error>>;
+
+    /// Polls for the bytes corresponding to the intersection of the provided
+    /// range and the archive, copying the first of them into `buffer`, and
+    /// returning their number.
+    ///
+    /// Fewer bytes than available may be copied, as long as at least one is;
+    /// 0 is only returned past the end of the archive, or when the `buffer` is
+    /// empty.
+    fn poll_read(
+        &mut self,
+        context: &mut Context,
+        range: Range<u64>,
+        buffer: &mut [u8]
+    )
+        -> Poll<Result<usize, Self::Error>>;
+}
+
+impl AsyncReader for &[u8] {
+    type Error = Infallible;
+
+    fn poll_size(&mut self, _: &mut Context) -> Poll<Result<u64, Infallible>> {
+        Poll::Ready(Ok(self.len() as u64))
+    }
+
+    fn poll_read(
+        &mut self,
+        _: &mut Context,
+        range: Range<u64>,
+        buffer: &mut [u8]
+    )
+        -> Poll<Result<usize, Infallible>>
+    {
+        let range = to_usize(range.start)..to_usize(range.end);
+        let source = intersect_slice(self, range);
+        let length = min(source.len(), buffer.len());
+
+        if let (Some(target), Some(source)) =
+            (buffer.get_mut(..length), source.get(..length))
+        {
+            target.copy_from_slice(source);
+        }
+
+        Poll::Ready(Ok(length))
+    }
+}
+
+/// The reason why a record of an archive could not be read.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ReadError<E> {
+    /// The archive ends before the record does.
+    Truncated,
+    /// The record cannot be located, or its content is inconsistent.
+    Malformed,
+    /// The `Reader` failed.
+    Io(E),
+}
+
+/// A trait used to write an archive, one chunk at a time, without the library
+/// performing any I/O itself.
+///
+/// Bytes are mostly appended, with previously written bytes occasionally
+/// overwritten in place, to patch a record once its content is known.
+pub trait Writer {
+    /// The error reported when the bytes cannot be written.
+    type Error;
+
+    /// Returns the number of bytes written so far.
+    fn size(&self) -> Result<u64, Self::Error>;
+
+    /// Overwrites the bytes previously written at `offset`.
+    ///
+    /// Fails if the bytes do not lie within the bytes written so far.
+    fn write_at(&mut self, offset: u64, data: &[u8])
+        -> Result<(), Self::Error>;
+
+    /// Appends the bytes at the end of the archive.
+    fn append(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// The reason why writing to an in-memory buffer failed.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum BufferError {
+    /// The buffer cannot hold the bytes to append.
+    Full,
+    /// The bytes to overwrite do not lie within the bytes written so far.
+    OutOfBounds,
+}
+
+impl From<Infallible> for BufferError {
+    fn from(error: Infallible) -> BufferError { match error {} }
+}
+
+/// A `Writer` over a caller-provided, fixed-size, buffer.
+///
+/// `Writer` is not implemented for `&mut [u8]` directly, as a bare slice has
+/// nowhere to track the number of bytes written so far, which `size` reports
+/// and `append` writes after.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SliceWriter<'a> {
+    buffer: &'a mut [u8],
+    size: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    /// Returns an instance writing at the start of the `buffer`.
+    pub fn new(buffer: &'a mut [u8]) -> SliceWriter<'a> {
+        SliceWriter { buffer, size: 0 }
+    }
+
+    /// Returns the bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        self.buffer.get(..self.size).unwrap_or(b"")
+    }
+}
+
+impl<'a> Writer for SliceWriter<'a> {
+    type Error = BufferError;
+
+    fn size(&self) -> Result<u64, BufferError> { Ok(self.size as u64) }
+
+    fn write_at(&mut self, offset: u64, data: &[u8])
+        -> Result<(), BufferError>
+    {
+        let written = match self.buffer.get_mut(..self.size) {
+            Some(written) => written,
+            None => return Err(BufferError::OutOfBounds),
+        };
+
+        write_within(written, offset, data)
+    }
+
+    fn append(&mut self, data: &[u8]) -> Result<(), BufferError> {
+        let end = self.size.saturating_add(data.len());
+
+        match self.buffer.get_mut(self.size..end) {
+            Some(target) => target.copy_from_slice(data),
+            None => return Err(BufferError::Full),
+        }
+
+        self.size = end;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Writer for ::alloc::vec::Vec<u8> {
+    type Error = BufferError;
+
+    fn size(&self) -> Result<u64, BufferError> { Ok(self.len() as u64) }
+
+    fn write_at(&mut self, offset: u64, data: &[u8])
+        -> Result<(), BufferError>
+    {
+        write_within(self, offset, data)
+    }
+
+    fn append(&mut self, data: &[u8]) -> Result<(), BufferError> {
+        self.extend_from_slice(data);
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Writer for ::std::fs::File {
+    type Error = ::std::io::Error;
+
+    fn size(&self) -> Result<u64, ::std::io::Error> {
+        Ok(self.metadata()?.len())
+    }
+
+    fn write_at(&mut self, offset: u64, data: &[u8])
+        -> Result<(), ::std::io::Error>
+    {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let end = offset.checked_add(data.len() as u64);
+
+        match end {
+            Some(end) if end <= Writer::size(self)? => (),
+            _ => return Err(::std::io::Error::new(
+                ::std::io::ErrorKind::InvalidInput,
+                "bytes beyond the end of the file"
+            )),
+        }
+
+        self.seek(SeekFrom::Start(offset))?;
+        self.write_all(data)
+    }
+
+    fn append(&mut self, data: &[u8]) -> Result<(), ::std::io::Error> {
+        use std::io::{Seek, SeekFrom, Write};
+
+        self.seek(SeekFrom::End(0))?;
+        self.write_all(data)
+    }
+}
+
+/// Overwrites the bytes of `written` at `offset` with `data`.
+fn write_within(written: &mut [u8], offset: u64, data: &[u8])
+    -> Result<(), BufferError>
+{
+    let offset = to_usize(offset);
+    let end = offset.saturating_add(data.len());
+
+    match written.get_mut(offset..end) {
+        Some(target) => {
+            target.copy_from_slice(data);
+            Ok(())
+        },
+        None => Err(BufferError::OutOfBounds),
+    }
+}
+
+/// Converts the `offset` to `usize`, saturating at `usize::MAX`.
+///
+/// The saturated offset lies beyond the end of any in-memory buffer.
+fn to_usize(offset: u64) -> usize {
+    if offset <= usize::MAX as u64 { offset as usize } else { usize::MAX }
+}
+
+/// A trait used to receive items produced by the library, one at a time,
+/// without the library having to allocate memory to store them.
+pub trait Sink<T> {
+    /// Receives an item.
+    fn push(&mut self, item: T);
+}
+
+impl<T, F> Sink<T> for F
+    where F: FnMut(T)
+{
+    fn push(&mut self, item: T) { self(item) }
+}
+
+/// The progress made by a single call to `Decoder::decode`.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Decoded {
+    consumed: usize,
+    produced: usize,
+    finished: bool,
+}
+
+impl Decoded {
+    /// Returns a new instance.
+    ///
+    /// The `consumed` parameter is the number of bytes of input consumed, the
+    /// `produced` parameter the number of bytes of output produced, and the
+    /// `finished` parameter whether the end of the compressed stream was
+    /// reached.
+    pub fn new(consumed: usize, produced: usize, finished: bool) -> Decoded {
+        Decoded { consumed, produced, finished }
+    }
+
+    /// Returns the number of bytes of input consumed.
+    pub fn consumed(&self) -> usize { self.consumed }
+
+    /// Returns the number of bytes of output produced.
+    pub fn produced(&self) -> usize { self.produced }
+
+    /// Returns whether the end of the compressed stream was reached.
+    pub fn finished(&self) -> bool { self.finished }
+}
+
+/// The reason why a `Decoder` failed.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum DecodeError {
+    /// The input is not a valid compressed stream.
+    InvalidData,
+    /// The input ended before the end of the compressed stream.
+    UnexpectedEndOfInput,
+}
+
+/// A trait used to decompress data, one chunk at a time, into caller-provided
+/// buffers.
+///
+/// The library provides no decompression algorithm, apart from `Stored`, and
+/// instead relies on user-provided implementations of this trait.
+pub trait Decoder {
+    /// Prepares the decoder to decode a new compressed stream.
+    fn reset(&mut self);
+
+    /// Decodes the `input`, which follows any input previously consumed, into
+    /// `output`.
+    ///
+    /// The `end_of_input` parameter indicates whether `input` contains the
+    /// last bytes of the compressed stream.
+    ///
+    /// The decoder need not consume the whole input, nor fill the whole
+    /// output; it is called again with the unconsumed input.
+    fn decode(&mut self, input: &[u8], output: &mut [u8], end_of_input: bool)
+        -> Result<Decoded, DecodeError>;
+}
+
+/// The `Decoder` of stored, ie uncompressed, data: copies the input.
+///
+/// The data is copied even if the reader borrows it; to access the data of a
+/// stored ZIP entry without copying, use `Extractor::read_stored` instead.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Stored;
+
+impl Decoder for Stored {
+    fn reset(&mut self) {}
+
+    fn decode(&mut self, input: &[u8], output: &mut [u8], end_of_input: bool)
+        -> Result<Decoded, DecodeError>
+    {
+        let copied = min(input.len(), output.len());
+
+        output
+            .iter_mut()
+            .zip(input.iter())
+            .for_each(|(o, i)| *o = *i);
+
+        Ok(Decoded::new(copied, copied, end_of_input && copied == input.len()))
+    }
+}
+
+/// A trait used to look up the `Decoder` associated to a compression method.
+///
+/// It is implemented for slices of `(method, decoder)` pairs.
+pub trait DecoderRegistry {
+    /// Returns the decoder for the compression `method`, if any.
+    fn decoder(&mut self, method: u16) -> Option<&mut dyn Decoder>;
+}
+
+impl DecoderRegistry for () {
+    fn decoder(&mut self, _: u16) -> Option<&mut dyn Decoder> { None }
+}
+
+impl DecoderRegistry for [(u16, &mut dyn Decoder)] {
+    fn decoder(&mut self, method: u16) -> Option<&mut dyn Decoder> {
+        self.iter_mut()
+            .find(|entry| entry.0 == method)
+            .map(|entry| &mut *entry.1 as &mut dyn Decoder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BufferError,
+        Decoded,
+        Decoder,
+        DecoderRegistry,
+        Reader,
+        SliceWriter,
+        Stored,
+        Writer
+    };
+
+    #[test]
+    fn slice_reader_get_intersection() {
+        let slice = &b"Hello"[..];
+
+        let get = |range| Reader::get(slice, range, &mut []);
+
+        assert_eq!(Reader::size(slice), Ok(5));
+        assert_eq!(get(1..3), Ok(&b"el"[..]));
+        assert_eq!(get(3..10), Ok(&b"lo"[..]));
+        assert_eq!(get(6..10), Ok(&b""[..]));
+        assert_eq!(get(1..u64::MAX), Ok(&b"ello"[..]));
+        assert_eq!(get(u64::MAX - 1..u64::MAX), Ok(&b""[..]));
+    }
+
+    #[test]
+    fn slice_writer_append_and_write_at() {
+        let mut buffer = [0; 8];
+        let mut writer = SliceWriter::new(&mut buffer);
+
+        writer.append(b"Hello").unwrap();
+        writer.write_at(1, b"a").unwrap();
+
+        assert_eq!(writer.size(), Ok(5));
+        assert_eq!(writer.written(), b"Hallo");
+    }
+
+    #[test]
+    fn slice_writer_failure_on_full_buffer() {
+        let mut buffer = [0; 4];
+        let mut writer = SliceWriter::new(&mut buffer);
+
+        assert_eq!(writer.append(b"Hello"), Err(BufferError::Full));
+        assert_eq!(writer.size(), Ok(0));
+    }
+
+    #[test]
+    fn slice_writer_failure_on_write_beyond_size() {
+        let mut buffer = [0; 8];
+        let mut writer = SliceWriter::new(&mut buffer);
+
+        writer.append(b"Hello").unwrap();
+
+        assert_eq!(writer.write_at(4, b"ab"), Err(BufferError::OutOfBounds));
+        assert_eq!(writer.written(), b"Hello");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn vec_writer_append_and_write_at() {
+        let mut writer: Vec<u8> = vec!();
+
+        Writer::append(&mut writer, b"Hello").unwrap();
+        writer.write_at(1, b"a").unwrap();
+
+        assert_eq!(Writer::size(&writer), Ok(5));
+        assert_eq!(writer.write_at(5, b"!"), Err(BufferError::OutOfBounds));
+        assert_eq!(writer, b"Hallo");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn file_writer_append_and_write_at() {
+        use std::fs::{File, OpenOptions, remove_file};
+        use std::io::Read;
+
+        let path = ::std::env::temp_dir()
+            .join(format!("arkiv-api-writer-{}", ::std::process::id()));
+
+        let mut file: File = OpenOptions::new()
+            .read(true).write(true).create(true).truncate(true)
+            .open(&path)
+            .unwrap();
+
+        file.append(b"Hello").unwrap();
+        file.write_at(1, b"a").unwrap();
+        file.append(b"!").unwrap();
+
+        assert!(file.write_at(5, b"??").is_err());
+        assert_eq!(Writer::size(&file).unwrap(), 6);
+
+        let mut content = vec!();
+        File::open(&path).unwrap().read_to_end(&mut content).unwrap();
+        remove_file(&path).unwrap();
+
+        assert_eq!(content, b"Hallo!");
+    }
+
+    #[test]
+    fn stored_copies_input() {
+        let mut output = [0; 4];
+
+        let decoded = Stored.decode(b"abcdef", &mut output, true).unwrap();
+
+        assert_eq!(decoded, Decoded::new(4, 4, false));
+        assert_eq!(&output, b"abcd");
+
+        let decoded = Stored.decode(b"ef", &mut output, true).unwrap();
+
+        assert_eq!(decoded, Decoded::new(2, 2, true));
+        assert_eq!(&output[..2], b"ef");
+    }
+
+    #[test]
+    fn registry_lookup_by_method() {
+        let mut stored = Stored;
+        let mut other = Stored;
+        let registry: &mut [(u16, &mut dyn Decoder)] =
+            &mut [(0, &mut stored), (8, &mut other)];
 
-    fn get(&self, range: Range<usize>) -> &[u8] { intersect_slice(self, range) }
+        assert!(registry.decoder(0).is_some());
+        assert!(registry.decoder(8).is_some());
+        assert!(registry.decoder(12).is_none());
+        assert!(().decoder(0).is_none());
+    }
 }
@@ -0,0 +1,366 @@
+//! # A `Reader` over any `Read + Seek` source, through a page cache
+
+use std::cell::RefCell;
+use std::cmp::{max, min};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+use super::Reader;
+
+/// The default size of a page of the cache, in bytes.
+const DEFAULT_PAGE_SIZE: usize = 4096;
+
+/// The default number of pages of the cache.
+const DEFAULT_NB_PAGES: usize = 16;
+
+/// A `Reader` over a `Read + Seek` source, such as a `File`.
+///
+/// The source is read one page at a time, and the most recently used pages are
+/// kept in a cache of fixed size, evicting the least recently used page when
+/// full. The memory used is thus bounded, whatever the size of the archive.
+///
+/// The bytes are copied into the `scratch` buffer; an empty `scratch` buffer
+/// yields an empty slice.
+///
+/// The size of the archive is determined once, on construction; the source is
+/// not expected to change afterwards.
+#[derive(Debug)]
+pub struct SeekReader<R> {
+    state: RefCell<State<R>>,
+    size: u64,
+    page_size: usize,
+    nb_pages: usize,
+}
+
+impl<R: Read + Seek> SeekReader<R> {
+    /// Returns an instance reading from `inner`, with a cache of 16 pages of
+    /// 4 KB.
+    pub fn new(inner: R) -> io::Result<SeekReader<R>> {
+        SeekReader::with_cache(inner, DEFAULT_PAGE_SIZE, DEFAULT_NB_PAGES)
+    }
+
+    /// Returns an instance reading from `inner`, with a cache of `nb_pages`
+    /// pages of `page_size` bytes each.
+    ///
+    /// Both the size and number of pages are raised to 1, if 0.
+    pub fn with_cache(mut inner: R, page_size: usize, nb_pages: usize)
+        -> io::Result<SeekReader<R>>
+    {
+        let size = inner.seek(SeekFrom::End(0))?;
+
+        let state = State { inner, pages: Vec::new(), clock: 0 };
+
+        Ok(SeekReader {
+            state: RefCell::new(state),
+            size,
+            page_size: max(page_size, 1),
+            nb_pages: max(nb_pages, 1),
+        })
+    }
+
+    /// Returns the size of a page of the cache, in bytes.
+    pub fn page_size(&self) -> usize { self.page_size }
+
+    /// Returns the number of pages of the cache.
+    pub fn nb_pages(&self) -> usize { self.nb_pages }
+
+    /// Returns the underlying source.
+    pub fn into_inner(self) -> R { self.state.into_inner().inner }
+}
+
+impl<R: Read + Seek> Reader for SeekReader<R> {
+    type Error = io::Error;
+
+    fn size(&self) -> io::Result<u64> { Ok(self.size) }
+
+    fn get<'a>(&'a self, range: Range<u64>, scratch: &'a mut [u8])
+        -> io::Result<&'a [u8]>
+    {
+        let end = min(range.end, self.size);
+        let wanted = end.saturating_sub(range.start);
+        let length = min(wanted, scratch.len() as u64) as usize;
+
+        let target = match scratch.get_mut(..length) {
+            Some(target) => target,
+            None => return Ok(b""),
+        };
+
+        let mut state = match self.state.try_borrow_mut() {
+            Ok(state) => state,
+            Err(_) => {
+                return Err(io::Error::other("reentrant access to SeekReader"));
+            },
+        };
+
+        let page_size = self.page_size as u64;
+        let mut copied = 0;
+
+        while copied < target.len() {
+            let offset = range.start + copied as u64;
+            let page =
+                state.page(offset / page_size, self.page_size, self.nb_pages)?;
+
+            let start = (offset % page_size) as usize;
+            let source = page.get(start..).unwrap_or(b"");
+            let count = min(source.len(), target.len() - copied);
+
+            if count == 0 {
+                break;
+            }
+
+            if let (Some(target), Some(source)) =
+                (target.get_mut(copied..copied + count), source.get(..count))
+            {
+                target.copy_from_slice(source);
+            }
+
+            copied += count;
+        }
+
+        Ok(target.get(..copied).unwrap_or(b""))
+    }
+}
+
+/// The mutable state of a `SeekReader`.
+#[derive(Debug)]
+struct State<R> {
+    inner: R,
+    pages: Vec<Page>,
+    clock: u64,
+}
+
+/// A page of the cache.
+#[derive(Debug)]
+struct Page {
+    index: u64,
+    data: Vec<u8>,
+    last_used: u64,
+}
+
+impl<R: Read + Seek> State<R> {
+    /// Returns the bytes of the page at `index`, reading it from the source if
+    /// it is not cached.
+    fn page(&mut self, index: u64, page_size: usize, nb_pages: usize)
+        -> io::Result<&[u8]>
+    {
+        self.clock = self.clock.wrapping_add(1);
+
+        let clock = self.clock;
+
+        let position = match self.pages.iter().position(|p| p.index == index) {
+            Some(position) => position,
+            None => self.load(index, page_size, nb_pages)?,
+        };
+
+        match self.pages.get_mut(position) {
+            Some(page) => {
+                page.last_used = clock;
+                Ok(&page.data)
+            },
+            None => Ok(b""),
+        }
+    }
+
+    /// Reads the page at `index` from the source, in place of the least
+    /// recently used page if the cache is full, and returns its position.
+    fn load(&mut self, index: u64, page_size: usize, nb_pages: usize)
+        -> io::Result<usize>
+    {
+        let position = if self.pages.len() < nb_pages {
+            self.pages.push(Page { index, data: Vec::new(), last_used: 0 });
+            self.pages.len() - 1
+        } else {
+            self.pages
+                .iter()
+                .enumerate()
+                .min_by_key(|&(_, page)| page.last_used)
+                .map(|(position, _)| position)
+                .unwrap_or(0)
+        };
+
+        let page = match self.pages.get_mut(position) {
+            Some(page) => page,
+            None => return Ok(position),
+        };
+
+        //  The page is invalidated first, in case reading it fails midway.
+        page.index = u64::MAX;
+        page.data.clear();
+
+        let page_size = page_size as u64;
+        let offset = index.saturating_mul(page_size);
+
+        self.inner.seek(SeekFrom::Start(offset))?;
+        (&mut self.inner).take(page_size).read_to_end(&mut page.data)?;
+
+        page.index = index;
+
+        Ok(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::io::{self, Cursor, Read, Seek, SeekFrom};
+    use std::rc::Rc;
+
+    use api::Reader;
+    use utils::test::{TEST_ENTRIES, test_archive, test_zip64_archive};
+    use zip::low_level::{
+        EndOfCentralDirectory,
+        Index,
+        IndexSlot,
+        Normalization,
+        validate
+    };
+    use super::SeekReader;
+
+    /// A source counting the reads performed, and failing once `failing`.
+    struct Source {
+        inner: Cursor<Vec<u8>>,
+        reads: Rc<Cell<usize>>,
+        failing: Rc<Cell<bool>>,
+    }
+
+    impl Source {
+        fn new(data: Vec<u8>) -> Source {
+            Source {
+                inner: Cursor::new(data),
+                reads: Rc::new(Cell::new(0)),
+                failing: Rc::new(Cell::new(false)),
+            }
+        }
+    }
+
+    impl Read for Source {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            if self.failing.get() {
+                return Err(io::Error::other("failing"));
+            }
+
+            self.reads.set(self.reads.get() + 1);
+            self.inner.read(buffer)
+        }
+    }
+
+    impl Seek for Source {
+        fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(position)
+        }
+    }
+
+    #[test]
+    fn get_across_pages() {
+        let data: Vec<u8> = (0..100).collect();
+        let reader =
+            SeekReader::with_cache(Cursor::new(data.clone()), 8, 2).unwrap();
+
+        let mut scratch = [0; 32];
+
+        assert_eq!(reader.size().unwrap(), 100);
+        assert_eq!(reader.get(5..30, &mut scratch).unwrap(), &data[5..30]);
+        assert_eq!(reader.get(90..200, &mut scratch).unwrap(), &data[90..]);
+        assert_eq!(reader.get(0..64, &mut scratch).unwrap(), &data[..32]);
+        assert_eq!(reader.get(100..200, &mut scratch).unwrap(), b"");
+        assert_eq!(reader.get(0..10, &mut []).unwrap(), b"");
+    }
+
+    #[test]
+    fn get_from_cache() {
+        let source = Source::new((0..100).collect());
+        let reads = source.reads.clone();
+
+        let reader = SeekReader::with_cache(source, 16, 2).unwrap();
+        let mut scratch = [0; 32];
+
+        reader.get(0..20, &mut scratch).unwrap();
+        let initial = reads.get();
+
+        reader.get(4..24, &mut scratch).unwrap();
+        assert_eq!(reads.get(), initial);
+
+        //  Evicts the least recently used page, 0..16, but not 16..32.
+        reader.get(40..44, &mut scratch).unwrap();
+        let loaded = reads.get();
+
+        reader.get(16..20, &mut scratch).unwrap();
+        assert_eq!(reads.get(), loaded);
+
+        reader.get(0..4, &mut scratch).unwrap();
+        assert!(reads.get() > loaded);
+    }
+
+    #[test]
+    fn get_io_on_failing_source() {
+        let source = Source::new((0..100).collect());
+        source.failing.set(true);
+
+        let reader = SeekReader::new(source).unwrap();
+
+        assert!(reader.get(0..10, &mut [0; 10]).is_err());
+    }
+
+    #[test]
+    fn validate_through_small_cache() {
+        let archive = test_zip64_archive(TEST_ENTRIES);
+
+        let reader = SeekReader::with_cache(Cursor::new(archive), 16, 4)
+            .unwrap();
+
+        let mut scratch = [0; 512];
+        let mut diagnostics = vec!();
+
+        validate(&reader, &mut scratch, &mut |d| diagnostics.push(d)).unwrap();
+
+        assert_eq!(diagnostics, vec!());
+    }
+
+    #[test]
+    fn index_through_small_cache() {
+        let archive = test_archive(&[(b"a.txt", b"1"), (b"b.txt", b"22")]);
+
+        let expected = EndOfCentralDirectory::locate(&archive[..], &mut []);
+
+        let reader = SeekReader::with_cache(Cursor::new(archive), 16, 2)
+            .unwrap();
+
+        let mut scratch = [0; 256];
+        let mut slots = [IndexSlot::default(); 2];
+
+        assert_eq!(
+            EndOfCentralDirectory::locate(&reader, &mut scratch).ok(),
+            expected.ok()
+        );
+
+        let normalization = Normalization::exact();
+        let index =
+            Index::build(&reader, &mut scratch, &mut slots, normalization)
+                .unwrap();
+
+        let cdfh = index.find(&reader, &mut scratch, b"b.txt").unwrap();
+
+        assert_eq!(cdfh.map(|cdfh| cdfh.compressed_size()), Some(2));
+    }
+
+    #[test]
+    fn locate_in_file() {
+        use std::fs::{File, remove_file};
+        use std::io::Write;
+
+        let archive = test_zip64_archive(&[(b"a.txt", b"Hello")]);
+        let expected = EndOfCentralDirectory::locate(&archive[..], &mut []);
+
+        let path = ::std::env::temp_dir()
+            .join(format!("arkiv-api-seek-{}", ::std::process::id()));
+
+        File::create(&path).unwrap().write_all(&archive).unwrap();
+
+        let reader = SeekReader::new(File::open(&path).unwrap()).unwrap();
+        let eocd = EndOfCentralDirectory::locate(&reader, &mut [0; 256]);
+
+        remove_file(&path).unwrap();
+
+        assert_eq!(eocd.ok(), expected.ok());
+    }
+}
@@ -0,0 +1,846 @@
+//! # Detection of the format of an archive
+//!
+//! The format is identified from its magic number, then confirmed, as far as
+//! possible, by checking the structure it announces: a header checksum, a
+//! reserved field, or the signature of the following record. The result comes
+//! with a confidence, from a lone magic number to a verified structure.
+//!
+//! Compressed streams are not decompressed; the format they wrap is only
+//! inferred from the original file name a gzip member may store.
+
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::ops::Range;
+
+use api::{ReadError, Reader};
+use checksum::Crc32;
+use utils::read::read_exact;
+
+use zip::ZipArchive;
+use zip::raw::CentralDirectoryFileHeaderReader;
+
+/// The number of bytes at the start of the archive looked into.
+const HEAD_SIZE: usize = 512;
+
+/// The size of the buffer used to locate the End of Central Directory record
+/// of a ZIP archive, if the reader copies: a longer comment is not skipped.
+const ZIP_SCRATCH_SIZE: usize = 1024;
+
+/// A detector of the formats recognized from the start of the archive.
+type Detector = fn(&[u8]) -> Option<Detection>;
+
+/// A family of archive, or compressed stream, formats.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Format {
+    /// ZIP, possibly preceded by a self-extracting stub.
+    Zip,
+    /// Tape archive.
+    Tar,
+    /// 7-Zip.
+    SevenZip,
+    /// Roshal Archive.
+    Rar,
+    /// Copy in/out archive.
+    Cpio,
+    /// Unix archiver, as used for static libraries and Debian packages.
+    Ar,
+    /// gzip compressed stream.
+    Gzip,
+    /// bzip2 compressed stream.
+    Bzip2,
+    /// xz compressed stream.
+    Xz,
+    /// Zstandard compressed stream.
+    Zstandard,
+}
+
+impl Format {
+    /// Returns whether the format is a compressed stream, rather than an
+    /// archive of entries.
+    pub fn is_compression(&self) -> bool {
+        matches!(
+            *self,
+            Format::Gzip | Format::Bzip2 | Format::Xz | Format::Zstandard
+        )
+    }
+}
+
+/// A variant of a format, whose layout differs from the other variants.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Variant {
+    /// POSIX.1-1988 tar, with the "ustar\0" magic.
+    TarUstar,
+    /// GNU tar, with the "ustar  \0" magic.
+    TarGnu,
+    /// Version 7 Unix tar, without magic.
+    TarV7,
+    /// RAR 1.5 to 4.x.
+    Rar4,
+    /// RAR 5.0 and later.
+    Rar5,
+    /// cpio "new ASCII" format, with the "070701" magic.
+    CpioNewc,
+    /// cpio "new ASCII" format with checksums, with the "070702" magic.
+    CpioCrc,
+    /// cpio "old ASCII" (portable) format, with the "070707" magic.
+    CpioOdc,
+    /// cpio "old binary" format, in either byte order.
+    CpioBinary,
+}
+
+/// How much of the format was recognized.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Confidence {
+    /// A weak hint, such as a short magic number whose structure is
+    /// inconsistent.
+    Low,
+    /// The magic number, without the structure it announces being confirmed.
+    Medium,
+    /// The magic number, and the structure it announces.
+    High,
+}
+
+/// The format of an archive, as detected by `detect_format`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Detection {
+    format: Format,
+    variant: Option<Variant>,
+    confidence: Confidence,
+    offset: u64,
+    inner: Option<Format>,
+}
+
+impl Detection {
+    fn new(format: Format, confidence: Confidence) -> Detection {
+        Detection {
+            format,
+            variant: None,
+            confidence,
+            offset: 0,
+            inner: None,
+        }
+    }
+
+    fn with_variant(self, variant: Variant) -> Detection {
+        Detection { variant: Some(variant), ..self }
+    }
+
+    /// Returns the format.
+    pub fn format(&self) -> Format { self.format }
+
+    /// Returns the variant of the format, if it has several.
+    pub fn variant(&self) -> Option<Variant> { self.variant }
+
+    /// Returns how much of the format was recognized.
+    pub fn confidence(&self) -> Confidence { self.confidence }
+
+    /// Returns the offset at which the archive starts, past a prefix such as
+    /// a self-extracting stub.
+    pub fn offset(&self) -> u64 { self.offset }
+
+    /// Returns the format wrapped by a compressed stream, as inferred from the
+    /// original file name it stores, if any.
+    pub fn inner(&self) -> Option<Format> { self.inner }
+}
+
+/// Returns the most likely format of the archive, or `None` if it is not
+/// recognized.
+///
+/// The first 512 bytes are looked into, as well as the end of the archive for
+/// a ZIP End of Central Directory record.
+pub fn detect_format<R>(reader: &R) -> Result<Option<Detection>, R::Error>
+    where R: Reader + ?Sized
+{
+    let mut buffer = [0; HEAD_SIZE];
+    let filled = read_exact(reader, 0, &mut buffer)?;
+    let head = buffer.get(..filled).unwrap_or(b"");
+
+    let detected = detect_head(head);
+
+    if let Some(Detection { confidence: Confidence::High, .. }) = detected {
+        return Ok(detected);
+    }
+
+    let zip = detect_zip(reader, head)?;
+
+    //  On a tie, the start of the archive prevails.
+    let best = match (detected, zip) {
+        (Some(head), Some(zip)) => match zip.confidence.cmp(&head.confidence) {
+            Ordering::Greater => Some(zip),
+            _ => Some(head),
+        },
+        (head, zip) => head.or(zip),
+    };
+
+    Ok(best)
+}
+
+/// Returns the format recognized at the start of the archive, if any.
+fn detect_head(head: &[u8]) -> Option<Detection> {
+    let detectors: [Detector; 9] = [
+        detect_seven_zip,
+        detect_rar,
+        detect_xz,
+        detect_zstandard,
+        detect_bzip2,
+        detect_gzip,
+        detect_ar,
+        detect_cpio,
+        detect_tar,
+    ];
+
+    detectors
+        .iter()
+        .filter_map(|detect| detect(head))
+        .fold(None, |best: Option<Detection>, detection| match best {
+            Some(best) if best.confidence >= detection.confidence => Some(best),
+            _ => Some(detection),
+        })
+}
+
+/// Detects a ZIP archive from its End of Central Directory record, or from a
+/// Local File Header record at its start.
+fn detect_zip<R>(reader: &R, head: &[u8])
+    -> Result<Option<Detection>, R::Error>
+    where R: Reader + ?Sized
+{
+    let mut scratch = [0; ZIP_SCRATCH_SIZE];
+
+    let archive = match ZipArchive::new(reader, &mut scratch) {
+        Ok(archive) => archive,
+        Err(ReadError::Io(error)) => return Err(error),
+        Err(_) if head.starts_with(b"PK\x03\x04") =>
+            return Ok(Some(Detection::new(Format::Zip, Confidence::Medium))),
+        Err(_) => return Ok(None),
+    };
+
+    let central_directory = archive.central_directory();
+
+    let confidence = if central_directory.is_empty() {
+        //  Only an End of Central Directory record, which may as well be the
+        //  tail of another file.
+        match archive.end_of_central_directory().offset() {
+            0 => Confidence::High,
+            _ => Confidence::Medium,
+        }
+    } else {
+        let mut signature = [0; 4];
+        let start = central_directory.range().start;
+
+        read_exact(reader, start, &mut signature)?;
+
+        let expected = CentralDirectoryFileHeaderReader::expected_signature();
+
+        if u32::from_le_bytes(signature) == expected {
+            Confidence::High
+        } else {
+            Confidence::Low
+        }
+    };
+
+    Ok(Some(Detection {
+        offset: archive.prefix(),
+        ..Detection::new(Format::Zip, confidence)
+    }))
+}
+
+/// Detects a 7-Zip archive, whose signature header is checksummed.
+fn detect_seven_zip(head: &[u8]) -> Option<Detection> {
+    if !head.starts_with(b"7z\xbc\xaf\x27\x1c") {
+        return None;
+    }
+
+    //  The CRC-32 of the next 20 bytes, following the version.
+    let checksum = le_u32(head, 8..12);
+    let confidence = match head.get(12..32) {
+        Some(header) if Some(Crc32::checksum(header)) == checksum =>
+            Confidence::High,
+        _ => Confidence::Medium,
+    };
+
+    Some(Detection::new(Format::SevenZip, confidence))
+}
+
+/// Detects a RAR archive, whose first header is checksummed.
+fn detect_rar(head: &[u8]) -> Option<Detection> {
+    if head.starts_with(b"Rar!\x1a\x07\x01\x00") {
+        //  A CRC-32, covering the size of the header, as a variable length
+        //  integer, and the header itself.
+        let checksum = le_u32(head, 8..12);
+        let rest = head.get(12..).unwrap_or(b"");
+
+        let header = vint(rest).and_then(|(size, length)| {
+            let end = (length as u64).checked_add(size)?;
+            rest.get(..end as usize)
+        });
+
+        let confidence = match header {
+            Some(header) if Some(Crc32::checksum(header)) == checksum =>
+                Confidence::High,
+            _ => Confidence::Medium,
+        };
+
+        return Some(
+            Detection::new(Format::Rar, confidence).with_variant(Variant::Rar5)
+        );
+    }
+
+    if head.starts_with(b"Rar!\x1a\x07\x00") {
+        //  The archive header: the lower half of the CRC-32 of the header,
+        //  from its type onward, then its type, flags, and size.
+        let checksum = le_u16(head, 7..9);
+        let size = le_u16(head, 12..14).unwrap_or(0) as usize;
+
+        let header = head.get(9..7 + size);
+        let is_archive_header = head.get(9) == Some(&0x73);
+
+        let confidence = match header {
+            Some(header) if is_archive_header &&
+                Some(Crc32::checksum(header) as u16) == checksum =>
+                Confidence::High,
+            _ => Confidence::Medium,
+        };
+
+        return Some(
+            Detection::new(Format::Rar, confidence).with_variant(Variant::Rar4)
+        );
+    }
+
+    None
+}
+
+/// Detects an xz stream, whose stream flags are checksummed.
+fn detect_xz(head: &[u8]) -> Option<Detection> {
+    if !head.starts_with(b"\xfd7zXZ\x00") {
+        return None;
+    }
+
+    let flags = head.get(6..8);
+    let checksum = le_u32(head, 8..12);
+
+    let confidence = match flags {
+        Some(flags) if Some(Crc32::checksum(flags)) == checksum =>
+            Confidence::High,
+        _ => Confidence::Medium,
+    };
+
+    Some(Detection::new(Format::Xz, confidence))
+}
+
+/// Detects a Zstandard frame, whose frame header has a reserved bit.
+fn detect_zstandard(head: &[u8]) -> Option<Detection> {
+    if !head.starts_with(b"\x28\xb5\x2f\xfd") {
+        return None;
+    }
+
+    let confidence = match head.get(4) {
+        Some(descriptor) if descriptor & 0x08 == 0 => Confidence::High,
+        Some(_) => Confidence::Low,
+        None => Confidence::Medium,
+    };
+
+    Some(Detection::new(Format::Zstandard, confidence))
+}
+
+/// Detects a bzip2 stream, whose first block, or end of stream, has a magic
+/// number of its own.
+fn detect_bzip2(head: &[u8]) -> Option<Detection> {
+    if !head.starts_with(b"BZh") {
+        return None;
+    }
+
+    match head.get(3) {
+        Some(level) if (b'1'..=b'9').contains(level) => (),
+        _ => return None,
+    }
+
+    let block = head.get(4..10);
+
+    let confidence = match block {
+        Some(b"\x31\x41\x59\x26\x53\x59") | Some(b"\x17\x72\x45\x38\x50\x90") =>
+            Confidence::High,
+        _ => Confidence::Medium,
+    };
+
+    Some(Detection::new(Format::Bzip2, confidence))
+}
+
+/// Detects a gzip member, which uses deflate and leaves its upper flags unset,
+/// inferring the wrapped format from the original file name, if stored.
+fn detect_gzip(head: &[u8]) -> Option<Detection> {
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+
+    if !head.starts_with(b"\x1f\x8b") {
+        return None;
+    }
+
+    let method = head.get(2).cloned();
+    let flags = head.get(3).cloned().unwrap_or(0);
+
+    let confidence = match method {
+        Some(8) if flags & 0xe0 == 0 => Confidence::High,
+        Some(8) | None => Confidence::Medium,
+        Some(_) => Confidence::Low,
+    };
+
+    let mut start = 10;
+
+    if flags & FEXTRA != 0 {
+        start += 2 + le_u16(head, 10..12).unwrap_or(0) as usize;
+    }
+
+    let name = match head.get(start..) {
+        Some(rest) if flags & FNAME != 0 =>
+            rest.split(|b| *b == 0).next().unwrap_or(b""),
+        _ => b"",
+    };
+
+    Some(Detection {
+        inner: format_from_name(name),
+        ..Detection::new(Format::Gzip, confidence)
+    })
+}
+
+/// Detects an ar archive, whose first file header ends with a terminator.
+fn detect_ar(head: &[u8]) -> Option<Detection> {
+    if !head.starts_with(b"!<arch>\n") {
+        return None;
+    }
+
+    let confidence = match head.get(8..68) {
+        None if head.len() == 8 => Confidence::High,
+        Some(header) if header.ends_with(b"`\n") => Confidence::High,
+        _ => Confidence::Medium,
+    };
+
+    Some(Detection::new(Format::Ar, confidence))
+}
+
+/// Detects a cpio archive, whose ASCII headers only hold digits.
+fn detect_cpio(head: &[u8]) -> Option<Detection> {
+    let ascii = [
+        (&b"070701"[..], Variant::CpioNewc, 110, 16),
+        (&b"070702"[..], Variant::CpioCrc, 110, 16),
+        (&b"070707"[..], Variant::CpioOdc, 76, 8),
+    ];
+
+    for &(magic, variant, size, radix) in &ascii {
+        if !head.starts_with(magic) {
+            continue;
+        }
+
+        let is_digit = |b: &u8| (*b as char).is_digit(radix);
+
+        let confidence = match head.get(magic.len()..size) {
+            Some(fields) if fields.iter().all(is_digit) => Confidence::High,
+            Some(_) => Confidence::Low,
+            None => Confidence::Medium,
+        };
+
+        return Some(
+            Detection::new(Format::Cpio, confidence).with_variant(variant)
+        );
+    }
+
+    //  The binary header, in either byte order, ends with the size of the
+    //  name, which includes its NUL terminator.
+    let namesize = match head.get(..2) {
+        Some(b"\xc7\x71") => le_u16(head, 20..22),
+        Some(b"\x71\xc7") => le_u16(head, 20..22).map(u16::swap_bytes),
+        _ => return None,
+    };
+
+    let name = namesize.and_then(|size| head.get(26..26 + size as usize));
+
+    let confidence = match name {
+        Some(name) if name.last() == Some(&0) => Confidence::Medium,
+        _ => Confidence::Low,
+    };
+
+    let detection = Detection::new(Format::Cpio, confidence);
+
+    Some(detection.with_variant(Variant::CpioBinary))
+}
+
+/// Detects a tar archive, whose header is checksummed.
+fn detect_tar(head: &[u8]) -> Option<Detection> {
+    let header = head.get(..512)?;
+
+    let variant = match header.get(257..265) {
+        Some(b"ustar\x0000") => Variant::TarUstar,
+        Some(b"ustar  \x00") => Variant::TarGnu,
+        _ => Variant::TarV7,
+    };
+
+    //  The checksum is computed with its own field set to spaces, summing
+    //  the bytes as either unsigned or, historically, signed.
+    let checksum = octal(header.get(148..156).unwrap_or(b""));
+
+    let (unsigned, signed) = header
+        .iter()
+        .enumerate()
+        .map(|(i, b)| if (148..156).contains(&i) { b' ' } else { *b })
+        .fold((0u64, 0i64), |(unsigned, signed), b| {
+            (unsigned + u64::from(b), signed + i64::from(b as i8))
+        });
+
+    let is_valid = checksum.is_some_and(|checksum| {
+        checksum == unsigned || Some(checksum) == u64::try_from(signed).ok()
+    });
+
+    let confidence = match (variant, is_valid) {
+        (Variant::TarV7, false) => return None,
+        //  Without magic, a name is expected.
+        (Variant::TarV7, true) if header.first() == Some(&0) => return None,
+        (Variant::TarV7, true) => Confidence::Medium,
+        (_, true) => Confidence::High,
+        (_, false) => Confidence::Low,
+    };
+
+    Some(Detection::new(Format::Tar, confidence).with_variant(variant))
+}
+
+/// Returns the format an archive named `name` likely has, from its extension.
+fn format_from_name(name: &[u8]) -> Option<Format> {
+    let extensions: [(&[u8], Format); 7] = [
+        (b".tar", Format::Tar),
+        (b".zip", Format::Zip),
+        (b".jar", Format::Zip),
+        (b".7z", Format::SevenZip),
+        (b".rar", Format::Rar),
+        (b".cpio", Format::Cpio),
+        (b".a", Format::Ar),
+    ];
+
+    extensions
+        .iter()
+        .find(|&&(extension, _)| {
+            name.len() > extension.len() &&
+                name.get(name.len() - extension.len()..)
+                    .is_some_and(|e| e.eq_ignore_ascii_case(extension))
+        })
+        .map(|&(_, format)| format)
+}
+
+/// Returns the octal number in `field`, surrounded by spaces or NULs.
+fn octal(field: &[u8]) -> Option<u64> {
+    let digits = field
+        .split(|b| *b == 0 || *b == b' ')
+        .find(|digits| !digits.is_empty())?;
+
+    digits.iter().try_fold(0u64, |n, b| match b {
+        b'0'..=b'7' => n.checked_mul(8)?.checked_add(u64::from(b - b'0')),
+        _ => None,
+    })
+}
+
+/// Returns the RAR 5 variable length integer at the start of `bytes`, and its
+/// length.
+fn vint(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+
+    for (i, b) in bytes.iter().take(10).enumerate() {
+        value |= u64::from(b & 0x7f) << (7 * i);
+
+        if b & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+
+    None
+}
+
+fn le_u16(bytes: &[u8], range: Range<usize>) -> Option<u16> {
+    let bytes = bytes.get(range)?;
+
+    Some(u16::from_le_bytes([*bytes.first()?, *bytes.get(1)?]))
+}
+
+fn le_u32(bytes: &[u8], range: Range<usize>) -> Option<u32> {
+    let bytes = bytes.get(range)?;
+    let low = le_u16(bytes, 0..2)?;
+    let high = le_u16(bytes, 2..4)?;
+
+    Some(u32::from(low) | u32::from(high) << 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use checksum::Crc32;
+    use utils::test::{FailingReader, TestError, test_archive};
+
+    use super::{Confidence, Format, Variant, detect_format};
+
+    /// Returns the format, variant and confidence detected in `data`.
+    fn detect(data: &[u8]) -> Option<(Format, Option<Variant>, Confidence)> {
+        detect_format(data)
+            .unwrap()
+            .map(|d| (d.format(), d.variant(), d.confidence()))
+    }
+
+    /// Returns a tar header for a file named `name`, with the `magic`.
+    fn tar_header(name: &[u8], magic: &[u8]) -> Vec<u8> {
+        let mut header = vec!(0; 512);
+
+        header[..name.len()].copy_from_slice(name);
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[124..136].copy_from_slice(b"00000000000\0");
+        header[148..156].copy_from_slice(b"        ");
+        header[257..257 + magic.len()].copy_from_slice(magic);
+
+        let sum: u32 = header.iter().map(|b| u32::from(*b)).sum();
+        let checksum = format!("{:06o}\0 ", sum);
+
+        header[148..156].copy_from_slice(checksum.as_bytes());
+        header
+    }
+
+    #[test]
+    fn detect_zip() {
+        let archive = test_archive(&[(b"hello.txt", b"Hello, World!")]);
+
+        let detection = detect_format(&archive[..]).unwrap().unwrap();
+
+        assert_eq!(detection.format(), Format::Zip);
+        assert_eq!(detection.confidence(), Confidence::High);
+        assert_eq!(detection.offset(), 0);
+
+        //  Self-extracting.
+        let stub = b"MZ\x90\x00 stub";
+
+        let mut data = stub.to_vec();
+        data.extend_from_slice(&archive);
+
+        let detection = detect_format(&data[..]).unwrap().unwrap();
+
+        assert_eq!(detection.format(), Format::Zip);
+        assert_eq!(detection.confidence(), Confidence::High);
+        assert_eq!(detection.offset(), stub.len() as u64);
+
+        //  Empty.
+        let empty = test_archive(&[]);
+        assert_eq!(detect(&empty), Some((Format::Zip, None, Confidence::High)));
+
+        //  Truncated, without Central Directory.
+        assert_eq!(
+            detect(&archive[..40]),
+            Some((Format::Zip, None, Confidence::Medium))
+        );
+    }
+
+    #[test]
+    fn detect_tar() {
+        let ustar = tar_header(b"a.txt", b"ustar\x0000");
+        let gnu = tar_header(b"a.txt", b"ustar  \x00");
+        let v7 = tar_header(b"a.txt", b"");
+
+        let tar = Some(Format::Tar);
+        let variant = |d: Option<(Format, Option<Variant>, Confidence)>| {
+            d.map(|(f, v, c)| (Some(f), v, c))
+        };
+
+        assert_eq!(
+            variant(detect(&ustar)),
+            Some((tar, Some(Variant::TarUstar), Confidence::High))
+        );
+        assert_eq!(
+            variant(detect(&gnu)),
+            Some((tar, Some(Variant::TarGnu), Confidence::High))
+        );
+        assert_eq!(
+            variant(detect(&v7)),
+            Some((tar, Some(Variant::TarV7), Confidence::Medium))
+        );
+
+        //  An invalid checksum.
+        let mut corrupted = ustar.clone();
+        corrupted[0] = b'b';
+
+        assert_eq!(
+            variant(detect(&corrupted)),
+            Some((tar, Some(Variant::TarUstar), Confidence::Low))
+        );
+
+        corrupted[257] = b'x';
+
+        assert_eq!(detect(&corrupted), None);
+        assert_eq!(detect(&[0; 1024]), None);
+    }
+
+    #[test]
+    fn detect_seven_zip() {
+        let header = [0x42; 20];
+
+        let mut data = b"7z\xbc\xaf\x27\x1c\x00\x04".to_vec();
+        data.extend_from_slice(&Crc32::checksum(&header).to_le_bytes());
+        data.extend_from_slice(&header);
+
+        assert_eq!(
+            detect(&data),
+            Some((Format::SevenZip, None, Confidence::High))
+        );
+
+        data[12] = 0;
+
+        assert_eq!(
+            detect(&data),
+            Some((Format::SevenZip, None, Confidence::Medium))
+        );
+    }
+
+    #[test]
+    fn detect_rar() {
+        let header = [3, 1, 0, 0];
+
+        let mut rar5 = b"Rar!\x1a\x07\x01\x00".to_vec();
+        rar5.extend_from_slice(&Crc32::checksum(&header).to_le_bytes());
+        rar5.extend_from_slice(&header);
+
+        assert_eq!(
+            detect(&rar5),
+            Some((Format::Rar, Some(Variant::Rar5), Confidence::High))
+        );
+
+        let header = [0x73, 0, 0, 13, 0, 0, 0, 0, 0, 0, 0];
+        let checksum = Crc32::checksum(&header) as u16;
+
+        let mut rar4 = b"Rar!\x1a\x07\x00".to_vec();
+        rar4.extend_from_slice(&checksum.to_le_bytes());
+        rar4.extend_from_slice(&header);
+
+        assert_eq!(
+            detect(&rar4),
+            Some((Format::Rar, Some(Variant::Rar4), Confidence::High))
+        );
+
+        assert_eq!(
+            detect(&rar4[..7]),
+            Some((Format::Rar, Some(Variant::Rar4), Confidence::Medium))
+        );
+    }
+
+    #[test]
+    fn detect_compressed_streams() {
+        let mut xz = b"\xfd7zXZ\x00\x00\x01".to_vec();
+        xz.extend_from_slice(&Crc32::checksum(b"\x00\x01").to_le_bytes());
+
+        let streams: [(&[u8], Format, Confidence); 7] = [
+            (&xz, Format::Xz, Confidence::High),
+            (&xz[..8], Format::Xz, Confidence::Medium),
+            (b"\x28\xb5\x2f\xfd\x20\x05", Format::Zstandard, Confidence::High),
+            (b"\x28\xb5\x2f\xfd\x28\x05", Format::Zstandard, Confidence::Low),
+            (b"BZh91AY&SY", Format::Bzip2, Confidence::High),
+            (b"BZh9\x17\x72\x45\x38\x50\x90", Format::Bzip2, Confidence::High),
+            (b"BZh9", Format::Bzip2, Confidence::Medium),
+        ];
+
+        for &(data, format, confidence) in &streams {
+            assert_eq!(detect(data), Some((format, None, confidence)));
+        }
+
+        assert_eq!(detect(b"BZh0"), None);
+    }
+
+    #[test]
+    fn detect_gzip() {
+        let data = b"\x1f\x8b\x08\x08\0\0\0\0\0\x03archive.tar\0\x03\0";
+        let detection = detect_format(&data[..]).unwrap().unwrap();
+
+        assert_eq!(detection.format(), Format::Gzip);
+        assert_eq!(detection.confidence(), Confidence::High);
+        assert_eq!(detection.inner(), Some(Format::Tar));
+
+        //  An extra field, followed by the name.
+        let data = b"\x1f\x8b\x08\x0c\0\0\0\0\0\x03\x02\0ab\x41.ZIP\0";
+        let detection = detect_format(&data[..]).unwrap().unwrap();
+
+        assert_eq!(detection.inner(), Some(Format::Zip));
+
+        let data = b"\x1f\x8b\x08\x00\0\0\0\0\0\x03";
+        let detection = detect_format(&data[..]).unwrap().unwrap();
+
+        assert_eq!(detection.inner(), None);
+
+        assert_eq!(
+            detect(b"\x1f\x8b\x08\xe0"),
+            Some((Format::Gzip, None, Confidence::Medium))
+        );
+        assert_eq!(
+            detect(b"\x1f\x8b\x07\x00"),
+            Some((Format::Gzip, None, Confidence::Low))
+        );
+    }
+
+    #[test]
+    fn detect_ar() {
+        let mut data = b"!<arch>\n".to_vec();
+
+        assert_eq!(detect(&data), Some((Format::Ar, None, Confidence::High)));
+
+        data.extend_from_slice(b"debian-binary   0           0     0     ");
+        data.extend_from_slice(b"100644  4         `\n2.0\n");
+
+        assert_eq!(detect(&data), Some((Format::Ar, None, Confidence::High)));
+
+        data[66] = b' ';
+
+        assert_eq!(detect(&data), Some((Format::Ar, None, Confidence::Medium)));
+    }
+
+    #[test]
+    fn detect_cpio() {
+        let mut newc = b"070701".to_vec();
+        newc.extend_from_slice(&[b'0'; 96]);
+        newc.extend_from_slice(b"0000000B");
+
+        let mut odc = b"070707".to_vec();
+        odc.extend_from_slice(&[b'7'; 70]);
+
+        let mut binary = vec!(0; 26);
+        binary[..2].copy_from_slice(b"\xc7\x71");
+        binary[20] = 2;
+        binary.extend_from_slice(b"a\0");
+
+        let mut big_endian = binary.clone();
+        big_endian[..2].copy_from_slice(b"\x71\xc7");
+        big_endian[20..22].copy_from_slice(b"\x00\x02");
+
+        let mut crc = b"070702".to_vec();
+        crc.extend_from_slice(&[b'x'; 104]);
+
+        let archives: [(&[u8], Variant, Confidence); 6] = [
+            (&newc, Variant::CpioNewc, Confidence::High),
+            (&newc[..50], Variant::CpioNewc, Confidence::Medium),
+            (&odc, Variant::CpioOdc, Confidence::High),
+            (&crc, Variant::CpioCrc, Confidence::Low),
+            (&binary, Variant::CpioBinary, Confidence::Medium),
+            (&big_endian, Variant::CpioBinary, Confidence::Medium),
+        ];
+
+        for &(data, variant, confidence) in &archives {
+            assert_eq!(
+                detect(data),
+                Some((Format::Cpio, Some(variant), confidence))
+            );
+        }
+    }
+
+    #[test]
+    fn detect_nothing() {
+        assert_eq!(detect(b""), None);
+        assert_eq!(detect(b"Hello, World!"), None);
+        assert_eq!(detect(&[0x42; 4096]), None);
+    }
+
+    #[test]
+    fn detect_failure() {
+        let data = test_archive(&[]);
+
+        for limit in 0..2 {
+            let reader = FailingReader::new(&data, limit);
+
+            assert_eq!(detect_format(&reader), Err(TestError));
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! # High-level uniform access to archives
+//!
+//! The `Archive` and `Entry` traits abstract over the archive formats, so that
+//! tooling written against them handles any format implementing them.
+//!
+//! As elsewhere in the library, nothing is allocated: the entries are pushed
+//! into a `Sink` one at a time, borrowing a caller-provided `scratch` buffer
+//! when the reader copies.
+
+use core::ops::Range;
+
+use api::Sink;
+
+mod detect;
+mod time;
+
+pub use self::detect::{
+    Confidence,
+    Detection,
+    Format,
+    Variant,
+    detect_format
+};
+pub use self::time::Timestamp;
+
+/// An archive, holding entries.
+pub trait Archive {
+    /// The error reported when the archive cannot be read.
+    type Error;
+
+    /// The entries of the archive, borrowing the archive or a `scratch`
+    /// buffer.
+    type Entry<'e>: Entry;
+
+    /// Pushes each of the entries of the archive into the `sink`, in the order
+    /// in which they are stored, returning their number.
+    ///
+    /// The `scratch` buffer holds the metadata of one entry at a time, if the
+    /// reader copies; it must thus be able to hold the largest.
+    fn entries<S>(&self, scratch: &mut [u8], sink: &mut S)
+        -> Result<u64, Self::Error>
+        where S: for<'e> Sink<Self::Entry<'e>> + ?Sized;
+
+    /// Returns the first entry whose name is exactly `name`, if any.
+    fn by_name<'e>(&'e self, name: &[u8], scratch: &'e mut [u8])
+        -> Result<Option<Self::Entry<'e>>, Self::Error>;
+
+    /// Returns the range of the data of the `entry`, as stored, that is
+    /// possibly compressed or encrypted, from the start of the archive.
+    fn data_range(&self, entry: &Self::Entry<'_>)
+        -> Result<Range<u64>, Self::Error>;
+}
+
+/// An entry of an archive.
+///
+/// The metadata are as announced by the archive, and not validated.
+pub trait Entry {
+    /// Returns the name of the entry, as stored.
+    fn name(&self) -> &[u8];
+
+    /// Returns the kind of the entry.
+    fn kind(&self) -> EntryKind;
+
+    /// Returns the size of the data, as stored.
+    fn compressed_size(&self) -> u64;
+
+    /// Returns the size of the data, once decompressed.
+    fn uncompressed_size(&self) -> u64;
+
+    /// Returns the time of the last modification, if known.
+    fn modified(&self) -> Option<Timestamp>;
+
+    /// Returns the time of the last access, if known.
+    fn accessed(&self) -> Option<Timestamp> { None }
+
+    /// Returns the time of creation, if known.
+    fn created(&self) -> Option<Timestamp> { None }
+
+    /// Returns the Unix permissions, that is the lower 12 bits of the mode, if
+    /// known.
+    fn permissions(&self) -> Option<u16>;
+
+    /// Returns the target of a symbolic link, if stored in the metadata; some
+    /// formats, ZIP among them, store it as the data of the entry instead.
+    fn link_target(&self) -> Option<&[u8]>;
+
+    /// Returns the checksum of the data, if any.
+    fn checksum(&self) -> Option<Checksum>;
+
+    /// Returns the compression method of the data.
+    fn compression_method(&self) -> CompressionMethod;
+}
+
+/// The kind of an entry.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum EntryKind {
+    /// A regular file.
+    File,
+    /// A directory.
+    Directory,
+    /// A symbolic link.
+    Symlink,
+    /// Anything else, such as a device or a FIFO.
+    Other,
+}
+
+/// The checksum of the data of an entry.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Checksum {
+    /// A CRC-32, see `checksum::Crc32`, of the decompressed data.
+    Crc32(u32),
+}
+
+/// The compression method of the data of an entry.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum CompressionMethod {
+    /// No compression.
+    Stored,
+    /// Deflate, as per RFC 1951.
+    Deflate,
+    /// Enhanced Deflate, or Deflate64.
+    Deflate64,
+    /// BZIP2.
+    Bzip2,
+    /// LZMA.
+    Lzma,
+    /// Zstandard.
+    Zstandard,
+    /// XZ.
+    Xz,
+    /// PPMd.
+    Ppmd,
+    /// Any other method, by its identifier in the format.
+    Other(u64),
+}
@@ -0,0 +1,153 @@
+//! # Timestamps of entries
+
+/// A point in time, as a number of seconds and nanoseconds since the Unix
+/// epoch, 1970-01-01 00:00:00 UTC.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Timestamp {
+    seconds: i64,
+    nanoseconds: u32,
+}
+
+impl Timestamp {
+    /// Returns an instance `seconds` and `nanoseconds` after the Unix epoch,
+    /// or `None` if `nanoseconds` is not less than 1,000,000,000.
+    pub fn from_unix(seconds: i64, nanoseconds: u32) -> Option<Timestamp> {
+        if nanoseconds < 1_000_000_000 {
+            Some(Timestamp { seconds, nanoseconds })
+        } else {
+            None
+        }
+    }
+
+    /// Returns an instance for the MS-DOS `date` and `time`, or `None` if
+    /// either is invalid.
+    ///
+    /// MS-DOS timestamps are in local time, with a 2 seconds resolution; the
+    /// time zone being unknown, they are taken as UTC.
+    pub fn from_dos(date: u16, time: u16) -> Option<Timestamp> {
+        let year = 1980 + i64::from(date >> 9);
+        let month = u32::from((date >> 5) & 0x0f);
+        let day = u32::from(date & 0x1f);
+
+        let hours = i64::from(time >> 11);
+        let minutes = i64::from((time >> 5) & 0x3f);
+        let seconds = i64::from(time & 0x1f) * 2;
+
+        if !(1..=12).contains(&month) || day == 0 || day > days_in(year, month)
+        {
+            return None;
+        }
+
+        if hours >= 24 || minutes >= 60 || seconds >= 60 {
+            return None;
+        }
+
+        let days = days_from_civil(year, month, day);
+        let seconds = days * 86400 + hours * 3600 + minutes * 60 + seconds;
+
+        Some(Timestamp { seconds, nanoseconds: 0 })
+    }
+
+    /// Returns an instance for the NTFS `ticks`, the number of 100 nanoseconds
+    /// intervals since 1601-01-01 00:00:00 UTC.
+    pub fn from_ntfs(ticks: u64) -> Timestamp {
+        //  The number of seconds between 1601-01-01 and the Unix epoch.
+        const NTFS_EPOCH: i64 = 11_644_473_600;
+
+        let seconds = (ticks / 10_000_000) as i64 - NTFS_EPOCH;
+        let nanoseconds = (ticks % 10_000_000) as u32 * 100;
+
+        Timestamp { seconds, nanoseconds }
+    }
+
+    /// Returns the number of whole seconds since the Unix epoch.
+    pub fn unix_seconds(&self) -> i64 { self.seconds }
+
+    /// Returns the number of nanoseconds past `unix_seconds`.
+    pub fn nanoseconds(&self) -> u32 { self.nanoseconds }
+}
+
+/// Returns the number of days in the `month` of the `year`.
+fn days_in(year: i64, month: u32) -> u32 {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Returns the number of days between the Unix epoch and the date, in the
+/// proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    //  Years start in March, so that the leap day ends them.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * i64::from((month + 9) % 12) + 2) / 5 +
+        i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 +
+        day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timestamp;
+
+    #[test]
+    fn from_dos_epoch() {
+        //  1980-01-01 00:00:00
+        let timestamp = Timestamp::from_dos(0x21, 0).unwrap();
+
+        assert_eq!(timestamp.unix_seconds(), 315532800);
+        assert_eq!(timestamp.nanoseconds(), 0);
+    }
+
+    #[test]
+    fn from_dos_leap_day() {
+        //  2024-02-29 13:37:42
+        let date = (44 << 9) | (2 << 5) | 29;
+        let time = (13 << 11) | (37 << 5) | 21;
+
+        let timestamp = Timestamp::from_dos(date, time).unwrap();
+
+        assert_eq!(timestamp.unix_seconds(), 1709213862);
+    }
+
+    #[test]
+    fn from_ntfs() {
+        let epoch = Timestamp::from_ntfs(116_444_736_000_000_000);
+
+        assert_eq!(epoch, Timestamp::from_unix(0, 0).unwrap());
+
+        //  2024-02-29 13:37:42.1234567
+        let timestamp = Timestamp::from_ntfs(133_536_874_621_234_567);
+
+        assert_eq!(timestamp.unix_seconds(), 1709213862);
+        assert_eq!(timestamp.nanoseconds(), 123_456_700);
+
+        assert_eq!(Timestamp::from_ntfs(0).unix_seconds(), -11_644_473_600);
+    }
+
+    #[test]
+    fn from_dos_failure_on_invalid_fields() {
+        let date = (44 << 9) | (2 << 5) | 29;
+
+        assert_eq!(Timestamp::from_dos(0, 0), None);
+        assert_eq!(Timestamp::from_dos((43 << 9) | (2 << 5) | 29, 0), None);
+        assert_eq!(Timestamp::from_dos((44 << 9) | (13 << 5) | 1, 0), None);
+        assert_eq!(Timestamp::from_dos(date, 24 << 11), None);
+        assert_eq!(Timestamp::from_dos(date, 60 << 5), None);
+        assert_eq!(Timestamp::from_dos(date, 30), None);
+    }
+
+    #[test]
+    fn from_unix_failure_on_invalid_nanoseconds() {
+        assert!(Timestamp::from_unix(-1, 999_999_999).is_some());
+        assert_eq!(Timestamp::from_unix(0, 1_000_000_000), None);
+    }
+}
@@ -0,0 +1,182 @@
+//! # CRC-32
+//!
+//! The CRC-32 used by ZIP, gzip or PNG, among others, based on the reversed
+//! polynomial 0xedb88320.
+//!
+//! The implementation uses the "slicing-by-8" technique: 8 tables of 256
+//! entries (8 KB) are computed at compile-time, allowing to process 8 bytes at
+//! a time.
+
+/// The reversed polynomial.
+const POLYNOMIAL: u32 = 0xedb88320;
+
+/// The tables: `TABLES[0]` is the classic byte-wise table, and `TABLES[k]`
+/// advances the CRC of a byte followed by `k` zero bytes.
+static TABLES: [[u32; 256]; 8] = make_tables();
+
+/// An incremental CRC-32 computation.
+///
+/// The register is pre-conditioned with 0xffffffff, and the value is
+/// post-conditioned by taking the one-complement of the register.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Crc32 {
+    register: u32,
+}
+
+impl Crc32 {
+    /// Returns a new instance, as if no byte had been processed yet.
+    pub fn new() -> Crc32 { Crc32 { register: 0xffffffff } }
+
+    /// Returns the CRC-32 of the `data`.
+    pub fn checksum(data: &[u8]) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(data);
+        crc.value()
+    }
+
+    /// Processes the `data`, which follows any data previously processed.
+    pub fn update(&mut self, data: &[u8]) {
+        //  Note: indexing the tables with a `u8` cannot go out of bounds.
+        let t = &TABLES;
+        let mut register = self.register;
+
+        let chunks = data.chunks_exact(8);
+        let remainder = chunks.remainder();
+
+        for chunk in chunks {
+            if let [b0, b1, b2, b3, b4, b5, b6, b7] = *chunk {
+                let low = register ^ u32::from_le_bytes([b0, b1, b2, b3]);
+
+                register =
+                    t[7][low as u8 as usize] ^
+                    t[6][(low >>  8) as u8 as usize] ^
+                    t[5][(low >> 16) as u8 as usize] ^
+                    t[4][(low >> 24) as u8 as usize] ^
+                    t[3][b4 as usize] ^
+                    t[2][b5 as usize] ^
+                    t[1][b6 as usize] ^
+                    t[0][b7 as usize];
+            }
+        }
+
+        for &byte in remainder {
+            register = update_register(register, byte);
+        }
+
+        self.register = register;
+    }
+
+    /// Returns the CRC-32 of the data processed so far.
+    pub fn value(&self) -> u32 { !self.register }
+}
+
+/// Returns the register after processing the `byte`, without any conditioning.
+pub fn update_register(register: u32, byte: u8) -> u32 {
+    TABLES[0][(register as u8 ^ byte) as usize] ^ (register >> 8)
+}
+
+impl Default for Crc32 {
+    fn default() -> Crc32 { Crc32::new() }
+}
+
+/// Computes the tables.
+const fn make_tables() -> [[u32; 256]; 8] {
+    let mut tables = [[0; 256]; 8];
+
+    let mut i = 0;
+    while i < 256 {
+        let mut value = i as u32;
+
+        let mut bit = 0;
+        while bit < 8 {
+            value = if value & 1 != 0 {
+                (value >> 1) ^ POLYNOMIAL
+            } else {
+                value >> 1
+            };
+            bit += 1;
+        }
+
+        tables[0][i] = value;
+        i += 1;
+    }
+
+    let mut k = 1;
+    while k < 8 {
+        let mut i = 0;
+        while i < 256 {
+            let previous = tables[k - 1][i];
+            tables[k][i] =
+                (previous >> 8) ^ tables[0][(previous & 0xff) as usize];
+            i += 1;
+        }
+        k += 1;
+    }
+
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Crc32;
+
+    /// Bit-wise reference implementation.
+    fn reference(data: &[u8]) -> u32 {
+        let mut register = 0xffffffffu32;
+
+        for &byte in data {
+            register ^= byte as u32;
+            for _ in 0..8 {
+                let mask = (register & 1).wrapping_neg();
+                register = (register >> 1) ^ (0xedb88320 & mask);
+            }
+        }
+
+        !register
+    }
+
+    #[test]
+    fn checksum_empty() {
+        assert_eq!(Crc32::checksum(b""), 0);
+    }
+
+    #[test]
+    fn checksum_check_value() {
+        assert_eq!(Crc32::checksum(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn checksum_quick_brown_fox() {
+        assert_eq!(
+            Crc32::checksum(b"The quick brown fox jumps over the lazy dog"),
+            0x414fa339
+        );
+    }
+
+    #[test]
+    fn checksum_matches_reference_on_all_lengths() {
+        let data: Vec<u8> =
+            (0..1024u32).map(|i| ((i * 7919) >> 3) as u8).collect();
+
+        for length in 0..data.len() {
+            assert_eq!(
+                Crc32::checksum(&data[..length]),
+                reference(&data[..length])
+            );
+        }
+    }
+
+    #[test]
+    fn update_incremental_matches_one_shot() {
+        let data: Vec<u8> = (0..1024u32).map(|i| (i * 31) as u8).collect();
+        let expected = Crc32::checksum(&data);
+
+        for split in 0..data.len() {
+            let mut crc = Crc32::new();
+            crc.update(&data[..split]);
+            crc.update(&data[split..]);
+
+            assert_eq!(crc.value(), expected);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+//! # Checksum algorithms shared by formats
+
+mod crc32;
+
+pub use self::crc32::Crc32;
+
+pub(crate) use self::crc32::update_register as update_crc32_register;
@@ -0,0 +1,271 @@
+//! # C FFI of the common API
+//!
+//! An `ArkivReader` lets a C consumer provide the archive through callbacks,
+//! rather than as bytes in memory; `CallbackReader` adapts it into a `Reader`.
+//!
+//! As a failing callback is reported as `ArkivStatus::Io`, the error code it
+//! returned is written into the `last_error` field of the `ArkivReader`.
+
+use core::cell::Cell;
+use core::cmp::min;
+use core::convert::Infallible;
+use core::ffi::c_void;
+use core::ops::Range;
+
+use api::{ReadError, Reader};
+
+use ffi::ArkivStatus;
+
+/// Writes the size of the archive into `size`, returning 0 on success, or an
+/// error code of the consumer's choosing otherwise.
+pub type ArkivReaderSize =
+    Option<unsafe extern "C" fn(user_data: *mut c_void, size: *mut u64) -> i32>;
+
+/// Copies up to `length` bytes of the archive, starting at `offset`, into
+/// `buffer`, writing their number into `read`, and returning 0 on success, or
+/// an error code of the consumer's choosing otherwise.
+///
+/// Fewer bytes than available may be copied, as long as at least one is; 0 is
+/// only read past the end of the archive.
+pub type ArkivReaderRead = Option<unsafe extern "C" fn(
+    user_data: *mut c_void,
+    offset: u64,
+    buffer: *mut u8,
+    length: usize,
+    read: *mut usize
+)
+    -> i32>;
+
+/// An archive provided by a C consumer through callbacks, each passed the
+/// `user_data`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct ArkivReader {
+    /// The opaque data of the consumer.
+    pub user_data: *mut c_void,
+    /// The callback returning the size of the archive.
+    pub size: ArkivReaderSize,
+    /// The callback copying a range of the archive.
+    pub read: ArkivReaderRead,
+    /// The error code returned by the callback which failed during the last
+    /// call passed this reader, or 0 if none did.
+    pub last_error: i32,
+}
+
+/// The error reported by a `CallbackReader`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ArkivReaderError {
+    /// The callback is null.
+    NullCallback,
+    /// The callback failed, with the error code it returned.
+    Callback(i32),
+}
+
+/// A `Reader` calling the callbacks of an `ArkivReader`, copying into the
+/// `scratch` buffer.
+#[derive(Clone, Debug)]
+pub struct CallbackReader<'a> {
+    reader: &'a ArkivReader,
+    last_error: Cell<i32>,
+}
+
+impl<'a> CallbackReader<'a> {
+    /// Returns a new instance calling the callbacks of the `reader`.
+    ///
+    /// # Safety
+    ///
+    /// The callbacks, if not null, must abide by their contract for as long as
+    /// the instance lives.
+    pub unsafe fn new(reader: &'a ArkivReader) -> CallbackReader<'a> {
+        CallbackReader { reader, last_error: Cell::new(0) }
+    }
+
+    /// Returns the error code returned by the last failing callback, or 0 if
+    /// none failed.
+    pub fn last_error(&self) -> i32 { self.last_error.get() }
+
+    fn fail(&self, code: i32) -> ArkivReaderError {
+        self.last_error.set(code);
+        ArkivReaderError::Callback(code)
+    }
+}
+
+impl<'a> Reader for CallbackReader<'a> {
+    type Error = ArkivReaderError;
+
+    fn size(&self) -> Result<u64, ArkivReaderError> {
+        let callback = self.reader.size.ok_or(ArkivReaderError::NullCallback)?;
+        let mut size = 0;
+
+        //  Safety: the callback abides by its contract, per `new`.
+        match unsafe { callback(self.reader.user_data, &mut size) } {
+            0 => Ok(size),
+            code => Err(self.fail(code)),
+        }
+    }
+
+    fn get<'b>(&'b self, range: Range<u64>, scratch: &'b mut [u8])
+        -> Result<&'b [u8], ArkivReaderError>
+    {
+        let callback = self.reader.read.ok_or(ArkivReaderError::NullCallback)?;
+
+        let wanted = range.end.saturating_sub(range.start);
+        let length = min(wanted, scratch.len() as u64) as usize;
+        let buffer = scratch.get_mut(..length).unwrap_or(&mut []);
+
+        if buffer.is_empty() {
+            return Ok(&[]);
+        }
+
+        let mut read = 0;
+
+        //  Safety: the callback abides by its contract, per `new`.
+        let code = unsafe {
+            callback(
+                self.reader.user_data,
+                range.start,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut read
+            )
+        };
+
+        if code != 0 {
+            return Err(self.fail(code));
+        }
+
+        let buffer: &'b [u8] = buffer;
+
+        Ok(buffer.get(..read).unwrap_or(buffer))
+    }
+}
+
+impl From<ArkivReaderError> for ArkivStatus {
+    fn from(error: ArkivReaderError) -> ArkivStatus {
+        match error {
+            ArkivReaderError::NullCallback => ArkivStatus::NullPointer,
+            ArkivReaderError::Callback(_) => ArkivStatus::Io,
+        }
+    }
+}
+
+impl From<Infallible> for ArkivStatus {
+    fn from(error: Infallible) -> ArkivStatus { match error {} }
+}
+
+impl<E: Into<ArkivStatus>> From<ReadError<E>> for ArkivStatus {
+    fn from(error: ReadError<E>) -> ArkivStatus {
+        match error {
+            ReadError::Truncated => ArkivStatus::Truncated,
+            ReadError::Malformed => ArkivStatus::Malformed,
+            ReadError::Io(error) => error.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use core::ffi::c_void;
+    use core::ptr;
+
+    use api::{ReadError, Reader};
+    use ffi::ArkivStatus;
+
+    use super::{ArkivReader, ArkivReaderError, CallbackReader};
+
+    /// Serves the slice `user_data` points to, one byte short of `length`, so
+    /// as to exercise short reads.
+    unsafe extern "C" fn read(
+        user_data: *mut c_void,
+        offset: u64,
+        buffer: *mut u8,
+        length: usize,
+        read: *mut usize
+    )
+        -> i32
+    {
+        let data = &*(user_data as *const &[u8]);
+        let start = offset as usize;
+        let count = data.len().saturating_sub(start).min(length.max(2) - 1);
+
+        if count > 0 {
+            ptr::copy_nonoverlapping(data[start..].as_ptr(), buffer, count);
+        }
+
+        *read = count;
+        0
+    }
+
+    unsafe extern "C" fn size(user_data: *mut c_void, size: *mut u64) -> i32 {
+        let data = &*(user_data as *const &[u8]);
+
+        *size = data.len() as u64;
+        0
+    }
+
+    /// Fails with 42.
+    pub(crate) unsafe extern "C" fn fail(_: *mut c_void, _: *mut u64) -> i32 {
+        42
+    }
+
+    /// Returns an `ArkivReader` over the slice `data` points to.
+    pub(crate) fn slice_reader(data: &mut &[u8]) -> ArkivReader {
+        ArkivReader {
+            user_data: data as *mut &[u8] as *mut c_void,
+            size: Some(size),
+            read: Some(read),
+            last_error: 0,
+        }
+    }
+
+    #[test]
+    fn callback_reader_copies_into_scratch() {
+        let mut data: &[u8] = b"Hello, World!";
+        let reader = slice_reader(&mut data);
+        let reader = unsafe { CallbackReader::new(&reader) };
+
+        let mut scratch = [0; 8];
+
+        assert_eq!(reader.size(), Ok(13));
+        assert_eq!(reader.get(7..13, &mut scratch), Ok(&b"World"[..]));
+        assert_eq!(reader.get(7..13, &mut []), Ok(&b""[..]));
+        assert_eq!(reader.get(13..20, &mut scratch), Ok(&b""[..]));
+    }
+
+    #[test]
+    fn callback_reader_failure_on_callbacks() {
+        let reader = ArkivReader {
+            user_data: ptr::null_mut(),
+            size: Some(fail),
+            read: None,
+            last_error: 0,
+        };
+        let reader = unsafe { CallbackReader::new(&reader) };
+
+        assert_eq!(reader.last_error(), 0);
+        assert_eq!(reader.size(), Err(ArkivReaderError::Callback(42)));
+        assert_eq!(reader.last_error(), 42);
+        assert_eq!(
+            reader.get(0..4, &mut [0; 4]),
+            Err(ArkivReaderError::NullCallback)
+        );
+    }
+
+    #[test]
+    fn status_from_read_error() {
+        use super::ArkivReaderError::{Callback, NullCallback};
+
+        type Error = ReadError<ArkivReaderError>;
+
+        let statuses = vec!(
+            (Error::Truncated, ArkivStatus::Truncated),
+            (Error::Malformed, ArkivStatus::Malformed),
+            (Error::Io(NullCallback), ArkivStatus::NullPointer),
+            (Error::Io(Callback(1)), ArkivStatus::Io),
+        );
+
+        for (error, status) in statuses {
+            assert_eq!(ArkivStatus::from(error), status);
+        }
+    }
+}
@@ -0,0 +1,530 @@
+//! # C FFI
+//!
+//! `extern "C"` functions mirroring the modules of the library, declared in
+//! `include/arkiv.h`, which cbindgen generates from this module.
+//!
+//! The records are exposed as plain structures, holding their fixed fields by
+//! value, and their variable fields as `ArkivBytes` views into the bytes they
+//! were read from.
+//!
+//! # Safety
+//!
+//! The functions check their pointers for null, but otherwise trust them:
+//!
+//! - a `data` pointer must point to `size` readable bytes, or be null if
+//!   `size` is 0;
+//! - an output pointer must point to memory writable as the structure it
+//!   points to, which is only written on success.
+//!
+//! The `ArkivBytes` views, and the iterators, borrow the bytes they were read
+//! from, which must outlive them.
+//!
+//! The library does not panic; should it nonetheless, the functions return
+//! `Panicked` rather than unwinding into the caller, provided the `std`
+//! feature is enabled, and abort otherwise.
+
+use core::slice;
+
+use self::api::{ArkivReader, CallbackReader};
+
+pub mod api;
+pub mod zip;
+
+/// The status returned by the functions of the FFI.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ArkivStatus {
+    /// The function succeeded.
+    Ok = 0,
+    /// A pointer argument is null.
+    NullPointer = 1,
+    /// The bytes end before the record does.
+    Truncated = 2,
+    /// The record cannot be located, or its content is inconsistent.
+    Malformed = 3,
+    /// The iterator is exhausted.
+    Done = 4,
+    /// A callback of an `ArkivReader` failed, see its `last_error`.
+    Io = 5,
+    /// The library panicked.
+    Panicked = 6,
+}
+
+/// A view of `size` bytes starting at `data`, borrowed from the bytes a
+/// record was read from.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ArkivBytes {
+    /// The first byte, never null.
+    pub data: *const u8,
+    /// The number of bytes.
+    pub size: usize,
+}
+
+impl ArkivBytes {
+    fn new(bytes: &[u8]) -> ArkivBytes {
+        ArkivBytes { data: bytes.as_ptr(), size: bytes.len() }
+    }
+
+    /// Returns the bytes viewed.
+    ///
+    /// # Safety
+    ///
+    /// The bytes viewed must still be alive.
+    unsafe fn as_slice<'a>(&self) -> Option<&'a [u8]> {
+        as_slice(self.data, self.size)
+    }
+}
+
+/// Returns the `size` bytes at `data`, or `None` if `data` is null while
+/// `size` is not 0.
+///
+/// # Safety
+///
+/// A non-null `data` must point to `size` readable bytes.
+unsafe fn as_slice<'a>(data: *const u8, size: usize) -> Option<&'a [u8]> {
+    if !data.is_null() {
+        Some(slice::from_raw_parts(data, size))
+    } else if size == 0 {
+        Some(&[])
+    } else {
+        None
+    }
+}
+
+/// Writes the `value` into `target`, unless null.
+///
+/// # Safety
+///
+/// A non-null `target` must point to memory writable as a `T`.
+unsafe fn write<T>(target: *mut T, value: T) -> ArkivStatus {
+    match target.as_mut() {
+        Some(target) => {
+            *target = value;
+            ArkivStatus::Ok
+        },
+        None => ArkivStatus::NullPointer,
+    }
+}
+
+/// Returns the `size` bytes at `data`, or `None` if `data` is null while
+/// `size` is not 0.
+///
+/// # Safety
+///
+/// A non-null `data` must point to `size` writable bytes.
+unsafe fn as_mut_slice<'a>(data: *mut u8, size: usize)
+    -> Option<&'a mut [u8]>
+{
+    if !data.is_null() {
+        Some(slice::from_raw_parts_mut(data, size))
+    } else if size == 0 {
+        Some(&mut [])
+    } else {
+        None
+    }
+}
+
+/// Runs `function`, returning `Panicked` should it panic.
+#[cfg(feature = "std")]
+fn guard<F>(function: F) -> ArkivStatus
+    where F: FnOnce() -> ArkivStatus
+{
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    //  Nothing observed by the caller is left inconsistent: outputs are only
+    //  written once computed.
+    catch_unwind(AssertUnwindSafe(function)).unwrap_or(ArkivStatus::Panicked)
+}
+
+/// Runs `function`; without `std`, a panic aborts at the boundary.
+#[cfg(not(feature = "std"))]
+fn guard<F>(function: F) -> ArkivStatus
+    where F: FnOnce() -> ArkivStatus
+{
+    function()
+}
+
+/// Reads a record from the `size` bytes at `data` with `read`, writing it
+/// into `record` on success.
+///
+/// # Safety
+///
+/// See the module documentation.
+unsafe fn read_record<'a, T, F>(
+    data: *const u8,
+    size: usize,
+    record: *mut T,
+    read: F
+)
+    -> ArkivStatus
+    where F: FnOnce(&'a [u8]) -> Result<T, ArkivStatus>
+{
+    guard(|| {
+        if record.is_null() {
+            return ArkivStatus::NullPointer;
+        }
+
+        let bytes = match as_slice(data, size) {
+            Some(bytes) => bytes,
+            None => return ArkivStatus::NullPointer,
+        };
+
+        match read(bytes) {
+            Ok(value) => write(record, value),
+            Err(status) => status,
+        }
+    })
+}
+
+/// Reads a record from the `reader` with `read`, copying into the
+/// `scratch_size` bytes at `scratch`, and writing it into `record` on success.
+///
+/// # Safety
+///
+/// See the module documentation; the callbacks of the `reader` must abide by
+/// their contract.
+unsafe fn read_record_from<'a, T, F>(
+    reader: *mut ArkivReader,
+    scratch: *mut u8,
+    scratch_size: usize,
+    record: *mut T,
+    read: F
+)
+    -> ArkivStatus
+    where F: FnOnce(&CallbackReader, &'a mut [u8]) -> Result<T, ArkivStatus>
+{
+    guard(|| {
+        let callbacks = match reader.as_mut() {
+            Some(callbacks) => callbacks,
+            None => return ArkivStatus::NullPointer,
+        };
+
+        if record.is_null() {
+            return ArkivStatus::NullPointer;
+        }
+
+        let scratch = match as_mut_slice(scratch, scratch_size) {
+            Some(scratch) => scratch,
+            None => return ArkivStatus::NullPointer,
+        };
+
+        let reader = CallbackReader::new(callbacks);
+
+        let status = match read(&reader, scratch) {
+            Ok(value) => write(record, value),
+            Err(status) => status,
+        };
+
+        callbacks.last_error = reader.last_error();
+
+        status
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::ErrorKind;
+    use std::mem;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    use utils::test::{TEST_ENTRIES, test_archive};
+
+    use super::{ArkivBytes, ArkivStatus};
+    use super::api::ArkivReader;
+    use super::zip::low_level::{
+        ArkivZipCentralDirectoryIterator,
+        ArkivZipEndOfCentralDirectory,
+    };
+    use super::zip::raw::{
+        ArkivZipRawCentralDirectoryFileHeader,
+        ArkivZipRawDataDescriptor,
+        ArkivZipRawEndOfCentralDirectory,
+        ArkivZipRawLocalFileHeader,
+        ArkivZipRawZip64DataDescriptor,
+        ArkivZipRawZip64EndOfCentralDirectory,
+        ArkivZipRawZip64EndOfCentralDirectoryLocator,
+    };
+
+    /// Returns the C checks of the layout of the FFI types, as laid out by
+    /// Rust: their sizes, and the offsets of their fields.
+    fn layout_checks() -> String {
+        macro_rules! checks {
+            ($($name:ident { $($field:ident),* })*) => {{
+                let mut checks = String::new();
+
+                $(
+                    checks += &format!(
+                        "CHECK_SIZE({}, {});\n",
+                        stringify!($name),
+                        mem::size_of::<$name>()
+                    );
+
+                    $(
+                        checks += &format!(
+                            "CHECK_OFFSET({}, {}, {});\n",
+                            stringify!($name),
+                            stringify!($field),
+                            mem::offset_of!($name, $field)
+                        );
+                    )*
+                )*
+
+                checks
+            }};
+        }
+
+        checks! {
+            ArkivStatus {}
+            ArkivBytes { data, size }
+            ArkivReader { user_data, size, read, last_error }
+            ArkivZipRawEndOfCentralDirectory {
+                signature,
+                disk,
+                central_directory_disk,
+                nb_local_central_directory_records,
+                nb_central_directory_records,
+                central_directory_size,
+                central_directory_offset,
+                comment_size,
+                comment
+            }
+            ArkivZipRawZip64EndOfCentralDirectory {
+                signature,
+                record_size,
+                version_made_by,
+                version_needed_to_extract,
+                disk,
+                central_directory_disk,
+                nb_local_central_directory_records,
+                nb_central_directory_records,
+                central_directory_size,
+                central_directory_offset,
+                extensible_data
+            }
+            ArkivZipRawZip64EndOfCentralDirectoryLocator {
+                signature,
+                zip64_end_of_central_directory_disk,
+                zip64_end_of_central_directory_offset,
+                nb_disks
+            }
+            ArkivZipRawCentralDirectoryFileHeader {
+                signature,
+                version_made_by,
+                version_needed_to_extract,
+                general_purpose_bit_flag,
+                compression_method,
+                file_last_modification_time,
+                file_last_modification_date,
+                crc32,
+                compressed_size,
+                uncompressed_size,
+                file_name_size,
+                extra_field_size,
+                file_comment_size,
+                file_start_disk,
+                internal_file_attributes,
+                external_file_attributes,
+                local_file_header_relative_offset,
+                file_name,
+                extra_field,
+                file_comment
+            }
+            ArkivZipRawLocalFileHeader {
+                signature,
+                version_needed_to_extract,
+                general_purpose_bit_flag,
+                compression_method,
+                file_last_modification_time,
+                file_last_modification_date,
+                crc32,
+                compressed_size,
+                uncompressed_size,
+                file_name_size,
+                extra_field_size,
+                file_name,
+                extra_field
+            }
+            ArkivZipRawDataDescriptor {
+                has_signature,
+                signature,
+                crc32,
+                compressed_size,
+                uncompressed_size
+            }
+            ArkivZipRawZip64DataDescriptor {
+                has_signature,
+                signature,
+                crc32,
+                compressed_size,
+                uncompressed_size
+            }
+            ArkivZipEndOfCentralDirectory {
+                offset,
+                is_zip64,
+                zip64_offset,
+                disk,
+                central_directory_disk,
+                nb_local_central_directory_records,
+                nb_central_directory_records,
+                central_directory_size,
+                central_directory_offset,
+                comment_offset,
+                comment_size
+            }
+            ArkivZipCentralDirectoryIterator { bytes, remaining }
+        }
+    }
+
+    /// Checks that `include/arkiv.h` declares each function of the FFI.
+    #[test]
+    fn c_header_declares_functions() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let header = fs::read_to_string(root.join("include/arkiv.h")).unwrap();
+
+        let sources = [
+            "src/ffi/api.rs",
+            "src/ffi/zip/raw.rs",
+            "src/ffi/zip/low_level.rs",
+        ];
+
+        for source in &sources {
+            let source = fs::read_to_string(root.join(source)).unwrap();
+
+            //  The exported functions, rather than any callback.
+            let functions = source
+                .split("#[no_mangle]\npub unsafe extern \"C\" fn")
+                .skip(1)
+                .filter_map(|rest| rest.split('(').next())
+                .map(str::trim);
+
+            for function in functions {
+                let declaration = format!("ArkivStatus {}(", function);
+
+                assert!(header.contains(&declaration), "{}", function);
+            }
+        }
+    }
+
+    /// Regenerates the header with cbindgen, per `cbindgen.toml`, and checks
+    /// that `include/arkiv.h` is up to date.
+    ///
+    /// Fails if `cbindgen` is not available.
+    #[test]
+    fn c_header_is_generated() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let header = fs::read_to_string(root.join("include/arkiv.h")).unwrap();
+
+        let output = Command::new("cbindgen")
+            .arg("--config")
+            .arg(root.join("cbindgen.toml"))
+            .arg("--quiet")
+            .arg(&root)
+            .output();
+
+        let output = match output {
+            Ok(output) => output,
+            Err(ref error) if error.kind() == ErrorKind::NotFound =>
+                panic!("cbindgen is required to check the C header"),
+            Err(error) => panic!("{}", error),
+        };
+
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        assert!(
+            String::from_utf8_lossy(&output.stdout) == header,
+            "include/arkiv.h is out of date, regenerate it with: \
+                cbindgen --config cbindgen.toml --output include/arkiv.h"
+        );
+    }
+
+    /// Compiles the library as a static library, then `tests/c/ffi.c` against
+    /// `include/arkiv.h` and this library, and runs it over a test archive.
+    ///
+    /// The program also checks the layout of the FFI types against the one of
+    /// Rust, written into `layout.h` alongside it.
+    ///
+    /// Fails if `gcc` is not available.
+    #[test]
+    fn c_test_program() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let dir = env::temp_dir()
+            .join(format!("arkiv-ffi-{}", ::std::process::id()));
+
+        fs::create_dir_all(&dir).unwrap();
+
+        let archive = dir.join("test.zip");
+        fs::write(&archive, test_archive(TEST_ENTRIES)).unwrap();
+
+        fs::write(dir.join("layout.h"), layout_checks()).unwrap();
+
+        let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+        let library = dir.join("libarkiv.a");
+
+        let output = Command::new(rustc)
+            .arg("--crate-name=arkiv")
+            .arg("--crate-type=staticlib")
+            .arg("--edition=2015")
+            .arg("--cfg=feature=\"std\"")
+            .arg("--cfg=feature=\"alloc\"")
+            .arg("--print=native-static-libs")
+            .arg("-o")
+            .arg(&library)
+            .arg(root.join("src/lib.rs"))
+            .output()
+            .unwrap();
+
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        //  The system libraries the static library depends on.
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let native: Vec<&str> = stderr
+            .lines()
+            .filter_map(|line| line.split("native-static-libs: ").nth(1))
+            .flat_map(|libs| libs.split_whitespace())
+            .collect();
+
+        let program = dir.join("ffi");
+
+        let compiled = Command::new("gcc")
+            .arg("-std=c99")
+            .arg("-Wall")
+            .arg("-Werror")
+            .arg("-I")
+            .arg(root.join("include"))
+            .arg("-I")
+            .arg(&dir)
+            .arg(root.join("tests/c/ffi.c"))
+            .arg(&library)
+            .args(&native)
+            .arg("-o")
+            .arg(&program)
+            .status();
+
+        match compiled {
+            Ok(status) => assert!(status.success()),
+            Err(ref error) if error.kind() == ErrorKind::NotFound => {
+                fs::remove_dir_all(&dir).unwrap();
+                panic!("gcc is required to compile the C test program");
+            },
+            Err(error) => panic!("{}", error),
+        }
+
+        let status = Command::new(&program).arg(&archive).status().unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(status.success());
+    }
+}
@@ -0,0 +1,566 @@
+//! # C FFI of the low-level access to ZIP archives
+
+use api::Reader;
+use ffi::{ArkivBytes, ArkivStatus, guard, read_record, read_record_from, write};
+use ffi::api::ArkivReader;
+use zip::low_level::{
+    EndOfCentralDirectory,
+    read_central_directory_file_header,
+    read_local_file_header
+};
+use zip::raw::CentralDirectoryFileHeaderReader;
+
+use super::raw::{
+    ArkivZipRawCentralDirectoryFileHeader,
+    ArkivZipRawLocalFileHeader
+};
+
+/// The End of Central Directory of an archive, combining the End of Central
+/// Directory record with the ZIP64 End of Central Directory record, if any.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ArkivZipEndOfCentralDirectory {
+    /// The offset of the End of Central Directory record.
+    pub offset: u64,
+    /// Whether the archive has a ZIP64 End of Central Directory record.
+    pub is_zip64: bool,
+    /// The offset of the ZIP64 End of Central Directory record, or 0 if none.
+    pub zip64_offset: u64,
+    /// The number of the disk of the End of Central Directory.
+    pub disk: u32,
+    /// The number of the disk where the Central Directory starts.
+    pub central_directory_disk: u32,
+    /// The number of Central Directory records on this disk.
+    pub nb_local_central_directory_records: u64,
+    /// The total number of Central Directory records.
+    pub nb_central_directory_records: u64,
+    /// The size of the Central Directory.
+    pub central_directory_size: u64,
+    /// The offset of the Central Directory.
+    pub central_directory_offset: u64,
+    /// The offset of the archive comment.
+    pub comment_offset: u64,
+    /// The size of the archive comment.
+    pub comment_size: u64,
+}
+
+impl ArkivZipEndOfCentralDirectory {
+    fn locate<R>(reader: &R, scratch: &mut [u8]) -> Result<Self, ArkivStatus>
+        where R: Reader + ?Sized,
+              R::Error: Into<ArkivStatus>
+    {
+        let eocd = EndOfCentralDirectory::locate(reader, scratch)?;
+        let comment = eocd.comment_range();
+
+        Ok(ArkivZipEndOfCentralDirectory {
+            offset: eocd.offset(),
+            is_zip64: eocd.is_zip64(),
+            zip64_offset: eocd.zip64_offset().unwrap_or(0),
+            disk: eocd.disk(),
+            central_directory_disk: eocd.central_directory_disk(),
+            nb_local_central_directory_records:
+                eocd.nb_local_central_directory_records(),
+            nb_central_directory_records: eocd.nb_central_directory_records(),
+            central_directory_size: eocd.central_directory_size(),
+            central_directory_offset: eocd.central_directory_offset(),
+            comment_offset: comment.start,
+            comment_size: comment.end - comment.start,
+        })
+    }
+}
+
+/// An iterator over a contiguous sequence of Central Directory File Header
+/// records, as `CentralDirectoryFileHeaderIterator`.
+///
+/// The fields are only meant to be set by
+/// `arkiv_zip_central_directory_iterator_init`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ArkivZipCentralDirectoryIterator {
+    /// The bytes not iterated over yet.
+    pub bytes: ArkivBytes,
+    /// The maximum number of records still returned.
+    pub remaining: u64,
+}
+
+/// Locates the End of Central Directory of the archive held in the `size`
+/// bytes at `data`, as `EndOfCentralDirectory::locate` does, into `eocd`.
+///
+/// # Safety
+///
+/// See the `ffi` module documentation.
+#[no_mangle]
+pub unsafe extern "C" fn arkiv_zip_locate_end_of_central_directory(
+    data: *const u8,
+    size: usize,
+    eocd: *mut ArkivZipEndOfCentralDirectory
+)
+    -> ArkivStatus
+{
+    read_record(data, size, eocd, |archive| {
+        ArkivZipEndOfCentralDirectory::locate(archive, &mut [])
+    })
+}
+
+/// Locates the End of Central Directory of the archive provided by the
+/// `reader`, as `EndOfCentralDirectory::locate` does, into `eocd`.
+///
+/// The `scratch_size` bytes at `scratch` hold the tail of the archive, which
+/// is looked into for the End of Central Directory record: 65557 bytes cover
+/// any archive comment.
+///
+/// # Safety
+///
+/// See the `ffi` module documentation; the callbacks of the `reader` must
+/// abide by their contract.
+#[no_mangle]
+pub unsafe extern "C" fn arkiv_zip_reader_locate_end_of_central_directory(
+    reader: *mut ArkivReader,
+    scratch: *mut u8,
+    scratch_size: usize,
+    eocd: *mut ArkivZipEndOfCentralDirectory
+)
+    -> ArkivStatus
+{
+    read_record_from(reader, scratch, scratch_size, eocd, |reader, scratch| {
+        ArkivZipEndOfCentralDirectory::locate(reader, scratch)
+    })
+}
+
+/// Reads the Central Directory File Header record at `offset` of the archive
+/// provided by the `reader`, not extending beyond `end`, into `record`, as
+/// `read_central_directory_file_header` does.
+///
+/// The record is copied into the `scratch_size` bytes at `scratch`, which its
+/// variable fields point into; `Truncated` is returned if it does not fit.
+///
+/// # Safety
+///
+/// See the `ffi` module documentation; the callbacks of the `reader` must
+/// abide by their contract.
+#[no_mangle]
+pub unsafe extern "C" fn arkiv_zip_reader_read_central_directory_file_header(
+    reader: *mut ArkivReader,
+    offset: u64,
+    end: u64,
+    scratch: *mut u8,
+    scratch_size: usize,
+    record: *mut ArkivZipRawCentralDirectoryFileHeader
+)
+    -> ArkivStatus
+{
+    read_record_from(reader, scratch, scratch_size, record, |reader, scratch| {
+        let cdfh =
+            read_central_directory_file_header(reader, offset, end, scratch)
+                .map_err(ArkivStatus::from)?
+                .ok_or(ArkivStatus::Truncated)?;
+
+        ArkivZipRawCentralDirectoryFileHeader::read(cdfh.raw())
+    })
+}
+
+/// Reads the Local File Header record at `offset` of the archive provided by
+/// the `reader`, not extending beyond `end`, into `record`, as
+/// `read_local_file_header` does.
+///
+/// The record is copied into the `scratch_size` bytes at `scratch`, which its
+/// variable fields point into; `Truncated` is returned if it does not fit.
+///
+/// # Safety
+///
+/// See the `ffi` module documentation; the callbacks of the `reader` must
+/// abide by their contract.
+#[no_mangle]
+pub unsafe extern "C" fn arkiv_zip_reader_read_local_file_header(
+    reader: *mut ArkivReader,
+    offset: u64,
+    end: u64,
+    scratch: *mut u8,
+    scratch_size: usize,
+    record: *mut ArkivZipRawLocalFileHeader
+)
+    -> ArkivStatus
+{
+    read_record_from(reader, scratch, scratch_size, record, |reader, scratch| {
+        let lfh = read_local_file_header(reader, offset, end, scratch)
+            .map_err(ArkivStatus::from)?
+            .ok_or(ArkivStatus::Truncated)?;
+
+        ArkivZipRawLocalFileHeader::read(lfh.raw())
+    })
+}
+
+/// Initializes the `iterator` over the `size` bytes at `data`, which should
+/// start exactly on the boundary of the first record, returning at most
+/// `total` records.
+///
+/// # Safety
+///
+/// See the `ffi` module documentation; the bytes must outlive the `iterator`.
+#[no_mangle]
+pub unsafe extern "C" fn arkiv_zip_central_directory_iterator_init(
+    iterator: *mut ArkivZipCentralDirectoryIterator,
+    data: *const u8,
+    size: usize,
+    total: u64
+)
+    -> ArkivStatus
+{
+    read_record(data, size, iterator, |bytes| {
+        Ok(ArkivZipCentralDirectoryIterator {
+            bytes: ArkivBytes::new(bytes),
+            remaining: total,
+        })
+    })
+}
+
+/// Reads the next record of the `iterator` into `record`, advancing it.
+///
+/// Returns `Done` once `total` records were returned, and `Truncated`,
+/// without advancing, if the bytes end before the next record does.
+///
+/// # Safety
+///
+/// See the `ffi` module documentation; the `iterator` must have been
+/// initialized by `arkiv_zip_central_directory_iterator_init`.
+#[no_mangle]
+pub unsafe extern "C" fn arkiv_zip_central_directory_iterator_next(
+    iterator: *mut ArkivZipCentralDirectoryIterator,
+    record: *mut ArkivZipRawCentralDirectoryFileHeader
+)
+    -> ArkivStatus
+{
+    guard(|| {
+        let iterator = match iterator.as_mut() {
+            Some(iterator) => iterator,
+            None => return ArkivStatus::NullPointer,
+        };
+
+        if record.is_null() {
+            return ArkivStatus::NullPointer;
+        }
+
+        if iterator.remaining == 0 {
+            return ArkivStatus::Done;
+        }
+
+        let bytes = match iterator.bytes.as_slice() {
+            Some(bytes) => bytes,
+            None => return ArkivStatus::NullPointer,
+        };
+
+        let value = match ArkivZipRawCentralDirectoryFileHeader::read(bytes) {
+            Ok(value) => value,
+            Err(status) => return status,
+        };
+
+        let size = CentralDirectoryFileHeaderReader::new(bytes)
+            .map(|cdfh| cdfh.raw().len())
+            .unwrap_or(bytes.len());
+
+        iterator.bytes = ArkivBytes::new(bytes.get(size..).unwrap_or(b""));
+        iterator.remaining -= 1;
+
+        write(record, value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use core::mem;
+    use core::ptr;
+
+    use ffi::ArkivStatus;
+    use ffi::api::tests::{fail, slice_reader};
+    use ffi::zip::raw::{
+        ArkivZipRawCentralDirectoryFileHeader,
+        ArkivZipRawLocalFileHeader
+    };
+    use utils::test::{TEST_ENTRIES, test_archive, test_zip64_archive};
+    use zip::low_level::EndOfCentralDirectory;
+
+    use super::*;
+
+    fn locate(archive: &[u8])
+        -> (ArkivStatus, ArkivZipEndOfCentralDirectory)
+    {
+        let mut eocd = unsafe { mem::zeroed() };
+
+        let status = unsafe {
+            arkiv_zip_locate_end_of_central_directory(
+                archive.as_ptr(),
+                archive.len(),
+                &mut eocd
+            )
+        };
+
+        (status, eocd)
+    }
+
+    #[test]
+    fn locate_end_of_central_directory_as_locate() {
+        let archives =
+            [test_archive(TEST_ENTRIES), test_zip64_archive(TEST_ENTRIES)];
+
+        for archive in &archives {
+            let expected =
+                EndOfCentralDirectory::locate(&archive[..], &mut []).unwrap();
+
+            let (status, eocd) = locate(archive);
+
+            assert_eq!(status, ArkivStatus::Ok);
+            assert_eq!(eocd.offset, expected.offset());
+            assert_eq!(eocd.is_zip64, expected.is_zip64());
+            assert_eq!(
+                eocd.zip64_offset,
+                expected.zip64_offset().unwrap_or(0)
+            );
+            assert_eq!(eocd.nb_central_directory_records, 4);
+            assert_eq!(
+                eocd.central_directory_offset,
+                expected.central_directory_offset()
+            );
+        }
+    }
+
+    #[test]
+    fn locate_end_of_central_directory_failure_on_truncated_archive() {
+        assert_eq!(locate(&[0; 21]).0, ArkivStatus::Truncated);
+    }
+
+    #[test]
+    fn central_directory_iterator_lists_records() {
+        let archive = test_archive(TEST_ENTRIES);
+        let (_, eocd) = locate(&archive);
+
+        let start = eocd.central_directory_offset as usize;
+        let end = start + eocd.central_directory_size as usize;
+        let cd = &archive[start..end];
+
+        let mut iterator: ArkivZipCentralDirectoryIterator =
+            unsafe { mem::zeroed() };
+        let mut record: ArkivZipRawCentralDirectoryFileHeader =
+            unsafe { mem::zeroed() };
+        let mut names = vec!();
+
+        let status = unsafe {
+            arkiv_zip_central_directory_iterator_init(
+                &mut iterator,
+                cd.as_ptr(),
+                cd.len(),
+                eocd.nb_central_directory_records
+            )
+        };
+
+        assert_eq!(status, ArkivStatus::Ok);
+
+        loop {
+            let status = unsafe {
+                arkiv_zip_central_directory_iterator_next(
+                    &mut iterator,
+                    &mut record
+                )
+            };
+
+            if status != ArkivStatus::Ok {
+                assert_eq!(status, ArkivStatus::Done);
+                break;
+            }
+
+            names.push(unsafe { record.file_name.as_slice() }.unwrap());
+        }
+
+        let expected: Vec<_> =
+            TEST_ENTRIES.iter().map(|&(name, _)| name).collect();
+
+        assert_eq!(names, expected);
+        assert_eq!(iterator.bytes.size, 0);
+    }
+
+    #[test]
+    fn central_directory_iterator_failure_on_truncated_record() {
+        let archive = test_archive(TEST_ENTRIES);
+        let (_, eocd) = locate(&archive);
+
+        let start = eocd.central_directory_offset as usize;
+        let cd = &archive[start..start + 60];
+
+        let mut iterator: ArkivZipCentralDirectoryIterator =
+            unsafe { mem::zeroed() };
+        let mut record: ArkivZipRawCentralDirectoryFileHeader =
+            unsafe { mem::zeroed() };
+
+        let status = unsafe {
+            arkiv_zip_central_directory_iterator_init(
+                &mut iterator,
+                cd.as_ptr(),
+                cd.len(),
+                2
+            );
+
+            arkiv_zip_central_directory_iterator_next(
+                &mut iterator,
+                &mut record
+            )
+        };
+
+        //  "hello.txt" is 55 bytes long, "empty" is left with 5 bytes.
+        assert_eq!(status, ArkivStatus::Ok);
+
+        let status = unsafe {
+            arkiv_zip_central_directory_iterator_next(
+                &mut iterator,
+                &mut record
+            )
+        };
+
+        assert_eq!(status, ArkivStatus::Truncated);
+        assert_eq!(iterator.remaining, 1);
+        assert_eq!(iterator.bytes.size, 5);
+    }
+
+    #[test]
+    fn reader_locate_end_of_central_directory_as_locate() {
+        let archive = test_zip64_archive(TEST_ENTRIES);
+        let (_, expected) = locate(&archive);
+
+        let mut data = &archive[..];
+        let mut reader = slice_reader(&mut data);
+        let mut scratch = [0; 1024];
+        let mut eocd = unsafe { mem::zeroed() };
+
+        let status = unsafe {
+            arkiv_zip_reader_locate_end_of_central_directory(
+                &mut reader,
+                scratch.as_mut_ptr(),
+                scratch.len(),
+                &mut eocd
+            )
+        };
+
+        assert_eq!(status, ArkivStatus::Ok);
+        assert_eq!(eocd, expected);
+    }
+
+    #[test]
+    fn reader_read_records() {
+        let archive = test_archive(TEST_ENTRIES);
+        let (_, eocd) = locate(&archive);
+
+        let mut data = &archive[..];
+        let mut reader = slice_reader(&mut data);
+        let mut scratch = [0; 64];
+        let mut cdfh: ArkivZipRawCentralDirectoryFileHeader =
+            unsafe { mem::zeroed() };
+        let mut lfh: ArkivZipRawLocalFileHeader = unsafe { mem::zeroed() };
+
+        let status = unsafe {
+            arkiv_zip_reader_read_central_directory_file_header(
+                &mut reader,
+                eocd.central_directory_offset,
+                eocd.offset,
+                scratch.as_mut_ptr(),
+                scratch.len(),
+                &mut cdfh
+            )
+        };
+
+        assert_eq!(status, ArkivStatus::Ok);
+        assert_eq!(
+            unsafe { cdfh.file_name.as_slice() },
+            Some(&b"hello.txt"[..])
+        );
+
+        let status = unsafe {
+            arkiv_zip_reader_read_local_file_header(
+                &mut reader,
+                u64::from(cdfh.local_file_header_relative_offset),
+                eocd.offset,
+                scratch.as_mut_ptr(),
+                scratch.len(),
+                &mut lfh
+            )
+        };
+
+        assert_eq!(status, ArkivStatus::Ok);
+        assert_eq!(lfh.crc32, cdfh.crc32);
+
+        //  The 55 bytes of "hello.txt" do not fit.
+        let status = unsafe {
+            arkiv_zip_reader_read_central_directory_file_header(
+                &mut reader,
+                eocd.central_directory_offset,
+                eocd.offset,
+                scratch.as_mut_ptr(),
+                50,
+                &mut cdfh
+            )
+        };
+
+        assert_eq!(status, ArkivStatus::Truncated);
+    }
+
+    #[test]
+    fn reader_failure_on_null_pointers_and_callbacks() {
+        let archive = test_archive(TEST_ENTRIES);
+        let mut data = &archive[..];
+        let mut scratch = [0; 64];
+        let mut eocd = unsafe { mem::zeroed() };
+
+        let status = unsafe {
+            arkiv_zip_reader_locate_end_of_central_directory(
+                ptr::null_mut(),
+                scratch.as_mut_ptr(),
+                scratch.len(),
+                &mut eocd
+            )
+        };
+
+        assert_eq!(status, ArkivStatus::NullPointer);
+
+        let mut reader =
+            ArkivReader { read: None, ..slice_reader(&mut data) };
+
+        let status = unsafe {
+            arkiv_zip_reader_locate_end_of_central_directory(
+                &mut reader,
+                scratch.as_mut_ptr(),
+                scratch.len(),
+                &mut eocd
+            )
+        };
+
+        assert_eq!(status, ArkivStatus::NullPointer);
+        assert_eq!(reader.last_error, 0);
+
+        let mut reader =
+            ArkivReader { size: Some(fail), ..slice_reader(&mut data) };
+
+        let status = unsafe {
+            arkiv_zip_reader_locate_end_of_central_directory(
+                &mut reader,
+                scratch.as_mut_ptr(),
+                scratch.len(),
+                &mut eocd
+            )
+        };
+
+        assert_eq!(status, ArkivStatus::Io);
+        assert_eq!(reader.last_error, 42);
+
+        //  The code is reset by the next call.
+        reader.size = slice_reader(&mut data).size;
+
+        let status = unsafe {
+            arkiv_zip_reader_locate_end_of_central_directory(
+                &mut reader,
+                scratch.as_mut_ptr(),
+                scratch.len(),
+                &mut eocd
+            )
+        };
+
+        assert_eq!(status, ArkivStatus::Ok);
+        assert_eq!(reader.last_error, 0);
+    }
+}
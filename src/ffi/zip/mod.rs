@@ -0,0 +1,4 @@
+//! # C FFI of the ZIP format
+
+pub mod low_level;
+pub mod raw;
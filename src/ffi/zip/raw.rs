@@ -0,0 +1,638 @@
+//! # C FFI of the raw ZIP structures
+//!
+//! Each `arkiv_zip_raw_read_*` function reads a record at the start of the
+//! bytes, as the matching reader of `zip::raw` does, and returns:
+//!
+//! - `Truncated` if the bytes end before the record does, variable fields
+//!   included;
+//! - `Malformed` if the bytes cannot hold the record, for Data Descriptor
+//!   records whose size is not exactly one of theirs.
+//!
+//! As with the readers, the signature is not checked.
+
+use ffi::{ArkivBytes, ArkivStatus, read_record};
+
+use zip::raw::{
+    CentralDirectoryFileHeaderReader,
+    DataDescriptorReader,
+    EndOfCentralDirectoryReader,
+    LocalFileHeaderReader,
+    Zip64DataDescriptorReader,
+    Zip64EndOfCentralDirectoryLocatorReader,
+    Zip64EndOfCentralDirectoryReader
+};
+
+/// An End of Central Directory record.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ArkivZipRawEndOfCentralDirectory {
+    /// The signature.
+    pub signature: u32,
+    /// The number of this disk.
+    pub disk: u16,
+    /// The number of the disk where the Central Directory starts.
+    pub central_directory_disk: u16,
+    /// The number of Central Directory records on this disk.
+    pub nb_local_central_directory_records: u16,
+    /// The total number of Central Directory records.
+    pub nb_central_directory_records: u16,
+    /// The size of the Central Directory.
+    pub central_directory_size: u32,
+    /// The offset of the Central Directory.
+    pub central_directory_offset: u32,
+    /// The size of the comment.
+    pub comment_size: u16,
+    /// The comment.
+    pub comment: ArkivBytes,
+}
+
+impl ArkivZipRawEndOfCentralDirectory {
+    fn read(bytes: &[u8]) -> Result<Self, ArkivStatus> {
+        let eocd = EndOfCentralDirectoryReader::new(bytes)
+            .ok_or(ArkivStatus::Truncated)?;
+
+        Ok(ArkivZipRawEndOfCentralDirectory {
+            signature: eocd.signature(),
+            disk: eocd.disk(),
+            central_directory_disk: eocd.central_directory_disk(),
+            nb_local_central_directory_records:
+                eocd.nb_local_central_directory_records(),
+            nb_central_directory_records: eocd.nb_central_directory_records(),
+            central_directory_size: eocd.central_directory_size(),
+            central_directory_offset: eocd.central_directory_offset(),
+            comment_size: eocd.comment_size(),
+            comment: field(eocd.comment())?,
+        })
+    }
+}
+
+/// A ZIP64 End of Central Directory record.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ArkivZipRawZip64EndOfCentralDirectory {
+    /// The signature.
+    pub signature: u32,
+    /// The size of the record, excluding its first 12 bytes.
+    pub record_size: u64,
+    /// The version of the specification, and the compatibility, of the
+    /// encoder.
+    pub version_made_by: u16,
+    /// The minimum version of the specification needed to extract.
+    pub version_needed_to_extract: u16,
+    /// The number of this disk.
+    pub disk: u32,
+    /// The number of the disk where the Central Directory starts.
+    pub central_directory_disk: u32,
+    /// The number of Central Directory records on this disk.
+    pub nb_local_central_directory_records: u64,
+    /// The total number of Central Directory records.
+    pub nb_central_directory_records: u64,
+    /// The size of the Central Directory.
+    pub central_directory_size: u64,
+    /// The offset of the Central Directory.
+    pub central_directory_offset: u64,
+    /// The extensible data sector.
+    pub extensible_data: ArkivBytes,
+}
+
+impl ArkivZipRawZip64EndOfCentralDirectory {
+    fn read(bytes: &[u8]) -> Result<Self, ArkivStatus> {
+        let eocd = Zip64EndOfCentralDirectoryReader::new(bytes)
+            .ok_or(ArkivStatus::Truncated)?;
+
+        Ok(ArkivZipRawZip64EndOfCentralDirectory {
+            signature: eocd.signature(),
+            record_size: eocd.record_size(),
+            version_made_by: eocd.version_made_by(),
+            version_needed_to_extract: eocd.version_needed_to_extract(),
+            disk: eocd.disk(),
+            central_directory_disk: eocd.central_directory_disk(),
+            nb_local_central_directory_records:
+                eocd.nb_local_central_directory_records(),
+            nb_central_directory_records: eocd.nb_central_directory_records(),
+            central_directory_size: eocd.central_directory_size(),
+            central_directory_offset: eocd.central_directory_offset(),
+            extensible_data: field(eocd.extensible_data())?,
+        })
+    }
+}
+
+/// A ZIP64 End of Central Directory Locator record.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ArkivZipRawZip64EndOfCentralDirectoryLocator {
+    /// The signature.
+    pub signature: u32,
+    /// The number of the disk where the ZIP64 End of Central Directory record
+    /// starts.
+    pub zip64_end_of_central_directory_disk: u32,
+    /// The offset of the ZIP64 End of Central Directory record.
+    pub zip64_end_of_central_directory_offset: u64,
+    /// The total number of disks.
+    pub nb_disks: u32,
+}
+
+impl ArkivZipRawZip64EndOfCentralDirectoryLocator {
+    fn read(bytes: &[u8]) -> Result<Self, ArkivStatus> {
+        let locator = Zip64EndOfCentralDirectoryLocatorReader::new(bytes)
+            .ok_or(ArkivStatus::Truncated)?;
+
+        Ok(ArkivZipRawZip64EndOfCentralDirectoryLocator {
+            signature: locator.signature(),
+            zip64_end_of_central_directory_disk:
+                locator.zip64_end_of_central_directory_disk(),
+            zip64_end_of_central_directory_offset:
+                locator.zip64_end_of_central_directory_offset(),
+            nb_disks: locator.nb_disks(),
+        })
+    }
+}
+
+/// A Central Directory File Header record.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ArkivZipRawCentralDirectoryFileHeader {
+    /// The signature.
+    pub signature: u32,
+    /// The version of the specification, and the compatibility, of the
+    /// encoder.
+    pub version_made_by: u16,
+    /// The minimum version of the specification needed to extract.
+    pub version_needed_to_extract: u16,
+    /// The general purpose bit flag.
+    pub general_purpose_bit_flag: u16,
+    /// The compression method.
+    pub compression_method: u16,
+    /// The last modification time of the file, in MS-DOS format.
+    pub file_last_modification_time: u16,
+    /// The last modification date of the file, in MS-DOS format.
+    pub file_last_modification_date: u16,
+    /// The CRC-32 of the file.
+    pub crc32: u32,
+    /// The compressed size of the file.
+    pub compressed_size: u32,
+    /// The uncompressed size of the file.
+    pub uncompressed_size: u32,
+    /// The size of the file name.
+    pub file_name_size: u16,
+    /// The size of the extra field.
+    pub extra_field_size: u16,
+    /// The size of the file comment.
+    pub file_comment_size: u16,
+    /// The number of the disk where the file starts.
+    pub file_start_disk: u16,
+    /// The internal file attributes.
+    pub internal_file_attributes: u16,
+    /// The external file attributes.
+    pub external_file_attributes: u32,
+    /// The offset of the Local File Header record, relative to the start of
+    /// the disk where the file starts.
+    pub local_file_header_relative_offset: u32,
+    /// The file name.
+    pub file_name: ArkivBytes,
+    /// The extra field.
+    pub extra_field: ArkivBytes,
+    /// The file comment.
+    pub file_comment: ArkivBytes,
+}
+
+impl ArkivZipRawCentralDirectoryFileHeader {
+    pub(crate) fn read(bytes: &[u8]) -> Result<Self, ArkivStatus> {
+        let cdfh = CentralDirectoryFileHeaderReader::new(bytes)
+            .ok_or(ArkivStatus::Truncated)?;
+
+        Ok(ArkivZipRawCentralDirectoryFileHeader {
+            signature: cdfh.signature(),
+            version_made_by: cdfh.version_made_by(),
+            version_needed_to_extract: cdfh.version_needed_to_extract(),
+            general_purpose_bit_flag: cdfh.general_purpose_bit_flag(),
+            compression_method: cdfh.compression_method(),
+            file_last_modification_time: cdfh.file_last_modification_time(),
+            file_last_modification_date: cdfh.file_last_modification_date(),
+            crc32: cdfh.crc32(),
+            compressed_size: cdfh.compressed_size(),
+            uncompressed_size: cdfh.uncompressed_size(),
+            file_name_size: cdfh.file_name_size(),
+            extra_field_size: cdfh.extra_field_size(),
+            file_comment_size: cdfh.file_comment_size(),
+            file_start_disk: cdfh.file_start_disk(),
+            internal_file_attributes: cdfh.interal_file_attributes(),
+            external_file_attributes: cdfh.external_file_attributes(),
+            local_file_header_relative_offset:
+                cdfh.local_file_header_relative_offset(),
+            file_name: field(cdfh.file_name())?,
+            extra_field: field(cdfh.extra_field())?,
+            file_comment: field(cdfh.file_comment())?,
+        })
+    }
+}
+
+/// A Local File Header record.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ArkivZipRawLocalFileHeader {
+    /// The signature.
+    pub signature: u32,
+    /// The minimum version of the specification needed to extract.
+    pub version_needed_to_extract: u16,
+    /// The general purpose bit flag.
+    pub general_purpose_bit_flag: u16,
+    /// The compression method.
+    pub compression_method: u16,
+    /// The last modification time of the file, in MS-DOS format.
+    pub file_last_modification_time: u16,
+    /// The last modification date of the file, in MS-DOS format.
+    pub file_last_modification_date: u16,
+    /// The CRC-32 of the file.
+    pub crc32: u32,
+    /// The compressed size of the file.
+    pub compressed_size: u32,
+    /// The uncompressed size of the file.
+    pub uncompressed_size: u32,
+    /// The size of the file name.
+    pub file_name_size: u16,
+    /// The size of the extra field.
+    pub extra_field_size: u16,
+    /// The file name.
+    pub file_name: ArkivBytes,
+    /// The extra field.
+    pub extra_field: ArkivBytes,
+}
+
+impl ArkivZipRawLocalFileHeader {
+    pub(crate) fn read(bytes: &[u8]) -> Result<Self, ArkivStatus> {
+        let lfh = LocalFileHeaderReader::new(bytes)
+            .ok_or(ArkivStatus::Truncated)?;
+
+        Ok(ArkivZipRawLocalFileHeader {
+            signature: lfh.signature(),
+            version_needed_to_extract: lfh.version_needed_to_extract(),
+            general_purpose_bit_flag: lfh.general_purpose_bit_flag(),
+            compression_method: lfh.compression_method(),
+            file_last_modification_time: lfh.file_last_modification_time(),
+            file_last_modification_date: lfh.file_last_modification_date(),
+            crc32: lfh.crc32(),
+            compressed_size: lfh.compressed_size(),
+            uncompressed_size: lfh.uncompressed_size(),
+            file_name_size: lfh.file_name_size(),
+            extra_field_size: lfh.extra_field_size(),
+            file_name: field(lfh.file_name())?,
+            extra_field: field(lfh.extra_field())?,
+        })
+    }
+}
+
+/// A Data Descriptor record.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ArkivZipRawDataDescriptor {
+    /// Whether the record starts with the optional signature.
+    pub has_signature: bool,
+    /// The signature, or 0 if absent.
+    pub signature: u32,
+    /// The CRC-32 of the file.
+    pub crc32: u32,
+    /// The compressed size of the file.
+    pub compressed_size: u32,
+    /// The uncompressed size of the file.
+    pub uncompressed_size: u32,
+}
+
+impl ArkivZipRawDataDescriptor {
+    fn read(bytes: &[u8]) -> Result<Self, ArkivStatus> {
+        if bytes.len() < DataDescriptorReader::min_size() {
+            return Err(ArkivStatus::Truncated);
+        }
+
+        let dd = DataDescriptorReader::new(bytes)
+            .ok_or(ArkivStatus::Malformed)?;
+
+        Ok(ArkivZipRawDataDescriptor {
+            has_signature: dd.has_signature(),
+            signature: dd.signature().unwrap_or(0),
+            crc32: dd.crc32(),
+            compressed_size: dd.compressed_size(),
+            uncompressed_size: dd.uncompressed_size(),
+        })
+    }
+}
+
+/// A ZIP64 Data Descriptor record.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ArkivZipRawZip64DataDescriptor {
+    /// Whether the record starts with the optional signature.
+    pub has_signature: bool,
+    /// The signature, or 0 if absent.
+    pub signature: u32,
+    /// The CRC-32 of the file.
+    pub crc32: u32,
+    /// The compressed size of the file.
+    pub compressed_size: u64,
+    /// The uncompressed size of the file.
+    pub uncompressed_size: u64,
+}
+
+impl ArkivZipRawZip64DataDescriptor {
+    fn read(bytes: &[u8]) -> Result<Self, ArkivStatus> {
+        if bytes.len() < Zip64DataDescriptorReader::min_size() {
+            return Err(ArkivStatus::Truncated);
+        }
+
+        let dd = Zip64DataDescriptorReader::new(bytes)
+            .ok_or(ArkivStatus::Malformed)?;
+
+        Ok(ArkivZipRawZip64DataDescriptor {
+            has_signature: dd.has_signature(),
+            signature: dd.signature().unwrap_or(0),
+            crc32: dd.crc32(),
+            compressed_size: dd.compressed_size(),
+            uncompressed_size: dd.uncompressed_size(),
+        })
+    }
+}
+
+/// Reads the End of Central Directory record at the start of the `size` bytes
+/// at `data` into `record`.
+///
+/// # Safety
+///
+/// See the `ffi` module documentation.
+#[no_mangle]
+pub unsafe extern "C" fn arkiv_zip_raw_read_end_of_central_directory(
+    data: *const u8,
+    size: usize,
+    record: *mut ArkivZipRawEndOfCentralDirectory
+)
+    -> ArkivStatus
+{
+    read_record(data, size, record, ArkivZipRawEndOfCentralDirectory::read)
+}
+
+/// Reads the ZIP64 End of Central Directory record at the start of the `size`
+/// bytes at `data` into `record`.
+///
+/// # Safety
+///
+/// See the `ffi` module documentation.
+#[no_mangle]
+pub unsafe extern "C" fn arkiv_zip_raw_read_zip64_end_of_central_directory(
+    data: *const u8,
+    size: usize,
+    record: *mut ArkivZipRawZip64EndOfCentralDirectory
+)
+    -> ArkivStatus
+{
+    read_record(data, size, record, ArkivZipRawZip64EndOfCentralDirectory::read)
+}
+
+/// Reads the ZIP64 End of Central Directory Locator record at the start of the
+/// `size` bytes at `data` into `record`.
+///
+/// # Safety
+///
+/// See the `ffi` module documentation.
+#[no_mangle]
+pub unsafe extern "C" fn
+    arkiv_zip_raw_read_zip64_end_of_central_directory_locator(
+        data: *const u8,
+        size: usize,
+        record: *mut ArkivZipRawZip64EndOfCentralDirectoryLocator
+    )
+    -> ArkivStatus
+{
+    read_record(
+        data,
+        size,
+        record,
+        ArkivZipRawZip64EndOfCentralDirectoryLocator::read
+    )
+}
+
+/// Reads the Central Directory File Header record at the start of the `size`
+/// bytes at `data` into `record`.
+///
+/// # Safety
+///
+/// See the `ffi` module documentation.
+#[no_mangle]
+pub unsafe extern "C" fn arkiv_zip_raw_read_central_directory_file_header(
+    data: *const u8,
+    size: usize,
+    record: *mut ArkivZipRawCentralDirectoryFileHeader
+)
+    -> ArkivStatus
+{
+    read_record(data, size, record, ArkivZipRawCentralDirectoryFileHeader::read)
+}
+
+/// Reads the Local File Header record at the start of the `size` bytes at
+/// `data` into `record`.
+///
+/// # Safety
+///
+/// See the `ffi` module documentation.
+#[no_mangle]
+pub unsafe extern "C" fn arkiv_zip_raw_read_local_file_header(
+    data: *const u8,
+    size: usize,
+    record: *mut ArkivZipRawLocalFileHeader
+)
+    -> ArkivStatus
+{
+    read_record(data, size, record, ArkivZipRawLocalFileHeader::read)
+}
+
+/// Reads the Data Descriptor record spanning the `size` bytes at `data`, which
+/// must be either 12 or 16, into `record`.
+///
+/// # Safety
+///
+/// See the `ffi` module documentation.
+#[no_mangle]
+pub unsafe extern "C" fn arkiv_zip_raw_read_data_descriptor(
+    data: *const u8,
+    size: usize,
+    record: *mut ArkivZipRawDataDescriptor
+)
+    -> ArkivStatus
+{
+    read_record(data, size, record, ArkivZipRawDataDescriptor::read)
+}
+
+/// Reads the ZIP64 Data Descriptor record spanning the `size` bytes at `data`,
+/// which must be either 20 or 24, into `record`.
+///
+/// # Safety
+///
+/// See the `ffi` module documentation.
+#[no_mangle]
+pub unsafe extern "C" fn arkiv_zip_raw_read_zip64_data_descriptor(
+    data: *const u8,
+    size: usize,
+    record: *mut ArkivZipRawZip64DataDescriptor
+)
+    -> ArkivStatus
+{
+    read_record(data, size, record, ArkivZipRawZip64DataDescriptor::read)
+}
+
+/// Returns a view of the variable `field`, or `Truncated` if missing.
+fn field(field: Option<&[u8]>) -> Result<ArkivBytes, ArkivStatus> {
+    field.map(ArkivBytes::new).ok_or(ArkivStatus::Truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ptr;
+
+    use ffi::ArkivStatus;
+    use utils::test::test_archive;
+    use zip::raw::{DataDescriptorWriter, Zip64DataDescriptorWriter};
+
+    use super::*;
+
+    #[test]
+    fn read_end_of_central_directory_success() {
+        let archive = test_archive(&[(b"a.txt", b"1")]);
+        let eocd = &archive[archive.len() - 22..];
+        let mut record = unsafe { ::core::mem::zeroed() };
+
+        let status = unsafe {
+            arkiv_zip_raw_read_end_of_central_directory(
+                eocd.as_ptr(),
+                eocd.len(),
+                &mut record
+            )
+        };
+
+        let record: ArkivZipRawEndOfCentralDirectory = record;
+
+        assert_eq!(status, ArkivStatus::Ok);
+        assert_eq!(record.signature, 0x06054b50);
+        assert_eq!(record.nb_central_directory_records, 1);
+        assert_eq!(record.comment.size, 0);
+    }
+
+    #[test]
+    fn read_central_directory_file_header_success() {
+        let archive = test_archive(&[(b"a.txt", b"1")]);
+        let cd = archive.len() - 22 - 51;
+        let mut record: ArkivZipRawCentralDirectoryFileHeader =
+            unsafe { ::core::mem::zeroed() };
+
+        let status = unsafe {
+            arkiv_zip_raw_read_central_directory_file_header(
+                archive[cd..].as_ptr(),
+                51,
+                &mut record
+            )
+        };
+
+        assert_eq!(status, ArkivStatus::Ok);
+        assert_eq!(record.file_name_size, 5);
+
+        let name = unsafe { record.file_name.as_slice() };
+
+        assert_eq!(name, Some(&b"a.txt"[..]));
+    }
+
+    #[test]
+    fn read_central_directory_file_header_failure_on_truncated_name() {
+        let archive = test_archive(&[(b"a.txt", b"1")]);
+        let cd = archive.len() - 22 - 51;
+        let mut record: ArkivZipRawCentralDirectoryFileHeader =
+            unsafe { ::core::mem::zeroed() };
+
+        for size in &[0, 45, 48, 50] {
+            let status = unsafe {
+                arkiv_zip_raw_read_central_directory_file_header(
+                    archive[cd..].as_ptr(),
+                    *size,
+                    &mut record
+                )
+            };
+
+            assert_eq!(status, ArkivStatus::Truncated);
+        }
+
+        assert_eq!(record.signature, 0);
+    }
+
+    #[test]
+    fn read_local_file_header_failure_on_null_pointers() {
+        let archive = test_archive(&[(b"a.txt", b"1")]);
+        let mut record: ArkivZipRawLocalFileHeader =
+            unsafe { ::core::mem::zeroed() };
+
+        let status = unsafe {
+            arkiv_zip_raw_read_local_file_header(ptr::null(), 30, &mut record)
+        };
+
+        assert_eq!(status, ArkivStatus::NullPointer);
+
+        let status = unsafe {
+            arkiv_zip_raw_read_local_file_header(
+                archive.as_ptr(),
+                archive.len(),
+                ptr::null_mut()
+            )
+        };
+
+        assert_eq!(status, ArkivStatus::NullPointer);
+
+        let status = unsafe {
+            arkiv_zip_raw_read_local_file_header(ptr::null(), 0, &mut record)
+        };
+
+        assert_eq!(status, ArkivStatus::Truncated);
+    }
+
+    #[test]
+    fn read_data_descriptors_by_size() {
+        let mut v = [0; 24];
+        let mut dd: ArkivZipRawDataDescriptor =
+            unsafe { ::core::mem::zeroed() };
+        let mut zip64: ArkivZipRawZip64DataDescriptor =
+            unsafe { ::core::mem::zeroed() };
+
+        DataDescriptorWriter::new()
+            .with_uncompressed_size(9)
+            .write(&mut v)
+            .unwrap();
+
+        let statuses: Vec<ArkivStatus> = (10..18)
+            .map(|size| unsafe {
+                arkiv_zip_raw_read_data_descriptor(v.as_ptr(), size, &mut dd)
+            })
+            .collect();
+
+        assert_eq!(statuses, vec!(
+            ArkivStatus::Truncated,
+            ArkivStatus::Truncated,
+            ArkivStatus::Ok,
+            ArkivStatus::Malformed,
+            ArkivStatus::Malformed,
+            ArkivStatus::Malformed,
+            ArkivStatus::Ok,
+            ArkivStatus::Malformed,
+        ));
+        assert!(dd.has_signature);
+        assert_eq!(dd.uncompressed_size, 9);
+
+        Zip64DataDescriptorWriter::new()
+            .with_signature(false)
+            .with_compressed_size(1 << 40)
+            .write(&mut v)
+            .unwrap();
+
+        let status = unsafe {
+            arkiv_zip_raw_read_zip64_data_descriptor(v.as_ptr(), 20, &mut zip64)
+        };
+
+        assert_eq!(status, ArkivStatus::Ok);
+        assert!(!zip64.has_signature);
+        assert_eq!(zip64.signature, 0);
+        assert_eq!(zip64.compressed_size, 1 << 40);
+    }
+}
@@ -1,3 +1,4 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![deny(missing_docs)]
 
 //! # Arkiv
@@ -12,11 +13,21 @@
 //!  - one high-level uniform module, allowing reading/writing archive formats generically
 //!  - one low-level module per format, allowing access to all the specifities of the format
 //!  - one api module, containing the user-definable traits to operate with the library
+//!  - one checksum module, containing the checksum algorithms shared by formats
 //!  - one ffi module, containing the C FFI modules mirroring each of the above modules
 //! 
 //! The library also contains a utils module, used to share commonly used code across components.
 //! 
 //! 
+//! # Cargo features
+//! 
+//! The library is `#[no_std]` by default; the following features are available:
+//! 
+//!  - `alloc`: implementations of the api traits for owned types, such as `Writer` for `Vec<u8>`.
+//!  - `std`: implies `alloc`, adapters over `std` I/O, such as `SeekReader`, and `Writer` for `File`.
+//!  - `mmap`: implies `std`, the memory-mapping `MmapReader`, on 64-bits Unix platforms.
+//! 
+//! 
 //! # Covered formats
 //! 
 //! Complete:
@@ -34,7 +45,16 @@
 //!  - tar
 //!  - zip
 
+#[cfg(any(feature = "std", test))]
+extern crate core;
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 pub mod api;
+pub mod archive;
+pub mod checksum;
+pub mod ffi;
 pub mod zip;
 
 mod utils;
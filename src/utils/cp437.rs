@@ -0,0 +1,48 @@
+//! # Code Page 437
+//!
+//! The original IBM PC character set, which is the default encoding of names
+//! and comments in a ZIP archive.
+
+/// The characters corresponding to the upper half (0x80 to 0xff) of the code
+/// page; the lower half matches ASCII.
+const UPPER_HALF: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// Returns the character encoded by `byte`.
+pub fn decode(byte: u8) -> char {
+    if byte < 0x80 {
+        byte as char
+    } else {
+        UPPER_HALF
+            .get((byte & 0x7f) as usize)
+            .cloned()
+            .unwrap_or('\u{fffd}')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+
+    #[test]
+    fn decode_ascii() {
+        for byte in 0..0x80u8 {
+            assert_eq!(decode(byte) as u32, byte as u32);
+        }
+    }
+
+    #[test]
+    fn decode_upper_half() {
+        assert_eq!(decode(0x80), 'Ç');
+        assert_eq!(decode(0xe1), 'ß');
+        assert_eq!(decode(0xff), '\u{a0}');
+    }
+}
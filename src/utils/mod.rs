@@ -1,10 +1,13 @@
 //! # Utility module
 
+pub mod cp437;
+pub mod read;
+
 #[cfg(test)]
 pub mod test;
 
-use std::ops::Range;
-use std::slice::from_raw_parts;
+use core::ops::Range;
+use core::slice::from_raw_parts;
 
 /// Constant used when unwrapping an empty `Option<u16>`
 pub const DEAD: u16 = 0xdead;
@@ -12,6 +15,9 @@ pub const DEAD: u16 = 0xdead;
 /// Constant used when unwrapping an empty `Option<u32>`
 pub const DEADBEEF: u32 = 0xdeadbeef;
 
+/// Constant used when unwrapping an empty `Option<u64>`
+pub const DEADBEEFDEADBEEF: u64 = 0xdeadbeefdeadbeef;
+
 /// A slice of bytes.
 ///
 /// It is used in preference to a raw `&[u8]` to avoid accidentally calling the
@@ -23,7 +29,7 @@ pub struct Slice<'a> {
 
 impl<'a> Slice<'a> {
     pub fn new(data: &'a [u8]) -> Slice<'a> {
-        Slice { data: data }
+        Slice { data }
     }
 
     /// Returns the underlying slice
@@ -91,14 +97,76 @@ pub trait LeFieldReader<'a> {
             .unwrap_or(DEADBEEF)
     }
 
+    /// Interprets the 8 bytes as u64 (little-endian).
+    fn read_u64(&self, range: Range<usize>) -> u64 {
+        debug_assert!(range.len() == 8);
+        debug_assert!(range.end <= Self::min_size());
+
+        self.get_slice()
+            .slice(range)
+            .and_then(read_u64_le)
+            .unwrap_or(DEADBEEFDEADBEEF)
+    }
+
     /// Optional binary field of a given length at a given index.
     fn read_field(&self, index: usize, length: usize) -> Option<&'a [u8]> {
         self.get_slice().slice(index..(index + length)).map(|s| s.raw())
     }
 }
 
+/// Helper to write little-endian fields, one after the other.
+///
+/// Fields which do not fit in the buffer are silently dropped: the size of
+/// the buffer is expected to be checked beforehand.
+#[derive(Debug, Eq, PartialEq)]
+pub struct LeFieldWriter<'a> {
+    buffer: &'a mut [u8],
+    position: usize,
+}
+
+impl<'a> LeFieldWriter<'a> {
+    pub fn new(buffer: &'a mut [u8]) -> LeFieldWriter<'a> {
+        LeFieldWriter { buffer, position: 0 }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn position(&self) -> usize { self.position }
+
+    /// Writes the u16 (little-endian).
+    pub fn write_u16(&mut self, value: u16) { self.write(&value.to_le_bytes()) }
+
+    /// Writes the u32 (little-endian).
+    pub fn write_u32(&mut self, value: u32) { self.write(&value.to_le_bytes()) }
+
+    /// Writes the u64 (little-endian).
+    pub fn write_u64(&mut self, value: u64) { self.write(&value.to_le_bytes()) }
+
+    /// Writes the bytes, as is.
+    pub fn write(&mut self, bytes: &[u8]) {
+        let end = self.position.saturating_add(bytes.len());
+
+        debug_assert!(end <= self.buffer.len());
+
+        if let Some(target) = self.buffer.get_mut(self.position..end) {
+            target.copy_from_slice(bytes);
+            self.position = end;
+        }
+    }
+}
+
+/// Returns the 64-bits FNV-1a hash of the bytes.
+///
+/// The hash is not resistant to collisions crafted on purpose.
+pub fn hash<I>(bytes: I) -> u64
+    where I: IntoIterator<Item = u8>
+{
+    bytes.into_iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
 /// Returns the elements of the `hay` slice that overlap with `range`.
-pub fn intersect_slice<'a>(hay: &'a [u8], range: Range<usize>) -> &'a [u8] {
+pub fn intersect_slice(hay: &[u8], range: Range<usize>) -> &[u8] {
     if range.start <= range.end && range.start <= hay.len() {
         take(skip(hay, range.start), range.end.wrapping_sub(range.start))
     } else {
@@ -139,6 +207,7 @@ pub fn read_u16_le<'a>(slice: Slice<'a>) -> Option<u16> {
 /// encoding, or `None` if the slice is too short.
 ///
 /// Does not assume that the data is suitably aligned.
+#[allow(clippy::identity_op)]
 pub fn read_u32_le<'a>(slice: Slice<'a>) -> Option<u32> {
     match (slice.get(0), slice.get(1), slice.get(2), slice.get(3)) {
         (Some(&b0), Some(&b1), Some(&b2), Some(&b3)) => {
@@ -151,9 +220,20 @@ pub fn read_u32_le<'a>(slice: Slice<'a>) -> Option<u32> {
     }
 }
 
+/// Returns a u64 interpreting the first 8 bytes of the slice in little-endian
+/// encoding, or `None` if the slice is too short.
+///
+/// Does not assume that the data is suitably aligned.
+pub fn read_u64_le(slice: Slice) -> Option<u64> {
+    let low = read_u32_le(slice)?;
+    let high = slice.slice(4..8).and_then(read_u32_le)?;
+
+    Some((low as u64) + ((high as u64) << 32))
+}
+
 /// Returns the elements of the slice comprised in the range, or `None` if
 /// the range is out of bounds or ill-formed.
-pub fn slice<'a>(original: &'a [u8], range: Range<usize>) -> Option<&'a [u8]> {
+pub fn slice(original: &[u8], range: Range<usize>) -> Option<&[u8]> {
     if range.start <= range.end && range.end <= original.len() {
         Some(
             take(
@@ -167,7 +247,7 @@ pub fn slice<'a>(original: &'a [u8], range: Range<usize>) -> Option<&'a [u8]> {
 }
 
 /// Returns a copy of the original slice, minus the `n` first elements.
-pub fn skip<'a>(original: &'a [u8], n: usize) -> &'a [u8] {
+pub fn skip(original: &[u8], n: usize) -> &[u8] {
     if n == 0 { return original; }
 
     let mut iter = original.iter();
@@ -176,8 +256,8 @@ pub fn skip<'a>(original: &'a [u8], n: usize) -> &'a [u8] {
 }
 
 /// Returns a copy of the original slice, minus any element after the `n`-th.
-pub fn take<'a>(original: &'a [u8], n: usize) -> &'a [u8] {
-    use std::cmp::min;
+pub fn take(original: &[u8], n: usize) -> &[u8] {
+    use core::cmp::min;
 
     // TODO: replace with safe code
     //
@@ -0,0 +1,150 @@
+//! # Reading through a `Reader` which may return short slices
+//!
+//! A chunked reader, such as a ring buffer or a series of memory-mapped
+//! windows, may return fewer bytes than requested even though more follow.
+//! These helpers fetch the remainder, so that records straddling chunk
+//! boundaries are read whole.
+
+use core::cmp::min;
+use core::ops::Range;
+
+use api::Reader;
+
+/// The size of the intermediate buffer used by `read_exact`, for readers which
+/// copy.
+const CHUNK_SIZE: usize = 256;
+
+/// Copies the bytes of the archive starting at `offset` into `buffer`, over as
+/// many calls to `Reader::get` as needed, returning the number of bytes copied.
+///
+/// Fewer bytes than the length of `buffer` are only copied at the end of the
+/// archive.
+pub fn read_exact<R>(reader: &R, offset: u64, buffer: &mut [u8])
+    -> Result<usize, R::Error>
+    where R: Reader + ?Sized
+{
+    let mut scratch = [0; CHUNK_SIZE];
+    let mut filled = 0;
+
+    while filled < buffer.len() {
+        let start = offset.saturating_add(filled as u64);
+        let end = start.saturating_add((buffer.len() - filled) as u64);
+
+        let chunk = reader.get(start..end, &mut scratch)?;
+        let length = min(chunk.len(), buffer.len() - filled);
+
+        if length == 0 {
+            break;
+        }
+
+        if let (Some(target), Some(source)) =
+            (buffer.get_mut(filled..filled + length), chunk.get(..length))
+        {
+            target.copy_from_slice(source);
+        }
+
+        filled += length;
+    }
+
+    Ok(filled)
+}
+
+/// Returns the bytes corresponding to the intersection of the `range` and the
+/// archive, as a single slice.
+///
+/// The slice is borrowed from the reader if it hands the whole intersection at
+/// once, otherwise it is assembled into the `scratch` buffer, and is at most as
+/// large.
+pub fn read_contiguous<'a, R>(
+    reader: &'a R,
+    range: Range<u64>,
+    scratch: &'a mut [u8]
+)
+    -> Result<&'a [u8], R::Error>
+    where R: Reader + ?Sized
+{
+    let wanted = range.end.saturating_sub(range.start);
+
+    //  An empty scratch buffer only lets a reader borrow.
+    let borrowed = reader.get(range.clone(), &mut [])?;
+
+    if borrowed.len() as u64 == wanted {
+        return Ok(borrowed);
+    }
+
+    let available = reader.size()?.saturating_sub(range.start);
+
+    if borrowed.len() as u64 == min(wanted, available) {
+        return Ok(borrowed);
+    }
+
+    let length = min(min(wanted, available), scratch.len() as u64) as usize;
+    let buffer = scratch.get_mut(..length).unwrap_or(&mut []);
+
+    let filled = read_exact(reader, range.start, buffer)?;
+
+    Ok(buffer.get(..filled).unwrap_or(b""))
+}
+
+#[cfg(test)]
+mod tests {
+    use utils::test::{ChunkedReader, CopyingReader, FailingReader, TestError};
+    use super::{read_contiguous, read_exact};
+
+    const DATA: &[u8] = b"Hello, World!";
+
+    #[test]
+    fn read_exact_across_chunks() {
+        let reader = ChunkedReader { data: DATA, chunk: 3 };
+        let mut buffer = [0; 8];
+
+        assert_eq!(read_exact(&reader, 2, &mut buffer), Ok(8));
+        assert_eq!(&buffer, b"llo, Wor");
+    }
+
+    #[test]
+    fn read_exact_short_at_end_of_archive() {
+        let reader = CopyingReader { data: DATA };
+        let mut buffer = [0; 8];
+
+        assert_eq!(read_exact(&reader, 10, &mut buffer), Ok(3));
+        assert_eq!(&buffer[..3], b"ld!");
+        assert_eq!(read_exact(&reader, 20, &mut buffer), Ok(0));
+    }
+
+    #[test]
+    fn read_contiguous_borrowed_from_slice() {
+        let result = read_contiguous(DATA, 7..20, &mut []).unwrap();
+
+        assert_eq!(result, b"World!");
+        assert_eq!(result.as_ptr(), DATA[7..].as_ptr());
+    }
+
+    #[test]
+    fn read_contiguous_assembled_across_chunks() {
+        let reader = ChunkedReader { data: DATA, chunk: 4 };
+        let mut scratch = [0; 16];
+
+        let result = read_contiguous(&reader, 2..11, &mut scratch);
+        assert_eq!(result, Ok(&DATA[2..11]));
+
+        let result = read_contiguous(&reader, 7..20, &mut scratch);
+        assert_eq!(result, Ok(&DATA[7..]));
+    }
+
+    #[test]
+    fn read_contiguous_truncated_to_scratch() {
+        let reader = CopyingReader { data: DATA };
+        let mut scratch = [0; 5];
+
+        let result = read_contiguous(&reader, 0..13, &mut scratch);
+        assert_eq!(result, Ok(&DATA[..5]));
+    }
+
+    #[test]
+    fn read_contiguous_io_on_failing_reader() {
+        let reader = FailingReader::new(DATA, 0);
+
+        assert_eq!(read_contiguous(&reader, 0..13, &mut []), Err(TestError));
+    }
+}
@@ -1,11 +1,460 @@
 //! # Test utilities
 
-use std;
+use std::cell::Cell;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+use std::ops::Range;
+
+use api::{AsyncReader, Reader, Writer};
+use checksum::Crc32;
+
+/// The error of a `TestWriter`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TestError;
+
+impl From<Infallible> for TestError {
+    fn from(error: Infallible) -> TestError { match error {} }
+}
+
+/// A `Writer` appending to a vector, failing beyond `limit` bytes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TestWriter {
+    pub data: Vec<u8>,
+    pub limit: Option<usize>,
+}
+
+impl Writer for TestWriter {
+    type Error = TestError;
+
+    fn size(&self) -> Result<u64, TestError> { Ok(self.data.len() as u64) }
+
+    fn write_at(&mut self, offset: u64, data: &[u8])
+        -> Result<(), TestError>
+    {
+        let offset = offset as usize;
+
+        match self.data.get_mut(offset..offset + data.len()) {
+            Some(target) => {
+                target.copy_from_slice(data);
+                Ok(())
+            },
+            None => Err(TestError),
+        }
+    }
+
+    fn append(&mut self, data: &[u8]) -> Result<(), TestError> {
+        match self.limit {
+            Some(limit) if self.data.len() + data.len() > limit =>
+                Err(TestError),
+            _ => {
+                self.data.extend_from_slice(data);
+                Ok(())
+            },
+        }
+    }
+}
+
+/// A `Writer` counting the bytes appended, without storing them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CountingWriter {
+    pub size: u64,
+}
+
+impl Writer for CountingWriter {
+    type Error = TestError;
+
+    fn size(&self) -> Result<u64, TestError> { Ok(self.size) }
+
+    fn write_at(&mut self, offset: u64, data: &[u8])
+        -> Result<(), TestError>
+    {
+        match offset.checked_add(data.len() as u64) {
+            Some(end) if end <= self.size => Ok(()),
+            _ => Err(TestError),
+        }
+    }
+
+    fn append(&mut self, data: &[u8]) -> Result<(), TestError> {
+        self.size += data.len() as u64;
+        Ok(())
+    }
+}
+
+/// A `Reader` over a slice, split into chunks of `chunk` bytes, borrowing from
+/// at most one chunk at a time, as would a reader over memory-mapped windows.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChunkedReader<'a> {
+    pub data: &'a [u8],
+    pub chunk: u64,
+}
+
+impl<'a> Reader for ChunkedReader<'a> {
+    type Error = Infallible;
+
+    fn size(&self) -> Result<u64, Infallible> { Ok(self.data.len() as u64) }
+
+    fn get<'b>(&'b self, range: Range<u64>, _: &'b mut [u8])
+        -> Result<&'b [u8], Infallible>
+    {
+        let boundary = (range.start / self.chunk + 1) * self.chunk;
+        let end = range.end.min(boundary);
+
+        Reader::get(self.data, range.start..end, &mut [])
+    }
+}
+
+/// A `Reader` over a slice, copying into the scratch buffer rather than
+/// borrowing from the slice.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CopyingReader<'a> {
+    pub data: &'a [u8],
+}
+
+impl<'a> Reader for CopyingReader<'a> {
+    type Error = Infallible;
+
+    fn size(&self) -> Result<u64, Infallible> { Ok(self.data.len() as u64) }
+
+    fn get<'b>(&'b self, range: Range<u64>, scratch: &'b mut [u8])
+        -> Result<&'b [u8], Infallible>
+    {
+        let data = Reader::get(self.data, range, &mut [])?;
+        let length = data.len().min(scratch.len());
+
+        scratch[..length].copy_from_slice(&data[..length]);
+
+        Ok(&scratch[..length])
+    }
+}
+
+/// A `Reader` over `size` zeroes, such as the padding before an archive
+/// written at a large offset, borrowing from a static chunk of zeroes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ZeroReader {
+    pub size: u64,
+}
+
+impl Reader for ZeroReader {
+    type Error = Infallible;
+
+    fn size(&self) -> Result<u64, Infallible> { Ok(self.size) }
+
+    fn get<'b>(&'b self, range: Range<u64>, _: &'b mut [u8])
+        -> Result<&'b [u8], Infallible>
+    {
+        static ZEROES: [u8; 512] = [0; 512];
+
+        let end = range.end.min(self.size);
+        let length = end.saturating_sub(range.start).min(512) as usize;
+
+        Ok(&ZEROES[..length])
+    }
+}
+
+/// A `Reader` over a slice, failing on any access once `limit` accesses
+/// succeeded.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FailingReader<'a> {
+    pub data: &'a [u8],
+    pub limit: usize,
+    pub accesses: Cell<usize>,
+}
+
+impl<'a> FailingReader<'a> {
+    pub fn new(data: &'a [u8], limit: usize) -> FailingReader<'a> {
+        FailingReader { data, limit, accesses: Cell::new(0) }
+    }
+
+    fn access(&self) -> Result<(), TestError> {
+        let accesses = self.accesses.get();
+        self.accesses.set(accesses + 1);
+
+        if accesses < self.limit { Ok(()) } else { Err(TestError) }
+    }
+}
+
+impl<'a> Reader for FailingReader<'a> {
+    type Error = TestError;
+
+    fn size(&self) -> Result<u64, TestError> {
+        self.access()?;
+
+        Ok(Reader::size(self.data)?)
+    }
+
+    fn get<'b>(&'b self, range: Range<u64>, scratch: &'b mut [u8])
+        -> Result<&'b [u8], TestError>
+    {
+        self.access()?;
+
+        Ok(Reader::get(self.data, range, scratch)?)
+    }
+}
+
+/// An `AsyncReader` standing in for a remote server: each request is pending
+/// once, waking the task, then serves at most `chunk` bytes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TestServer<'a> {
+    pub data: &'a [u8],
+    pub chunk: usize,
+    pub requests: usize,
+    ready: bool,
+}
+
+impl<'a> TestServer<'a> {
+    pub fn new(data: &'a [u8], chunk: usize) -> TestServer<'a> {
+        TestServer { data, chunk, requests: 0, ready: false }
+    }
+
+    fn poll_ready(&mut self, context: &mut Context) -> Poll<()> {
+        if self.ready {
+            self.ready = false;
+            return Poll::Ready(());
+        }
+
+        self.ready = true;
+        self.requests += 1;
+        context.waker().wake_by_ref();
+
+        Poll::Pending
+    }
+}
+
+impl<'a> AsyncReader for TestServer<'a> {
+    type Error = Infallible;
+
+    fn poll_size(&mut self, context: &mut Context)
+        -> Poll<Result<u64, Infallible>>
+    {
+        self.poll_ready(context).map(|_| Ok(self.data.len() as u64))
+    }
+
+    fn poll_read(
+        &mut self,
+        context: &mut Context,
+        range: Range<u64>,
+        buffer: &mut [u8]
+    )
+        -> Poll<Result<usize, Infallible>>
+    {
+        if self.poll_ready(context).is_pending() {
+            return Poll::Pending;
+        }
+
+        let length = buffer.len().min(self.chunk);
+
+        let mut data = self.data;
+        data.poll_read(context, range, &mut buffer[..length])
+    }
+}
+
+/// Runs the `future` to completion on the current thread, busy-polling it.
+///
+/// Panics if the future is still pending after many polls, as it is then
+/// likely never to complete.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+
+    for _ in 0..1_000_000 {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+
+    panic!("future still pending");
+}
+
+/// The `(name, content)` entries of the archives shared by the tests: a file,
+/// an empty file, a directory, and a file within it.
+pub const TEST_ENTRIES: &[(&[u8], &[u8])] = &[
+    (b"hello.txt", b"Hello, World!"),
+    (b"empty", b""),
+    (b"dir/", b""),
+    (b"dir/nested.txt", b"Nested"),
+];
+
+/// Returns a well-formed ZIP archive storing (without compression) each of the
+/// `(name, content)` entries, in order.
+pub fn test_archive(entries: &[(&[u8], &[u8])]) -> Vec<u8> {
+    fn push_u16(v: &mut Vec<u8>, n: usize) {
+        v.extend_from_slice(&(n as u16).to_le_bytes());
+    }
+
+    fn push_u32(v: &mut Vec<u8>, n: usize) {
+        push_u16(v, n & 0xffff);
+        push_u16(v, n >> 16);
+    }
+
+    let mut archive = vec!();
+    let mut offsets = vec!();
+
+    for &(name, content) in entries {
+        offsets.push(archive.len());
+
+        archive.extend_from_slice(b"PK\x03\x04");
+        push_u16(&mut archive, 10);             // version needed
+        push_u16(&mut archive, 0);              // flags
+        push_u16(&mut archive, 0);              // method
+        push_u16(&mut archive, 0);              // time
+        push_u16(&mut archive, 0x21);           // date: 1980-01-01
+        push_u32(&mut archive, Crc32::checksum(content) as usize);
+        push_u32(&mut archive, content.len());  // compressed size
+        push_u32(&mut archive, content.len());  // uncompressed size
+        push_u16(&mut archive, name.len());
+        push_u16(&mut archive, 0);              // extra field size
+        archive.extend_from_slice(name);
+        archive.extend_from_slice(content);
+    }
+
+    let cd_offset = archive.len();
+
+    for (&(name, content), &offset) in entries.iter().zip(offsets.iter()) {
+        archive.extend_from_slice(b"PK\x01\x02");
+        push_u16(&mut archive, 10);             // version made by
+        push_u16(&mut archive, 10);             // version needed
+        push_u16(&mut archive, 0);              // flags
+        push_u16(&mut archive, 0);              // method
+        push_u16(&mut archive, 0);              // time
+        push_u16(&mut archive, 0x21);           // date: 1980-01-01
+        push_u32(&mut archive, Crc32::checksum(content) as usize);
+        push_u32(&mut archive, content.len());  // compressed size
+        push_u32(&mut archive, content.len());  // uncompressed size
+        push_u16(&mut archive, name.len());
+        push_u16(&mut archive, 0);              // extra field size
+        push_u16(&mut archive, 0);              // file comment size
+        push_u16(&mut archive, 0);              // disk
+        push_u16(&mut archive, 0);              // internal attributes
+        push_u32(&mut archive, 0);              // external attributes
+        push_u32(&mut archive, offset);
+        archive.extend_from_slice(name);
+    }
+
+    let cd_size = archive.len() - cd_offset;
+
+    archive.extend_from_slice(b"PK\x05\x06");
+    push_u16(&mut archive, 0);                  // disk
+    push_u16(&mut archive, 0);                  // central directory disk
+    push_u16(&mut archive, entries.len());
+    push_u16(&mut archive, entries.len());
+    push_u32(&mut archive, cd_size);
+    push_u32(&mut archive, cd_offset);
+    push_u16(&mut archive, 0);                  // comment size
+
+    archive
+}
+
+/// Returns a well-formed ZIP64 archive storing (without compression) each of
+/// the `(name, content)` entries, in order.
+///
+/// All sizes, offsets and counts are stored in ZIP64 extra fields and records,
+/// the regular fields being set to their maximum value.
+pub fn test_zip64_archive(entries: &[(&[u8], &[u8])]) -> Vec<u8> {
+    fn push_u16(v: &mut Vec<u8>, n: u16) {
+        v.extend_from_slice(&n.to_le_bytes());
+    }
+
+    fn push_u32(v: &mut Vec<u8>, n: u32) {
+        v.extend_from_slice(&n.to_le_bytes());
+    }
+
+    fn push_u64(v: &mut Vec<u8>, n: u64) {
+        v.extend_from_slice(&n.to_le_bytes());
+    }
+
+
+    let mut archive = vec!();
+    let mut offsets = vec!();
+
+    for &(name, content) in entries {
+        offsets.push(archive.len() as u64);
+
+        archive.extend_from_slice(b"PK\x03\x04");
+        push_u16(&mut archive, 45);             // version needed
+        push_u16(&mut archive, 0);              // flags
+        push_u16(&mut archive, 0);              // method
+        push_u16(&mut archive, 0);              // time
+        push_u16(&mut archive, 0x21);           // date: 1980-01-01
+        push_u32(&mut archive, Crc32::checksum(content));
+        push_u32(&mut archive, 0xffffffff);     // compressed size
+        push_u32(&mut archive, 0xffffffff);     // uncompressed size
+        push_u16(&mut archive, name.len() as u16);
+        push_u16(&mut archive, 20);             // extra field size
+        archive.extend_from_slice(name);
+        push_u16(&mut archive, 1);              // ZIP64 extra field
+        push_u16(&mut archive, 16);
+        push_u64(&mut archive, content.len() as u64);
+        push_u64(&mut archive, content.len() as u64);
+        archive.extend_from_slice(content);
+    }
+
+    let cd_offset = archive.len() as u64;
+
+    for (&(name, content), &offset) in entries.iter().zip(offsets.iter()) {
+        archive.extend_from_slice(b"PK\x01\x02");
+        push_u16(&mut archive, 45);             // version made by
+        push_u16(&mut archive, 45);             // version needed
+        push_u16(&mut archive, 0);              // flags
+        push_u16(&mut archive, 0);              // method
+        push_u16(&mut archive, 0);              // time
+        push_u16(&mut archive, 0x21);           // date: 1980-01-01
+        push_u32(&mut archive, Crc32::checksum(content));
+        push_u32(&mut archive, 0xffffffff);     // compressed size
+        push_u32(&mut archive, 0xffffffff);     // uncompressed size
+        push_u16(&mut archive, name.len() as u16);
+        push_u16(&mut archive, 28);             // extra field size
+        push_u16(&mut archive, 0);              // file comment size
+        push_u16(&mut archive, 0);              // disk
+        push_u16(&mut archive, 0);              // internal attributes
+        push_u32(&mut archive, 0);              // external attributes
+        push_u32(&mut archive, 0xffffffff);     // offset
+        archive.extend_from_slice(name);
+        push_u16(&mut archive, 1);              // ZIP64 extra field
+        push_u16(&mut archive, 24);
+        push_u64(&mut archive, content.len() as u64);
+        push_u64(&mut archive, content.len() as u64);
+        push_u64(&mut archive, offset);
+    }
+
+    let cd_size = archive.len() as u64 - cd_offset;
+    let zip64_offset = archive.len() as u64;
+
+    archive.extend_from_slice(b"PK\x06\x06");
+    push_u64(&mut archive, 44);                 // size of the record
+    push_u16(&mut archive, 45);                 // version made by
+    push_u16(&mut archive, 45);                 // version needed
+    push_u32(&mut archive, 0);                  // disk
+    push_u32(&mut archive, 0);                  // central directory disk
+    push_u64(&mut archive, entries.len() as u64);
+    push_u64(&mut archive, entries.len() as u64);
+    push_u64(&mut archive, cd_size);
+    push_u64(&mut archive, cd_offset);
+
+    archive.extend_from_slice(b"PK\x06\x07");
+    push_u32(&mut archive, 0);                  // ZIP64 EOCD disk
+    push_u64(&mut archive, zip64_offset);
+    push_u32(&mut archive, 1);                  // number of disks
+
+    archive.extend_from_slice(b"PK\x05\x06");
+    push_u16(&mut archive, 0);                  // disk
+    push_u16(&mut archive, 0);                  // central directory disk
+    push_u16(&mut archive, 0xffff);
+    push_u16(&mut archive, 0xffff);
+    push_u32(&mut archive, 0xffffffff);         // central directory size
+    push_u32(&mut archive, 0xffffffff);         // central directory offset
+    push_u16(&mut archive, 0);                  // comment size
+
+    archive
+}
 
 /// Invokes the function 65536 times, once for each possible u16 value after
 /// writing it in little-endian at [index, index+1] in the buffer.
+#[allow(clippy::identity_op)]
 pub fn test_all_u16_at<F>(buffer: &mut [u8], index: usize, f: F)
-    where F: Fn(&[u8], u16) -> ()
+    where F: Fn(&[u8], u16)
 {
     for data in 0..65536u32 {
         let data = data as u16;
@@ -19,11 +468,12 @@ pub fn test_all_u16_at<F>(buffer: &mut [u8], index: usize, f: F)
 /// Invokes the function 4*65536 times, once for each u32 value close to 0,
 /// close to the maximum, and with a scattering of values in the middle, after
 /// writing it in little-endian at [index, index+3] in the buffer.
+#[allow(clippy::identity_op)]
 pub fn test_some_u32_at<F>(buffer: &mut [u8], index: usize, f: F)
-    where F: Fn(&[u8], u32) -> ()
+    where F: Fn(&[u8], u32)
 {
     fn test<F>(buffer: &mut [u8], data: u32, index: usize, f: &F)
-        where F: Fn(&[u8], u32) -> ()
+        where F: Fn(&[u8], u32)
     {
         buffer[index + 3] = (data >> 24) as u8;
         buffer[index + 2] = (data >> 16) as u8;
@@ -42,10 +492,10 @@ pub fn test_some_u32_at<F>(buffer: &mut [u8], index: usize, f: F)
     }
 
     for data in 0..65536 {
-        test(buffer, std::u32::MAX - 65536 - data * 251, index, &f);
+        test(buffer, u32::MAX - 65536 - data * 251, index, &f);
     }
 
     for data in 0..65536 {
-        test(buffer, std::u32::MAX - data, index, &f);
+        test(buffer, u32::MAX - data, index, &f);
     }
 }
@@ -0,0 +1,321 @@
+//! # High-level access to a ZIP archive
+//!
+//! A `ZipArchive` locates the End of Central Directory record, and the ZIP64
+//! records if any, then lists the entries of the Central Directory and locates
+//! their data, all offsets being resolved to 64 bits, as `ZipEntry`.
+//!
+//! The archive may be preceded by arbitrary bytes, such as the stub of a
+//! self-extracting archive, to which its offsets are relative; the size of this
+//! prefix is inferred from where the Central Directory ends.
+
+use core::ops::Range;
+
+use api::{ReadError, Reader, Sink, SubReader};
+use archive::Archive;
+use utils::read::read_exact;
+
+use zip::ZipEntry;
+use zip::low_level::{CentralDirectory, EndOfCentralDirectory, data_range};
+use zip::raw::{
+    CentralDirectoryFileHeaderReader,
+    Zip64EndOfCentralDirectoryLocatorReader,
+    Zip64EndOfCentralDirectoryReader,
+};
+
+/// A ZIP archive, as located within a `Reader`.
+///
+/// The entries are listed, looked up and located through the `Archive` trait;
+/// the ranges of their data are given from the start of the reader, prefix
+/// included.
+///
+/// The entries themselves report the offsets of the Central Directory, such as
+/// `ZipEntry::local_file_header_offset`, from the start of the archive, prefix
+/// excluded: add `prefix` to resolve them within the reader.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ZipArchive<'a, R: Reader + ?Sized + 'a> {
+    reader: &'a R,
+    prefix: u64,
+    eocd: EndOfCentralDirectory,
+    central_directory: CentralDirectory<'a, R>,
+}
+
+impl<'a, R> ZipArchive<'a, R>
+    where R: Reader + ?Sized
+{
+    /// Returns the archive found in `reader`, using `scratch` to locate its
+    /// End of Central Directory record.
+    ///
+    /// Returns `Malformed` if the Central Directory does not lie before the End
+    /// of Central Directory record.
+    pub fn new(reader: &'a R, scratch: &mut [u8])
+        -> Result<ZipArchive<'a, R>, ReadError<R::Error>>
+    {
+        let eocd = EndOfCentralDirectory::locate(reader, &mut *scratch)?;
+
+        let (prefix, eocd) = match locate_prefix(reader, &eocd)? {
+            0 => (0, eocd),
+            prefix => {
+                let shifted = SubReader::new(reader, prefix..u64::MAX);
+                let shifted_eocd =
+                    EndOfCentralDirectory::locate(&shifted, scratch)?;
+
+                if starts_central_directory(&shifted, &shifted_eocd)? {
+                    (prefix, shifted_eocd)
+                } else {
+                    (0, eocd)
+                }
+            },
+        };
+
+        let range = eocd
+            .central_directory_range()
+            .ok_or(ReadError::Malformed)?;
+
+        let range = range.start.saturating_add(prefix)..
+            range.end.saturating_add(prefix);
+
+        let central_directory = CentralDirectory::from_range(
+            reader,
+            range,
+            eocd.nb_central_directory_records()
+        );
+
+        Ok(ZipArchive { reader, prefix, eocd, central_directory })
+    }
+
+    /// Returns the number of bytes preceding the archive.
+    pub fn prefix(&self) -> u64 { self.prefix }
+
+    /// Returns the End of Central Directory, whose offsets are relative to the
+    /// start of the archive, past the prefix.
+    pub fn end_of_central_directory(&self) -> &EndOfCentralDirectory {
+        &self.eocd
+    }
+
+    /// Returns the Central Directory, whose range is given from the start of
+    /// the reader.
+    pub fn central_directory(&self) -> &CentralDirectory<'a, R> {
+        &self.central_directory
+    }
+
+    /// Returns the range of the archive comment, from the start of the reader.
+    pub fn comment_range(&self) -> Range<u64> {
+        let range = self.eocd.comment_range();
+
+        range.start.saturating_add(self.prefix)..
+            range.end.saturating_add(self.prefix)
+    }
+}
+
+impl<'a, R> Archive for ZipArchive<'a, R>
+    where R: Reader + ?Sized
+{
+    type Error = ReadError<R::Error>;
+    type Entry<'e> = ZipEntry<'e>;
+
+    fn entries<S>(&self, scratch: &mut [u8], sink: &mut S)
+        -> Result<u64, Self::Error>
+        where S: for<'e> Sink<Self::Entry<'e>> + ?Sized
+    {
+        self.central_directory.entries(scratch, sink)
+    }
+
+    fn by_name<'e>(&'e self, name: &[u8], scratch: &'e mut [u8])
+        -> Result<Option<Self::Entry<'e>>, Self::Error>
+    {
+        self.central_directory.by_name(name, scratch)
+    }
+
+    fn data_range(&self, entry: &Self::Entry<'_>)
+        -> Result<Range<u64>, Self::Error>
+    {
+        let shifted = SubReader::new(self.reader, self.prefix..u64::MAX);
+        let cdfh = entry.central_directory_file_header();
+        let range = data_range(&shifted, cdfh)?;
+
+        Ok(range.start.saturating_add(self.prefix)..
+            range.end.saturating_add(self.prefix))
+    }
+}
+
+/// Returns the number of bytes preceding the archive, inferred from the gap
+/// between where the Central Directory ends and where it is announced to end.
+///
+/// When the ZIP64 End of Central Directory record could not be found at its
+/// announced offset, it is assumed to have no extensible data.
+fn locate_prefix<R>(reader: &R, eocd: &EndOfCentralDirectory)
+    -> Result<u64, ReadError<R::Error>>
+    where R: Reader + ?Sized
+{
+    type LocatorReader<'a> = Zip64EndOfCentralDirectoryLocatorReader<'a>;
+
+    if eocd.is_zip64() {
+        return Ok(0);
+    }
+
+    let locator_size = LocatorReader::min_size() as u64;
+    let zip64_size = Zip64EndOfCentralDirectoryReader::min_size() as u64;
+
+    let mut buffer = [0; 20];
+
+    let locator = match eocd.offset().checked_sub(locator_size) {
+        Some(offset) => {
+            let filled = read_exact(reader, offset, &mut buffer)
+                .map_err(ReadError::Io)?;
+
+            LocatorReader::new(buffer.get(..filled).unwrap_or(b"")).filter(
+                |l| l.signature() == LocatorReader::expected_signature()
+            )
+        },
+        None => None,
+    };
+
+    let (end, announced) = match locator {
+        Some(locator) => (
+            eocd.offset().saturating_sub(locator_size + zip64_size),
+            locator.zip64_end_of_central_directory_offset()
+        ),
+        None => (
+            eocd.offset(),
+            eocd.central_directory_offset()
+                .saturating_add(eocd.central_directory_size())
+        ),
+    };
+
+    Ok(end.saturating_sub(announced))
+}
+
+/// Returns whether the Central Directory announced by `eocd` is empty, or
+/// starts with a Central Directory File Header record.
+fn starts_central_directory<R>(reader: &R, eocd: &EndOfCentralDirectory)
+    -> Result<bool, ReadError<R::Error>>
+    where R: Reader + ?Sized
+{
+    type CdfhReader<'a> = CentralDirectoryFileHeaderReader<'a>;
+
+    if eocd.nb_central_directory_records() == 0 {
+        return Ok(true);
+    }
+
+    let start = eocd.central_directory_offset();
+    let mut signature = [0; 4];
+
+    let filled = read_exact(reader, start, &mut signature)
+        .map_err(ReadError::Io)?;
+
+    Ok(filled == signature.len() &&
+        u32::from_le_bytes(signature) == CdfhReader::expected_signature())
+}
+
+#[cfg(test)]
+mod tests {
+    use api::ReadError;
+    use archive::{Archive, Entry};
+    use utils::test::{
+        FailingReader,
+        TEST_ENTRIES,
+        TestError,
+        test_archive,
+        test_zip64_archive
+    };
+
+    use zip::ZipEntry;
+
+    use super::ZipArchive;
+
+    /// Checks that the `TEST_ENTRIES` are listed, looked up and located in
+    /// `data`.
+    fn check(data: &[u8], prefix: u64) {
+        let mut scratch = [0; 256];
+        let archive = ZipArchive::new(data, &mut scratch).unwrap();
+
+        assert_eq!(archive.prefix(), prefix);
+        assert_eq!(archive.central_directory().len(), 4);
+
+        let mut names = vec!();
+        let mut sink = |entry: ZipEntry| names.push(entry.name().to_vec());
+
+        assert_eq!(archive.entries(&mut scratch, &mut sink), Ok(4));
+
+        let expected: Vec<_> =
+            TEST_ENTRIES.iter().map(|e| e.0.to_vec()).collect();
+        assert_eq!(names, expected);
+
+        for &(name, content) in TEST_ENTRIES {
+            let entry = archive.by_name(name, &mut scratch).unwrap().unwrap();
+            let range = archive.data_range(&entry).unwrap();
+
+            let range = range.start as usize..range.end as usize;
+
+            assert_eq!(&data[range], content);
+
+            let lfh = (entry.local_file_header_offset() + prefix) as usize;
+
+            assert_eq!(&data[lfh..lfh + 4], b"PK\x03\x04");
+        }
+
+        assert_eq!(archive.by_name(b"missing", &mut scratch), Ok(None));
+    }
+
+    #[test]
+    fn archive_entries_and_data() {
+        check(&test_archive(TEST_ENTRIES), 0);
+    }
+
+    #[test]
+    fn archive_zip64_entries_and_data() {
+        check(&test_zip64_archive(TEST_ENTRIES), 0);
+    }
+
+    #[test]
+    fn archive_with_prefix() {
+        let stub = b"MZ\x90\x00 self-extracting stub PK\x03\x04";
+
+        let mut data = stub.to_vec();
+        data.extend_from_slice(&test_archive(TEST_ENTRIES));
+        check(&data, stub.len() as u64);
+
+        let mut data = stub.to_vec();
+        data.extend_from_slice(&test_zip64_archive(TEST_ENTRIES));
+        check(&data, stub.len() as u64);
+    }
+
+    #[test]
+    fn archive_empty() {
+        let mut scratch = [0; 64];
+
+        for &prefix in &[0, 7] {
+            let mut data = vec!(0; prefix);
+            data.extend_from_slice(&test_archive(&[]));
+
+            let archive = ZipArchive::new(&data[..], &mut scratch).unwrap();
+
+            assert_eq!(archive.prefix(), prefix as u64);
+            assert!(archive.central_directory().is_empty());
+
+            let mut sink = |_: ZipEntry| ();
+            assert_eq!(archive.entries(&mut scratch, &mut sink), Ok(0));
+
+            let end = data.len() as u64;
+            assert_eq!(archive.comment_range(), end..end);
+        }
+    }
+
+    #[test]
+    fn archive_failure() {
+        let mut scratch = [0; 64];
+        let data = test_archive(TEST_ENTRIES);
+
+        assert_eq!(
+            ZipArchive::new(&[0; 100][..], &mut scratch),
+            Err(ReadError::Malformed)
+        );
+
+        let reader = FailingReader::new(&data, 0);
+
+        assert_eq!(
+            ZipArchive::new(&reader, &mut scratch),
+            Err(ReadError::Io(TestError))
+        );
+    }
+}
@@ -0,0 +1,574 @@
+//! # Resolved metadata of a ZIP entry
+//!
+//! A `ZipEntry` interprets the fields of a Central Directory File Header
+//! record: the sizes and offset set to their maximum value are taken from the
+//! ZIP64 Extended Information extra field, the timestamps from the Extended
+//! Timestamp or NTFS extra fields when present, the compression method of an
+//! AES encrypted entry from its AE-x extra field, and the name and comment are
+//! decoded as UTF-8 or CP437 according to the general purpose bit flag.
+//!
+//! Nothing is allocated: the name and comment are decoded on the fly.
+
+use core::convert::TryInto;
+use core::fmt;
+use core::str::{self, Chars, Utf8Chunks};
+
+use archive::{Checksum, CompressionMethod, Entry, EntryKind, Timestamp};
+use utils::cp437;
+
+use zip::low_level::{
+    ExtraFieldIterator,
+    Zip64Fields,
+    aes_compression_method,
+    is_ae2
+};
+use zip::raw::CentralDirectoryFileHeaderReader;
+
+/// The bit of the general purpose bit flag set when the name and comment are
+/// encoded in UTF-8.
+const UTF8_FLAG: u16 = 0x0800;
+
+/// The ID of the Extended Timestamp extra field.
+const EXTENDED_TIMESTAMP_ID: u16 = 0x5455;
+
+/// The ID of the NTFS extra field.
+const NTFS_ID: u16 = 0x000a;
+
+/// The compression method announced by entries encrypted with AE-x.
+const AES_METHOD: u16 = 99;
+
+/// An entry of a ZIP archive, as described by its Central Directory File
+/// Header record.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ZipEntry<'a> {
+    cdfh: CentralDirectoryFileHeaderReader<'a>,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    local_file_header_offset: u64,
+    times: Times,
+}
+
+impl<'a> ZipEntry<'a> {
+    /// Returns the entry described by `cdfh`.
+    pub fn new(cdfh: CentralDirectoryFileHeaderReader<'a>) -> ZipEntry<'a> {
+        let zip64 = Zip64Fields::from_central_directory_file_header(cdfh);
+        let extra = cdfh.extra_field().unwrap_or(b"");
+
+        let times = Times::from_extended_timestamp(extra)
+            .unwrap_or_default()
+            .or(Times::from_ntfs(extra).unwrap_or_default());
+
+        let offset = u64::from(cdfh.local_file_header_relative_offset());
+
+        ZipEntry {
+            cdfh,
+            compressed_size: zip64
+                .compressed_size()
+                .unwrap_or(u64::from(cdfh.compressed_size())),
+            uncompressed_size: zip64
+                .uncompressed_size()
+                .unwrap_or(u64::from(cdfh.uncompressed_size())),
+            local_file_header_offset: zip64
+                .local_file_header_relative_offset()
+                .unwrap_or(offset),
+            times,
+        }
+    }
+
+    /// Returns the underlying record.
+    pub fn central_directory_file_header(&self)
+        -> CentralDirectoryFileHeaderReader<'a>
+    {
+        self.cdfh
+    }
+
+    /// Returns the offset of the Local File Header record, from the start of
+    /// the archive.
+    ///
+    /// For an archive preceded by a prefix, such as the stub of a
+    /// self-extracting archive, the offset excludes the prefix: see
+    /// `ZipArchive::prefix`.
+    pub fn local_file_header_offset(&self) -> u64 {
+        self.local_file_header_offset
+    }
+
+    /// Returns the name of the entry, decoded.
+    pub fn file_name(&self) -> ZipText<'a> {
+        self.text(self.cdfh.file_name())
+    }
+
+    /// Returns the comment of the entry, decoded.
+    pub fn file_comment(&self) -> ZipText<'a> {
+        self.text(self.cdfh.file_comment())
+    }
+
+    /// Returns whether the data of the entry is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.cdfh.general_purpose_bit_flag() & 0x0001 != 0
+    }
+
+    /// Returns the Unix mode stored in the upper half of the external file
+    /// attributes, if the entry was made on Unix or OS X.
+    fn unix_mode(&self) -> Option<u32> {
+        match self.cdfh.version_made_by() >> 8 {
+            3 | 19 => Some(self.cdfh.external_file_attributes() >> 16),
+            _ => None,
+        }
+    }
+
+    fn text(&self, raw: Option<&'a [u8]>) -> ZipText<'a> {
+        ZipText {
+            raw: raw.unwrap_or(b""),
+            is_utf8: self.cdfh.general_purpose_bit_flag() & UTF8_FLAG != 0,
+        }
+    }
+}
+
+impl<'a> Entry for ZipEntry<'a> {
+    fn name(&self) -> &[u8] { self.cdfh.file_name().unwrap_or(b"") }
+
+    fn kind(&self) -> EntryKind {
+        const S_IFMT: u32 = 0o170000;
+
+        let mode = self.unix_mode().map(|mode| mode & S_IFMT);
+
+        match mode {
+            Some(0o120000) => EntryKind::Symlink,
+            Some(0o040000) => EntryKind::Directory,
+            _ if self.name().ends_with(b"/") => EntryKind::Directory,
+            //  The MS-DOS directory attribute.
+            _ if self.cdfh.external_file_attributes() & 0x10 != 0 =>
+                EntryKind::Directory,
+            Some(0o100000) | Some(0) | None => EntryKind::File,
+            Some(_) => EntryKind::Other,
+        }
+    }
+
+    fn compressed_size(&self) -> u64 { self.compressed_size }
+
+    fn uncompressed_size(&self) -> u64 { self.uncompressed_size }
+
+    fn modified(&self) -> Option<Timestamp> {
+        self.times.modified.or_else(|| {
+            Timestamp::from_dos(
+                self.cdfh.file_last_modification_date(),
+                self.cdfh.file_last_modification_time()
+            )
+        })
+    }
+
+    fn accessed(&self) -> Option<Timestamp> { self.times.accessed }
+
+    fn created(&self) -> Option<Timestamp> { self.times.created }
+
+    fn permissions(&self) -> Option<u16> {
+        self.unix_mode().map(|mode| (mode & 0o7777) as u16)
+    }
+
+    fn link_target(&self) -> Option<&[u8]> { None }
+
+    fn checksum(&self) -> Option<Checksum> {
+        //  The CRC-32 of an AE-2 entry is meaningless, and usually zero.
+        if is_ae2(self.cdfh) {
+            return None;
+        }
+
+        Some(Checksum::Crc32(self.cdfh.crc32()))
+    }
+
+    fn compression_method(&self) -> CompressionMethod {
+        let method = match self.cdfh.compression_method() {
+            AES_METHOD =>
+                aes_compression_method(self.cdfh).unwrap_or(AES_METHOD),
+            method => method,
+        };
+
+        match method {
+            0 => CompressionMethod::Stored,
+            8 => CompressionMethod::Deflate,
+            9 => CompressionMethod::Deflate64,
+            12 => CompressionMethod::Bzip2,
+            14 => CompressionMethod::Lzma,
+            93 => CompressionMethod::Zstandard,
+            95 => CompressionMethod::Xz,
+            98 => CompressionMethod::Ppmd,
+            method => CompressionMethod::Other(u64::from(method)),
+        }
+    }
+}
+
+/// The name or comment of an entry, encoded in UTF-8 or CP437.
+///
+/// Bytes which are not valid UTF-8, despite the encoding, are decoded as
+/// U+FFFD, the replacement character.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ZipText<'a> {
+    raw: &'a [u8],
+    is_utf8: bool,
+}
+
+impl<'a> ZipText<'a> {
+    /// Returns the bytes, as stored.
+    pub fn raw(&self) -> &'a [u8] { self.raw }
+
+    /// Returns whether the bytes are encoded in UTF-8, rather than CP437.
+    pub fn is_utf8(&self) -> bool { self.is_utf8 }
+
+    /// Returns the text as a `str`, if it can be borrowed as is: valid UTF-8,
+    /// or ASCII in CP437.
+    pub fn as_str(&self) -> Option<&'a str> {
+        if self.is_utf8 || self.raw.is_ascii() {
+            str::from_utf8(self.raw).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the characters of the text.
+    pub fn chars(&self) -> ZipTextChars<'a> {
+        ZipTextChars {
+            raw: if self.is_utf8 { b"" } else { self.raw },
+            chunks: if self.is_utf8 { self.raw } else { b"" }.utf8_chunks(),
+            valid: "".chars(),
+            invalid: false,
+        }
+    }
+}
+
+impl<'a> fmt::Display for ZipText<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use core::fmt::Write;
+
+        self.chars().try_for_each(|c| f.write_char(c))
+    }
+}
+
+/// The characters of a `ZipText`.
+#[derive(Clone, Debug)]
+pub struct ZipTextChars<'a> {
+    //  The CP437 bytes left.
+    raw: &'a [u8],
+    //  The UTF-8 chunks left, past the current one.
+    chunks: Utf8Chunks<'a>,
+    valid: Chars<'a>,
+    invalid: bool,
+}
+
+impl<'a> Iterator for ZipTextChars<'a> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if let Some((byte, raw)) = self.raw.split_first() {
+            self.raw = raw;
+            return Some(cp437::decode(*byte));
+        }
+
+        loop {
+            if let Some(c) = self.valid.next() {
+                return Some(c);
+            }
+
+            if self.invalid {
+                self.invalid = false;
+                return Some('\u{fffd}');
+            }
+
+            let chunk = self.chunks.next()?;
+
+            self.valid = chunk.valid().chars();
+            self.invalid = !chunk.invalid().is_empty();
+        }
+    }
+}
+
+/// The timestamps found in an extra field.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+struct Times {
+    modified: Option<Timestamp>,
+    accessed: Option<Timestamp>,
+    created: Option<Timestamp>,
+}
+
+impl Times {
+    /// Returns these timestamps, completed by those of `other`.
+    fn or(self, other: Times) -> Times {
+        Times {
+            modified: self.modified.or(other.modified),
+            accessed: self.accessed.or(other.accessed),
+            created: self.created.or(other.created),
+        }
+    }
+
+    /// Returns the timestamps of the Extended Timestamp extra field, if any.
+    ///
+    /// The flags announce the timestamps of the Local File Header record, of
+    /// which the Central Directory File Header record may only hold the first.
+    fn from_extended_timestamp(extra: &[u8]) -> Option<Times> {
+        let data = ExtraFieldIterator::find_id(extra, EXTENDED_TIMESTAMP_ID)?
+            .data()?;
+
+        let (flags, mut rest) = data.split_first()?;
+        let mut times = [None; 3];
+
+        for (bit, time) in times.iter_mut().enumerate() {
+            if flags & (1 << bit) == 0 {
+                continue;
+            }
+
+            let (seconds, tail) = match rest.split_first_chunk::<4>() {
+                Some(split) => split,
+                None => break,
+            };
+
+            let seconds = i64::from(i32::from_le_bytes(*seconds));
+
+            *time = Timestamp::from_unix(seconds, 0);
+            rest = tail;
+        }
+
+        let [modified, accessed, created] = times;
+
+        Some(Times { modified, accessed, created })
+    }
+
+    /// Returns the timestamps of the first attribute of the NTFS extra field,
+    /// if any.
+    fn from_ntfs(extra: &[u8]) -> Option<Times> {
+        let data = ExtraFieldIterator::find_id(extra, NTFS_ID)?.data()?;
+
+        //  A reserved field, followed by attributes laid out as extra fields.
+        let attributes = data.get(4..)?;
+        let times = ExtraFieldIterator::find_id(attributes, 0x0001)?.data()?;
+
+        let time = |index: usize| {
+            let bytes = times.get(index * 8..index * 8 + 8)?;
+            let ticks = u64::from_le_bytes(bytes.try_into().ok()?);
+
+            Some(Timestamp::from_ntfs(ticks))
+        };
+
+        Some(Times {
+            modified: time(0),
+            accessed: time(1),
+            created: time(2),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use archive::{Checksum, CompressionMethod, Entry, EntryKind, Timestamp};
+    use utils::test::test_zip64_archive;
+    use zip::raw::{
+        CentralDirectoryFileHeaderReader,
+        CentralDirectoryFileHeaderWriter
+    };
+
+    use super::ZipEntry;
+
+    /// Returns a Central Directory File Header record, written into `buffer`.
+    fn cdfh<'a>(
+        buffer: &'a mut [u8],
+        flags: u16,
+        name: &[u8],
+        extra: &[u8],
+        comment: &[u8]
+    )
+        -> CentralDirectoryFileHeaderReader<'a>
+    {
+        let size = CentralDirectoryFileHeaderWriter::new()
+            .with_general_purpose_bit_flag(flags)
+            .with_file_last_modification_date(0x21)
+            .with_file_name(name)
+            .with_extra_field(extra)
+            .with_file_comment(comment)
+            .write(buffer)
+            .unwrap();
+
+        CentralDirectoryFileHeaderReader::new(&buffer[..size]).unwrap()
+    }
+
+    #[test]
+    fn entry_zip64_sizes_and_offset() {
+        let data = test_zip64_archive(&[
+            (b"a", b"Hello"),
+            (b"b", b"World!"),
+        ]);
+
+        //  The second record of the Central Directory.
+        let start = data.len() - 22 - 20 - 56 - (46 + 1 + 28);
+        let cdfh = CentralDirectoryFileHeaderReader::new(&data[start..])
+            .unwrap();
+        let entry = ZipEntry::new(cdfh);
+
+        assert_eq!(entry.compressed_size(), 6);
+        assert_eq!(entry.uncompressed_size(), 6);
+        assert_eq!(entry.local_file_header_offset(), 30 + 1 + 20 + 5);
+        assert_eq!(entry.kind(), EntryKind::File);
+    }
+
+    #[test]
+    fn entry_decoded_text() {
+        let mut buffer = [0; 128];
+
+        //  CP437, unless bit 11 is set.
+        let entry = ZipEntry::new(cdfh(&mut buffer, 0, b"caf\x82", b"", b""));
+
+        assert_eq!(entry.file_name().as_str(), None);
+        assert!(entry.file_name().chars().eq("café".chars()));
+        assert_eq!(entry.file_name().raw(), b"caf\x82");
+
+        let entry = ZipEntry::new(
+            cdfh(&mut buffer, 0x0800, "café".as_bytes(), b"", b"d\xffe")
+        );
+
+        assert_eq!(entry.file_name().as_str(), Some("café"));
+        assert_eq!(format!("{}", entry.file_name()), "café");
+        assert_eq!(format!("{}", entry.file_comment()), "d\u{fffd}e");
+
+        let entry = ZipEntry::new(cdfh(&mut buffer, 0, b"ascii", b"", b""));
+
+        assert_eq!(entry.file_name().as_str(), Some("ascii"));
+        assert_eq!(entry.file_comment().as_str(), Some(""));
+    }
+
+    #[test]
+    fn entry_extended_timestamp() {
+        let mut buffer = [0; 128];
+
+        //  Modification and access times announced, only the former stored.
+        let extra = [
+            0x55, 0x54, 5, 0, 0x03,
+            0xa6, 0x86, 0xe0, 0x65,
+        ];
+
+        let entry = ZipEntry::new(cdfh(&mut buffer, 0, b"a", &extra, b""));
+
+        assert_eq!(entry.modified(), Timestamp::from_unix(1709213350, 0));
+        assert_eq!(entry.accessed(), None);
+        assert_eq!(entry.created(), None);
+    }
+
+    #[test]
+    fn entry_ntfs_timestamps() {
+        let mut buffer = [0; 128];
+
+        let mut extra = vec!(0x0a, 0x00, 32, 0, 0, 0, 0, 0, 0x01, 0x00, 24, 0);
+        extra.extend_from_slice(&133_536_874_621_234_567u64.to_le_bytes());
+        extra.extend_from_slice(&116_444_736_000_000_000u64.to_le_bytes());
+        extra.extend_from_slice(&116_444_736_010_000_000u64.to_le_bytes());
+
+        let entry = ZipEntry::new(cdfh(&mut buffer, 0, b"a", &extra, b""));
+
+        assert_eq!(
+            entry.modified(),
+            Timestamp::from_unix(1709213862, 123_456_700)
+        );
+        assert_eq!(entry.accessed(), Timestamp::from_unix(0, 0));
+        assert_eq!(entry.created(), Timestamp::from_unix(1, 0));
+    }
+
+    #[test]
+    fn entry_dos_timestamp() {
+        let mut buffer = [0; 128];
+
+        //  A truncated NTFS extra field is ignored.
+        let extra = [0x0a, 0x00, 4, 0, 0, 0, 0, 0];
+
+        let entry = ZipEntry::new(cdfh(&mut buffer, 0, b"a", &extra, b""));
+
+        assert_eq!(entry.modified(), Timestamp::from_dos(0x21, 0));
+        assert_eq!(entry.accessed(), None);
+    }
+
+    #[test]
+    fn entry_metadata() {
+        let mut buffer = [0; 128];
+
+        let file = ZipEntry::new(cdfh(&mut buffer, 0, b"hello.txt", b"", b""));
+
+        assert_eq!(file.kind(), EntryKind::File);
+        assert_eq!(file.permissions(), None);
+        assert_eq!(file.link_target(), None);
+        assert_eq!(file.checksum(), Some(Checksum::Crc32(0)));
+        assert_eq!(file.compression_method(), CompressionMethod::Stored);
+
+        let directory = ZipEntry::new(cdfh(&mut buffer, 0, b"dir/", b"", b""));
+
+        assert_eq!(directory.kind(), EntryKind::Directory);
+    }
+
+    #[test]
+    fn entry_aes_checksum_and_compression_method() {
+        let mut buffer = [0; 128];
+
+        //  AE-1 and AE-2, 256 bits, over Deflate.
+        for &(version, checksum) in &[
+            (1, Some(Checksum::Crc32(0x12345678))),
+            (2, None),
+        ] {
+            let extra = [0x01, 0x99, 7, 0, version, 0, b'A', b'E', 3, 8, 0];
+            let size = CentralDirectoryFileHeaderWriter::new()
+                .with_general_purpose_bit_flag(0x01)
+                .with_compression_method(99)
+                .with_crc32(0x12345678)
+                .with_file_name(b"a")
+                .with_extra_field(&extra)
+                .write(&mut buffer)
+                .unwrap();
+            let cdfh =
+                CentralDirectoryFileHeaderReader::new(&buffer[..size]).unwrap();
+            let entry = ZipEntry::new(cdfh);
+
+            assert!(entry.is_encrypted());
+            assert_eq!(entry.checksum(), checksum);
+            assert_eq!(entry.compression_method(), CompressionMethod::Deflate);
+        }
+
+        //  Without its extra field, the actual method is unknown.
+        let size = CentralDirectoryFileHeaderWriter::new()
+            .with_compression_method(99)
+            .write(&mut buffer)
+            .unwrap();
+        let cdfh =
+            CentralDirectoryFileHeaderReader::new(&buffer[..size]).unwrap();
+
+        assert_eq!(
+            ZipEntry::new(cdfh).compression_method(),
+            CompressionMethod::Other(99)
+        );
+    }
+
+    #[test]
+    fn entry_unix_kind_and_permissions() {
+        let kinds = [
+            (0o100644, EntryKind::File),
+            (0o040755, EntryKind::Directory),
+            (0o120777, EntryKind::Symlink),
+            (0o010644, EntryKind::Other),
+        ];
+
+        for &(mode, kind) in &kinds {
+            let mut v = [0; 64];
+
+            let size = CentralDirectoryFileHeaderWriter::new()
+                .with_version_made_by(0x031e)
+                .with_compression_method(93)
+                .with_external_file_attributes(mode << 16)
+                .with_file_name(b"a")
+                .write(&mut v)
+                .unwrap();
+
+            let entry = ZipEntry::new(
+                CentralDirectoryFileHeaderReader::new(&v[..size]).unwrap()
+            );
+
+            assert_eq!(entry.kind(), kind);
+            assert_eq!(entry.permissions(), Some((mode & 0o7777) as u16));
+            assert_eq!(
+                entry.compression_method(),
+                CompressionMethod::Zstandard
+            );
+        }
+    }
+}
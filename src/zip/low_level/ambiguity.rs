@@ -0,0 +1,965 @@
+//! # Detection of ambiguities in the structure of a ZIP archive
+//!
+//! An ambiguity is a property of an archive which may lead different tools to
+//! interpret it differently, for example to list different entries, or to
+//! extract different content for the same entry.
+
+use core::cmp::min;
+use core::iter::Iterator;
+use core::ops::Range;
+use core::str;
+
+use api::{ReadError, Reader, Sink};
+use utils::{cp437, hash, position};
+use utils::read::{read_contiguous, read_exact};
+
+use zip::raw::{
+    CentralDirectoryFileHeaderReader,
+    EndOfCentralDirectoryReader,
+    LocalFileHeaderReader
+};
+
+use super::cdfh::read_central_directory_file_header;
+use super::dd::{read_data_descriptor, read_zip64_data_descriptor};
+use super::diagnostic::{Code, Diagnostic, Record};
+use super::eocd::{
+    EndOfCentralDirectory,
+    EndOfCentralDirectoryField,
+    EndOfCentralDirectoryIterator,
+    read_tail
+};
+use super::extra::ExtraFieldIterator;
+use super::index::IndexSlot;
+use super::lfh::read_local_file_header;
+use super::zip64::Zip64Fields;
+
+/// Inspects the archive, pushing a `Diagnostic` into the `sink` for each
+/// ambiguity found.
+///
+/// The ambiguities detected are:
+///
+/// - `MultipleEndOfCentralDirectory`: any End of Central Directory record
+///   candidate, as returned by `EndOfCentralDirectoryIterator`, other than the
+///   one located by `EndOfCentralDirectory::locate`.
+/// - `RecordCountMismatch`: the counts of records of the End of Central
+///   Directory differ from the number of records actually parsed from the
+///   Central Directory.
+/// - `DuplicateName`: several Central Directory File Header records bear the
+///   same name; each of them is reported.
+/// - `UncheckedName`: the first record whose name could not be checked for
+///   duplicates, for lack of `names` slots.
+/// - `FieldMismatch`: the name of a Local File Header record differs from the
+///   name of its Central Directory File Header record.
+/// - `UnicodePathMismatch`: the name of a Central Directory File Header record,
+///   decoded as CP437 or UTF-8 depending on bit 11 of the general purpose bit
+///   flag, differs from its Info-ZIP Unicode Path extra field.
+/// - `EmbeddedArchive`: the data preceding the first entry, in-between two
+///   entries, or following the last entry, contains a Local File Header or End
+///   of Central Directory record, whose fields are consistent and fit within
+///   this data.
+///
+/// The `names` parameter is a scratch buffer used to detect duplicate names
+/// without allocating: only the first `names.len()` records are checked. Names
+/// are sorted by their 64-bits hash, and matching hashes are confirmed by
+/// comparing the names themselves.
+///
+/// The data in-between entries is only inspected if the Central Directory lists
+/// the entries in the order in which they appear in the archive, and none of
+/// them overlap.
+///
+/// Returns an error only if the `reader` fails.
+///
+/// If the reader copies, the `scratch` buffer is split in two halves, holding
+/// respectively a Central Directory File Header record and a Local File Header
+/// record.
+pub fn detect_ambiguities<R, S>(
+    reader: &R,
+    scratch: &mut [u8],
+    names: &mut [IndexSlot],
+    sink: &mut S
+)
+    -> Result<(), R::Error>
+    where R: Reader + ?Sized,
+          S: Sink<Diagnostic> + ?Sized
+{
+    let eocd = match EndOfCentralDirectory::locate(reader, scratch) {
+        Ok(eocd) => eocd,
+        Err(ReadError::Io(error)) => return Err(error),
+        Err(_) => return Ok(()),
+    };
+
+    detect_multiple_eocd(reader, scratch, eocd.offset(), sink)?;
+
+    let cd = match eocd.central_directory_range() {
+        Some(cd) => cd,
+        None => return Ok(()),
+    };
+
+    let middle = scratch.len() / 2;
+    let (cd_scratch, lfh_scratch) = scratch.split_at_mut(middle);
+
+    let mut count = 0;
+    let mut is_ordered = true;
+    let mut next = 0;
+    let mut cursor = cd.start;
+
+    while let Some((offset, cdfh)) =
+        next_record(reader, &mut cursor, cd.end, &mut *cd_scratch)?
+    {
+        if let Some(slot) = names.get_mut(count) {
+            let name = cdfh.file_name().unwrap_or(b"");
+            *slot = IndexSlot::new(hash(name.iter().cloned()), offset);
+        }
+
+        detect_local_name_mismatch(reader, lfh_scratch, cdfh, cd.start, sink)?;
+        detect_unicode_path_mismatch(cdfh, offset, sink);
+
+        match entry_range(reader, lfh_scratch, cdfh, cd.start)? {
+            Some(entry) if entry.start >= next => next = entry.end,
+            _ => is_ordered = false,
+        }
+
+        count += 1;
+    }
+
+    let counts = [
+        (eocd.nb_local_central_directory_records(),
+         EndOfCentralDirectoryField::NbLocalCentralDirectoryRecords),
+        (eocd.nb_central_directory_records(),
+         EndOfCentralDirectoryField::NbCentralDirectoryRecords),
+    ];
+
+    for &(expected, field) in &counts {
+        if expected != count as u64 {
+            sink.push(eocd.diagnostic(Code::RecordCountMismatch, field));
+        }
+    }
+
+    let hashed = min(count, names.len());
+    let names = names.get_mut(..hashed).unwrap_or(&mut []);
+
+    names.sort_unstable();
+
+    let name_position = CentralDirectoryFileHeaderReader::min_size();
+
+    let mut index = 0;
+    let mut next = 0;
+    let mut cursor = cd.start;
+
+    while let Some((offset, cdfh)) =
+        next_record(reader, &mut cursor, cd.end, &mut *cd_scratch)?
+    {
+        let name = cdfh.file_name().unwrap_or(b"");
+        let slot = IndexSlot::new(hash(name.iter().cloned()), offset);
+
+        let code = if index == hashed {
+            Some(Code::UncheckedName)
+        } else if index < hashed &&
+            has_duplicate(reader, lfh_scratch, names, slot, name, cd.end)?
+        {
+            Some(Code::DuplicateName)
+        } else {
+            None
+        };
+
+        if let Some(code) = code {
+            sink.push(Diagnostic::new(
+                code,
+                offset.wrapping_add(name_position as u64),
+                Record::CentralDirectoryFileHeader,
+                "file_name"
+            ));
+        }
+
+        if is_ordered {
+            let entry = entry_range(reader, lfh_scratch, cdfh, cd.start)?;
+
+            if let Some(entry) = entry {
+                let range = next..entry.start;
+                detect_embedded_archive(reader, lfh_scratch, range, sink)?;
+                next = entry.end;
+            }
+        }
+
+        index += 1;
+    }
+
+    if is_ordered {
+        detect_embedded_archive(reader, lfh_scratch, next..cd.start, sink)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the Central Directory File Header record at `cursor`, alongside its
+/// offset, advancing the `cursor` past it, or `None` if it cannot be read or
+/// its signature does not match.
+fn next_record<'a, R>(
+    reader: &'a R,
+    cursor: &mut u64,
+    cd_end: u64,
+    scratch: &'a mut [u8]
+)
+    -> Result<Option<(u64, CentralDirectoryFileHeaderReader<'a>)>, R::Error>
+    where R: Reader + ?Sized
+{
+    let signature = CentralDirectoryFileHeaderReader::expected_signature();
+
+    let offset = *cursor;
+
+    let cdfh =
+        read_central_directory_file_header(reader, offset, cd_end, scratch)?
+            .filter(|cdfh| cdfh.signature() == signature);
+
+    if let Some(cdfh) = cdfh {
+        *cursor = offset.wrapping_add(cdfh.raw().len() as u64);
+    }
+
+    Ok(cdfh.map(|cdfh| (offset, cdfh)))
+}
+
+/// Returns the range spanned by the entry, from the start of its Local File
+/// Header record to the end of its data, or Data Descriptor record if any.
+fn entry_range<R>(
+    reader: &R,
+    scratch: &mut [u8],
+    cdfh: CentralDirectoryFileHeaderReader,
+    cd_start: u64
+)
+    -> Result<Option<Range<u64>>, R::Error>
+    where R: Reader + ?Sized
+{
+    let fields = Zip64Fields::from_central_directory_file_header(cdfh);
+
+    let start = match fields.local_file_header_relative_offset() {
+        Some(start) => start,
+        None => return Ok(None),
+    };
+
+    let lfh = match local_file_header(reader, scratch, start, cd_start)? {
+        Some(lfh) if lfh.extra_field().is_some() => lfh,
+        _ => return Ok(None),
+    };
+
+    let end = fields.compressed_size().and_then(|size| {
+        start.checked_add(lfh.raw().len() as u64)?.checked_add(size)
+    });
+
+    let mut end = match end {
+        Some(end) => end,
+        None => return Ok(None),
+    };
+
+    if cdfh.general_purpose_bit_flag() & 0x08 != 0 {
+        let is_zip64 = fields.has_zip64_sizes() ||
+            Zip64Fields::from_local_file_header(lfh).has_zip64_sizes();
+
+        //  Sized for the largest record, a signed ZIP64 Data Descriptor.
+        let mut scratch = [0; 24];
+
+        let length = min(cd_start.saturating_sub(end), 24) as usize;
+        let buffer = scratch.get_mut(..length).unwrap_or(&mut []);
+        let filled = read_exact(reader, end, buffer)?;
+        let dd = buffer.get(..filled).unwrap_or(b"");
+
+        let dd_size = if is_zip64 {
+            read_zip64_data_descriptor(dd).map(|dd| dd.raw().len())
+        } else {
+            read_data_descriptor(dd).map(|dd| dd.raw().len())
+        };
+
+        end = match dd_size.and_then(|size| end.checked_add(size as u64)) {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+    }
+
+    Ok(if end <= cd_start { Some(start..end) } else { None })
+}
+
+/// Returns the Local File Header record at `offset`, if its signature matches.
+fn local_file_header<'a, R>(
+    reader: &'a R,
+    scratch: &'a mut [u8],
+    offset: u64,
+    cd_start: u64
+)
+    -> Result<Option<LocalFileHeaderReader<'a>>, R::Error>
+    where R: Reader + ?Sized
+{
+    type LfhReader<'a> = LocalFileHeaderReader<'a>;
+
+    let lfh = read_local_file_header(reader, offset, cd_start, scratch)?
+        .filter(|lfh| lfh.signature() == LfhReader::expected_signature());
+
+    Ok(lfh)
+}
+
+/// Returns whether a record, other than the one of the `slot`, bears the `name`
+/// amongst the sorted `slots`.
+///
+/// Each record whose hash matches is read, so as to compare the names byte for
+/// byte.
+fn has_duplicate<R>(
+    reader: &R,
+    scratch: &mut [u8],
+    slots: &[IndexSlot],
+    slot: IndexSlot,
+    name: &[u8],
+    cd_end: u64
+)
+    -> Result<bool, R::Error>
+    where R: Reader + ?Sized
+{
+    let first = slots.partition_point(|s| s.hash() < slot.hash());
+
+    let candidates = slots
+        .iter()
+        .skip(first)
+        .take_while(|s| s.hash() == slot.hash())
+        .filter(|s| s.offset() != slot.offset());
+
+    for candidate in candidates {
+        let cdfh = read_central_directory_file_header(
+            reader,
+            candidate.offset(),
+            cd_end,
+            &mut *scratch
+        )?;
+
+        if cdfh.and_then(|cdfh| cdfh.file_name()) == Some(name) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Returns whether the `name`, decoded as UTF-8 or CP437, matches `unicode`.
+fn is_same_name(name: &[u8], is_utf8: bool, unicode: &[u8]) -> bool {
+    let unicode = match str::from_utf8(unicode) {
+        Ok(unicode) => unicode,
+        Err(_) => return false,
+    };
+
+    if is_utf8 {
+        str::from_utf8(name) == Ok(unicode)
+    } else {
+        name.iter().map(|b| cp437::decode(*b)).eq(unicode.chars())
+    }
+}
+
+/// Detects End of Central Directory record candidates, other than the one at
+/// `eocd_offset`.
+fn detect_multiple_eocd<R, S>(
+    reader: &R,
+    scratch: &mut [u8],
+    eocd_offset: u64,
+    sink: &mut S
+)
+    -> Result<(), R::Error>
+    where R: Reader + ?Sized,
+          S: Sink<Diagnostic> + ?Sized
+{
+    let (start, tail) = read_tail(reader, scratch)?;
+
+    for eocd in EndOfCentralDirectoryIterator::new(tail) {
+        let offset = position(eocd.raw(), tail).unwrap_or(0);
+        let offset = start.wrapping_add(offset as u64);
+
+        if offset != eocd_offset {
+            sink.push(Diagnostic::new(
+                Code::MultipleEndOfCentralDirectory,
+                offset,
+                Record::EndOfCentralDirectory,
+                "signature"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Detects a mismatch between the name of the Local File Header record and the
+/// name of the Central Directory File Header record.
+fn detect_local_name_mismatch<R, S>(
+    reader: &R,
+    scratch: &mut [u8],
+    cdfh: CentralDirectoryFileHeaderReader,
+    cd_start: u64,
+    sink: &mut S
+)
+    -> Result<(), R::Error>
+    where R: Reader + ?Sized,
+          S: Sink<Diagnostic> + ?Sized
+{
+    let fields = Zip64Fields::from_central_directory_file_header(cdfh);
+
+    let offset = match fields.local_file_header_relative_offset() {
+        Some(offset) => offset,
+        None => return Ok(()),
+    };
+
+    let lfh = match local_file_header(reader, scratch, offset, cd_start)? {
+        Some(lfh) => lfh,
+        None => return Ok(()),
+    };
+
+    if lfh.file_name() != cdfh.file_name() {
+        sink.push(Diagnostic::new(
+            Code::FieldMismatch,
+            offset.wrapping_add(LocalFileHeaderReader::min_size() as u64),
+            Record::LocalFileHeader,
+            "file_name"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Detects a mismatch between the name of the Central Directory File Header
+/// record at `offset` and its Info-ZIP Unicode Path extra field, if any.
+fn detect_unicode_path_mismatch<S>(
+    cdfh: CentralDirectoryFileHeaderReader,
+    offset: u64,
+    sink: &mut S
+)
+    where S: Sink<Diagnostic> + ?Sized
+{
+    //  The Info-ZIP Unicode Path extra field is composed of:
+    //  - a 1 byte version, 1,
+    //  - a 4 bytes CRC-32 of the file name,
+    //  - the UTF-8 encoded file name.
+    const UNICODE_PATH_ID: u16 = 0x7075;
+
+    let (name, extra_field) = match (cdfh.file_name(), cdfh.extra_field()) {
+        (Some(name), Some(extra_field)) => (name, extra_field),
+        _ => return,
+    };
+
+    let extra = ExtraFieldIterator::find_id(extra_field, UNICODE_PATH_ID);
+
+    let extra = match extra {
+        Some(extra) => extra,
+        None => return,
+    };
+
+    let unicode = match extra.data().and_then(|data| data.get(5..)) {
+        Some(unicode) => unicode,
+        None => return,
+    };
+
+    let is_utf8 = cdfh.general_purpose_bit_flag() & 0x0800 != 0;
+
+    if !is_same_name(name, is_utf8, unicode) {
+        let position = CentralDirectoryFileHeaderReader::min_size() +
+            name.len() +
+            position(extra.raw(), extra_field).unwrap_or(0);
+
+        sink.push(Diagnostic::new(
+            Code::UnicodePathMismatch,
+            offset.wrapping_add(position as u64),
+            Record::CentralDirectoryFileHeader,
+            "extra_field"
+        ));
+    }
+}
+
+/// Detects a Local File Header or End of Central Directory record within the
+/// `range`, reporting the first one, if any.
+///
+/// Each signature found is only reported if the record it starts is plausible,
+/// as checked by `is_embedded_record`.
+///
+/// The `range` is read in chunks no larger than the `scratch` buffer, unless
+/// the reader hands it whole.
+fn detect_embedded_archive<R, S>(
+    reader: &R,
+    scratch: &mut [u8],
+    range: Range<u64>,
+    sink: &mut S
+)
+    -> Result<(), R::Error>
+    where R: Reader + ?Sized,
+          S: Sink<Diagnostic> + ?Sized
+{
+    let mut start = range.start;
+
+    while start < range.end {
+        let chunk = read_contiguous(reader, start..range.end, &mut *scratch)?;
+
+        let found = chunk.windows(4).enumerate().find_map(|(i, w)| {
+            match w {
+                b"PK\x03\x04" => Some((i, Record::LocalFileHeader)),
+                b"PK\x05\x06" => Some((i, Record::EndOfCentralDirectory)),
+                _ => None,
+            }
+        });
+
+        if let Some((position, record)) = found {
+            let offset = start.wrapping_add(position as u64);
+
+            if is_embedded_record(reader, offset, record, &range)? {
+                sink.push(Diagnostic::new(
+                    Code::EmbeddedArchive,
+                    offset,
+                    record,
+                    "signature"
+                ));
+
+                return Ok(());
+            }
+
+            //  Resume the search right past this signature.
+            start = offset.saturating_add(1);
+            continue;
+        }
+
+        //  The next chunk overlaps the last 3 bytes of this one, in case a
+        //  signature straddles both.
+        let advance = chunk.len().saturating_sub(3) as u64;
+        let scanned = start.saturating_add(chunk.len() as u64);
+
+        if advance == 0 || scanned >= range.end {
+            break;
+        }
+
+        start = start.saturating_add(advance);
+    }
+
+    Ok(())
+}
+
+/// Returns whether the `record` at `offset` is plausible: its fixed part is
+/// consistent, and it fits within the `range` alongside its variable-length
+/// fields, and the data or Central Directory it announces.
+fn is_embedded_record<R>(
+    reader: &R,
+    offset: u64,
+    record: Record,
+    range: &Range<u64>
+)
+    -> Result<bool, R::Error>
+    where R: Reader + ?Sized
+{
+    type CdfhReader<'a> = CentralDirectoryFileHeaderReader<'a>;
+    type EocdReader<'a> = EndOfCentralDirectoryReader<'a>;
+    type LfhReader<'a> = LocalFileHeaderReader<'a>;
+
+    //  Sized for the largest fixed part, that of a Local File Header record.
+    let mut buffer = [0; 30];
+
+    let filled = read_exact(reader, offset, &mut buffer)?;
+    let fixed = buffer.get(..filled).unwrap_or(b"");
+
+    let before = offset.saturating_sub(range.start);
+    let after = range.end.saturating_sub(offset);
+
+    let is_plausible = match record {
+        Record::LocalFileHeader => match LfhReader::new(fixed) {
+            Some(lfh) => {
+                let mut size = lfh.expected_size() as u64;
+
+                //  The size of the data is unknown if deferred to a Data
+                //  Descriptor or a ZIP64 extra field.
+                let compressed_size = lfh.compressed_size();
+
+                if lfh.general_purpose_bit_flag() & 0x08 == 0 &&
+                    compressed_size != u32::MAX
+                {
+                    size += compressed_size as u64;
+                }
+
+                size <= after
+            },
+            None => false,
+        },
+        Record::EndOfCentralDirectory => match EocdReader::new(fixed) {
+            Some(eocd) => {
+                let size = EocdReader::min_size() as u64 +
+                    eocd.comment_size() as u64;
+
+                let nb_local = eocd.nb_local_central_directory_records();
+                let nb = eocd.nb_central_directory_records();
+                let cd_size = eocd.central_directory_size() as u64;
+                let cd_min_size = nb as u64 * CdfhReader::min_size() as u64;
+
+                //  The ZIP64 placeholders defer the checks to the ZIP64
+                //  End of Central Directory record.
+                let is_zip64 = nb == u16::MAX || cd_size == u32::MAX as u64;
+
+                let is_consistent = is_zip64 || (
+                    nb_local <= nb &&
+                    cd_min_size <= cd_size &&
+                    cd_size <= before
+                );
+
+                size <= after && is_consistent
+            },
+            None => false,
+        },
+        _ => false,
+    };
+
+    Ok(is_plausible)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use api::Reader;
+    use utils::test::{
+        ChunkedReader,
+        CopyingReader,
+        FailingReader,
+        TEST_ENTRIES,
+        TestError,
+        test_archive,
+        test_zip64_archive
+    };
+    use zip::low_level::{Code, Diagnostic, IndexSlot, Record};
+    use super::{detect_ambiguities, detect_embedded_archive, has_duplicate};
+
+    fn ambiguities(archive: &[u8]) -> Vec<Diagnostic> {
+        let mut names = [IndexSlot::default(); 16];
+        let mut result = vec!();
+        let sink = &mut |d| result.push(d);
+        detect_ambiguities(archive, &mut [], &mut names, sink).unwrap();
+        result
+    }
+
+    #[test]
+    fn detect_nothing_on_well_formed_archive() {
+        let archive = test_archive(TEST_ENTRIES);
+
+        assert_eq!(ambiguities(&archive), vec!());
+    }
+
+    #[test]
+    fn detect_eocd_in_comment() {
+        let mut archive = test_archive(&[(b"a", b"a")]);
+        let eocd = archive.len() - 22;
+
+        archive.extend_from_slice(b"PK\x05\x06");
+        archive.extend_from_slice(&[0; 18]);
+        archive[eocd + 20] = 22;
+
+        //  The record within the comment also ends at the end of the archive,
+        //  and is therefore the one located.
+        assert_eq!(
+            ambiguities(&archive),
+            vec!(Diagnostic::new(
+                Code::MultipleEndOfCentralDirectory,
+                eocd as u64,
+                Record::EndOfCentralDirectory,
+                "signature"
+            ))
+        );
+    }
+
+    #[test]
+    fn detect_record_count_mismatch() {
+        let mut archive = test_archive(&[(b"a", b"a")]);
+        let eocd = archive.len() - 22;
+        archive[eocd + 10] = 0;
+
+        assert_eq!(
+            ambiguities(&archive),
+            vec!(Diagnostic::new(
+                Code::RecordCountMismatch,
+                eocd as u64 + 10,
+                Record::EndOfCentralDirectory,
+                "nb_central_directory_records"
+            ))
+        );
+    }
+
+    #[test]
+    fn detect_duplicate_names() {
+        let archive = test_archive(&[
+            (b"a", b"1"), (b"b", b"2"), (b"a", b"3"),
+        ]);
+
+        let cd = 3 * 32;
+
+        assert_eq!(
+            ambiguities(&archive),
+            vec!(
+                Diagnostic::new(
+                    Code::DuplicateName,
+                    cd + 46,
+                    Record::CentralDirectoryFileHeader,
+                    "file_name"
+                ),
+                Diagnostic::new(
+                    Code::DuplicateName,
+                    cd + 2 * 47 + 46,
+                    Record::CentralDirectoryFileHeader,
+                    "file_name"
+                ),
+            )
+        );
+    }
+
+    #[test]
+    fn detect_local_name_mismatch() {
+        let mut archive = test_archive(&[(b"a", b"a")]);
+        archive[30] = b'b';
+
+        assert_eq!(
+            ambiguities(&archive),
+            vec!(Diagnostic::new(
+                Code::FieldMismatch,
+                30,
+                Record::LocalFileHeader,
+                "file_name"
+            ))
+        );
+    }
+
+    #[test]
+    fn detect_unicode_path_mismatch() {
+        //  Name: CP437 "\x84" is 'ä', the Unicode Path says 'ö'.
+        let with_extra = |unicode: &[u8]| {
+            let mut archive = test_archive(&[(b"\x84", b"a")]);
+            let cdfh = 32;
+
+            let mut extra = vec!(0x75, 0x70, 5 + unicode.len() as u8, 0, 1);
+            extra.extend_from_slice(&[0; 4]);
+            extra.extend_from_slice(unicode);
+
+            for (i, b) in extra.iter().enumerate() {
+                archive.insert(cdfh + 47 + i, *b);
+            }
+
+            let eocd = archive.len() - 22;
+            archive[cdfh + 30] = extra.len() as u8;
+            archive[eocd + 12] += extra.len() as u8;
+
+            archive
+        };
+
+        assert_eq!(ambiguities(&with_extra("ä".as_bytes())), vec!());
+
+        assert_eq!(
+            ambiguities(&with_extra("ö".as_bytes())),
+            vec!(Diagnostic::new(
+                Code::UnicodePathMismatch,
+                32 + 47,
+                Record::CentralDirectoryFileHeader,
+                "extra_field"
+            ))
+        );
+    }
+
+    /// Returns an archive of two entries, with another archive hidden between
+    /// them.
+    fn archive_with_hidden_archive() -> (Vec<u8>, usize) {
+        let nested = test_archive(&[(b"hidden", b"")]);
+
+        let mut archive = test_archive(&[(b"a", b"a"), (b"b", b"b")]);
+
+        for (i, b) in nested.iter().enumerate() {
+            archive.insert(32 + i, *b);
+        }
+
+        //  Shift the LFH offset of the second entry, and the CD offset.
+        let eocd = archive.len() - 22;
+        let second_cdfh = 64 + nested.len() + 47;
+
+        archive[second_cdfh + 42] += nested.len() as u8;
+        archive[eocd + 16] += nested.len() as u8;
+
+        (archive, nested.len())
+    }
+
+    #[test]
+    fn detect_embedded_archive_between_entries() {
+        let (archive, nested) = archive_with_hidden_archive();
+
+        assert_eq!(
+            ambiguities(&archive),
+            vec!(
+                Diagnostic::new(
+                    Code::MultipleEndOfCentralDirectory,
+                    (32 + nested - 22) as u64,
+                    Record::EndOfCentralDirectory,
+                    "signature"
+                ),
+                Diagnostic::new(
+                    Code::EmbeddedArchive,
+                    32,
+                    Record::LocalFileHeader,
+                    "signature"
+                ),
+            )
+        );
+    }
+
+    #[test]
+    fn detect_through_copying_and_chunked_readers() {
+        let (archive, _) = archive_with_hidden_archive();
+        let expected = ambiguities(&archive);
+
+        let copying = CopyingReader { data: &archive };
+        let chunked: Vec<_> = (1..16)
+            .map(|chunk| ChunkedReader { data: &archive, chunk })
+            .collect();
+
+        let mut readers: Vec<&dyn Reader<Error = Infallible>> = vec!(&copying);
+        readers.extend(chunked.iter().map(|r| r as &dyn Reader<Error = _>));
+
+        for reader in readers {
+            let mut scratch = [0; 1024];
+            let mut names = [IndexSlot::default(); 16];
+            let mut result = vec!();
+
+            let sink = &mut |d| result.push(d);
+            detect_ambiguities(reader, &mut scratch, &mut names, sink)
+                .unwrap();
+
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn detect_embedded_archive_straddling_chunks() {
+        let mut data = [0; 48];
+        data[9..13].copy_from_slice(b"PK\x03\x04");
+
+        //  Chunks overlap by 3 bytes, so that none misses the signature.
+        for size in 4..8 {
+            let reader = CopyingReader { data: &data };
+            let mut scratch = vec!(0; size);
+            let mut result = vec!();
+
+            let sink = &mut |d| result.push(d);
+            detect_embedded_archive(&reader, &mut scratch, 1..48, sink)
+                .unwrap();
+
+            assert_eq!(
+                result,
+                vec!(Diagnostic::new(
+                    Code::EmbeddedArchive,
+                    9,
+                    Record::LocalFileHeader,
+                    "signature"
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn detect_embedded_archive_skipping_implausible_records() {
+        let mut data = [0; 96];
+
+        //  A Local File Header whose name extends beyond the range.
+        data[4..8].copy_from_slice(b"PK\x03\x04");
+        data[30] = 0xff;
+
+        //  An End of Central Directory announcing more local records than
+        //  records.
+        data[40..44].copy_from_slice(b"PK\x05\x06");
+        data[48] = 1;
+
+        //  An End of Central Directory of an empty archive.
+        data[70..74].copy_from_slice(b"PK\x05\x06");
+
+        let mut result = vec!();
+
+        let sink = &mut |d| result.push(d);
+        detect_embedded_archive(&data[..], &mut [], 0..96, sink).unwrap();
+
+        assert_eq!(
+            result,
+            vec!(Diagnostic::new(
+                Code::EmbeddedArchive,
+                70,
+                Record::EndOfCentralDirectory,
+                "signature"
+            ))
+        );
+
+        //  Truncating the range leaves no room for the last record.
+        let mut result = vec!();
+
+        let sink = &mut |d| result.push(d);
+        detect_embedded_archive(&data[..], &mut [], 0..91, sink).unwrap();
+
+        assert_eq!(result, vec!());
+    }
+
+    #[test]
+    fn detect_unchecked_names() {
+        let archive = test_archive(&[
+            (b"a", b"1"), (b"b", b"2"), (b"a", b"3"),
+        ]);
+
+        let cd = 3 * 32;
+        let mut names = [IndexSlot::default(); 1];
+        let mut result = vec!();
+
+        let sink = &mut |d| result.push(d);
+        detect_ambiguities(&archive[..], &mut [], &mut names, sink).unwrap();
+
+        assert_eq!(
+            result,
+            vec!(Diagnostic::new(
+                Code::UncheckedName,
+                cd + 47 + 46,
+                Record::CentralDirectoryFileHeader,
+                "file_name"
+            ))
+        );
+    }
+
+    #[test]
+    fn duplicate_confirmed_by_name() {
+        //  Colliding hashes, as forged slots, are not enough.
+        let archive = test_archive(&[(b"a", b"1"), (b"b", b"2")]);
+
+        let cd = 2 * 32;
+        let end = archive.len() as u64 - 22;
+        let slots = [IndexSlot::new(7, cd), IndexSlot::new(7, cd + 47)];
+
+        let result =
+            has_duplicate(&archive[..], &mut [], &slots, slots[0], b"a", end);
+
+        assert_eq!(result, Ok(false));
+
+        let archive = test_archive(&[(b"a", b"1"), (b"a", b"2")]);
+
+        let result =
+            has_duplicate(&archive[..], &mut [], &slots, slots[0], b"a", end);
+
+        assert_eq!(result, Ok(true));
+    }
+
+    #[test]
+    fn detect_nothing_on_well_formed_zip64_archive() {
+        let archive = test_zip64_archive(TEST_ENTRIES);
+
+        assert_eq!(ambiguities(&archive), vec!());
+    }
+
+    #[test]
+    fn detect_io_on_failing_reader() {
+        let archive = test_zip64_archive(&[(b"a", b"a"), (b"b", b"")]);
+        let mut names = [IndexSlot::default(); 16];
+
+        let reader = FailingReader::new(&archive, usize::MAX);
+        let result =
+                detect_ambiguities(&reader, &mut [], &mut names, &mut |_| ());
+
+        assert_eq!(result, Ok(()));
+
+        for limit in 0..reader.accesses.get() {
+            let reader = FailingReader::new(&archive, limit);
+            let result =
+                detect_ambiguities(&reader, &mut [], &mut names, &mut |_| ());
+
+            assert_eq!(result, Err(TestError));
+        }
+    }
+}
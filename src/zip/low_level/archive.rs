@@ -0,0 +1,295 @@
+//! # Uniform access to the entries of a ZIP archive
+//!
+//! Implements the `Archive` trait for the Central Directory as a whole, its
+//! records being presented as `ZipEntry`.
+
+use core::ops::Range;
+
+use api::{ReadError, Reader, Sink};
+use archive::Archive;
+
+use zip::ZipEntry;
+use zip::raw::CentralDirectoryFileHeaderReader;
+
+use super::cdfh::read_central_directory_file_header;
+use super::eocd::EndOfCentralDirectory;
+use super::extract::{ExtractError, locate_data};
+
+/// The Central Directory of an archive, as located by its End of Central
+/// Directory.
+///
+/// The records are read one at a time, and the entries are looked up by name
+/// linearly; see `Index` for faster lookups.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CentralDirectory<'r, R: ?Sized + 'r> {
+    reader: &'r R,
+    range: Range<u64>,
+    total: u64,
+}
+
+impl<'r, R> CentralDirectory<'r, R>
+    where R: Reader + ?Sized
+{
+    /// Returns the Central Directory of the archive, as located by `eocd`, or
+    /// `None` if it does not lie before the End of Central Directory.
+    pub fn new(reader: &'r R, eocd: &EndOfCentralDirectory)
+        -> Option<CentralDirectory<'r, R>>
+    {
+        eocd.central_directory_range().map(|range| CentralDirectory {
+            reader,
+            range,
+            total: eocd.nb_central_directory_records(),
+        })
+    }
+
+    /// Returns the Central Directory spanning `range`, and holding `total`
+    /// records.
+    pub(crate) fn from_range(reader: &'r R, range: Range<u64>, total: u64)
+        -> CentralDirectory<'r, R>
+    {
+        CentralDirectory { reader, range, total }
+    }
+
+    /// Returns the range of the Central Directory.
+    pub fn range(&self) -> Range<u64> { self.range.clone() }
+
+    /// Returns the number of records announced.
+    pub fn len(&self) -> u64 { self.total }
+
+    /// Returns whether no record is announced.
+    pub fn is_empty(&self) -> bool { self.total == 0 }
+
+    /// Calls `f` with each record and its offset, until it returns `false`.
+    ///
+    /// Returns the number of records read, or `Truncated` if a record is
+    /// incomplete, or the Central Directory ends before the last of them.
+    fn scan<F>(&self, scratch: &mut [u8], mut f: F)
+        -> Result<u64, ReadError<R::Error>>
+        where F: FnMut(u64, CentralDirectoryFileHeaderReader) -> bool
+    {
+        let mut offset = self.range.start;
+        let mut count = 0;
+
+        while count < self.total {
+            let cdfh = read_central_directory_file_header(
+                self.reader,
+                offset,
+                self.range.end,
+                &mut *scratch
+            );
+
+            let cdfh = match cdfh.map_err(ReadError::Io)? {
+                Some(cdfh) if cdfh.raw().len() == cdfh.expected_size() => cdfh,
+                _ => return Err(ReadError::Truncated),
+            };
+
+            count += 1;
+
+            if !f(offset, cdfh) {
+                break;
+            }
+
+            offset = offset.saturating_add(cdfh.raw().len() as u64);
+        }
+
+        Ok(count)
+    }
+}
+
+impl<'r, R> Archive for CentralDirectory<'r, R>
+    where R: Reader + ?Sized
+{
+    type Error = ReadError<R::Error>;
+    type Entry<'e> = ZipEntry<'e>;
+
+    fn entries<S>(&self, scratch: &mut [u8], sink: &mut S)
+        -> Result<u64, Self::Error>
+        where S: for<'e> Sink<Self::Entry<'e>> + ?Sized
+    {
+        self.scan(scratch, |_, cdfh| {
+            sink.push(ZipEntry::new(cdfh));
+            true
+        })
+    }
+
+    fn by_name<'e>(&'e self, name: &[u8], scratch: &'e mut [u8])
+        -> Result<Option<Self::Entry<'e>>, Self::Error>
+    {
+        let mut found = None;
+
+        self.scan(scratch, |offset, cdfh| {
+            if cdfh.file_name() == Some(name) {
+                found = Some(offset);
+            }
+
+            found.is_none()
+        })?;
+
+        let offset = match found {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+
+        let cdfh = read_central_directory_file_header(
+            self.reader,
+            offset,
+            self.range.end,
+            scratch
+        );
+
+        Ok(cdfh.map_err(ReadError::Io)?.map(ZipEntry::new))
+    }
+
+    fn data_range(&self, entry: &Self::Entry<'_>)
+        -> Result<Range<u64>, Self::Error>
+    {
+        data_range(self.reader, entry.central_directory_file_header())
+    }
+}
+
+/// Returns the range of the (compressed) data of the entry, as `locate_data`
+/// does, with its errors reported as a `ReadError`.
+pub(crate) fn data_range<R>(reader: &R, cdfh: CentralDirectoryFileHeaderReader)
+    -> Result<Range<u64>, ReadError<R::Error>>
+    where R: Reader + ?Sized
+{
+    locate_data(reader, cdfh).map_err(|error| match error {
+        ExtractError::DataOutOfBounds => ReadError::Truncated,
+        ExtractError::Io(error) => ReadError::Io(error),
+        _ => ReadError::Malformed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use api::ReadError;
+    use archive::{Archive, Checksum, Entry, EntryKind, Timestamp};
+    use utils::test::{
+        CopyingReader,
+        TEST_ENTRIES,
+        test_archive,
+        test_zip64_archive
+    };
+    use zip::ZipEntry;
+    use zip::low_level::{EndOfCentralDirectory, Extractor};
+
+    use super::CentralDirectory;
+
+    /// Returns the names and data of the entries, through the traits only.
+    fn contents<A: Archive>(archive: &A, data: &[u8])
+        -> Vec<(Vec<u8>, Vec<u8>)>
+    {
+        let mut scratch = [0; 128];
+        let mut names = vec!();
+
+        {
+            let mut sink = |entry: A::Entry<'_>|
+                names.push(entry.name().to_vec());
+
+            archive.entries(&mut scratch, &mut sink).ok().unwrap();
+        }
+
+        names
+            .into_iter()
+            .map(|name| {
+                let entry = archive.by_name(&name, &mut scratch)
+                    .ok()
+                    .unwrap()
+                    .unwrap();
+                let range = archive.data_range(&entry).ok().unwrap();
+                let data = &data[range.start as usize..range.end as usize];
+
+                (name, data.to_vec())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn archive_through_traits() {
+        let expected: Vec<(Vec<u8>, Vec<u8>)> = TEST_ENTRIES
+            .iter()
+            .map(|&(name, data)| (name.to_vec(), data.to_vec()))
+            .collect();
+
+        let archives =
+            [test_archive(TEST_ENTRIES), test_zip64_archive(TEST_ENTRIES)];
+
+        for data in &archives {
+            let eocd =
+                EndOfCentralDirectory::locate(&data[..], &mut []).unwrap();
+
+            let borrowed = CentralDirectory::new(&data[..], &eocd).unwrap();
+            assert_eq!(contents(&borrowed, data), expected);
+
+            let reader = CopyingReader { data };
+            let copied = CentralDirectory::new(&reader, &eocd).unwrap();
+            assert_eq!(contents(&copied, data), expected);
+        }
+    }
+
+    #[test]
+    fn archive_data_range_as_extractor() {
+        let data = test_zip64_archive(TEST_ENTRIES);
+        let eocd = EndOfCentralDirectory::locate(&data[..], &mut []).unwrap();
+        let cd = CentralDirectory::new(&data[..], &eocd).unwrap();
+
+        let entry = cd.by_name(b"dir/nested.txt", &mut []).unwrap().unwrap();
+        let cdfh = entry.central_directory_file_header();
+        let extractor = Extractor::new(&data[..], cdfh).unwrap();
+
+        assert_eq!(cd.data_range(&entry), Ok(extractor.data_range()));
+        assert_eq!(cd.by_name(b"missing", &mut []), Ok(None));
+    }
+
+    #[test]
+    fn archive_failure_on_truncated_central_directory() {
+        let data = test_archive(TEST_ENTRIES);
+        let eocd = EndOfCentralDirectory::locate(&data[..], &mut []).unwrap();
+        let cd = CentralDirectory::new(&data[..], &eocd).unwrap();
+
+        //  The copied records do not fit in the scratch buffer.
+        let reader = CopyingReader { data: &data };
+        let copied = CentralDirectory::new(&reader, &eocd).unwrap();
+        let mut count = 0;
+
+        let result = {
+            let mut sink = |_: ZipEntry| count += 1;
+            copied.entries(&mut [0; 50], &mut sink)
+        };
+
+        assert_eq!(result, Err(ReadError::Truncated));
+        assert_eq!(count, 0);
+
+        let mut sink = |_: ZipEntry| ();
+        assert_eq!(cd.entries(&mut [], &mut sink), Ok(4));
+
+        //  The End of Central Directory announces a record too many.
+        let mut data = data.clone();
+        let eocd_offset = data.len() - 22;
+        data[eocd_offset + 8] = 5;
+        data[eocd_offset + 10] = 5;
+
+        let eocd = EndOfCentralDirectory::locate(&data[..], &mut []).unwrap();
+        let cd = CentralDirectory::new(&data[..], &eocd).unwrap();
+        assert_eq!(cd.entries(&mut [], &mut sink), Err(ReadError::Truncated));
+    }
+
+    #[test]
+    fn entry_metadata() {
+        let data = test_archive(TEST_ENTRIES);
+        let eocd = EndOfCentralDirectory::locate(&data[..], &mut []).unwrap();
+        let cd = CentralDirectory::new(&data[..], &eocd).unwrap();
+
+        let file = cd.by_name(b"hello.txt", &mut []).unwrap().unwrap();
+
+        assert_eq!(file.kind(), EntryKind::File);
+        assert_eq!(file.compressed_size(), 13);
+        assert_eq!(file.uncompressed_size(), 13);
+        assert_eq!(file.modified(), Timestamp::from_dos(0x21, 0));
+        assert_eq!(file.checksum(), Some(Checksum::Crc32(0xec4ac3d0)));
+
+        let directory = cd.by_name(b"dir/", &mut []).unwrap().unwrap();
+
+        assert_eq!(directory.kind(), EntryKind::Directory);
+    }
+}
@@ -0,0 +1,391 @@
+//! # Asynchronous access to the records of a ZIP archive
+//!
+//! The futures of this module drive an `AsyncReader`, requesting only the
+//! ranges of the archive they need, and are independent of any runtime.
+
+use core::cmp::min;
+use core::convert::Infallible;
+use core::future::Future;
+use core::ops::Range;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use api::{AsyncReader, ReadError, Sink};
+
+use zip::raw::CentralDirectoryFileHeaderReader;
+
+use super::eocd::EndOfCentralDirectory;
+use super::plan::{EndOfCentralDirectoryPlanner, Step, ZIP64_RECORDS_SIZE};
+
+/// A future locating the End of Central Directory of an archive, as
+/// `EndOfCentralDirectory::locate` does.
+///
+/// See `EndOfCentralDirectory::locate_async`.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct LocateEndOfCentralDirectory<'a, R: AsyncReader + ?Sized + 'a> {
+    reader: &'a mut R,
+    scratch: &'a mut [u8],
+    //  Holds the ZIP64 records, should they not fit in scratch.
+    records: [u8; ZIP64_RECORDS_SIZE],
+    planner: Option<EndOfCentralDirectoryPlanner>,
+    filled: usize,
+}
+
+impl<'a, R> LocateEndOfCentralDirectory<'a, R>
+    where R: AsyncReader + ?Sized
+{
+    pub(crate) fn new(reader: &'a mut R, scratch: &'a mut [u8])
+        -> LocateEndOfCentralDirectory<'a, R>
+    {
+        LocateEndOfCentralDirectory {
+            reader,
+            scratch,
+            records: [0; ZIP64_RECORDS_SIZE],
+            planner: None,
+            filled: 0,
+        }
+    }
+}
+
+impl<'a, R> Future for LocateEndOfCentralDirectory<'a, R>
+    where R: AsyncReader + ?Sized
+{
+    type Output = Result<EndOfCentralDirectory, ReadError<R::Error>>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context)
+        -> Poll<Self::Output>
+    {
+        let this = self.get_mut();
+
+        loop {
+            let planner = match this.planner {
+                Some(ref mut planner) => planner,
+                None => {
+                    let size = match this.reader.poll_size(context) {
+                        Poll::Ready(Ok(size)) => size,
+                        Poll::Ready(Err(error)) =>
+                            return Poll::Ready(Err(ReadError::Io(error))),
+                        Poll::Pending => return Poll::Pending,
+                    };
+
+                    let max_tail = this.scratch.len();
+                    this.planner =
+                        Some(EndOfCentralDirectoryPlanner::new(size, max_tail));
+                    continue;
+                },
+            };
+
+            let range = match planner.next() {
+                Step::Need(range) => range,
+                Step::Done(result) =>
+                    return Poll::Ready(result.map_err(from_infallible)),
+            };
+
+            //  The tail always fits in scratch, the ZIP64 records in records.
+            let wanted = range.end.saturating_sub(range.start);
+            let buffer = if wanted <= this.scratch.len() as u64 {
+                &mut this.scratch[..]
+            } else {
+                &mut this.records[..]
+            };
+            let length = min(wanted, buffer.len() as u64) as usize;
+            let buffer = buffer.get_mut(..length).unwrap_or(&mut []);
+
+            let filled = &mut this.filled;
+
+            match poll_fill(this.reader, context, range, buffer, filled) {
+                Poll::Ready(Ok(())) => (),
+                Poll::Ready(Err(error)) =>
+                    return Poll::Ready(Err(ReadError::Io(error))),
+                Poll::Pending => return Poll::Pending,
+            }
+
+            planner.provide(buffer.get(..this.filled).unwrap_or(b""));
+            this.filled = 0;
+        }
+    }
+}
+
+/// Returns a future pushing each of the Central Directory File Header records
+/// of the archive, as located by the `eocd`, into the `sink`, and resolving to
+/// the number of records pushed.
+///
+/// The Central Directory is read in chunks, each as large as the `scratch`
+/// buffer, which must thus be able to hold any single record whole; `Truncated`
+/// is returned otherwise, as well as if the Central Directory ends before the
+/// number of records it announces. `Malformed` is returned if the Central
+/// Directory does not lie before the End of Central Directory.
+///
+/// As with `CentralDirectoryFileHeaderIterator`, the records are not validated
+/// in any way.
+pub fn list_central_directory<'a, R, S>(
+    reader: &'a mut R,
+    eocd: &EndOfCentralDirectory,
+    scratch: &'a mut [u8],
+    sink: &'a mut S
+)
+    -> ListCentralDirectory<'a, R, S>
+    where R: AsyncReader + ?Sized,
+          S: for<'b> Sink<CentralDirectoryFileHeaderReader<'b>> + ?Sized
+{
+    ListCentralDirectory {
+        reader,
+        scratch,
+        sink,
+        range: eocd.central_directory_range(),
+        total: eocd.nb_central_directory_records(),
+        count: 0,
+        pending: 0,
+    }
+}
+
+/// A future listing the Central Directory File Header records of an archive.
+///
+/// See `list_central_directory`.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct ListCentralDirectory<'a, R, S>
+    where R: AsyncReader + ?Sized + 'a,
+          S: ?Sized + 'a
+{
+    reader: &'a mut R,
+    scratch: &'a mut [u8],
+    sink: &'a mut S,
+    //  The range of the Central Directory not read yet.
+    range: Option<Range<u64>>,
+    total: u64,
+    count: u64,
+    //  The number of bytes read, but not pushed yet, at the start of scratch.
+    pending: usize,
+}
+
+impl<'a, R, S> Future for ListCentralDirectory<'a, R, S>
+    where R: AsyncReader + ?Sized,
+          S: for<'b> Sink<CentralDirectoryFileHeaderReader<'b>> + ?Sized
+{
+    type Output = Result<u64, ReadError<R::Error>>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context)
+        -> Poll<Self::Output>
+    {
+        let this = self.get_mut();
+
+        loop {
+            let mut consumed = 0;
+
+            while this.count < this.total {
+                let bytes = this.scratch
+                    .get(consumed..this.pending)
+                    .unwrap_or(b"");
+
+                let cdfh = match CentralDirectoryFileHeaderReader::new(bytes) {
+                    Some(cdfh) if cdfh.raw().len() == cdfh.expected_size() =>
+                        cdfh,
+                    _ => break,
+                };
+
+                consumed += cdfh.raw().len();
+                this.count += 1;
+                this.sink.push(cdfh);
+            }
+
+            this.scratch.copy_within(consumed..this.pending, 0);
+            this.pending -= consumed;
+
+            if this.count == this.total {
+                return Poll::Ready(Ok(this.count));
+            }
+
+            let range = match this.range {
+                Some(ref range) => range.clone(),
+                None => return Poll::Ready(Err(ReadError::Malformed)),
+            };
+
+            if range.start >= range.end || this.pending == this.scratch.len() {
+                return Poll::Ready(Err(ReadError::Truncated));
+            }
+
+            let room = (this.scratch.len() - this.pending) as u64;
+            let end = min(range.end, range.start.saturating_add(room));
+            let length = (end - range.start) as usize;
+
+            let buffer = this.scratch
+                .get_mut(this.pending..this.pending + length)
+                .unwrap_or(&mut []);
+
+            let read = match this.reader.poll_read(context, range, buffer) {
+                Poll::Ready(Ok(read)) => min(read, length),
+                Poll::Ready(Err(error)) =>
+                    return Poll::Ready(Err(ReadError::Io(error))),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if read == 0 {
+                return Poll::Ready(Err(ReadError::Truncated));
+            }
+
+            this.pending += read;
+            this.range = this.range
+                .as_ref()
+                .map(|range| range.start + read as u64..range.end);
+        }
+    }
+}
+
+/// Polls the `reader` until the `buffer` is filled with the bytes starting at
+/// `range.start`, or the archive ends, tracking progress in `filled` across
+/// polls.
+fn poll_fill<R>(
+    reader: &mut R,
+    context: &mut Context,
+    range: Range<u64>,
+    buffer: &mut [u8],
+    filled: &mut usize
+)
+    -> Poll<Result<(), R::Error>>
+    where R: AsyncReader + ?Sized
+{
+    while *filled < buffer.len() {
+        let start = range.start.saturating_add(*filled as u64);
+        let target = buffer.get_mut(*filled..).unwrap_or(&mut []);
+        let length = target.len();
+
+        match reader.poll_read(context, start..range.end, target) {
+            Poll::Ready(Ok(0)) => break,
+            Poll::Ready(Ok(read)) => *filled += min(read, length),
+            Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+            Poll::Pending => return Poll::Pending,
+        }
+    }
+
+    Poll::Ready(Ok(()))
+}
+
+fn from_infallible<E>(error: ReadError<Infallible>) -> ReadError<E> {
+    match error {
+        ReadError::Truncated => ReadError::Truncated,
+        ReadError::Malformed => ReadError::Malformed,
+        ReadError::Io(error) => match error {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use api::ReadError;
+    use utils::test::{
+        TEST_ENTRIES,
+        TestServer,
+        block_on,
+        test_archive,
+        test_zip64_archive
+    };
+    use zip::raw::CentralDirectoryFileHeaderReader;
+    use zip::low_level::{
+        CentralDirectoryFileHeaderIterator,
+        EndOfCentralDirectory
+    };
+    use super::list_central_directory;
+
+    #[test]
+    fn locate_async_as_locate() {
+        let archives =
+            [test_archive(TEST_ENTRIES), test_zip64_archive(TEST_ENTRIES)];
+
+        for archive in &archives {
+            let expected = EndOfCentralDirectory::locate(&archive[..], &mut []);
+
+            for &(chunk, scratch) in &[(1, 22), (7, 64), (1024, 1024)] {
+                let mut server = TestServer::new(archive, chunk);
+                let mut scratch = vec!(0; scratch);
+
+                let eocd = block_on(
+                    EndOfCentralDirectory::locate_async(
+                        &mut server,
+                        &mut scratch
+                    )
+                );
+
+                assert_eq!(eocd, expected);
+                assert!(server.requests > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn locate_async_failure_on_truncated_archive() {
+        let mut server = TestServer::new(&[0; 21], 4);
+        let mut scratch = [0; 64];
+
+        let eocd = block_on(
+            EndOfCentralDirectory::locate_async(&mut server, &mut scratch)
+        );
+
+        assert_eq!(eocd, Err(ReadError::Truncated));
+    }
+
+    #[test]
+    fn list_central_directory_as_iterator() {
+        let archive = test_zip64_archive(TEST_ENTRIES);
+
+        let eocd =
+            EndOfCentralDirectory::locate(&archive[..], &mut []).unwrap();
+        let cd = eocd.central_directory_range().unwrap();
+
+        let expected: Vec<Vec<u8>> = CentralDirectoryFileHeaderIterator::new(
+            &archive[cd.start as usize..cd.end as usize],
+            eocd.nb_central_directory_records()
+        )
+            .map(|cdfh| cdfh.raw().to_vec())
+            .collect();
+
+        for &(chunk, scratch) in &[(1, 100), (5, 128), (1024, 1024)] {
+            let mut server = TestServer::new(&archive, chunk);
+            let mut scratch = vec!(0; scratch);
+            let mut records: Vec<Vec<u8>> = vec!();
+
+            let count = {
+                let mut sink = |cdfh: CentralDirectoryFileHeaderReader|
+                    records.push(cdfh.raw().to_vec());
+
+                block_on(list_central_directory(
+                    &mut server,
+                    &eocd,
+                    &mut scratch,
+                    &mut sink
+                ))
+            };
+
+            assert_eq!(count, Ok(4));
+            assert_eq!(records, expected);
+        }
+    }
+
+    #[test]
+    fn list_central_directory_failure_on_small_scratch() {
+        let archive = test_archive(TEST_ENTRIES);
+
+        let eocd =
+            EndOfCentralDirectory::locate(&archive[..], &mut []).unwrap();
+
+        let mut server = TestServer::new(&archive, 1024);
+        let mut scratch = [0; 50];
+        let mut records = vec!();
+
+        let count = {
+            let mut sink = |cdfh: CentralDirectoryFileHeaderReader|
+                records.push(cdfh.raw().to_vec());
+
+            block_on(list_central_directory(
+                &mut server,
+                &eocd,
+                &mut scratch,
+                &mut sink
+            ))
+        };
+
+        //  "empty" fits, but is preceded by "hello.txt", which does not.
+        assert_eq!(count, Err(ReadError::Truncated));
+        assert_eq!(records, Vec::<Vec<u8>>::new());
+    }
+}
@@ -1,9 +1,12 @@
 //! # Low-level access to the Central Directory File Header records of a ZIP
 //! archive
 
-use std::iter::Iterator;
+use core::cmp::min;
+use core::iter::Iterator;
 
+use api::Reader;
 use utils::Slice;
+use utils::read::{read_contiguous, read_exact};
 
 use zip::raw::CentralDirectoryFileHeaderReader;
 
@@ -23,7 +26,7 @@ use zip::raw::CentralDirectoryFileHeaderReader;
 #[derive(Debug)]
 pub struct CentralDirectoryFileHeaderIterator<'a> {
     data: Slice<'a>,
-    remaining: usize,
+    remaining: u64,
 }
 
 impl<'a> CentralDirectoryFileHeaderIterator<'a> {
@@ -37,7 +40,7 @@ impl<'a> CentralDirectoryFileHeaderIterator<'a> {
     /// The `total` parameter is the maximum number of records that will be
     /// returned by the iterator. See 
     /// `EndOfCentralDirectoryReader::nb_central_directory_records`.
-    pub fn new(slice: &'a [u8], total: usize)
+    pub fn new(slice: &'a [u8], total: u64)
         -> CentralDirectoryFileHeaderIterator<'a>
     {
         CentralDirectoryFileHeaderIterator {
@@ -70,11 +73,56 @@ impl<'a> Iterator for CentralDirectoryFileHeaderIterator<'a> {
     }
 }
 
+/// Returns the Central Directory File Header record at `offset`, not extending
+/// beyond `end`, either borrowed from the reader or copied into the `scratch`
+/// buffer.
+///
+/// The fixed part of the record is read first, then the record as a whole, so
+/// that a record straddling chunks of the reader is assembled into the
+/// `scratch` buffer.
+///
+/// Returns `None` if fewer bytes than the fixed part of a record are available.
+/// A record copied into a `scratch` buffer smaller than itself is truncated.
+///
+/// Note: the signature is not checked.
+pub fn read_central_directory_file_header<'a, R>(
+    reader: &'a R,
+    offset: u64,
+    end: u64,
+    scratch: &'a mut [u8]
+)
+    -> Result<Option<CentralDirectoryFileHeaderReader<'a>>, R::Error>
+    where R: Reader + ?Sized + 'a
+{
+    type CdfhReader<'a> = CentralDirectoryFileHeaderReader<'a>;
+
+    let mut fixed = [0; 46];
+
+    let size = {
+        let limit = min(end.saturating_sub(offset), fixed.len() as u64);
+        let fixed = fixed.get_mut(..limit as usize).unwrap_or(&mut []);
+        let filled = read_exact(reader, offset, fixed)?;
+
+        match CdfhReader::new(fixed.get(..filled).unwrap_or(b"")) {
+            Some(cdfh) => cdfh.expected_size(),
+            None => return Ok(None),
+        }
+    };
+
+    let end = min(offset.saturating_add(size as u64), end);
+    let slice = read_contiguous(reader, offset..end, scratch)?;
+
+    Ok(CdfhReader::new(slice))
+}
+
 #[cfg(test)]
 mod tests {
+    use api::Reader;
     use utils::position;
+    use utils::test::{ChunkedReader, CopyingReader, test_archive};
     use zip::raw::CentralDirectoryFileHeaderReader as CdfhReader;
     use super::CentralDirectoryFileHeaderIterator as CdfhIterator;
+    use super::read_central_directory_file_header;
 
     #[test]
     fn iter_cdfh_none_on_too_small_slice() {
@@ -92,7 +140,7 @@ mod tests {
         for count in 0..40 {
             let it = CdfhIterator::new(&v[..], count);
 
-            assert_eq!(it.count(), count);
+            assert_eq!(it.count() as u64, count);
         }
     }
 
@@ -137,4 +185,65 @@ mod tests {
         assert_eq!(position(all[0].raw(), &v[..]), Some(0));
         assert_eq!(position(all[1].raw(), &v[..]), Some(min_size + 40));
     }
+
+    #[test]
+    fn read_cdfh_borrowed_or_copied() {
+        let archive = test_archive(&[(b"a.txt", b"1")]);
+        let cd = (archive.len() - 22 - 51) as u64;
+        let end = (archive.len() - 22) as u64;
+
+        let borrowed =
+            read_central_directory_file_header(&archive[..], cd, end, &mut [])
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(borrowed.file_name(), Some(&b"a.txt"[..]));
+
+        let reader = CopyingReader { data: &archive };
+        let mut scratch = [0; 64];
+        let copied =
+            read_central_directory_file_header(&reader, cd, end, &mut scratch)
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(copied.raw(), borrowed.raw());
+    }
+
+    #[test]
+    fn read_cdfh_assembled_across_chunks() {
+        let archive = test_archive(&[(b"a.txt", b"1")]);
+        let cd = archive.len() - 22 - 51;
+        let (start, end) = (cd as u64, (archive.len() - 22) as u64);
+
+        let reader = ChunkedReader { data: &archive, chunk: 16 };
+        let scratch = &mut [0; 64];
+        let cdfh =
+            read_central_directory_file_header(&reader, start, end, scratch)
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(cdfh.raw(), &archive[cd..cd + 51]);
+    }
+
+    #[test]
+    fn read_cdfh_none_on_short_read() {
+        let archive = test_archive(&[(b"a.txt", b"1")]);
+        let cd = (archive.len() - 22 - 51) as u64;
+        let size = Reader::size(&archive[..]).unwrap();
+
+        let reader = CopyingReader { data: &archive };
+        let mut scratch = [0; 45];
+
+        assert_eq!(
+            read_central_directory_file_header(&reader, cd, size, &mut scratch),
+            Ok(None)
+        );
+
+        let archive = &archive[..];
+
+        assert_eq!(
+            read_central_directory_file_header(archive, cd, cd + 45, &mut []),
+            Ok(None)
+        );
+    }
 }
@@ -0,0 +1,232 @@
+//! # Verification of the CRC-32 of the entries of a ZIP archive
+
+use checksum::Crc32;
+use utils::{Slice, read_u16_le};
+
+use zip::raw::{CentralDirectoryFileHeaderReader, DataDescriptorReader};
+
+use super::extra::ExtraFieldIterator;
+
+/// The outcome of a CRC-32 verification.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Verification {
+    /// The CRC-32 of the data matches the stored CRC-32.
+    Match,
+    /// The CRC-32 of the data differs from the stored CRC-32.
+    Mismatch {
+        /// The stored CRC-32.
+        expected: u32,
+        /// The CRC-32 of the data.
+        actual: u32,
+    },
+    /// The stored CRC-32 is not meant to be verified, as is the case for AE-2
+    /// encrypted entries, whose integrity is instead guaranteed by their
+    /// authentication code.
+    Skipped,
+}
+
+/// A verifier of the CRC-32 of an entry.
+///
+/// The uncompressed data of the entry is fed, chunk by chunk, through
+/// `update`, and the outcome is obtained through `verify`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Crc32Verifier {
+    expected: Option<u32>,
+    crc: Crc32,
+}
+
+impl Crc32Verifier {
+    /// Returns an instance verifying against the `expected` CRC-32.
+    pub fn new(expected: u32) -> Crc32Verifier {
+        Crc32Verifier { expected: Some(expected), crc: Crc32::new() }
+    }
+
+    /// Returns an instance verifying against the CRC-32 of the Central
+    /// Directory File Header record, or skipping verification for an AE-2
+    /// encrypted entry.
+    pub fn from_central_directory_file_header(
+        cdfh: CentralDirectoryFileHeaderReader
+    )
+        -> Crc32Verifier
+    {
+        if is_ae2(cdfh) {
+            Crc32Verifier { expected: None, crc: Crc32::new() }
+        } else {
+            Crc32Verifier::new(cdfh.crc32())
+        }
+    }
+
+    /// Returns an instance verifying against the CRC-32 of the Data Descriptor
+    /// record, or skipping verification for an AE-2 encrypted entry.
+    ///
+    /// The `cdfh` parameter is the Central Directory File Header record of the
+    /// entry, used to detect AE-2 encryption.
+    pub fn from_data_descriptor(
+        dd: DataDescriptorReader,
+        cdfh: CentralDirectoryFileHeaderReader
+    )
+        -> Crc32Verifier
+    {
+        if is_ae2(cdfh) {
+            Crc32Verifier { expected: None, crc: Crc32::new() }
+        } else {
+            Crc32Verifier::new(dd.crc32())
+        }
+    }
+
+    /// Processes the next chunk of uncompressed data.
+    pub fn update(&mut self, chunk: &[u8]) {
+        if self.expected.is_some() {
+            self.crc.update(chunk);
+        }
+    }
+
+    /// Returns the outcome of the verification, for the data processed so far.
+    pub fn verify(&self) -> Verification {
+        match self.expected {
+            Some(expected) if expected == self.crc.value() =>
+                Verification::Match,
+            Some(expected) =>
+                Verification::Mismatch {
+                    expected,
+                    actual: self.crc.value()
+                },
+            None => Verification::Skipped,
+        }
+    }
+}
+
+/// The ID of the AE-x extra field.
+const AES_ID: u16 = 0x9901;
+
+/// Returns whether the entry is encrypted with AE-2.
+pub(crate) fn is_ae2(cdfh: CentralDirectoryFileHeaderReader) -> bool {
+    match aes_extra_field(cdfh) {
+        Some(data) => read_u16_le(data) == Some(2),
+        None => false,
+    }
+}
+
+/// Returns the actual compression method of an entry encrypted with AE-x,
+/// whose Central Directory File Header record announces method 99 instead.
+pub(crate) fn aes_compression_method(cdfh: CentralDirectoryFileHeaderReader)
+    -> Option<u16>
+{
+    aes_extra_field(cdfh)
+        .and_then(|data| data.slice(5..7))
+        .and_then(read_u16_le)
+}
+
+/// Returns the data of the AE-x extra field of the entry, if any.
+///
+/// The AE-x extra field is composed of:
+/// - a 2 bytes vendor version, 1 for AE-1 and 2 for AE-2,
+/// - a 2 bytes vendor ID, "AE",
+/// - a 1 byte encryption strength,
+/// - a 2 bytes actual compression method.
+fn aes_extra_field(cdfh: CentralDirectoryFileHeaderReader) -> Option<Slice> {
+    let data = cdfh
+        .extra_field()
+        .and_then(|extra| ExtraFieldIterator::find_id(extra, AES_ID))
+        .and_then(|extra| extra.data())
+        .map(Slice::new);
+
+    match data {
+        Some(data) if data.slice(2..4).map(|s| s.raw()) == Some(&b"AE"[..]) =>
+            Some(data),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use checksum::Crc32;
+    use zip::raw::{CentralDirectoryFileHeaderReader, DataDescriptorReader};
+    use super::{Crc32Verifier, Verification};
+
+    fn cdfh_bytes(crc32: u32, extra: &[u8]) -> Vec<u8> {
+        let mut v = vec!(0; CentralDirectoryFileHeaderReader::min_size());
+        v[16..20].copy_from_slice(&crc32.to_le_bytes());
+        v[30] = extra.len() as u8;
+        v.extend_from_slice(extra);
+        v
+    }
+
+    #[test]
+    fn verify_match_on_chunks() {
+        let data = b"Hello, World!";
+        let mut verifier = Crc32Verifier::new(Crc32::checksum(data));
+
+        for chunk in data.chunks(3) {
+            verifier.update(chunk);
+        }
+
+        assert_eq!(verifier.verify(), Verification::Match);
+    }
+
+    #[test]
+    fn verify_mismatch() {
+        let mut verifier = Crc32Verifier::new(0x12345678);
+        verifier.update(b"123456789");
+
+        assert_eq!(
+            verifier.verify(),
+            Verification::Mismatch { expected: 0x12345678, actual: 0xcbf43926 }
+        );
+    }
+
+    #[test]
+    fn verify_cdfh_match() {
+        let v = cdfh_bytes(0xcbf43926, b"");
+        let cdfh = CentralDirectoryFileHeaderReader::new(&v).unwrap();
+
+        let mut verifier =
+            Crc32Verifier::from_central_directory_file_header(cdfh);
+        verifier.update(b"123456789");
+
+        assert_eq!(verifier.verify(), Verification::Match);
+    }
+
+    #[test]
+    fn verify_cdfh_skipped_on_ae2() {
+        let ae2 = [0x01, 0x99, 7, 0, 2, 0, b'A', b'E', 3, 8, 0];
+        let v = cdfh_bytes(0, &ae2);
+        let cdfh = CentralDirectoryFileHeaderReader::new(&v).unwrap();
+
+        let mut verifier =
+            Crc32Verifier::from_central_directory_file_header(cdfh);
+        verifier.update(b"123456789");
+
+        assert_eq!(verifier.verify(), Verification::Skipped);
+    }
+
+    #[test]
+    fn verify_cdfh_checked_on_ae1() {
+        let ae1 = [0x01, 0x99, 7, 0, 1, 0, b'A', b'E', 3, 8, 0];
+        let v = cdfh_bytes(0, &ae1);
+        let cdfh = CentralDirectoryFileHeaderReader::new(&v).unwrap();
+
+        let mut verifier =
+            Crc32Verifier::from_central_directory_file_header(cdfh);
+        verifier.update(b"123456789");
+
+        assert_eq!(
+            verifier.verify(),
+            Verification::Mismatch { expected: 0, actual: 0xcbf43926 }
+        );
+    }
+
+    #[test]
+    fn verify_dd_match() {
+        let v = cdfh_bytes(0, b"");
+        let cdfh = CentralDirectoryFileHeaderReader::new(&v).unwrap();
+
+        let d = [0x26, 0x39, 0xf4, 0xcb, 9, 0, 0, 0, 9, 0, 0, 0];
+        let dd = DataDescriptorReader::new(&d).unwrap();
+
+        let mut verifier = Crc32Verifier::from_data_descriptor(dd, cdfh);
+        verifier.update(b"123456789");
+
+        assert_eq!(verifier.verify(), Verification::Match);
+    }
+}
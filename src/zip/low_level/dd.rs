@@ -0,0 +1,111 @@
+//! # Low-level access to the Data Descriptor records of a ZIP archive
+
+use utils::{Slice, read_u32_le, take};
+
+use zip::raw::{DataDescriptorReader, Zip64DataDescriptorReader};
+
+/// Returns the Data Descriptor record at the start of the slice, or `None` if
+/// the slice is too short.
+///
+/// The signature of the record being optional, the record is deemed to have one
+/// if its first 4 bytes match the expected signature. An unsigned record whose
+/// CRC-32 matches the signature is therefore misinterpreted; this is the
+/// heuristic used by most implementations.
+pub fn read_data_descriptor<'a>(slice: &'a [u8])
+    -> Option<DataDescriptorReader<'a>>
+{
+    type DdReader<'a> = DataDescriptorReader<'a>;
+
+    let has_signature =
+        read_u32_le(Slice::new(slice)) == Some(DdReader::expected_signature());
+
+    let size = if has_signature {
+        DdReader::max_size()
+    } else {
+        DdReader::min_size()
+    };
+
+    DdReader::new(take(slice, size))
+}
+
+/// Returns the ZIP64 Data Descriptor record at the start of the slice, or
+/// `None` if the slice is too short.
+///
+/// The signature is detected with the same heuristic as
+/// `read_data_descriptor`.
+pub fn read_zip64_data_descriptor<'a>(slice: &'a [u8])
+    -> Option<Zip64DataDescriptorReader<'a>>
+{
+    type DdReader<'a> = Zip64DataDescriptorReader<'a>;
+
+    let has_signature =
+        read_u32_le(Slice::new(slice)) == Some(DdReader::expected_signature());
+
+    let size = if has_signature {
+        DdReader::max_size()
+    } else {
+        DdReader::min_size()
+    };
+
+    DdReader::new(take(slice, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_data_descriptor, read_zip64_data_descriptor};
+
+    #[test]
+    fn read_dd_none_on_too_small_slice() {
+        let v = [0; 11];
+        for length in 0..v.len() {
+            assert_eq!(read_data_descriptor(&v[..length]), None);
+        }
+    }
+
+    #[test]
+    fn read_dd_none_on_truncated_signed_slice() {
+        let v = b"PK\x07\x08\x01\x00\x00\x00\x02\x00\x00\x00\x03\x00\x00";
+        assert_eq!(read_data_descriptor(&v[..]), None);
+    }
+
+    #[test]
+    fn read_dd_without_signature() {
+        let v = [1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0];
+        let dd = read_data_descriptor(&v).unwrap();
+
+        assert!(!dd.has_signature());
+        assert_eq!(dd.crc32(), 1);
+        assert_eq!(dd.uncompressed_size(), 3);
+    }
+
+    #[test]
+    fn read_dd_with_signature() {
+        let v = b"PK\x07\x08\x01\x00\x00\x00\x02\x00\x00\x00\x03\x00\x00\x00";
+        let dd = read_data_descriptor(&v[..]).unwrap();
+
+        assert!(dd.has_signature());
+        assert_eq!(dd.crc32(), 1);
+        assert_eq!(dd.uncompressed_size(), 3);
+    }
+
+    #[test]
+    fn read_zip64_dd_none_on_too_small_slice() {
+        let v = [0; 19];
+        for length in 0..v.len() {
+            assert_eq!(read_zip64_data_descriptor(&v[..length]), None);
+        }
+    }
+
+    #[test]
+    fn read_zip64_dd_with_signature() {
+        let mut v = b"PK\x07\x08\x01\x00\x00\x00".to_vec();
+        v.extend_from_slice(&[2, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0, 1, 0, 0, 0]);
+
+        let dd = read_zip64_data_descriptor(&v).unwrap();
+
+        assert!(dd.has_signature());
+        assert_eq!(dd.crc32(), 1);
+        assert_eq!(dd.compressed_size(), 0x1_0000_0002);
+        assert_eq!(dd.uncompressed_size(), 0x1_0000_0003);
+    }
+}
@@ -0,0 +1,170 @@
+//! # Diagnostics reported on the structure of a ZIP archive
+
+/// The severity of a diagnostic.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    /// The archive is not well-formed, and different tools may interpret it
+    /// differently, or not at all.
+    Error,
+    /// The archive is well-formed, but uses unusual or unsupported features.
+    Warning,
+}
+
+/// The kind of record a diagnostic applies to.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Record {
+    /// An End of Central Directory record.
+    EndOfCentralDirectory,
+    /// A ZIP64 End of Central Directory record.
+    Zip64EndOfCentralDirectory,
+    /// A Central Directory File Header record.
+    CentralDirectoryFileHeader,
+    /// A Local File Header record.
+    LocalFileHeader,
+    /// A Data Descriptor record.
+    DataDescriptor,
+}
+
+/// The code of a diagnostic.
+///
+/// The numeric value of each code, available through `Code::id`, is stable:
+/// it is never reused nor re-assigned, and can therefore be stored or shown to
+/// users.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[repr(u16)]
+pub enum Code {
+    /// No End of Central Directory record could be located.
+    MissingEndOfCentralDirectory = 1,
+    /// The signature of the record does not match the expected signature.
+    InvalidSignature = 2,
+    /// The record extends beyond the end of the available data.
+    TruncatedRecord = 3,
+    /// The archive spans multiple disks.
+    MultipleDisks = 4,
+    /// The number of records on this disk differs from the total number of
+    /// records, in a single disk archive.
+    InconsistentRecordCounts = 5,
+    /// The Central Directory extends beyond the End of Central Directory.
+    CentralDirectoryOutOfBounds = 6,
+    /// Unaccounted bytes lie between the Central Directory and the End of
+    /// Central Directory.
+    CentralDirectoryGap = 7,
+    /// The number of records in the Central Directory differs from the number
+    /// announced by the End of Central Directory.
+    RecordCountMismatch = 8,
+    /// The size of the Central Directory differs from the size announced by the
+    /// End of Central Directory.
+    CentralDirectorySizeMismatch = 9,
+    /// The version needed to extract is greater than the latest version of the
+    /// specification known to this library.
+    UnsupportedVersion = 10,
+    /// The date or time is not a valid MS-DOS date or time.
+    InvalidTimestamp = 11,
+    /// The Local File Header is not located before the Central Directory.
+    LocalFileHeaderOutOfBounds = 12,
+    /// A field of the record differs from the corresponding field of the
+    /// Central Directory File Header.
+    FieldMismatch = 13,
+    /// The data of the entry extends beyond the start of the Central Directory.
+    EntryDataOutOfBounds = 14,
+    /// The record uses ZIP64 placeholders, which no ZIP64 record or extra
+    /// field resolves.
+    Zip64Placeholder = 15,
+    /// Another End of Central Directory record candidate exists, possibly
+    /// within the comment of the located one.
+    MultipleEndOfCentralDirectory = 16,
+    /// Another Central Directory File Header record bears the same name.
+    DuplicateName = 17,
+    /// The Info-ZIP Unicode Path extra field differs from the file name.
+    UnicodePathMismatch = 18,
+    /// Data outside of any entry contains the signature of a record, such as
+    /// a hidden entry or a nested archive.
+    EmbeddedArchive = 19,
+    /// The name of the record, and of those following it, could not be checked
+    /// for duplicates, for lack of slots.
+    UncheckedName = 20,
+}
+
+impl Code {
+    /// Returns the stable numeric identifier of the code.
+    pub fn id(&self) -> u16 { *self as u16 }
+
+    /// Returns the severity associated to the code.
+    pub fn severity(&self) -> Severity {
+        match *self {
+            Code::MultipleDisks |
+            Code::InconsistentRecordCounts |
+            Code::CentralDirectoryGap |
+            Code::CentralDirectorySizeMismatch |
+            Code::UnsupportedVersion |
+            Code::InvalidTimestamp |
+            Code::Zip64Placeholder |
+            Code::UncheckedName => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+/// A Diagnostic, pinpointing an issue in the structure of an archive.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Diagnostic {
+    code: Code,
+    offset: u64,
+    record: Record,
+    field: &'static str,
+}
+
+impl Diagnostic {
+    /// Returns a new instance.
+    ///
+    /// The `offset` parameter is the absolute offset, from the start of the
+    /// archive, of the field the diagnostic applies to.
+    ///
+    /// The `field` parameter is the name of the field, matching the name of the
+    /// accessor in the corresponding `zip::raw` reader.
+    pub fn new(code: Code, offset: u64, record: Record, field: &'static str)
+        -> Diagnostic
+    {
+        Diagnostic { code, offset, record, field }
+    }
+
+    /// Returns the code.
+    pub fn code(&self) -> Code { self.code }
+
+    /// Returns the severity.
+    pub fn severity(&self) -> Severity { self.code.severity() }
+
+    /// Returns the absolute offset of the field, from the start of the archive.
+    pub fn offset(&self) -> u64 { self.offset }
+
+    /// Returns the kind of record.
+    pub fn record(&self) -> Record { self.record }
+
+    /// Returns the name of the field.
+    pub fn field(&self) -> &'static str { self.field }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Code, Diagnostic, Record, Severity};
+
+    #[test]
+    fn code_id_stable() {
+        assert_eq!(Code::MissingEndOfCentralDirectory.id(), 1);
+        assert_eq!(Code::FieldMismatch.id(), 13);
+        assert_eq!(Code::Zip64Placeholder.id(), 15);
+        assert_eq!(Code::EmbeddedArchive.id(), 19);
+        assert_eq!(Code::UncheckedName.id(), 20);
+    }
+
+    #[test]
+    fn diagnostic_severity_from_code() {
+        let error = Diagnostic::new(
+            Code::InvalidSignature, 4, Record::LocalFileHeader, "signature");
+        let warning = Diagnostic::new(
+            Code::MultipleDisks, 4, Record::EndOfCentralDirectory, "disk");
+
+        assert_eq!(error.severity(), Severity::Error);
+        assert_eq!(warning.severity(), Severity::Warning);
+    }
+}
@@ -1,12 +1,22 @@
 //! # Low-level access to the End Of Central Directory records of a ZIP archive
 
-use std::iter::Iterator;
+use core::cmp::max;
+use core::iter::Iterator;
+use core::ops::Range;
 
-use api::Reader;
-use utils::{Slice, skip};
+use api::{AsyncReader, ReadError, Reader};
+use utils::{Slice, position, skip};
+use utils::read::read_contiguous;
 
 use zip::raw::EndOfCentralDirectoryReader;
 
+use super::asynchronous::LocateEndOfCentralDirectory;
+use super::diagnostic::{Code, Diagnostic, Record};
+use super::zip64::locate_zip64_end_of_central_directory;
+
+/// The size of the fixed part of the ZIP64 End of Central Directory record.
+pub(crate) const ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE: usize = 56;
+
 /// An iterator over all potential End of Central Directory records within the
 /// slice, iterating *backward* (from the end of the slice).
 ///
@@ -77,9 +87,50 @@ impl<'a> Iterator for EndOfCentralDirectoryIterator<'a> {
 /// - to pick the first record whose comment length field concords with the size
 ///   of the archive.
 ///
-pub fn locate_end_of_central_directory<'a, R: ?Sized>(reader: &'a R)
-    -> Option<EndOfCentralDirectoryReader<'a>>
-    where R: Reader + 'a
+/// If the reader copies into the `scratch` buffer, only the last
+/// `scratch.len()` bytes of the archive are looked into.
+///
+/// Returns `Truncated` if the archive is too small to hold any record, and
+/// `Malformed` if no record matches.
+pub fn locate_end_of_central_directory<'a, R>(
+    reader: &'a R,
+    scratch: &'a mut [u8]
+)
+    -> Result<EndOfCentralDirectoryReader<'a>, ReadError<R::Error>>
+    where R: Reader + ?Sized + 'a
+{
+    locate(reader, scratch).map(|(_, eocd)| eocd)
+}
+
+/// Returns the last bytes of the archive which may hold End of Central
+/// Directory records, alongside their offset, either borrowed from the reader
+/// or copied into the `scratch` buffer.
+pub(crate) fn read_tail<'a, R>(reader: &'a R, scratch: &'a mut [u8])
+    -> Result<(u64, &'a [u8]), R::Error>
+    where R: Reader + ?Sized + 'a
+{
+    let end = reader.size()?;
+    let start =
+        end.saturating_sub(EndOfCentralDirectoryReader::max_size() as u64);
+
+    let borrowed = reader.get(start..end, &mut [])?;
+
+    if borrowed.len() as u64 == end - start {
+        return Ok((start, borrowed));
+    }
+
+    //  Otherwise, as the records lie at the end of the archive, only as many
+    //  of the last bytes as fit into the scratch buffer are assembled.
+    let start = max(start, end.saturating_sub(scratch.len() as u64));
+
+    Ok((start, read_contiguous(reader, start..end, scratch)?))
+}
+
+/// Returns the most likely End of Central Directory record in the archive,
+/// alongside its offset.
+pub(crate) fn locate<'a, R>(reader: &'a R, scratch: &'a mut [u8])
+    -> Result<(u64, EndOfCentralDirectoryReader<'a>), ReadError<R::Error>>
+    where R: Reader + ?Sized + 'a
 {
     type EocdReader<'a> = EndOfCentralDirectoryReader<'a>;
 
@@ -95,25 +146,305 @@ pub fn locate_end_of_central_directory<'a, R: ?Sized>(reader: &'a R)
         eocd_end == slice_end
     }
 
-    let slice = {
-        use std::cmp::min;
+    let (start, slice) = read_tail(reader, scratch).map_err(ReadError::Io)?;
 
-        let end = reader.size();
-        let start = end - min(EocdReader::max_size(), end);
+    let end = start.saturating_add(slice.len() as u64);
 
-        reader.get(start..end)
-    };
+    if end < EocdReader::min_size() as u64 {
+        return Err(ReadError::Truncated);
+    }
 
     EndOfCentralDirectoryIterator::new(slice)
-        .filter(|eocd| matches(eocd, slice))
-        .next()
+        .find(|eocd| matches(eocd, slice))
+        .map(|eocd| {
+            let offset = position(eocd.raw(), slice).unwrap_or(0);
+            (start.wrapping_add(offset as u64), eocd)
+        })
+        .ok_or(ReadError::Malformed)
+}
+
+/// The End of Central Directory record, superseded by the ZIP64 End of Central
+/// Directory record if the latter can be located.
+///
+/// The fields of the Central Directory are read from the ZIP64 End of Central
+/// Directory record, if any, and from the End of Central Directory record
+/// otherwise.
+///
+/// The fields are decoded upfront, so that an instance does not borrow the
+/// reader, or the scratch buffer used to read the records.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct EndOfCentralDirectory {
+    offset: u64,
+    zip64_offset: Option<u64>,
+    disk: u32,
+    central_directory_disk: u32,
+    nb_local_central_directory_records: u64,
+    nb_central_directory_records: u64,
+    central_directory_size: u64,
+    central_directory_offset: u64,
+    comment_size: u16,
+}
+
+impl EndOfCentralDirectory {
+    /// Returns the most likely End of Central Directory of the archive, as
+    /// located by `locate_end_of_central_directory`.
+    pub fn locate<R>(reader: &R, scratch: &mut [u8])
+        -> Result<EndOfCentralDirectory, ReadError<R::Error>>
+        where R: Reader + ?Sized
+    {
+        let (offset, eocd) = locate(reader, scratch)?;
+
+        EndOfCentralDirectory::new(reader, eocd, offset)
+    }
+
+    /// Returns a future resolving to the most likely End of Central Directory
+    /// of the archive, as `locate` does.
+    ///
+    /// The tail of the archive is requested first, as large as the `scratch`
+    /// buffer allows, followed if need be by the ZIP64 records.
+    pub fn locate_async<'a, R>(reader: &'a mut R, scratch: &'a mut [u8])
+        -> LocateEndOfCentralDirectory<'a, R>
+        where R: AsyncReader + ?Sized
+    {
+        LocateEndOfCentralDirectory::new(reader, scratch)
+    }
+
+    /// Returns an instance for the End of Central Directory record located at
+    /// `offset`, looking up the ZIP64 End of Central Directory record with
+    /// `locate_zip64_end_of_central_directory`.
+    ///
+    /// A ZIP64 End of Central Directory record which cannot be read is
+    /// ignored, hence only `Io` is ever returned.
+    pub fn new<R>(
+        reader: &R,
+        eocd: EndOfCentralDirectoryReader,
+        offset: u64
+    )
+        -> Result<EndOfCentralDirectory, ReadError<R::Error>>
+        where R: Reader + ?Sized
+    {
+        EndOfCentralDirectory::from_record(eocd, offset).resolve_zip64(reader)
+    }
+
+    /// Returns an instance for the End of Central Directory record located at
+    /// `offset`, disregarding any ZIP64 End of Central Directory record.
+    pub(crate) fn from_record(
+        eocd: EndOfCentralDirectoryReader,
+        offset: u64
+    )
+        -> EndOfCentralDirectory
+    {
+        EndOfCentralDirectory {
+            offset,
+            zip64_offset: None,
+            disk: u32::from(eocd.disk()),
+            central_directory_disk: u32::from(eocd.central_directory_disk()),
+            nb_local_central_directory_records:
+                u64::from(eocd.nb_local_central_directory_records()),
+            nb_central_directory_records:
+                u64::from(eocd.nb_central_directory_records()),
+            central_directory_size: u64::from(eocd.central_directory_size()),
+            central_directory_offset:
+                u64::from(eocd.central_directory_offset()),
+            comment_size: eocd.comment_size(),
+        }
+    }
+
+    /// Returns this instance, superseded by the ZIP64 End of Central Directory
+    /// record, if it can be located.
+    pub(crate) fn resolve_zip64<R>(self, reader: &R)
+        -> Result<EndOfCentralDirectory, ReadError<R::Error>>
+        where R: Reader + ?Sized
+    {
+        let mut scratch = [0; ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE];
+
+        let zip64 =
+            match locate_zip64_end_of_central_directory(
+                reader,
+                self.offset,
+                &mut scratch
+            ) {
+                Ok(zip64) => zip64,
+                Err(ReadError::Io(error)) => return Err(ReadError::Io(error)),
+                Err(_) => None,
+            };
+
+        let (zip64_offset, zip64) = match zip64 {
+            Some(zip64) => zip64,
+            None => return Ok(self),
+        };
+
+        Ok(EndOfCentralDirectory {
+            zip64_offset: Some(zip64_offset),
+            disk: zip64.disk(),
+            central_directory_disk: zip64.central_directory_disk(),
+            nb_local_central_directory_records:
+                zip64.nb_local_central_directory_records(),
+            nb_central_directory_records: zip64.nb_central_directory_records(),
+            central_directory_size: zip64.central_directory_size(),
+            central_directory_offset: zip64.central_directory_offset(),
+            ..self
+        })
+    }
+
+    /// Returns the offset of the End of Central Directory record.
+    pub fn offset(&self) -> u64 { self.offset }
+
+    /// Returns the offset of the ZIP64 End of Central Directory record, if any.
+    pub fn zip64_offset(&self) -> Option<u64> { self.zip64_offset }
+
+    /// Returns whether the fields are read from a ZIP64 End of Central
+    /// Directory record.
+    pub fn is_zip64(&self) -> bool { self.zip64_offset.is_some() }
+
+    /// Returns the number of the disk.
+    pub fn disk(&self) -> u32 { self.disk }
+
+    /// Returns the number of the disk where the Central Directory starts.
+    pub fn central_directory_disk(&self) -> u32 {
+        self.central_directory_disk
+    }
+
+    /// Returns the number of Central Directory records on this disk.
+    pub fn nb_local_central_directory_records(&self) -> u64 {
+        self.nb_local_central_directory_records
+    }
+
+    /// Returns the number of Central Directory records on all disks.
+    pub fn nb_central_directory_records(&self) -> u64 {
+        self.nb_central_directory_records
+    }
+
+    /// Returns the size of the Central Directory (in bytes).
+    pub fn central_directory_size(&self) -> u64 {
+        self.central_directory_size
+    }
+
+    /// Returns the offset of the Central Directory, from the start of the
+    /// archive.
+    pub fn central_directory_offset(&self) -> u64 {
+        self.central_directory_offset
+    }
+
+    /// Returns the range of the archive comment, which follows the End of
+    /// Central Directory record.
+    pub fn comment_range(&self) -> Range<u64> {
+        let start = self.offset
+            .saturating_add(EndOfCentralDirectoryReader::min_size() as u64);
+
+        start..start.saturating_add(u64::from(self.comment_size))
+    }
+
+    /// Returns the offset of the record following the Central Directory: the
+    /// ZIP64 End of Central Directory record, if any, or the End of Central
+    /// Directory record.
+    pub fn central_directory_limit(&self) -> u64 {
+        self.zip64_offset().unwrap_or(self.offset)
+    }
+
+    /// Returns the range of the Central Directory, if it lies before the record
+    /// following it.
+    pub fn central_directory_range(&self) -> Option<Range<u64>> {
+        let start = self.central_directory_offset();
+
+        match start.checked_add(self.central_directory_size()) {
+            Some(end) if end <= self.central_directory_limit() =>
+                Some(start..end),
+            _ => None,
+        }
+    }
+
+    /// Returns a diagnostic applying to the `field` of the record providing
+    /// the fields of the Central Directory.
+    pub(crate) fn diagnostic(
+        &self,
+        code: Code,
+        field: EndOfCentralDirectoryField
+    )
+        -> Diagnostic
+    {
+        let (eocd_position, zip64_position) = field.positions();
+
+        let (record, offset) = match self.zip64_offset {
+            Some(offset) => (
+                Record::Zip64EndOfCentralDirectory,
+                offset.wrapping_add(zip64_position)
+            ),
+            None => (
+                Record::EndOfCentralDirectory,
+                self.offset.wrapping_add(eocd_position)
+            ),
+        };
+
+        Diagnostic::new(code, offset, record, field.name())
+    }
+}
+
+/// A field of `EndOfCentralDirectory`, which a diagnostic may apply to.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum EndOfCentralDirectoryField {
+    Disk,
+    CentralDirectoryDisk,
+    NbLocalCentralDirectoryRecords,
+    NbCentralDirectoryRecords,
+    CentralDirectorySize,
+    CentralDirectoryOffset,
+}
+
+impl EndOfCentralDirectoryField {
+    /// Returns the name of the accessor of the field.
+    fn name(self) -> &'static str {
+        match self {
+            EndOfCentralDirectoryField::Disk => "disk",
+            EndOfCentralDirectoryField::CentralDirectoryDisk =>
+                "central_directory_disk",
+            EndOfCentralDirectoryField::NbLocalCentralDirectoryRecords =>
+                "nb_local_central_directory_records",
+            EndOfCentralDirectoryField::NbCentralDirectoryRecords =>
+                "nb_central_directory_records",
+            EndOfCentralDirectoryField::CentralDirectorySize =>
+                "central_directory_size",
+            EndOfCentralDirectoryField::CentralDirectoryOffset =>
+                "central_directory_offset",
+        }
+    }
+
+    /// Returns the positions of the field within the End of Central Directory
+    /// record and the ZIP64 End of Central Directory record.
+    fn positions(self) -> (u64, u64) {
+        match self {
+            EndOfCentralDirectoryField::Disk => (4, 16),
+            EndOfCentralDirectoryField::CentralDirectoryDisk => (6, 20),
+            EndOfCentralDirectoryField::NbLocalCentralDirectoryRecords =>
+                (8, 24),
+            EndOfCentralDirectoryField::NbCentralDirectoryRecords => (10, 32),
+            EndOfCentralDirectoryField::CentralDirectorySize => (12, 40),
+            EndOfCentralDirectoryField::CentralDirectoryOffset => (16, 48),
+        }
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::identity_op)]
 mod tests {
+    use api::ReadError;
     use utils::position;
+    use utils::test::{
+        ChunkedReader,
+        CopyingReader,
+        FailingReader,
+        TestError,
+        test_archive,
+        test_zip64_archive
+    };
+    use zip::low_level::{Code, Diagnostic, Record};
     use zip::raw::EndOfCentralDirectoryReader as EocdReader;
-    use super::{EndOfCentralDirectoryIterator, locate_end_of_central_directory};
+    use super::{
+        EndOfCentralDirectory,
+        EndOfCentralDirectoryField,
+        EndOfCentralDirectoryIterator,
+        locate_end_of_central_directory
+    };
 
     #[test]
     fn iter_eocd_none_on_too_small_slice() {
@@ -149,7 +480,7 @@ mod tests {
         if let Some(eocd) = it.next() {
             assert_eq!(position(eocd.raw(), &v[..]), Some(0));
         } else {
-            assert!(false);
+            unreachable!();
         }
 
         assert_eq!(it.next(), None);
@@ -171,12 +502,12 @@ mod tests {
             v
         };
 
-        let mut it = EndOfCentralDirectoryIterator::new(&v[..]);
+        let it = EndOfCentralDirectoryIterator::new(&v[..]);
 
         let mut pos = max_pos + 4;
         let mut total = 0;
 
-        while let Some(eocd) = it.next() {
+        for eocd in it {
             assert_eq!(eocd.signature(), EocdReader::expected_signature());
             assert_eq!(position(eocd.raw(), &v[..]), Some(pos - 4));
             pos -= 4;
@@ -187,17 +518,23 @@ mod tests {
     }
 
     #[test]
-    fn locate_eocd_none_on_too_small_slice() {
+    fn locate_eocd_truncated_on_too_small_slice() {
         let v = vec![0; EocdReader::min_size() - 1];
         for length in 0..v.len() {
-            assert_eq!(locate_end_of_central_directory(&v[..length]), None);
+            assert_eq!(
+                locate_end_of_central_directory(&v[..length], &mut []),
+                Err(ReadError::Truncated)
+            );
         }
     }
 
     #[test]
-    fn locate_eocd_none_on_maximum_size_zeroed_slice() {
+    fn locate_eocd_malformed_on_maximum_size_zeroed_slice() {
         let v = vec![0; EocdReader::max_size()];
-        assert_eq!(locate_end_of_central_directory(&v[..]), None);
+        assert_eq!(
+            locate_end_of_central_directory(&v[..], &mut []),
+            Err(ReadError::Malformed)
+        );
     }
 
     #[test]
@@ -211,10 +548,10 @@ mod tests {
             v
         };
 
-        if let Some(eocd) = locate_end_of_central_directory(&v[..]) {
+        if let Ok(eocd) = locate_end_of_central_directory(&v[..], &mut []) {
             assert_eq!(position(eocd.raw(), &v[..]), Some(0));
         } else {
-            assert!(false);
+            unreachable!();
         }
     }
 
@@ -234,10 +571,10 @@ mod tests {
             v
         };
 
-        if let Some(eocd) = locate_end_of_central_directory(&v[..]) {
+        if let Ok(eocd) = locate_end_of_central_directory(&v[..], &mut []) {
             assert_eq!(position(eocd.raw(), &v[..]), Some(65535));
         } else {
-            assert!(false);
+            unreachable!();
         }
     }
 
@@ -266,10 +603,109 @@ mod tests {
             v
         };
 
-        if let Some(eocd) = locate_end_of_central_directory(&v[..]) {
+        if let Ok(eocd) = locate_end_of_central_directory(&v[..], &mut []) {
             assert_eq!(position(eocd.raw(), &v[..]), Some(4));
         } else {
-            assert!(false);
+            unreachable!();
+        }
+    }
+
+    #[test]
+    fn end_of_central_directory_without_zip64() {
+        let archive = test_archive(&[(b"a", b"a")]);
+        let eocd =
+            EndOfCentralDirectory::locate(&archive[..], &mut []).unwrap();
+
+        assert_eq!(eocd.offset(), 79);
+        assert_eq!(eocd.zip64_offset(), None);
+        assert_eq!(eocd.nb_central_directory_records(), 1);
+        assert_eq!(eocd.central_directory_range(), Some(32..79));
+        assert_eq!(
+            eocd.diagnostic(
+                Code::CentralDirectoryGap,
+                EndOfCentralDirectoryField::CentralDirectorySize
+            ),
+            Diagnostic::new(
+                Code::CentralDirectoryGap,
+                79 + 12,
+                Record::EndOfCentralDirectory,
+                "central_directory_size"
+            )
+        );
+    }
+
+    #[test]
+    fn end_of_central_directory_superseded_by_zip64() {
+        let archive = test_zip64_archive(&[(b"a", b"a")]);
+        let eocd =
+            EndOfCentralDirectory::locate(&archive[..], &mut []).unwrap();
+
+        assert_eq!(eocd.offset(), 203);
+        assert_eq!(eocd.zip64_offset(), Some(127));
+        assert!(eocd.is_zip64());
+        assert_eq!(eocd.nb_central_directory_records(), 1);
+        assert_eq!(eocd.central_directory_limit(), 127);
+        assert_eq!(eocd.central_directory_range(), Some(52..127));
+        assert_eq!(
+            eocd.diagnostic(
+                Code::CentralDirectoryGap,
+                EndOfCentralDirectoryField::CentralDirectorySize
+            ),
+            Diagnostic::new(
+                Code::CentralDirectoryGap,
+                127 + 40,
+                Record::Zip64EndOfCentralDirectory,
+                "central_directory_size"
+            )
+        );
+    }
+
+    #[test]
+    fn end_of_central_directory_through_copying_reader() {
+        let archive = test_zip64_archive(&[(b"a", b"a")]);
+        let reader = CopyingReader { data: &archive };
+
+        let mut scratch = [0; 128];
+        let eocd = EndOfCentralDirectory::locate(&reader, &mut scratch);
+
+        assert_eq!(
+            eocd,
+            EndOfCentralDirectory::locate(&archive[..], &mut [])
+        );
+        assert_eq!(eocd.map(|eocd| eocd.zip64_offset()), Ok(Some(127)));
+    }
+
+    #[test]
+    fn end_of_central_directory_through_chunked_reader() {
+        let archive = test_zip64_archive(&[(b"a", b"a")]);
+
+        for chunk in 1..24 {
+            let reader = ChunkedReader { data: &archive, chunk };
+
+            let mut scratch = [0; 128];
+            let eocd = EndOfCentralDirectory::locate(&reader, &mut scratch);
+
+            assert_eq!(
+                eocd,
+                EndOfCentralDirectory::locate(&archive[..], &mut [])
+            );
+        }
+    }
+
+    #[test]
+    fn end_of_central_directory_io_on_failing_reader() {
+        let archive = test_zip64_archive(&[(b"a", b"a")]);
+
+        let reader = FailingReader::new(&archive, usize::MAX);
+        assert!(EndOfCentralDirectory::locate(&reader, &mut []).is_ok());
+
+        for limit in 0..reader.accesses.get() {
+            let reader = FailingReader::new(&archive, limit);
+
+            assert_eq!(
+                EndOfCentralDirectory::locate(&reader, &mut []),
+                Err(ReadError::Io(TestError))
+            );
         }
     }
 }
@@ -0,0 +1,87 @@
+//! # Low-level access to the Extra Field blocks of a ZIP record
+
+use core::iter::Iterator;
+
+use utils::Slice;
+
+use zip::raw::ExtraFieldReader;
+
+/// An iterator over the Extra Field blocks of an extra field, such as returned
+/// by `CentralDirectoryFileHeaderReader::extra_field`.
+///
+/// The iterator returns the blocks in the order in which they appear, and stops
+/// at the first block which does not fit within the slice.
+///
+/// Note: this iterator does not attempt to validate the blocks in any way.
+#[derive(Debug)]
+pub struct ExtraFieldIterator<'a> {
+    data: Slice<'a>,
+}
+
+impl<'a> ExtraFieldIterator<'a> {
+    /// Returns an instance of ExtraFieldIterator over the `slice`.
+    pub fn new(slice: &'a [u8]) -> ExtraFieldIterator<'a> {
+        ExtraFieldIterator { data: Slice::new(slice) }
+    }
+
+    /// Returns the first block with the given `id`, if any.
+    pub fn find_id(slice: &'a [u8], id: u16) -> Option<ExtraFieldReader<'a>> {
+        ExtraFieldIterator::new(slice).find(|extra| extra.id() == id)
+    }
+}
+
+impl<'a> Iterator for ExtraFieldIterator<'a> {
+    type Item = ExtraFieldReader<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(extra) = ExtraFieldReader::new(self.data.raw()) {
+            if extra.data().is_some() {
+                self.data = self.data.skip(extra.raw().len());
+
+                return Some(extra);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExtraFieldIterator;
+
+    #[test]
+    fn iter_extra_none_on_empty_slice() {
+        assert_eq!(ExtraFieldIterator::new(&[]).next(), None);
+    }
+
+    #[test]
+    fn iter_extra_all_blocks() {
+        let v = [0x01, 0x00, 0x02, 0x00, 0xaa, 0xbb,
+                 0x75, 0x70, 0x00, 0x00,
+                 0x0a, 0x00, 0x01, 0x00, 0xcc];
+
+        let ids: Vec<_> = ExtraFieldIterator::new(&v).map(|e| e.id()).collect();
+
+        assert_eq!(ids, vec!(0x0001, 0x7075, 0x000a));
+    }
+
+    #[test]
+    fn iter_extra_stop_on_truncated_block() {
+        let v = [0x01, 0x00, 0x00, 0x00,
+                 0x75, 0x70, 0x08, 0x00, 0xaa];
+
+        assert_eq!(ExtraFieldIterator::new(&v).count(), 1);
+    }
+
+    #[test]
+    fn find_id_success() {
+        let v = [0x01, 0x00, 0x00, 0x00,
+                 0x75, 0x70, 0x01, 0x00, 0xaa];
+
+        let extra = ExtraFieldIterator::find_id(&v, 0x7075).unwrap();
+
+        assert_eq!(extra.data(), Some(&[0xaa][..]));
+        assert_eq!(ExtraFieldIterator::find_id(&v, 0x000a), None);
+    }
+}
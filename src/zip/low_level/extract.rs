@@ -0,0 +1,778 @@
+//! # Extraction of the entries of a ZIP archive
+
+use core::cmp::min;
+use core::ops::Range;
+
+use api::{DecodeError, Decoder, DecoderRegistry, Reader, Stored};
+use utils::read::read_exact;
+
+use zip::raw::{CentralDirectoryFileHeaderReader, LocalFileHeaderReader};
+
+use super::crc::{Crc32Verifier, Verification};
+use super::zip64::Zip64Fields;
+use super::zipcrypto::{ENCRYPTION_HEADER_SIZE, ZipCrypto, check_byte};
+
+/// The size of the buffer holding the input of the decoder, either decrypted
+/// data, or data copied by the reader.
+const INPUT_BUFFER_SIZE: usize = 512;
+
+/// The reason why an extraction failed.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ExtractError<E> {
+    /// The Local File Header record cannot be read, or its signature does not
+    /// match.
+    InvalidLocalFileHeader,
+    /// The data of the entry extends beyond the end of the archive.
+    DataOutOfBounds,
+    /// The entry is encrypted, and no password was provided, or the
+    /// encryption method is not supported.
+    Encrypted,
+    /// The entry is too small to hold an encryption header.
+    InvalidEncryptionHeader,
+    /// The password does not match the check byte of the encryption header.
+    InvalidPassword,
+    /// No decoder is available for the compression method.
+    UnsupportedMethod(u16),
+    /// The decoder failed.
+    Decode(DecodeError),
+    /// The decoder made no progress, despite available input and output.
+    Stalled,
+    /// The amount of decoded data differs from the uncompressed size.
+    SizeMismatch,
+    /// The CRC-32 of the decoded data differs from the stored CRC-32.
+    CrcMismatch {
+        /// The stored CRC-32.
+        expected: u32,
+        /// The CRC-32 of the decoded data.
+        actual: u32,
+    },
+    /// The reader failed.
+    Io(E),
+}
+
+/// A streaming extractor of the data of a single entry.
+///
+/// The extractor locates the data through the Local File Header record, feeds
+/// it to the `Decoder` registered for the compression method of the entry, and
+/// checks that the decoded data matches the uncompressed size and the CRC-32
+/// of the Central Directory File Header record.
+///
+/// Entries encrypted with the traditional PKWARE encryption are decrypted in
+/// front of the decoder, through an internal buffer; the same buffer serves as
+/// scratch buffer to readers which copy.
+///
+/// The decoded data should not be trusted until the extraction finishes
+/// successfully.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Extractor {
+    method: u16,
+    data: Range<u64>,
+    position: u64,
+    produced: u64,
+    uncompressed_size: u64,
+    verifier: Crc32Verifier,
+    cipher: Option<ZipCrypto>,
+    buffer: [u8; INPUT_BUFFER_SIZE],
+    buffered: Range<usize>,
+    started: bool,
+    finished: bool,
+}
+
+impl Extractor {
+    /// Returns an instance extracting the entry described by the Central
+    /// Directory File Header record.
+    pub fn new<R>(reader: &R, cdfh: CentralDirectoryFileHeaderReader)
+        -> Result<Extractor, ExtractError<R::Error>>
+        where R: Reader + ?Sized
+    {
+        if cdfh.general_purpose_bit_flag() & 0x01 != 0 {
+            return Err(ExtractError::Encrypted);
+        }
+
+        let data = locate_data(reader, cdfh)?;
+
+        Ok(Extractor::with_data(cdfh, data, None))
+    }
+
+    /// Returns an instance extracting the entry described by the Central
+    /// Directory File Header record, decrypting it with the `password`.
+    ///
+    /// Only the traditional PKWARE encryption is supported: entries using
+    /// strong encryption (bit 6) or AES encryption (method 99) are rejected
+    /// with `Encrypted`. Entries which are not encrypted are extracted as is.
+    pub fn with_password<R>(
+        reader: &R,
+        cdfh: CentralDirectoryFileHeaderReader,
+        password: &[u8]
+    )
+        -> Result<Extractor, ExtractError<R::Error>>
+        where R: Reader + ?Sized
+    {
+        let flags = cdfh.general_purpose_bit_flag();
+
+        if flags & 0x01 == 0 {
+            return Extractor::new(reader, cdfh);
+        }
+
+        if flags & 0x40 != 0 || cdfh.compression_method() == 99 {
+            return Err(ExtractError::Encrypted);
+        }
+
+        let data = locate_data(reader, cdfh)?;
+
+        let start = data.start.saturating_add(ENCRYPTION_HEADER_SIZE as u64);
+
+        if start > data.end {
+            return Err(ExtractError::InvalidEncryptionHeader);
+        }
+
+        let mut header = [0; ENCRYPTION_HEADER_SIZE];
+
+        let filled = read_exact(reader, data.start, &mut header)
+            .map_err(ExtractError::Io)?;
+
+        if filled != ENCRYPTION_HEADER_SIZE {
+            return Err(ExtractError::DataOutOfBounds);
+        }
+
+        let mut cipher = ZipCrypto::new(password);
+
+        if !cipher.decrypt_header(&mut header, check_byte(cdfh)) {
+            return Err(ExtractError::InvalidPassword);
+        }
+
+        Ok(Extractor::with_data(cdfh, start..data.end, Some(cipher)))
+    }
+
+    fn with_data(
+        cdfh: CentralDirectoryFileHeaderReader,
+        data: Range<u64>,
+        cipher: Option<ZipCrypto>
+    )
+        -> Extractor
+    {
+        //  An unresolved ZIP64 placeholder is taken at face value.
+        let uncompressed_size =
+            Zip64Fields::from_central_directory_file_header(cdfh)
+                .uncompressed_size()
+                .unwrap_or(u64::from(cdfh.uncompressed_size()));
+
+        Extractor {
+            method: cdfh.compression_method(),
+            data: data.clone(),
+            position: data.start,
+            produced: 0,
+            uncompressed_size,
+            verifier: Crc32Verifier::from_central_directory_file_header(cdfh),
+            cipher,
+            buffer: [0; INPUT_BUFFER_SIZE],
+            buffered: 0..0,
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Returns the compression method of the entry.
+    pub fn compression_method(&self) -> u16 { self.method }
+
+    /// Returns the range of the (compressed) data of the entry, from the start
+    /// of the archive.
+    pub fn data_range(&self) -> Range<u64> { self.data.clone() }
+
+    /// Returns whether the entry is decrypted.
+    pub fn is_encrypted(&self) -> bool { self.cipher.is_some() }
+
+    /// Returns whether the extraction finished, successfully.
+    pub fn is_finished(&self) -> bool { self.finished }
+
+    /// Decodes the next chunk of data into `output`, returning the number of
+    /// bytes produced, or 0 once the extraction finished.
+    ///
+    /// The decoder is looked up in `decoders` by compression method; stored
+    /// entries are decoded by `Stored` if no decoder is registered for them.
+    ///
+    /// The data of stored entries is thus copied into `output`, even if the
+    /// reader borrows it; `read_stored` returns it without copying.
+    pub fn read<R, D>(
+        &mut self,
+        reader: &R,
+        decoders: &mut D,
+        output: &mut [u8]
+    )
+        -> Result<usize, ExtractError<R::Error>>
+        where R: Reader + ?Sized,
+              D: DecoderRegistry + ?Sized
+    {
+        if self.finished || output.is_empty() {
+            return Ok(0);
+        }
+
+        let mut stored = Stored;
+
+        let method = self.method;
+
+        let decoder: &mut dyn Decoder = match decoders.decoder(method) {
+            Some(decoder) => decoder,
+            None if method == 0 => &mut stored,
+            None => return Err(ExtractError::UnsupportedMethod(method)),
+        };
+
+        if !self.started {
+            decoder.reset();
+            self.started = true;
+        }
+
+        if self.cipher.is_some() {
+            self.decrypt_next(reader).map_err(ExtractError::Io)?;
+        }
+
+        let input = match self.cipher {
+            Some(_) => self.buffer.get(self.buffered.clone()).unwrap_or(b""),
+            None => reader
+                .get(self.position..self.data.end, &mut self.buffer)
+                .map_err(ExtractError::Io)?,
+        };
+
+        let end_of_input = match self.cipher {
+            Some(_) => self.position == self.data.end,
+            None => {
+                self.position.wrapping_add(input.len() as u64) == self.data.end
+            },
+        };
+
+        let decoded = decoder
+            .decode(input, output, end_of_input)
+            .map_err(ExtractError::Decode)?;
+
+        let consumed = min(decoded.consumed(), input.len());
+        let produced = min(decoded.produced(), output.len());
+
+        match self.cipher {
+            Some(_) => self.buffered.start += consumed,
+            None => self.position += consumed as u64,
+        }
+
+        self.produced = self.produced.saturating_add(produced as u64);
+
+        if let Some(output) = output.get(..produced) {
+            self.verifier.update(output);
+        }
+
+        if self.produced > self.uncompressed_size {
+            return Err(ExtractError::SizeMismatch);
+        }
+
+        if decoded.finished() {
+            self.finish()?;
+        } else if consumed == 0 && produced == 0 {
+            return Err(ExtractError::Stalled);
+        }
+
+        Ok(produced)
+    }
+
+    /// Returns the next chunk of data of a stored entry, directly from the
+    /// `reader`, or an empty slice once the extraction finished.
+    ///
+    /// If the reader copies, the chunk is copied into the `scratch` buffer, and
+    /// is at most as large.
+    ///
+    /// Returns `UnsupportedMethod` if the entry is not stored, and `Encrypted`
+    /// if the entry is decrypted, as its data cannot be used as is.
+    pub fn read_stored<'a, R>(&mut self, reader: &'a R, scratch: &'a mut [u8])
+        -> Result<&'a [u8], ExtractError<R::Error>>
+        where R: Reader + ?Sized
+    {
+        if self.method != 0 {
+            return Err(ExtractError::UnsupportedMethod(self.method));
+        }
+
+        if self.cipher.is_some() {
+            return Err(ExtractError::Encrypted);
+        }
+
+        if self.finished {
+            return Ok(b"");
+        }
+
+        let chunk = reader
+            .get(self.position..self.data.end, scratch)
+            .map_err(ExtractError::Io)?;
+
+        self.position += chunk.len() as u64;
+        self.produced = self.produced.saturating_add(chunk.len() as u64);
+        self.verifier.update(chunk);
+
+        if self.position == self.data.end {
+            self.finish()?;
+        } else if chunk.is_empty() {
+            return Err(ExtractError::Decode(DecodeError::UnexpectedEndOfInput));
+        }
+
+        Ok(chunk)
+    }
+
+    /// Moves the pending decrypted data to the front of the buffer, then fills
+    /// the remainder of the buffer with the next chunk of decrypted data.
+    fn decrypt_next<R>(&mut self, reader: &R) -> Result<(), R::Error>
+        where R: Reader + ?Sized
+    {
+        let pending = self.buffered.len();
+
+        self.buffer.copy_within(self.buffered.clone(), 0);
+        self.buffered = 0..pending;
+
+        let mut scratch = [0; INPUT_BUFFER_SIZE];
+
+        let free = (INPUT_BUFFER_SIZE - pending) as u64;
+        let end = min(self.position.saturating_add(free), self.data.end);
+        let chunk = reader.get(self.position..end, &mut scratch)?;
+
+        let target = pending.wrapping_add(chunk.len());
+
+        if let (Some(cipher), Some(free)) =
+            (self.cipher.as_mut(), self.buffer.get_mut(pending..target))
+        {
+            free.copy_from_slice(chunk);
+            cipher.decrypt(free);
+
+            self.position += chunk.len() as u64;
+            self.buffered = 0..target;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the size and CRC-32 of the decoded data.
+    fn finish<E>(&mut self) -> Result<(), ExtractError<E>> {
+        if self.produced != self.uncompressed_size {
+            return Err(ExtractError::SizeMismatch);
+        }
+
+        if let Verification::Mismatch { expected, actual } =
+            self.verifier.verify()
+        {
+            return Err(ExtractError::CrcMismatch { expected, actual });
+        }
+
+        self.finished = true;
+
+        Ok(())
+    }
+}
+
+/// Returns the range of the (compressed) data of the entry, from the start of
+/// the archive.
+///
+/// An unresolved ZIP64 placeholder is taken at face value.
+pub(crate) fn locate_data<R>(reader: &R, cdfh: CentralDirectoryFileHeaderReader)
+    -> Result<Range<u64>, ExtractError<R::Error>>
+    where R: Reader + ?Sized
+{
+    type LfhReader<'a> = LocalFileHeaderReader<'a>;
+
+    let zip64 = Zip64Fields::from_central_directory_file_header(cdfh);
+
+    let offset = zip64
+        .local_file_header_relative_offset()
+        .unwrap_or(u64::from(cdfh.local_file_header_relative_offset()));
+
+    let compressed_size = zip64
+        .compressed_size()
+        .unwrap_or(u64::from(cdfh.compressed_size()));
+
+    //  Only the fixed part is read, the dynamic fields are merely skipped.
+    let mut fixed = [0; 30];
+
+    let lfh = {
+        let filled = read_exact(reader, offset, &mut fixed)
+            .map_err(ExtractError::Io)?;
+        LfhReader::new(fixed.get(..filled).unwrap_or(b""))
+    };
+
+    let lfh = match lfh {
+        Some(lfh) if lfh.signature() == LfhReader::expected_signature() => lfh,
+        _ => return Err(ExtractError::InvalidLocalFileHeader),
+    };
+
+    let lfh_size = LfhReader::min_size() as u64 +
+        u64::from(lfh.file_name_size()) +
+        u64::from(lfh.extra_field_size());
+
+    let size = reader.size().map_err(ExtractError::Io)?;
+
+    let start = match offset.checked_add(lfh_size) {
+        Some(start) if start <= size => start,
+        _ => return Err(ExtractError::InvalidLocalFileHeader),
+    };
+
+    let end = start.checked_add(compressed_size);
+
+    match end {
+        Some(end) if end <= size => Ok(start..end),
+        _ => Err(ExtractError::DataOutOfBounds),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::Infallible;
+
+    use api::{DecodeError, Decoded, Decoder, DecoderRegistry};
+    use checksum::Crc32;
+    use utils::test::{
+        ChunkedReader,
+        CopyingReader,
+        FailingReader,
+        TestError,
+        test_archive,
+        test_zip64_archive
+    };
+    use zip::raw::CentralDirectoryFileHeaderReader;
+    use zip::low_level::CentralDirectoryFileHeaderIterator as CdfhIterator;
+    use zip::low_level::EndOfCentralDirectory;
+    use zip::low_level::ZipCrypto;
+    use super::{ExtractError, Extractor};
+
+    //  Produced by `zip -0 -X -P secret`, on "Hello, World!", with a Data
+    //  Descriptor record.
+    const ENCRYPTED_ARCHIVE: [u8; 149] = [
+        0x50, 0x4b, 0x03, 0x04, 0x0a, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x21, 0x50, 0xd0, 0xc3, 0x4a, 0xec, 0x19, 0x00, 0x00, 0x00, 0x0d, 0x00,
+        0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x61, 0x2e, 0x74, 0x78, 0x74, 0xcd,
+        0x86, 0x32, 0x3a, 0xcb, 0xb5, 0x66, 0x5e, 0xe5, 0xf6, 0x81, 0xd1, 0x37,
+        0x7f, 0xcd, 0x06, 0x58, 0xa6, 0x04, 0x57, 0x79, 0x62, 0x09, 0xbc, 0x6a,
+        0x50, 0x4b, 0x07, 0x08, 0xd0, 0xc3, 0x4a, 0xec, 0x19, 0x00, 0x00, 0x00,
+        0x0d, 0x00, 0x00, 0x00, 0x50, 0x4b, 0x01, 0x02, 0x1e, 0x03, 0x0a, 0x00,
+        0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x50, 0xd0, 0xc3, 0x4a, 0xec,
+        0x19, 0x00, 0x00, 0x00, 0x0d, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xa4, 0x81, 0x00, 0x00,
+        0x00, 0x00, 0x61, 0x2e, 0x74, 0x78, 0x74, 0x50, 0x4b, 0x05, 0x06, 0x00,
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x33, 0x00, 0x00, 0x00, 0x4c,
+        0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    fn cdfh(archive: &[u8]) -> CentralDirectoryFileHeaderReader<'_> {
+        let eocd = EndOfCentralDirectory::locate(archive, &mut []).unwrap();
+        let cd = eocd.central_directory_offset() as usize;
+
+        CdfhIterator::new(&archive[cd..], 1).next().unwrap()
+    }
+
+    fn extractor(archive: &[u8]) -> Extractor {
+        Extractor::new(archive, cdfh(archive)).unwrap()
+    }
+
+    fn read_all<D>(extractor: &mut Extractor, archive: &[u8], decoders: &mut D)
+        -> Result<Vec<u8>, ExtractError<Infallible>>
+        where D: DecoderRegistry + ?Sized
+    {
+        let mut output = [0; 5];
+        let mut result = vec!();
+
+        loop {
+            let produced = extractor.read(archive, decoders, &mut output)?;
+
+            if produced == 0 { return Ok(result); }
+
+            result.extend_from_slice(&output[..produced]);
+        }
+    }
+
+    /// A decoder expanding each byte `n` of the input into `n` zero bytes.
+    struct Expander { pending: usize }
+
+    impl Decoder for Expander {
+        fn reset(&mut self) { self.pending = 0; }
+
+        fn decode(&mut self, input: &[u8], output: &mut [u8], end: bool)
+            -> Result<Decoded, DecodeError>
+        {
+            let mut consumed = 0;
+            let mut produced = 0;
+
+            loop {
+                while self.pending > 0 && produced < output.len() {
+                    output[produced] = 0;
+                    produced += 1;
+                    self.pending -= 1;
+                }
+
+                if self.pending > 0 || consumed == input.len() {
+                    break;
+                }
+
+                self.pending = input[consumed] as usize;
+                consumed += 1;
+            }
+
+            let finished = end && consumed == input.len() && self.pending == 0;
+
+            Ok(Decoded::new(consumed, produced, finished))
+        }
+    }
+
+    #[test]
+    fn read_stored_zero_copy() {
+        let archive = test_archive(&[(b"a.txt", b"Hello, World!")]);
+        let mut extractor = extractor(&archive);
+
+        assert_eq!(extractor.data_range(), 35..48);
+
+        let chunk = extractor.read_stored(&archive[..], &mut []).unwrap();
+
+        assert_eq!(chunk, b"Hello, World!");
+        assert_eq!(chunk.as_ptr(), archive[35..].as_ptr());
+        assert!(extractor.is_finished());
+        assert_eq!(extractor.read_stored(&archive[..], &mut []), Ok(&b""[..]));
+    }
+
+    #[test]
+    fn read_stored_through_default_decoder() {
+        let archive = test_archive(&[(b"a.txt", b"Hello, World!")]);
+        let mut extractor = extractor(&archive);
+
+        let result = read_all(&mut extractor, &archive, &mut ()).unwrap();
+
+        assert_eq!(result, b"Hello, World!");
+        assert!(extractor.is_finished());
+    }
+
+    #[test]
+    fn read_encrypted_success() {
+        let archive = &ENCRYPTED_ARCHIVE[..];
+
+        assert_eq!(
+            Extractor::new(archive, cdfh(archive)),
+            Err(ExtractError::Encrypted)
+        );
+
+        let mut extractor =
+            Extractor::with_password(archive, cdfh(archive), b"secret")
+                .unwrap();
+
+        assert!(extractor.is_encrypted());
+        assert_eq!(extractor.data_range(), 47..60);
+        assert_eq!(
+            extractor.read_stored(archive, &mut []),
+            Err(ExtractError::Encrypted)
+        );
+
+        let result = read_all(&mut extractor, archive, &mut ()).unwrap();
+
+        assert_eq!(result, b"Hello, World!");
+        assert!(extractor.is_finished());
+    }
+
+    #[test]
+    fn read_encrypted_through_decoder() {
+        //  "\x03\x02" expands into 5 zero bytes.
+        let crc = Crc32::checksum(&[0; 5]);
+
+        let mut data = [0; 14];
+        data[11] = (crc >> 24) as u8;
+        data[12..].copy_from_slice(b"\x03\x02");
+        ZipCrypto::new(b"pass").encrypt(&mut data);
+
+        let mut archive = test_archive(&[(b"a", &data)]);
+        let cd = archive.len() - 22 - 47;
+
+        //  Flags, method, CRC-32 and uncompressed size of both headers.
+        for &(at, value) in &[(6, 1), (8, 8), (22, 5), (cd + 8, 1),
+                              (cd + 10, 8), (cd + 24, 5)]
+        {
+            archive[at] = value;
+        }
+        for &at in &[14, cd + 16] {
+            archive[at..at + 4].copy_from_slice(&crc.to_le_bytes());
+        }
+
+        let mut expander = Expander { pending: 0 };
+        let registry: &mut [(u16, &mut dyn Decoder)] =
+            &mut [(8, &mut expander)];
+
+        let mut extractor =
+            Extractor::with_password(&archive[..], cdfh(&archive), b"pass")
+                .unwrap();
+
+        let result = read_all(&mut extractor, &archive, registry).unwrap();
+
+        assert_eq!(result, vec!(0; 5));
+        assert!(extractor.is_finished());
+    }
+
+    #[test]
+    fn with_password_failure_on_invalid_password() {
+        let archive = &ENCRYPTED_ARCHIVE[..];
+
+        assert_eq!(
+            Extractor::with_password(archive, cdfh(archive), b"Secret"),
+            Err(ExtractError::InvalidPassword)
+        );
+    }
+
+    #[test]
+    fn with_password_failure_on_strong_encryption() {
+        let mut archive = ENCRYPTED_ARCHIVE;
+        archive[0x4c + 8] |= 0x40;
+
+        assert_eq!(
+            Extractor::with_password(&archive[..], cdfh(&archive), b"secret"),
+            Err(ExtractError::Encrypted)
+        );
+    }
+
+    #[test]
+    fn read_failure_on_crc_mismatch() {
+        let mut archive = test_archive(&[(b"a.txt", b"Hello, World!")]);
+        archive[35] = b'h';
+
+        let mut extractor = extractor(&archive);
+
+        assert_eq!(
+            extractor.read_stored(&archive[..], &mut []),
+            Err(ExtractError::CrcMismatch {
+                expected: 0xec4ac3d0,
+                actual: 0x5f348825
+            })
+        );
+    }
+
+    #[test]
+    fn read_failure_on_unsupported_method() {
+        let mut archive = test_archive(&[(b"a", b"\x03")]);
+        let cd = archive.len() - 22 - 47;
+        archive[8] = 8;
+        archive[cd + 10] = 8;
+
+        let mut extractor = extractor(&archive);
+        let mut output = [0; 8];
+
+        assert_eq!(
+            extractor.read(&archive[..], &mut (), &mut output),
+            Err(ExtractError::UnsupportedMethod(8))
+        );
+    }
+
+    #[test]
+    fn read_failure_on_too_much_data() {
+        //  The "compressed" data expands into 3 bytes, instead of 1.
+        let mut archive = test_archive(&[(b"a", b"\x03")]);
+        let cd = archive.len() - 22 - 47;
+        archive[8] = 8;
+        archive[cd + 10] = 8;
+
+        let mut extractor = extractor(&archive);
+        let mut expander = Expander { pending: 0 };
+        let registry: &mut [(u16, &mut dyn Decoder)] =
+            &mut [(8, &mut expander)];
+        let mut output = [0; 8];
+
+        assert_eq!(
+            extractor.read(&archive[..], registry, &mut output),
+            Err(ExtractError::SizeMismatch)
+        );
+    }
+
+    #[test]
+    fn read_stored_zip64() {
+        let archive = test_zip64_archive(&[(b"a.txt", b"Hello, World!")]);
+        let mut extractor = extractor(&archive);
+
+        assert_eq!(extractor.data_range(), 55..68);
+
+        let chunk = extractor.read_stored(&archive[..], &mut []).unwrap();
+
+        assert_eq!(chunk, b"Hello, World!");
+        assert!(extractor.is_finished());
+    }
+
+    #[test]
+    fn read_stored_through_copying_reader() {
+        let archive = test_archive(&[(b"a.txt", b"Hello, World!")]);
+        let reader = CopyingReader { data: &archive };
+        let mut extractor = Extractor::new(&reader, cdfh(&archive)).unwrap();
+        let mut scratch = [0; 5];
+        let mut result = vec!();
+
+        while !extractor.is_finished() {
+            let chunk =
+                extractor.read_stored(&reader, &mut scratch).unwrap();
+            result.extend_from_slice(chunk);
+        }
+
+        assert_eq!(result, b"Hello, World!");
+    }
+
+    #[test]
+    fn read_encrypted_through_copying_reader() {
+        let archive = &ENCRYPTED_ARCHIVE[..];
+        let reader = CopyingReader { data: archive };
+        let mut extractor =
+            Extractor::with_password(&reader, cdfh(archive), b"secret")
+                .unwrap();
+        let mut output = [0; 5];
+        let mut result = vec!();
+
+        loop {
+            let produced =
+                extractor.read(&reader, &mut (), &mut output).unwrap();
+
+            if produced == 0 { break; }
+
+            result.extend_from_slice(&output[..produced]);
+        }
+
+        assert_eq!(result, b"Hello, World!");
+    }
+
+    #[test]
+    fn read_encrypted_through_chunked_reader() {
+        let archive = &ENCRYPTED_ARCHIVE[..];
+
+        for chunk in 1..16 {
+            let reader = ChunkedReader { data: archive, chunk };
+            let mut extractor =
+                Extractor::with_password(&reader, cdfh(archive), b"secret")
+                    .unwrap();
+            let mut output = [0; 5];
+            let mut result = vec!();
+
+            loop {
+                let produced =
+                    extractor.read(&reader, &mut (), &mut output).unwrap();
+
+                if produced == 0 { break; }
+
+                result.extend_from_slice(&output[..produced]);
+            }
+
+            assert_eq!(result, b"Hello, World!");
+        }
+    }
+
+    #[test]
+    fn new_failure_on_truncated_local_file_header() {
+        let mut archive = test_archive(&[(b"a.txt", b"1")]);
+
+        //  The extra field of the Local File Header exceeds the archive.
+        archive[28] = 0xff;
+        archive[29] = 0xff;
+
+        assert_eq!(
+            Extractor::new(&archive[..], cdfh(&archive)),
+            Err(ExtractError::InvalidLocalFileHeader)
+        );
+    }
+
+    #[test]
+    fn new_io_on_failing_reader() {
+        let archive = test_archive(&[(b"a.txt", b"1")]);
+        let reader = FailingReader::new(&archive, 0);
+
+        assert_eq!(
+            Extractor::new(&reader, cdfh(&archive)),
+            Err(ExtractError::Io(TestError))
+        );
+    }
+}
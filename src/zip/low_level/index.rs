@@ -0,0 +1,490 @@
+//! # Lookup of the entries of a ZIP archive by name
+//!
+//! Iterating over the Central Directory to find a single entry is linear in
+//! the number of entries. An `Index` instead sorts the hashes of the names of
+//! the entries in a caller-provided buffer, allowing logarithmic lookups.
+
+use core::iter::Iterator;
+
+use api::{ReadError, Reader};
+use utils::hash;
+
+use zip::raw::CentralDirectoryFileHeaderReader;
+
+use super::cdfh::read_central_directory_file_header;
+use super::eocd::EndOfCentralDirectory;
+
+/// The normalization applied to names, prior to comparing them.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Normalization {
+    fold_case: bool,
+    normalize_slashes: bool,
+}
+
+impl Normalization {
+    /// Returns an instance comparing names exactly.
+    pub fn exact() -> Normalization { Normalization::default() }
+
+    /// Returns a copy of this instance, folding ASCII letters to lower case.
+    ///
+    /// Non-ASCII characters are compared exactly.
+    pub fn with_case_folding(self) -> Normalization {
+        Normalization { fold_case: true, ..self }
+    }
+
+    /// Returns a copy of this instance, normalizing slashes.
+    ///
+    /// Backward slashes are converted to forward slashes, consecutive slashes
+    /// are collapsed into one, and leading slashes are stripped.
+    pub fn with_slash_normalization(self) -> Normalization {
+        Normalization { normalize_slashes: true, ..self }
+    }
+
+    /// Returns whether ASCII letters are folded to lower case.
+    pub fn folds_case(&self) -> bool { self.fold_case }
+
+    /// Returns whether slashes are normalized.
+    pub fn normalizes_slashes(&self) -> bool { self.normalize_slashes }
+
+    /// Returns the normalized bytes of the `name`.
+    pub fn apply<'a>(&self, name: &'a [u8]) -> Normalized<'a> {
+        Normalized {
+            name: name.iter(),
+            normalization: *self,
+            previous: b'/',
+        }
+    }
+}
+
+/// An iterator over the normalized bytes of a name.
+#[derive(Clone, Debug)]
+pub struct Normalized<'a> {
+    name: ::core::slice::Iter<'a, u8>,
+    normalization: Normalization,
+    previous: u8,
+}
+
+impl<'a> Iterator for Normalized<'a> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        for &byte in &mut self.name {
+            let mut byte = byte;
+
+            if self.normalization.normalize_slashes {
+                if byte == b'\\' {
+                    byte = b'/';
+                }
+
+                //  Initialized with a slash, to strip leading slashes.
+                if byte == b'/' && self.previous == b'/' {
+                    continue;
+                }
+
+                self.previous = byte;
+            }
+
+            if self.normalization.fold_case {
+                byte = byte.to_ascii_lowercase();
+            }
+
+            return Some(byte);
+        }
+
+        None
+    }
+}
+
+/// A slot of an `Index`.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct IndexSlot {
+    hash: u64,
+    offset: u64,
+}
+
+impl IndexSlot {
+    /// Returns a new instance.
+    pub(crate) fn new(hash: u64, offset: u64) -> IndexSlot {
+        IndexSlot { hash, offset }
+    }
+
+    /// Returns the hash of the normalized name of the entry.
+    pub fn hash(&self) -> u64 { self.hash }
+
+    /// Returns the offset of the Central Directory File Header record of the
+    /// entry, from the start of the archive.
+    pub fn offset(&self) -> u64 { self.offset }
+}
+
+/// The reason why an `Index` could not be built.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum IndexError<E> {
+    /// No End of Central Directory record could be located.
+    MissingEndOfCentralDirectory,
+    /// The Central Directory does not lie before the End of Central Directory.
+    CentralDirectoryOutOfBounds,
+    /// The number of slots is lower than the number of records announced by
+    /// the End of Central Directory.
+    InsufficientSlots,
+    /// The reader failed.
+    Io(E),
+}
+
+/// An index of the entries of an archive, by name.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Index<'s> {
+    slots: &'s [IndexSlot],
+    normalization: Normalization,
+}
+
+impl<'s> Index<'s> {
+    /// Builds an index of the entries of the archive into `slots`.
+    ///
+    /// The number of slots must be at least the number of records announced
+    /// by the End of Central Directory, as located by
+    /// `EndOfCentralDirectory::locate`. Only the Central Directory File Header
+    /// records which can be read are indexed.
+    ///
+    /// The records are read one at a time, through the `scratch` buffer if the
+    /// reader copies, which should then hold the largest record.
+    pub fn build<R>(
+        reader: &R,
+        scratch: &mut [u8],
+        slots: &'s mut [IndexSlot],
+        normalization: Normalization
+    )
+        -> Result<Index<'s>, IndexError<R::Error>>
+        where R: Reader + ?Sized
+    {
+        let eocd = match EndOfCentralDirectory::locate(reader, scratch) {
+            Ok(eocd) => eocd,
+            Err(ReadError::Io(error)) => return Err(IndexError::Io(error)),
+            Err(_) => return Err(IndexError::MissingEndOfCentralDirectory),
+        };
+
+        let cd = match eocd.central_directory_range() {
+            Some(cd) => cd,
+            None => return Err(IndexError::CentralDirectoryOutOfBounds),
+        };
+
+        let total = eocd.nb_central_directory_records();
+
+        if (slots.len() as u64) < total {
+            return Err(IndexError::InsufficientSlots);
+        }
+
+        let mut offset = cd.start;
+        let mut count = 0;
+
+        for slot in slots.iter_mut() {
+            if count as u64 == total {
+                break;
+            }
+
+            let cdfh = read_central_directory_file_header(
+                reader,
+                offset,
+                cd.end,
+                &mut *scratch
+            );
+
+            let cdfh = match cdfh.map_err(IndexError::Io)? {
+                Some(cdfh) => cdfh,
+                None => break,
+            };
+
+            let name = cdfh.file_name().unwrap_or(b"");
+
+            *slot = IndexSlot { hash: hash(normalization.apply(name)), offset };
+
+            offset = offset.wrapping_add(cdfh.raw().len() as u64);
+            count += 1;
+        }
+
+        let slots = match slots.get_mut(..count) {
+            Some(slots) => slots,
+            None => return Err(IndexError::InsufficientSlots),
+        };
+
+        slots.sort_unstable();
+
+        Ok(Index { slots, normalization })
+    }
+
+    /// Returns the slots of the index, sorted by hash then offset.
+    pub fn slots(&self) -> &'s [IndexSlot] { self.slots }
+
+    /// Returns the normalization applied to names.
+    pub fn normalization(&self) -> Normalization { self.normalization }
+
+    /// Returns the number of entries in the index.
+    pub fn len(&self) -> usize { self.slots.len() }
+
+    /// Returns whether the index is empty.
+    pub fn is_empty(&self) -> bool { self.slots.is_empty() }
+
+    /// Returns the Central Directory File Header record whose normalized name
+    /// matches the normalized `name`, or `None` if there is none.
+    ///
+    /// The record is either borrowed from the reader or copied into the
+    /// `scratch` buffer.
+    ///
+    /// Returns an error only if the `reader` fails.
+    ///
+    /// If multiple records match, the first one in the Central Directory is
+    /// returned.
+    ///
+    /// The `reader` must be the reader used to build this instance.
+    pub fn find<'a, R>(&self, reader: &'a R, scratch: &'a mut [u8], name: &[u8])
+        -> Result<Option<CentralDirectoryFileHeaderReader<'a>>, R::Error>
+        where R: Reader + ?Sized
+    {
+        let normalization = self.normalization;
+        let size = reader.size()?;
+        let hash = hash(normalization.apply(name));
+
+        let first = self.slots.partition_point(|slot| slot.hash < hash);
+
+        let candidates = self.slots
+            .iter()
+            .skip(first)
+            .take_while(|slot| slot.hash == hash);
+
+        let mut found = None;
+
+        for slot in candidates {
+            let cdfh = read_central_directory_file_header(
+                reader,
+                slot.offset,
+                size,
+                &mut *scratch
+            )?;
+
+            if let Some(cdfh) = cdfh {
+                let candidate =
+                    normalization.apply(cdfh.file_name().unwrap_or(b""));
+
+                if candidate.eq(normalization.apply(name)) {
+                    found = Some(slot.offset);
+                    break;
+                }
+            }
+        }
+
+        //  The matching record is read anew, as the candidates are read through
+        //  short-lived borrows of the `scratch` buffer.
+        match found {
+            Some(offset) => read_central_directory_file_header(
+                reader,
+                offset,
+                size,
+                scratch
+            ),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use utils::test::{
+        ChunkedReader,
+        CopyingReader,
+        FailingReader,
+        TestError,
+        test_archive,
+        test_zip64_archive
+    };
+    use core::convert::Infallible;
+
+    use zip::raw::CentralDirectoryFileHeaderReader;
+    use super::{Index, IndexError, IndexSlot, Normalization};
+
+    fn build<'s>(
+        archive: &[u8],
+        slots: &'s mut [IndexSlot],
+        normalization: Normalization
+    )
+        -> Result<Index<'s>, IndexError<Infallible>>
+    {
+        Index::build(archive, &mut [], slots, normalization)
+    }
+
+    fn find<'a>(index: &Index, archive: &'a [u8], name: &[u8])
+        -> Option<CentralDirectoryFileHeaderReader<'a>>
+    {
+        index.find(archive, &mut [], name).unwrap()
+    }
+
+    fn normalize(normalization: Normalization, name: &[u8]) -> Vec<u8> {
+        normalization.apply(name).collect()
+    }
+
+    #[test]
+    fn normalization_exact() {
+        let exact = Normalization::exact();
+
+        assert_eq!(normalize(exact, b"/Dir\\A.txt"), b"/Dir\\A.txt".to_vec());
+    }
+
+    #[test]
+    fn normalization_case_folding() {
+        let folding = Normalization::exact().with_case_folding();
+
+        assert_eq!(normalize(folding, b"/Dir\\A.txt"), b"/dir\\a.txt".to_vec());
+    }
+
+    #[test]
+    fn normalization_slashes() {
+        let slashes = Normalization::exact().with_slash_normalization();
+
+        assert_eq!(
+            normalize(slashes, b"//Dir\\\\A.txt"),
+            b"Dir/A.txt".to_vec()
+        );
+        assert_eq!(normalize(slashes, b"Dir/"), b"Dir/".to_vec());
+    }
+
+    #[test]
+    fn build_failure_on_missing_eocd() {
+        let mut slots = [IndexSlot::default(); 4];
+
+        assert_eq!(
+            build(&[0u8; 64], &mut slots, Normalization::exact()),
+            Err(IndexError::MissingEndOfCentralDirectory)
+        );
+    }
+
+    #[test]
+    fn build_failure_on_insufficient_slots() {
+        let archive = test_archive(&[(b"a", b""), (b"b", b""), (b"c", b"")]);
+        let mut slots = [IndexSlot::default(); 2];
+
+        assert_eq!(
+            build(&archive, &mut slots, Normalization::exact()),
+            Err(IndexError::InsufficientSlots)
+        );
+    }
+
+    #[test]
+    fn find_success_on_exact_name() {
+        let archive = test_archive(&[
+            (b"a.txt", b"1"), (b"dir/b.txt", b"22"), (b"c.txt", b"333"),
+        ]);
+        let mut slots = [IndexSlot::default(); 8];
+
+        let index =
+            build(&archive, &mut slots, Normalization::exact()).unwrap();
+
+        assert_eq!(index.len(), 3);
+
+        for &(name, size) in &[(&b"a.txt"[..], 1), (b"dir/b.txt", 2),
+                               (b"c.txt", 3)]
+        {
+            let cdfh = find(&index, &archive, name).unwrap();
+
+            assert_eq!(cdfh.file_name(), Some(name));
+            assert_eq!(cdfh.compressed_size(), size);
+        }
+
+        assert_eq!(find(&index, &archive, b"A.txt"), None);
+        assert_eq!(find(&index, &archive, b"dir\\b.txt"), None);
+    }
+
+    #[test]
+    fn find_success_on_normalized_name() {
+        let archive = test_archive(&[(b"Dir\\B.txt", b"22")]);
+        let mut slots = [IndexSlot::default(); 1];
+
+        let normalization = Normalization::exact()
+            .with_case_folding()
+            .with_slash_normalization();
+
+        let index = build(&archive, &mut slots, normalization).unwrap();
+
+        let cdfh = find(&index, &archive, b"/dir/b.TXT").unwrap();
+
+        assert_eq!(cdfh.file_name(), Some(&b"Dir\\B.txt"[..]));
+    }
+
+    #[test]
+    fn find_first_on_duplicate_names() {
+        let archive = test_archive(&[(b"a", b"1"), (b"a", b"22")]);
+        let mut slots = [IndexSlot::default(); 2];
+
+        let index =
+            build(&archive, &mut slots, Normalization::exact()).unwrap();
+
+        let cdfh = find(&index, &archive, b"a").unwrap();
+
+        assert_eq!(cdfh.compressed_size(), 1);
+    }
+
+    #[test]
+    fn find_success_on_zip64_archive() {
+        let archive = test_zip64_archive(&[(b"a.txt", b"1"), (b"b.txt", b"2")]);
+        let mut slots = [IndexSlot::default(); 2];
+
+        let index =
+            build(&archive, &mut slots, Normalization::exact()).unwrap();
+
+        let cdfh = find(&index, &archive, b"b.txt").unwrap();
+
+        assert_eq!(cdfh.file_name(), Some(&b"b.txt"[..]));
+        assert_eq!(find(&index, &archive, b"c.txt"), None);
+    }
+
+    #[test]
+    fn build_io_on_failing_reader() {
+        let archive = test_archive(&[(b"a", b"1")]);
+        let reader = FailingReader::new(&archive, 0);
+        let mut slots = [IndexSlot::default(); 1];
+
+        assert_eq!(
+            Index::build(&reader, &mut [], &mut slots, Normalization::exact()),
+            Err(IndexError::Io(TestError))
+        );
+    }
+
+    #[test]
+    fn find_success_through_copying_reader() {
+        let archive = test_archive(&[(b"a.txt", b"1"), (b"b.txt", b"22")]);
+        let reader = CopyingReader { data: &archive };
+
+        let mut scratch = [0; 64];
+        let mut slots = [IndexSlot::default(); 2];
+
+        let normalization = Normalization::exact();
+
+        let index =
+            Index::build(&reader, &mut scratch, &mut slots, normalization)
+                .unwrap();
+
+        let cdfh = index.find(&reader, &mut scratch, b"b.txt").unwrap();
+        let cdfh = cdfh.unwrap();
+
+        assert_eq!(cdfh.file_name(), Some(&b"b.txt"[..]));
+        assert_eq!(cdfh.compressed_size(), 2);
+    }
+
+    #[test]
+    fn find_success_through_chunked_reader() {
+        let archive = test_archive(&[(b"a.txt", b"1"), (b"b.txt", b"22")]);
+        let reader = ChunkedReader { data: &archive, chunk: 7 };
+
+        let mut scratch = [0; 128];
+        let mut slots = [IndexSlot::default(); 2];
+
+        let normalization = Normalization::exact();
+
+        let index =
+            Index::build(&reader, &mut scratch, &mut slots, normalization)
+                .unwrap();
+
+        let cdfh = index.find(&reader, &mut scratch, b"b.txt").unwrap();
+        let cdfh = cdfh.unwrap();
+
+        assert_eq!(cdfh.file_name(), Some(&b"b.txt"[..]));
+        assert_eq!(cdfh.compressed_size(), 2);
+    }
+}
@@ -0,0 +1,106 @@
+//! # Low-level access to the Local File Header records of a ZIP archive
+
+use core::cmp::min;
+
+use api::Reader;
+use utils::read::{read_contiguous, read_exact};
+
+use zip::raw::LocalFileHeaderReader;
+
+/// Returns the Local File Header record at `offset`, not extending beyond
+/// `end`, either borrowed from the reader or copied into the `scratch` buffer.
+///
+/// The fixed part of the record is read first, then the record as a whole, so
+/// that a record straddling chunks of the reader is assembled into the
+/// `scratch` buffer.
+///
+/// Returns `None` if fewer bytes than the fixed part of a record are available.
+/// A record copied into a `scratch` buffer smaller than itself is truncated.
+///
+/// Note: the signature is not checked.
+pub fn read_local_file_header<'a, R>(
+    reader: &'a R,
+    offset: u64,
+    end: u64,
+    scratch: &'a mut [u8]
+)
+    -> Result<Option<LocalFileHeaderReader<'a>>, R::Error>
+    where R: Reader + ?Sized + 'a
+{
+    type LfhReader<'a> = LocalFileHeaderReader<'a>;
+
+    let mut fixed = [0; 30];
+
+    let size = {
+        let limit = min(end.saturating_sub(offset), fixed.len() as u64);
+        let fixed = fixed.get_mut(..limit as usize).unwrap_or(&mut []);
+        let filled = read_exact(reader, offset, fixed)?;
+
+        match LfhReader::new(fixed.get(..filled).unwrap_or(b"")) {
+            Some(lfh) => lfh.expected_size(),
+            None => return Ok(None),
+        }
+    };
+
+    let end = min(offset.saturating_add(size as u64), end);
+    let slice = read_contiguous(reader, offset..end, scratch)?;
+
+    Ok(LfhReader::new(slice))
+}
+
+#[cfg(test)]
+mod tests {
+    use utils::test::{ChunkedReader, CopyingReader, test_archive};
+    use super::read_local_file_header;
+
+    #[test]
+    fn read_lfh_borrowed_from_slice() {
+        let archive = test_archive(&[(b"a.txt", b"1")]);
+        let archive = &archive[..];
+
+        let lfh = read_local_file_header(archive, 0, 36, &mut [])
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(lfh.file_name(), Some(&b"a.txt"[..]));
+        assert_eq!(lfh.raw().as_ptr(), archive.as_ptr());
+    }
+
+    #[test]
+    fn read_lfh_assembled_across_chunks() {
+        let archive = test_archive(&[(b"a.txt", b"1")]);
+        let reader = ChunkedReader { data: &archive, chunk: 7 };
+        let mut scratch = [0; 64];
+
+        let lfh = read_local_file_header(&reader, 0, 36, &mut scratch)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(lfh.raw(), &archive[..35]);
+    }
+
+    #[test]
+    fn read_lfh_none_on_short_read() {
+        let archive = test_archive(&[(b"a.txt", b"1")]);
+        let reader = CopyingReader { data: &archive };
+
+        assert_eq!(
+            read_local_file_header(&reader, 0, 29, &mut [0; 64]),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn read_lfh_truncated_to_scratch() {
+        let archive = test_archive(&[(b"a.txt", b"1")]);
+        let reader = CopyingReader { data: &archive };
+        let mut scratch = [0; 32];
+
+        let lfh = read_local_file_header(&reader, 0, 36, &mut scratch)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(lfh.raw(), &archive[..32]);
+        assert_eq!(lfh.file_name(), None);
+    }
+}
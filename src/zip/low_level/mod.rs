@@ -3,12 +3,69 @@
 //! This module provides types and functions to navigate around an archive,
 //! whether correctly formed or not.
 
+mod ambiguity;
+mod archive;
+mod asynchronous;
 mod cdfh;
+mod crc;
+mod dd;
+mod diagnostic;
 mod eocd;
+mod extra;
+mod extract;
+mod index;
+mod lfh;
+mod plan;
+mod validate;
+mod zip64;
+mod zipcrypto;
 
 pub use self::eocd::{
+    EndOfCentralDirectory,
     EndOfCentralDirectoryIterator,
     locate_end_of_central_directory
 };
 
-pub use self::cdfh::CentralDirectoryFileHeaderIterator;
+pub use self::archive::CentralDirectory;
+pub(crate) use self::archive::data_range;
+
+pub use self::asynchronous::{
+    ListCentralDirectory,
+    LocateEndOfCentralDirectory,
+    list_central_directory
+};
+
+pub use self::cdfh::{
+    CentralDirectoryFileHeaderIterator,
+    read_central_directory_file_header
+};
+pub use self::crc::{Crc32Verifier, Verification};
+pub(crate) use self::crc::{aes_compression_method, is_ae2};
+pub use self::dd::{read_data_descriptor, read_zip64_data_descriptor};
+pub use self::extra::ExtraFieldIterator;
+pub use self::extract::{ExtractError, Extractor};
+pub use self::lfh::read_local_file_header;
+pub use self::plan::{
+    EndOfCentralDirectoryPlanner,
+    PlanError,
+    ReadPlanner,
+    Step
+};
+pub use self::zip64::{Zip64Fields, locate_zip64_end_of_central_directory};
+pub use self::zipcrypto::{
+    ENCRYPTION_HEADER_SIZE,
+    ZipCrypto,
+    check_byte
+};
+pub use self::index::{
+    Index,
+    IndexError,
+    IndexSlot,
+    Normalization,
+    Normalized
+};
+
+pub use self::diagnostic::{Code, Diagnostic, Record, Severity};
+
+pub use self::ambiguity::detect_ambiguities;
+pub use self::validate::validate;
@@ -0,0 +1,868 @@
+//! # Planning of the byte ranges needed to read the records of a ZIP archive
+//!
+//! A planner requests the byte ranges it needs, one at a time, and decodes the
+//! bytes handed back, without performing any I/O itself; it is thus usable
+//! whether the bytes are fetched synchronously or not, and lets the caller
+//! fetch a remote archive in a few large requests rather than many small reads.
+//!
+//! The caller is expected to loop over `next`, fetching the bytes of each range
+//! needed and handing them back with `provide`, until the planner is done.
+
+use core::cmp::{max, min};
+use core::convert::Infallible;
+use core::ops::Range;
+
+use api::{ReadError, Reader};
+
+use zip::raw::{
+    CentralDirectoryFileHeaderReader,
+    EndOfCentralDirectoryReader,
+    LocalFileHeaderReader,
+    Zip64EndOfCentralDirectoryLocatorReader
+};
+
+use super::eocd::{
+    EndOfCentralDirectory,
+    ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE,
+    locate
+};
+use super::zip64::Zip64Fields;
+
+/// The size of the ZIP64 End of Central Directory Locator record.
+const LOCATOR_SIZE: usize = 20;
+
+/// The size of the fixed parts of the ZIP64 End of Central Directory record
+/// and of its Locator record, which usually immediately precede the End of
+/// Central Directory record, and are thus requested together.
+pub(crate) const ZIP64_RECORDS_SIZE: usize =
+    ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE + LOCATOR_SIZE;
+
+/// The next step of a planner.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Step<T> {
+    /// The bytes of the range are needed, and are to be handed back with
+    /// `provide`.
+    Need(Range<u64>),
+    /// The planner is done.
+    Done(Result<T, ReadError<Infallible>>),
+}
+
+/// A planner locating the End of Central Directory of an archive, superseded
+/// by the ZIP64 End of Central Directory record if any, as
+/// `EndOfCentralDirectory::locate` does.
+///
+/// The tail of the archive is requested first, which usually also holds the
+/// ZIP64 records; otherwise these are requested next, together.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct EndOfCentralDirectoryPlanner {
+    size: u64,
+    tail_size: u64,
+    state: State,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum State {
+    Tail,
+    Locator(EndOfCentralDirectory),
+    Zip64(EndOfCentralDirectory, [u8; LOCATOR_SIZE]),
+    Done(Result<EndOfCentralDirectory, ReadError<Infallible>>),
+}
+
+impl EndOfCentralDirectoryPlanner {
+    /// Returns an instance for an archive of `size` bytes, requesting at most
+    /// `max_tail` bytes of its tail.
+    ///
+    /// A tail of less than 65557 bytes may miss an End of Central Directory
+    /// record with a long comment.
+    pub fn new(size: u64, max_tail: usize) -> EndOfCentralDirectoryPlanner {
+        let tail_size = min(
+            min(size, EndOfCentralDirectoryReader::max_size() as u64),
+            max_tail as u64
+        );
+
+        EndOfCentralDirectoryPlanner { size, tail_size, state: State::Tail }
+    }
+
+    /// Returns the range of the tail of the archive, requested first.
+    pub fn tail(&self) -> Range<u64> { self.size - self.tail_size..self.size }
+
+    /// Returns the End of Central Directory record located in the tail, not
+    /// superseded by the ZIP64 End of Central Directory record yet.
+    pub(crate) fn located(&self) -> Option<EndOfCentralDirectory> {
+        match self.state {
+            State::Locator(eocd) | State::Zip64(eocd, _) => Some(eocd),
+            _ => None,
+        }
+    }
+
+    /// Returns the next step.
+    pub fn next(&self) -> Step<EndOfCentralDirectory> {
+        match self.state {
+            State::Tail => Step::Need(self.tail()),
+            State::Locator(ref eocd) =>
+                Step::Need(zip64_records_range(eocd.offset())),
+            State::Zip64(ref eocd, ref locator) =>
+                Step::Need(zip64_range(eocd.offset(), locator)),
+            State::Done(ref result) => Step::Done(*result),
+        }
+    }
+
+    /// Provides the bytes of the range needed, from its start.
+    ///
+    /// Fewer bytes than needed denote the end of the archive.
+    pub fn provide(&mut self, bytes: &[u8]) {
+        let range = match self.next() {
+            Step::Need(range) => range,
+            Step::Done(_) => return,
+        };
+
+        let fetched = (range.start, bytes);
+
+        self.state = match self.state {
+            State::Tail => self.on_tail(fetched),
+            State::Locator(eocd) => {
+                match segment(fetched, locator_range(eocd.offset())) {
+                    Some(bytes) => {
+                        let mut locator = [0; LOCATOR_SIZE];
+                        locator.copy_from_slice(bytes);
+                        self.on_locator(eocd, locator, fetched)
+                    },
+                    //  As `EndOfCentralDirectory::new`, which ignores an
+                    //  unreadable Locator record.
+                    None => State::Done(Ok(eocd)),
+                }
+            },
+            State::Zip64(eocd, locator) =>
+                self.resolve(eocd, &locator, fetched),
+            State::Done(ref result) => State::Done(*result),
+        };
+    }
+
+    fn on_tail(&self, tail: (u64, &[u8])) -> State {
+        //  The End of Central Directory record ends with the archive.
+        if tail.1.len() as u64 != self.tail_size {
+            return State::Done(Err(ReadError::Truncated));
+        }
+
+        let eocd = match locate(tail.1, &mut []) {
+            Ok((offset, eocd)) => EndOfCentralDirectory::from_record(
+                eocd,
+                tail.0.wrapping_add(offset)
+            ),
+            Err(error) => return State::Done(Err(error)),
+        };
+
+        let range = locator_range(eocd.offset());
+
+        if range.is_empty() {
+            return State::Done(Ok(eocd));
+        }
+
+        match segment(tail, range) {
+            Some(bytes) => {
+                let mut locator = [0; LOCATOR_SIZE];
+                locator.copy_from_slice(bytes);
+                self.on_locator(eocd, locator, tail)
+            },
+            None => State::Locator(eocd),
+        }
+    }
+
+    fn on_locator(
+        &self,
+        eocd: EndOfCentralDirectory,
+        locator: [u8; LOCATOR_SIZE],
+        fetched: (u64, &[u8])
+    )
+        -> State
+    {
+        type LocatorReader<'a> = Zip64EndOfCentralDirectoryLocatorReader<'a>;
+
+        let is_locator = LocatorReader::new(&locator)
+            .map(|l| l.signature() == LocatorReader::expected_signature())
+            .unwrap_or(false);
+
+        let range = zip64_range(eocd.offset(), &locator);
+
+        //  Without a locator, or with an invalid one, the ZIP64 records are
+        //  ignored, as `EndOfCentralDirectory::new` does.
+        if !is_locator || range.is_empty() {
+            return State::Done(Ok(eocd));
+        }
+
+        match segment(fetched, range) {
+            Some(_) => self.resolve(eocd, &locator, fetched),
+            None => State::Zip64(eocd, locator),
+        }
+    }
+
+    fn resolve(
+        &self,
+        eocd: EndOfCentralDirectory,
+        locator: &[u8; LOCATOR_SIZE],
+        zip64: (u64, &[u8])
+    )
+        -> State
+    {
+        let segments = Segments {
+            size: self.size,
+            segments: [(locator_range(eocd.offset()).start, locator), zip64],
+        };
+
+        State::Done(eocd.resolve_zip64(&segments))
+    }
+}
+
+/// The reason why an entry could not be selected by a `ReadPlanner`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum PlanError {
+    /// Entries are selected once the Central Directory is provided, and
+    /// before any Local File Header record is.
+    NotSelecting,
+    /// All the slots are already used.
+    InsufficientSlots,
+}
+
+/// A planner of the byte ranges needed to list the entries of an archive, and
+/// to read the Local File Header records of some of them.
+///
+/// The ranges are requested in order:
+///
+/// - the tail of the archive, holding the End of Central Directory record;
+/// - the ZIP64 records, if not within the tail, coalesced with the Central
+///   Directory when it immediately precedes them;
+/// - the Central Directory, if not fetched already;
+/// - the Local File Header records of the entries selected, not within the
+///   tail, coalescing the records less than `max_gap` bytes apart.
+///
+/// A range is never requested if its bytes were fetched already: the caller is
+/// expected to keep them, to list the entries of the Central Directory in
+/// particular. Listing a small archive thus takes a single request, and a large
+/// one two, or three with ZIP64 records.
+///
+/// Once the Central Directory is provided, the entries whose Local File Header
+/// records are needed are selected with `select`. The ranges of the records are
+/// kept in caller-provided slots, so that no memory is allocated.
+///
+/// The size of a Local File Header record is estimated from its Central
+/// Directory File Header record; should it be larger, the whole record is
+/// requested anew.
+#[derive(Debug, Eq, PartialEq)]
+pub struct ReadPlanner<'s> {
+    eocd: EndOfCentralDirectoryPlanner,
+    //  The tail, and the range coalescing the Central Directory and the ZIP64
+    //  records, if any.
+    fetched: [Range<u64>; 2],
+    max_gap: u64,
+    state: ReadState,
+    slots: &'s mut [Range<u64>],
+    nb_selected: usize,
+    //  The first selected slot whose record was not fetched yet.
+    next_slot: usize,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum ReadState {
+    EndOfCentralDirectory,
+    CentralDirectory(EndOfCentralDirectory),
+    Selecting(EndOfCentralDirectory),
+    LocalFileHeaders(EndOfCentralDirectory),
+    Done(Result<EndOfCentralDirectory, ReadError<Infallible>>),
+}
+
+impl<'s> ReadPlanner<'s> {
+    /// Returns an instance for an archive of `size` bytes, requesting at most
+    /// `max_tail` bytes of its tail, coalescing ranges less than `max_gap`
+    /// bytes apart, and able to select as many entries as there are `slots`.
+    pub fn new(
+        size: u64,
+        max_tail: usize,
+        max_gap: u64,
+        slots: &'s mut [Range<u64>]
+    )
+        -> ReadPlanner<'s>
+    {
+        let eocd = EndOfCentralDirectoryPlanner::new(size, max_tail);
+        let tail = eocd.tail();
+
+        ReadPlanner {
+            eocd,
+            fetched: [tail, 0..0],
+            max_gap,
+            state: ReadState::EndOfCentralDirectory,
+            slots,
+            nb_selected: 0,
+            next_slot: 0,
+        }
+    }
+
+    /// Returns the End of Central Directory, once located.
+    pub fn end_of_central_directory(&self) -> Option<EndOfCentralDirectory> {
+        match self.state {
+            ReadState::EndOfCentralDirectory => None,
+            ReadState::CentralDirectory(eocd) |
+            ReadState::Selecting(eocd) |
+            ReadState::LocalFileHeaders(eocd) => Some(eocd),
+            ReadState::Done(ref result) => result.ok(),
+        }
+    }
+
+    /// Returns the ranges of the Local File Header records selected so far,
+    /// sorted by offset, omitting those fetched already.
+    pub fn selected(&self) -> &[Range<u64>] {
+        self.slots.get(..self.nb_selected).unwrap_or(&[])
+    }
+
+    /// Returns the next step.
+    pub fn next(&self) -> Step<EndOfCentralDirectory> {
+        match self.state {
+            ReadState::EndOfCentralDirectory => match self.eocd.next() {
+                Step::Need(range) => Step::Need(self.coalesce(range)),
+                step => step,
+            },
+            ReadState::CentralDirectory(ref eocd) =>
+                match eocd.central_directory_range() {
+                    Some(range) => Step::Need(range),
+                    None => Step::Done(Err(ReadError::Malformed)),
+                },
+            ReadState::Selecting(ref eocd) |
+            ReadState::LocalFileHeaders(ref eocd) => match self.group() {
+                Some((range, _)) => Step::Need(range),
+                None => Step::Done(Ok(*eocd)),
+            },
+            ReadState::Done(ref result) => Step::Done(*result),
+        }
+    }
+
+    /// Provides the bytes of the range needed, from its start.
+    ///
+    /// Fewer bytes than needed denote the end of the archive.
+    pub fn provide(&mut self, bytes: &[u8]) {
+        let range = match self.next() {
+            Step::Need(range) => range,
+            Step::Done(_) => return,
+        };
+
+        let length = range.end - range.start;
+
+        self.state = match self.state {
+            ReadState::EndOfCentralDirectory => {
+                let needed = match self.eocd.next() {
+                    Step::Need(needed) => needed,
+                    Step::Done(_) => range.clone(),
+                };
+
+                if needed != range {
+                    let end = range.start.saturating_add(bytes.len() as u64);
+                    self.fetched[1] = range.start..end;
+                }
+
+                let skipped = (needed.start - range.start) as usize;
+                self.eocd.provide(bytes.get(skipped..).unwrap_or(b""));
+
+                match self.eocd.next() {
+                    Step::Need(_) => ReadState::EndOfCentralDirectory,
+                    Step::Done(Ok(eocd)) => self.on_end_of_central_directory(
+                        eocd
+                    ),
+                    Step::Done(Err(error)) => ReadState::Done(Err(error)),
+                }
+            },
+            ReadState::CentralDirectory(eocd) => {
+                if (bytes.len() as u64) < length {
+                    ReadState::Done(Err(ReadError::Truncated))
+                } else {
+                    ReadState::Selecting(eocd)
+                }
+            },
+            ReadState::Selecting(eocd) |
+            ReadState::LocalFileHeaders(eocd) => {
+                if (bytes.len() as u64) < length {
+                    ReadState::Done(Err(ReadError::Truncated))
+                } else {
+                    self.on_local_file_headers(bytes);
+                    ReadState::LocalFileHeaders(eocd)
+                }
+            },
+            ReadState::Done(ref result) => ReadState::Done(*result),
+        };
+    }
+
+    /// Selects the entry of the `cdfh` record, so that its Local File Header
+    /// record is requested.
+    pub fn select(&mut self, cdfh: &CentralDirectoryFileHeaderReader)
+        -> Result<(), PlanError>
+    {
+        match self.state {
+            ReadState::Selecting(_) => (),
+            _ => return Err(PlanError::NotSelecting),
+        }
+
+        let offset = Zip64Fields::from_central_directory_file_header(*cdfh)
+            .local_file_header_relative_offset()
+            .unwrap_or(u64::from(cdfh.local_file_header_relative_offset()));
+
+        let size = LocalFileHeaderReader::min_size() as u64 +
+            u64::from(cdfh.file_name_size()) +
+            u64::from(cdfh.extra_field_size());
+
+        let range = offset..offset.saturating_add(size);
+
+        if self.within_fetched(&range) {
+            return Ok(());
+        }
+
+        let selected = match self.slots.get_mut(..self.nb_selected + 1) {
+            Some(selected) => selected,
+            None => return Err(PlanError::InsufficientSlots),
+        };
+
+        let position = selected
+            .get(..self.nb_selected)
+            .unwrap_or(&[])
+            .partition_point(|slot| slot.start <= range.start);
+
+        if let Some(slots) = selected.get_mut(position..) {
+            slots.rotate_right(1);
+            if let Some(slot) = slots.first_mut() {
+                *slot = range;
+            }
+        }
+
+        self.nb_selected += 1;
+
+        Ok(())
+    }
+
+    fn on_end_of_central_directory(&self, eocd: EndOfCentralDirectory)
+        -> ReadState
+    {
+        match eocd.central_directory_range() {
+            Some(ref range) if self.within_fetched(range) =>
+                ReadState::Selecting(eocd),
+            Some(_) => ReadState::CentralDirectory(eocd),
+            None => ReadState::Done(Err(ReadError::Malformed)),
+        }
+    }
+
+    /// Checks the size of each Local File Header record of the current group,
+    /// enlarging the range of those which do not fit.
+    fn on_local_file_headers(&mut self, bytes: &[u8]) {
+        let (group, end) = match self.group() {
+            Some(group) => group,
+            None => return,
+        };
+
+        let mut next_slot = end;
+
+        for index in (self.next_slot..end).rev() {
+            let slot = match self.slots.get_mut(index) {
+                Some(slot) => slot,
+                None => continue,
+            };
+
+            let start = (slot.start - group.start) as usize;
+
+            let size = bytes.get(start..)
+                .and_then(LocalFileHeaderReader::new)
+                .map(|lfh| lfh.expected_size() as u64)
+                .unwrap_or(0);
+
+            let record_end = slot.start.saturating_add(size);
+
+            if record_end > group.end {
+                slot.end = record_end;
+                next_slot = index;
+            }
+        }
+
+        self.next_slot = next_slot;
+    }
+
+    /// Returns the range coalescing the slots from the next one, and the index
+    /// of the first slot past them, if any slot remains.
+    fn group(&self) -> Option<(Range<u64>, usize)> {
+        let slots = self.selected();
+        let first = slots.get(self.next_slot)?;
+
+        let mut range = first.clone();
+        let mut end = self.next_slot + 1;
+
+        for slot in slots.get(end..).unwrap_or(&[]) {
+            if slot.start > range.end.saturating_add(self.max_gap) {
+                break;
+            }
+
+            range.end = max(range.end, slot.end);
+            end += 1;
+        }
+
+        Some((range, end))
+    }
+
+    /// Returns the `range` of the ZIP64 records, extended to the start of the
+    /// Central Directory if it ends less than `max_gap` bytes before.
+    fn coalesce(&self, range: Range<u64>) -> Range<u64> {
+        let cd = self.eocd
+            .located()
+            .and_then(|eocd| eocd.central_directory_range());
+
+        match cd {
+            Some(cd) if cd.start < range.start &&
+                cd.end.saturating_add(self.max_gap) >= range.start =>
+                cd.start..range.end,
+            _ => range,
+        }
+    }
+
+    fn within_fetched(&self, range: &Range<u64>) -> bool {
+        self.fetched
+            .iter()
+            .any(|fetched| range.start >= fetched.start &&
+                 range.end <= fetched.end)
+    }
+}
+
+/// Returns the range of the ZIP64 End of Central Directory Locator record
+/// preceding the End of Central Directory record at `eocd_offset`, or an
+/// empty range if there is no room for it.
+fn locator_range(eocd_offset: u64) -> Range<u64> {
+    let start = eocd_offset.saturating_sub(LOCATOR_SIZE as u64);
+
+    if eocd_offset - start == LOCATOR_SIZE as u64 {
+        start..eocd_offset
+    } else {
+        0..0
+    }
+}
+
+/// Returns the range of the ZIP64 records, when they immediately precede the
+/// End of Central Directory record at `eocd_offset`.
+fn zip64_records_range(eocd_offset: u64) -> Range<u64> {
+    eocd_offset.saturating_sub(ZIP64_RECORDS_SIZE as u64)..eocd_offset
+}
+
+/// Returns the range of the fixed part of the ZIP64 End of Central Directory
+/// record pointed to by the `locator`, or an empty range if it does not lie
+/// before the locator.
+fn zip64_range(eocd_offset: u64, locator: &[u8; LOCATOR_SIZE]) -> Range<u64> {
+    let limit = locator_range(eocd_offset).start;
+
+    let start = Zip64EndOfCentralDirectoryLocatorReader::new(locator)
+        .map(|l| l.zip64_end_of_central_directory_offset())
+        .unwrap_or(u64::MAX);
+
+    if start >= limit {
+        return 0..0;
+    }
+
+    let size = ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE as u64;
+
+    start..min(start.saturating_add(size), limit)
+}
+
+/// Returns the bytes of the `range`, if the `segment` holds all of them.
+fn segment(segment: (u64, &[u8]), range: Range<u64>) -> Option<&[u8]> {
+    let start = range.start.checked_sub(segment.0)?;
+    let end = range.end.checked_sub(segment.0)?;
+
+    if end > segment.1.len() as u64 {
+        return None;
+    }
+
+    segment.1.get(start as usize..end as usize)
+}
+
+/// A `Reader` over a few segments of an archive, borrowing from the segment
+/// holding the start of the range requested, if any.
+struct Segments<'a> {
+    size: u64,
+    segments: [(u64, &'a [u8]); 2],
+}
+
+impl<'a> Reader for Segments<'a> {
+    type Error = Infallible;
+
+    fn size(&self) -> Result<u64, Infallible> { Ok(self.size) }
+
+    fn get<'b>(&'b self, range: Range<u64>, _: &'b mut [u8])
+        -> Result<&'b [u8], Infallible>
+    {
+        for &(offset, bytes) in &self.segments {
+            let end = offset.saturating_add(bytes.len() as u64);
+
+            if range.start < offset || range.start >= end {
+                continue;
+            }
+
+            let start = (range.start - offset) as usize;
+            let end = (min(range.end, end) - offset) as usize;
+
+            return Ok(bytes.get(start..end).unwrap_or(b""));
+        }
+
+        Ok(b"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ops::Range;
+
+    use api::ReadError;
+    use utils::test::{TEST_ENTRIES, test_archive, test_zip64_archive};
+    use zip::low_level::{
+        CentralDirectoryFileHeaderIterator,
+        EndOfCentralDirectory
+    };
+    use super::{EndOfCentralDirectoryPlanner, PlanError, ReadPlanner, Step};
+
+    fn plan(archive: &[u8], max_tail: usize)
+        -> (Vec<(u64, u64)>, Step<EndOfCentralDirectory>)
+    {
+        let mut planner =
+            EndOfCentralDirectoryPlanner::new(archive.len() as u64, max_tail);
+        let mut requests = vec!();
+
+        while let Step::Need(range) = planner.next() {
+            assert!(requests.len() < 4);
+
+            let bytes = &archive[range.start as usize..range.end as usize];
+            requests.push((range.start, range.end));
+            planner.provide(bytes);
+        }
+
+        (requests, planner.next())
+    }
+
+    /// Drives the `planner` over the `archive`, selecting all its entries as
+    /// soon as possible.
+    fn drive(archive: &[u8], planner: &mut ReadPlanner)
+        -> (Vec<(u64, u64)>, Step<EndOfCentralDirectory>)
+    {
+        let mut requests = vec!();
+        let mut selected = false;
+
+        loop {
+            if let (false, Some(eocd)) =
+                (selected, planner.end_of_central_directory())
+            {
+                let cd = eocd.central_directory_range().unwrap();
+                let iterator = CentralDirectoryFileHeaderIterator::new(
+                    &archive[cd.start as usize..cd.end as usize],
+                    eocd.nb_central_directory_records()
+                );
+
+                for cdfh in iterator {
+                    match planner.select(&cdfh) {
+                        Ok(()) => selected = true,
+                        Err(PlanError::NotSelecting) => break,
+                        Err(error) => panic!("{:?}", error),
+                    }
+                }
+            }
+
+            let range = match planner.next() {
+                Step::Need(range) => range,
+                step => return (requests, step),
+            };
+
+            assert!(requests.len() < 16);
+
+            let end = range.end.min(archive.len() as u64);
+            requests.push((range.start, range.end));
+            planner.provide(&archive[range.start as usize..end as usize]);
+        }
+    }
+
+    #[test]
+    fn plan_eocd_in_one_request() {
+        let archive = test_archive(&[(b"a", b"a")]);
+        let size = archive.len() as u64;
+
+        let expected = EndOfCentralDirectory::locate(&archive[..], &mut []);
+
+        assert_eq!(
+            plan(&archive, 1024),
+            (vec!((0, size)), Step::Done(expected))
+        );
+    }
+
+    #[test]
+    fn plan_zip64_eocd_in_one_request() {
+        let archive = test_zip64_archive(&[(b"a", b"a")]);
+        let size = archive.len() as u64;
+
+        let expected = EndOfCentralDirectory::locate(&archive[..], &mut []);
+
+        assert!(expected.unwrap().is_zip64());
+        assert_eq!(
+            plan(&archive, 1024),
+            (vec!((0, size)), Step::Done(expected))
+        );
+    }
+
+    #[test]
+    fn plan_zip64_eocd_in_two_requests() {
+        let archive = test_zip64_archive(&[(b"a", b"a")]);
+        let size = archive.len() as u64;
+
+        let expected = EndOfCentralDirectory::locate(&archive[..], &mut []);
+
+        //  The tail only holds the End of Central Directory record, and the
+        //  ZIP64 records immediately precede it.
+        assert_eq!(
+            plan(&archive, 22),
+            (
+                vec!((size - 22, size), (size - 98, size - 22)),
+                Step::Done(expected)
+            )
+        );
+    }
+
+    #[test]
+    fn plan_failure_on_truncated_archive() {
+        assert_eq!(
+            plan(&[0; 21], 1024),
+            (vec!((0, 21)), Step::Done(Err(ReadError::Truncated)))
+        );
+        assert_eq!(
+            plan(&[0; 64], 1024),
+            (vec!((0, 64)), Step::Done(Err(ReadError::Malformed)))
+        );
+    }
+
+    #[test]
+    fn read_plan_in_one_request_for_small_archive() {
+        let archive = test_zip64_archive(TEST_ENTRIES);
+        let size = archive.len() as u64;
+
+        let expected = EndOfCentralDirectory::locate(&archive[..], &mut []);
+
+        let mut slots = vec!(0..0; 0);
+        let mut planner = ReadPlanner::new(size, 65557, 0, &mut slots);
+
+        assert_eq!(
+            drive(&archive, &mut planner),
+            (vec!((0, size)), Step::Done(expected))
+        );
+        assert_eq!(planner.selected(), &[]);
+    }
+
+    #[test]
+    fn read_plan_coalesced_local_file_headers() {
+        let archive = test_archive(TEST_ENTRIES);
+        let size = archive.len() as u64;
+
+        let expected = EndOfCentralDirectory::locate(&archive[..], &mut []);
+        let cd = expected.unwrap().central_directory_range().unwrap();
+
+        //  Each Local File Header record is 30 bytes plus the name, followed
+        //  by the content: only the first one is followed by a gap, of 13.
+        let lfhs: &[Range<u64>] = &[0..39, 52..87, 87..121, 121..165];
+
+        let mut slots = vec!(0..0; 4);
+        let mut planner = ReadPlanner::new(size, 22, 13, &mut slots);
+
+        assert_eq!(
+            drive(&archive, &mut planner),
+            (
+                vec!((size - 22, size), (cd.start, cd.end), (0, 165)),
+                Step::Done(expected)
+            )
+        );
+        assert_eq!(planner.selected(), lfhs);
+
+        let mut slots = vec!(0..0; 4);
+        let mut planner = ReadPlanner::new(size, 22, 12, &mut slots);
+
+        assert_eq!(
+            drive(&archive, &mut planner),
+            (
+                vec!(
+                    (size - 22, size),
+                    (cd.start, cd.end),
+                    (0, 39),
+                    (52, 165)
+                ),
+                Step::Done(expected)
+            )
+        );
+    }
+
+    #[test]
+    fn read_plan_local_file_header_larger_than_estimated() {
+        let mut archive = test_archive(TEST_ENTRIES);
+        let size = archive.len() as u64;
+
+        //  The first record claims the first 2 bytes of its content as extra
+        //  field, which its Central Directory File Header record lacks.
+        archive[28] = 2;
+
+        let mut slots = vec!(0..0; 4);
+        let mut planner = ReadPlanner::new(size, 22, 0, &mut slots);
+
+        let (requests, step) = drive(&archive, &mut planner);
+
+        let eocd = planner.end_of_central_directory();
+
+        assert_eq!(Some(step), eocd.map(|eocd| Step::Done(Ok(eocd))));
+        assert_eq!(&requests[2..], &[(0, 39), (0, 41), (52, 165)]);
+    }
+
+    #[test]
+    fn read_plan_failure_on_truncated_central_directory() {
+        let archive = test_zip64_archive(TEST_ENTRIES);
+        let size = archive.len() as u64;
+
+        let eocd =
+            EndOfCentralDirectory::locate(&archive[..], &mut []).unwrap();
+        let cd = eocd.central_directory_range().unwrap();
+
+        let mut slots = vec!(0..0; 3);
+        let mut planner = ReadPlanner::new(size, 22, 0, &mut slots);
+
+        //  The tail, then the ZIP64 records.
+        for _ in 0..2 {
+            match planner.next() {
+                Step::Need(range) => planner.provide(
+                    &archive[range.start as usize..range.end as usize]
+                ),
+                step => panic!("{:?}", step),
+            }
+        }
+
+        assert_eq!(planner.next(), Step::Need(cd.clone()));
+
+        planner.provide(&archive[cd.start as usize..][..4]);
+
+        assert_eq!(planner.next(), Step::Done(Err(ReadError::Truncated)));
+    }
+
+    #[test]
+    fn read_plan_select_failures() {
+        let archive = test_archive(TEST_ENTRIES);
+        let size = archive.len() as u64;
+
+        let eocd =
+            EndOfCentralDirectory::locate(&archive[..], &mut []).unwrap();
+        let cd = eocd.central_directory_range().unwrap();
+        let cdfh = CentralDirectoryFileHeaderIterator::new(
+            &archive[cd.start as usize..cd.end as usize],
+            1
+        )
+            .next()
+            .unwrap();
+
+        let mut slots = vec!(0..0; 0);
+        let mut planner = ReadPlanner::new(size, 22, 0, &mut slots);
+
+        assert_eq!(planner.select(&cdfh), Err(PlanError::NotSelecting));
+
+        planner.provide(&archive[size as usize - 22..]);
+        planner.provide(&archive[cd.start as usize..cd.end as usize]);
+
+        assert_eq!(planner.end_of_central_directory(), Some(eocd));
+        assert_eq!(planner.select(&cdfh), Err(PlanError::InsufficientSlots));
+    }
+}
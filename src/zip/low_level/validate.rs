@@ -0,0 +1,783 @@
+//! # Validation of the structure of a ZIP archive
+
+use core::cmp::min;
+use core::ops::Range;
+
+use api::{ReadError, Reader, Sink};
+use utils::read::read_exact;
+
+use zip::raw::{
+    CentralDirectoryFileHeaderReader,
+    DataDescriptorReader,
+    LocalFileHeaderReader,
+    Zip64DataDescriptorReader
+};
+
+use super::cdfh::read_central_directory_file_header;
+use super::dd::{read_data_descriptor, read_zip64_data_descriptor};
+use super::diagnostic::{Code, Diagnostic, Record};
+use super::eocd::{EndOfCentralDirectory, EndOfCentralDirectoryField};
+use super::lfh::read_local_file_header;
+use super::zip64::Zip64Fields;
+
+/// Walks the archive, pushing a `Diagnostic` into the `sink` for each issue
+/// found.
+///
+/// The walk covers:
+///
+/// - the End of Central Directory record, as located by
+///   `EndOfCentralDirectory::locate`, and the ZIP64 End of Central Directory
+///   record superseding it, if any,
+/// - each Central Directory File Header record it references,
+/// - each Local File Header record referenced by a Central Directory File
+///   Header record, which is compared to the latter,
+/// - each Data Descriptor record, if the general purpose bit flag announces
+///   one, which is compared to the Central Directory File Header record.
+///
+/// A record which cannot be located, or whose signature does not match, is
+/// not explored further.
+///
+/// Note: ZIP64 placeholders are resolved through the ZIP64 records and extra
+/// fields; only those which cannot be resolved are reported.
+///
+/// Returns an error only if the `reader` fails; truncated or malformed records
+/// are reported as diagnostics.
+///
+/// If the reader copies, the `scratch` buffer is split in two halves, holding
+/// respectively a Central Directory File Header record and a Local File Header
+/// record; records larger than their half are reported as truncated.
+pub fn validate<R, S>(reader: &R, scratch: &mut [u8], sink: &mut S)
+    -> Result<(), R::Error>
+    where R: Reader + ?Sized,
+          S: Sink<Diagnostic> + ?Sized
+{
+    let eocd = match EndOfCentralDirectory::locate(reader, scratch) {
+        Ok(eocd) => eocd,
+        Err(ReadError::Io(error)) => return Err(error),
+        Err(_) => {
+            sink.push(Diagnostic::new(
+                Code::MissingEndOfCentralDirectory,
+                reader.size()?,
+                Record::EndOfCentralDirectory,
+                "signature"
+            ));
+            return Ok(());
+        }
+    };
+
+    let cd = match validate_eocd(eocd, sink) {
+        Some(cd) => cd,
+        None => return Ok(()),
+    };
+
+    let total = eocd.nb_central_directory_records();
+
+    let middle = scratch.len() / 2;
+    let (cd_scratch, lfh_scratch) = scratch.split_at_mut(middle);
+
+    let mut offset = cd.start;
+    let mut count = 0;
+
+    while count < total {
+        let cdfh = read_central_directory_file_header(
+            reader,
+            offset,
+            cd.end,
+            &mut *cd_scratch
+        )?;
+
+        let cdfh = match cdfh {
+            Some(cdfh) => cdfh,
+            None => break,
+        };
+
+        if validate_cdfh(cdfh, offset, cd.start, sink) {
+            validate_entry(reader, &mut *lfh_scratch, cdfh, cd.start, sink)?;
+        }
+
+        offset = offset.wrapping_add(cdfh.raw().len() as u64);
+        count += 1;
+    }
+
+    if count != total {
+        sink.push(eocd.diagnostic(
+            Code::RecordCountMismatch,
+            EndOfCentralDirectoryField::NbCentralDirectoryRecords
+        ));
+    } else if offset != cd.end {
+        sink.push(eocd.diagnostic(
+            Code::CentralDirectorySizeMismatch,
+            EndOfCentralDirectoryField::CentralDirectorySize
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns whether the `time` is a valid MS-DOS time.
+fn is_valid_dos_time(time: u16) -> bool {
+    let seconds = (time & 0x1f) * 2;
+    let minutes = (time >> 5) & 0x3f;
+    let hours = time >> 11;
+
+    seconds < 60 && minutes < 60 && hours < 24
+}
+
+/// Returns whether the `date` is a valid MS-DOS date.
+fn is_valid_dos_date(date: u16) -> bool {
+    let day = date & 0x1f;
+    let month = (date >> 5) & 0x0f;
+
+    day >= 1 && (1..=12).contains(&month)
+}
+
+/// Validates the End of Central Directory record, and the ZIP64 End of Central
+/// Directory record if any, returning the range of the Central Directory if it
+/// can be explored.
+fn validate_eocd<S>(eocd: EndOfCentralDirectory, sink: &mut S)
+    -> Option<Range<u64>>
+    where S: Sink<Diagnostic> + ?Sized
+{
+    if eocd.disk() != 0 {
+        sink.push(eocd.diagnostic(
+            Code::MultipleDisks,
+            EndOfCentralDirectoryField::Disk
+        ));
+    }
+
+    if eocd.central_directory_disk() != 0 {
+        sink.push(eocd.diagnostic(
+            Code::MultipleDisks,
+            EndOfCentralDirectoryField::CentralDirectoryDisk
+        ));
+    }
+
+    if eocd.nb_local_central_directory_records() !=
+        eocd.nb_central_directory_records()
+    {
+        sink.push(eocd.diagnostic(
+            Code::InconsistentRecordCounts,
+            EndOfCentralDirectoryField::NbLocalCentralDirectoryRecords
+        ));
+    }
+
+    if !eocd.is_zip64() {
+        let placeholders = [
+            (eocd.nb_central_directory_records() == 0xffff,
+             EndOfCentralDirectoryField::NbCentralDirectoryRecords),
+            (eocd.central_directory_size() == 0xffffffff,
+             EndOfCentralDirectoryField::CentralDirectorySize),
+            (eocd.central_directory_offset() == 0xffffffff,
+             EndOfCentralDirectoryField::CentralDirectoryOffset),
+        ];
+
+        let mut zip64 = false;
+
+        for &(is_placeholder, field) in &placeholders {
+            if is_placeholder {
+                sink.push(eocd.diagnostic(Code::Zip64Placeholder, field));
+                zip64 = true;
+            }
+        }
+
+        if zip64 {
+            return None;
+        }
+    }
+
+    match eocd.central_directory_range() {
+        Some(cd) => {
+            if cd.end < eocd.central_directory_limit() {
+                sink.push(eocd.diagnostic(
+                    Code::CentralDirectoryGap,
+                    EndOfCentralDirectoryField::CentralDirectorySize
+                ));
+            }
+            Some(cd)
+        },
+        None => {
+            sink.push(eocd.diagnostic(
+                Code::CentralDirectoryOutOfBounds,
+                EndOfCentralDirectoryField::CentralDirectoryOffset
+            ));
+            None
+        },
+    }
+}
+
+/// Validates a Central Directory File Header record in isolation, returning
+/// whether the entry it describes can be explored.
+fn validate_cdfh<S>(
+    cdfh: CentralDirectoryFileHeaderReader,
+    offset: u64,
+    cd_start: u64,
+    sink: &mut S
+)
+    -> bool
+    where S: Sink<Diagnostic> + ?Sized
+{
+    type CdfhReader<'a> = CentralDirectoryFileHeaderReader<'a>;
+
+    let mut report = |code, position: usize, field| {
+        sink.push(Diagnostic::new(
+            code,
+            offset.wrapping_add(position as u64),
+            Record::CentralDirectoryFileHeader,
+            field
+        ));
+    };
+
+    if cdfh.signature() != CdfhReader::expected_signature() {
+        report(Code::InvalidSignature, 0, "signature");
+        return false;
+    }
+
+    let mut explorable = true;
+
+    if cdfh.version_needed_to_extract() & 0xff > 63 {
+        report(Code::UnsupportedVersion, 6, "version_needed_to_extract");
+    }
+
+    if !is_valid_dos_time(cdfh.file_last_modification_time()) {
+        report(Code::InvalidTimestamp, 12, "file_last_modification_time");
+    }
+
+    if !is_valid_dos_date(cdfh.file_last_modification_date()) {
+        report(Code::InvalidTimestamp, 14, "file_last_modification_date");
+    }
+
+    let zip64 = Zip64Fields::from_central_directory_file_header(cdfh);
+
+    if zip64.compressed_size().is_none() {
+        report(Code::Zip64Placeholder, 20, "compressed_size");
+        explorable = false;
+    }
+
+    if zip64.uncompressed_size().is_none() {
+        report(Code::Zip64Placeholder, 24, "uncompressed_size");
+    }
+
+    if cdfh.file_start_disk() != 0 {
+        report(Code::MultipleDisks, 34, "file_start_disk");
+    }
+
+    match zip64.local_file_header_relative_offset() {
+        None => {
+            report(
+                Code::Zip64Placeholder,
+                42,
+                "local_file_header_relative_offset"
+            );
+            explorable = false;
+        },
+        Some(lfh_offset) if lfh_offset >= cd_start => {
+            report(
+                Code::LocalFileHeaderOutOfBounds,
+                42,
+                "local_file_header_relative_offset"
+            );
+            explorable = false;
+        },
+        Some(_) => (),
+    }
+
+    let name_position = CdfhReader::min_size();
+    let extra_position = name_position + cdfh.file_name_size() as usize;
+    let comment_position = extra_position + cdfh.extra_field_size() as usize;
+
+    if cdfh.file_name().is_none() {
+        report(Code::TruncatedRecord, name_position, "file_name");
+    } else if cdfh.extra_field().is_none() {
+        report(Code::TruncatedRecord, extra_position, "extra_field");
+    } else if cdfh.file_comment().is_none() {
+        report(Code::TruncatedRecord, comment_position, "file_comment");
+    }
+
+    explorable
+}
+
+/// Validates the Local File Header record, and Data Descriptor record if any,
+/// of an entry against its Central Directory File Header record.
+fn validate_entry<R, S>(
+    reader: &R,
+    scratch: &mut [u8],
+    cdfh: CentralDirectoryFileHeaderReader,
+    cd_start: u64,
+    sink: &mut S
+)
+    -> Result<(), R::Error>
+    where R: Reader + ?Sized,
+          S: Sink<Diagnostic> + ?Sized
+{
+    type LfhReader<'a> = LocalFileHeaderReader<'a>;
+
+    let zip64 = Zip64Fields::from_central_directory_file_header(cdfh);
+
+    let offset = match zip64.local_file_header_relative_offset() {
+        Some(offset) => offset,
+        None => return Ok(()),
+    };
+
+    let lfh = read_local_file_header(reader, offset, cd_start, scratch)?;
+
+    let mut report = |code, position: usize, field| {
+        sink.push(Diagnostic::new(
+            code,
+            offset.wrapping_add(position as u64),
+            Record::LocalFileHeader,
+            field
+        ));
+    };
+
+    let lfh = match lfh {
+        Some(lfh) => lfh,
+        None => {
+            report(Code::TruncatedRecord, 0, "signature");
+            return Ok(());
+        }
+    };
+
+    if lfh.signature() != LfhReader::expected_signature() {
+        report(Code::InvalidSignature, 0, "signature");
+        return Ok(());
+    }
+
+    let flags = cdfh.general_purpose_bit_flag();
+    let has_data_descriptor = flags & 0x08 != 0;
+
+    let lfh_zip64 = Zip64Fields::from_local_file_header(lfh);
+
+    //  When a Data Descriptor is present, the CRC-32 and sizes of the Local
+    //  File Header may be left at 0.
+    let matches = |lfh: Option<u64>, cdfh: Option<u64>| {
+        lfh == cdfh || (has_data_descriptor && lfh == Some(0))
+    };
+
+    let crc32 = |crc32: u32| Some(u64::from(crc32));
+
+    let fields = [
+        (lfh.version_needed_to_extract() == cdfh.version_needed_to_extract(),
+         4, "version_needed_to_extract"),
+        (lfh.general_purpose_bit_flag() == flags,
+         6, "general_purpose_bit_flag"),
+        (lfh.compression_method() == cdfh.compression_method(),
+         8, "compression_method"),
+        (lfh.file_last_modification_time() ==
+            cdfh.file_last_modification_time(),
+         10, "file_last_modification_time"),
+        (lfh.file_last_modification_date() ==
+            cdfh.file_last_modification_date(),
+         12, "file_last_modification_date"),
+        (matches(crc32(lfh.crc32()), crc32(cdfh.crc32())), 14, "crc32"),
+        (matches(lfh_zip64.compressed_size(), zip64.compressed_size()),
+         18, "compressed_size"),
+        (matches(lfh_zip64.uncompressed_size(), zip64.uncompressed_size()),
+         22, "uncompressed_size"),
+    ];
+
+    for &(is_matching, position, field) in &fields {
+        if !is_matching {
+            report(Code::FieldMismatch, position, field);
+        }
+    }
+
+    let name_position = LfhReader::min_size();
+    let extra_position = name_position + lfh.file_name_size() as usize;
+
+    match lfh.file_name() {
+        None => {
+            report(Code::TruncatedRecord, name_position, "file_name");
+            return Ok(());
+        },
+        Some(name) if Some(name) != cdfh.file_name() => {
+            report(Code::FieldMismatch, name_position, "file_name");
+        },
+        Some(_) => (),
+    }
+
+    if lfh.extra_field().is_none() {
+        report(Code::TruncatedRecord, extra_position, "extra_field");
+        return Ok(());
+    }
+
+    let data_end = offset
+        .checked_add(lfh.raw().len() as u64)
+        .and_then(|start| start.checked_add(zip64.compressed_size()?));
+
+    let data_end = match data_end {
+        Some(end) if end <= cd_start => end,
+        _ => {
+            report(Code::EntryDataOutOfBounds, 18, "compressed_size");
+            return Ok(());
+        }
+    };
+
+    if has_data_descriptor {
+        let is_zip64 = zip64.has_zip64_sizes() || lfh_zip64.has_zip64_sizes();
+
+        validate_dd(reader, cdfh, is_zip64, data_end, cd_start, sink)?;
+    }
+
+    Ok(())
+}
+
+/// Validates the Data Descriptor record located at `offset`, a ZIP64 one if
+/// `is_zip64`, against the Central Directory File Header record of its entry.
+fn validate_dd<R, S>(
+    reader: &R,
+    cdfh: CentralDirectoryFileHeaderReader,
+    is_zip64: bool,
+    offset: u64,
+    cd_start: u64,
+    sink: &mut S
+)
+    -> Result<(), R::Error>
+    where R: Reader + ?Sized,
+          S: Sink<Diagnostic> + ?Sized
+{
+    let mut report = |code, position: usize, field| {
+        sink.push(Diagnostic::new(
+            code,
+            offset.wrapping_add(position as u64),
+            Record::DataDescriptor,
+            field
+        ));
+    };
+
+    let max_size = if is_zip64 {
+        Zip64DataDescriptorReader::max_size()
+    } else {
+        DataDescriptorReader::max_size()
+    };
+
+    let dd = {
+        let end = min(offset.saturating_add(max_size as u64), cd_start);
+        //  Sized for the largest record, a signed ZIP64 Data Descriptor.
+        let mut scratch = [0; 24];
+        let length = min(end.saturating_sub(offset), 24) as usize;
+        let buffer = scratch.get_mut(..length).unwrap_or(&mut []);
+        let filled = read_exact(reader, offset, buffer)?;
+        let dd = buffer.get(..filled).unwrap_or(b"");
+
+        //  The sizes of the ZIP64 Data Descriptor are 8 bytes wide.
+        if is_zip64 {
+            read_zip64_data_descriptor(dd).map(|dd| {
+                let sizes = (dd.compressed_size(), dd.uncompressed_size());
+                (dd.has_signature(), dd.crc32(), sizes, 8)
+            })
+        } else {
+            read_data_descriptor(dd).map(|dd| {
+                let sizes = (
+                    u64::from(dd.compressed_size()),
+                    u64::from(dd.uncompressed_size())
+                );
+                (dd.has_signature(), dd.crc32(), sizes, 4)
+            })
+        }
+    };
+
+    let (has_signature, crc32, (compressed_size, uncompressed_size), width) =
+        match dd {
+            Some(dd) => dd,
+            None => {
+                report(Code::TruncatedRecord, 0, "crc32");
+                return Ok(());
+            }
+        };
+
+    let zip64 = Zip64Fields::from_central_directory_file_header(cdfh);
+
+    let base = if has_signature { 4 } else { 0 };
+
+    let fields = [
+        (crc32 == cdfh.crc32(), 0, "crc32"),
+        (Some(compressed_size) == zip64.compressed_size(),
+         4, "compressed_size"),
+        (Some(uncompressed_size) == zip64.uncompressed_size(),
+         4 + width, "uncompressed_size"),
+    ];
+
+    for &(is_matching, position, field) in &fields {
+        if !is_matching {
+            report(Code::FieldMismatch, base + position, field);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use utils::test::{
+        ChunkedReader,
+        CopyingReader,
+        FailingReader,
+        TEST_ENTRIES,
+        TestError,
+        test_archive,
+        test_zip64_archive
+    };
+    use zip::low_level::{Code, Diagnostic, Record};
+    use super::validate;
+
+    fn diagnostics(archive: &[u8]) -> Vec<Diagnostic> {
+        let mut result = vec!();
+        validate(archive, &mut [], &mut |d| result.push(d)).unwrap();
+        result
+    }
+
+    #[test]
+    fn validate_nothing_on_well_formed_archive() {
+        let archive = test_archive(TEST_ENTRIES);
+
+        assert_eq!(diagnostics(&archive), vec!());
+    }
+
+    #[test]
+    fn validate_missing_eocd_on_zeroed_slice() {
+        let archive = vec![0; 64];
+
+        assert_eq!(
+            diagnostics(&archive),
+            vec!(Diagnostic::new(
+                Code::MissingEndOfCentralDirectory,
+                64,
+                Record::EndOfCentralDirectory,
+                "signature"
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_cd_out_of_bounds() {
+        let mut archive = test_archive(&[(b"a", b"a")]);
+        let eocd = archive.len() - 22;
+        archive[eocd + 16] = 0xf0;
+
+        assert_eq!(
+            diagnostics(&archive),
+            vec!(Diagnostic::new(
+                Code::CentralDirectoryOutOfBounds,
+                eocd as u64 + 16,
+                Record::EndOfCentralDirectory,
+                "central_directory_offset"
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_record_count_mismatch() {
+        let mut archive = test_archive(&[(b"a", b"a")]);
+        let eocd = archive.len() - 22;
+        archive[eocd + 8] = 2;
+        archive[eocd + 10] = 2;
+
+        assert_eq!(
+            diagnostics(&archive),
+            vec!(Diagnostic::new(
+                Code::RecordCountMismatch,
+                eocd as u64 + 10,
+                Record::EndOfCentralDirectory,
+                "nb_central_directory_records"
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_lfh_name_mismatch() {
+        let mut archive = test_archive(&[(b"a", b"a")]);
+        archive[30] = b'b';
+
+        assert_eq!(
+            diagnostics(&archive),
+            vec!(Diagnostic::new(
+                Code::FieldMismatch,
+                30,
+                Record::LocalFileHeader,
+                "file_name"
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_lfh_invalid_signature() {
+        let mut archive = test_archive(&[(b"a", b"a")]);
+        archive[0] = 0;
+
+        assert_eq!(
+            diagnostics(&archive),
+            vec!(Diagnostic::new(
+                Code::InvalidSignature,
+                0,
+                Record::LocalFileHeader,
+                "signature"
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_dd_mismatch() {
+        //  Insert a Data Descriptor, with signature, after the data.
+        let mut archive = test_archive(&[(b"a", b"a")]);
+        let dd = b"PK\x07\x08\x43\xbe\xb7\xe8\x01\x00\x00\x00\x02\x00\x00\x00";
+        for (i, b) in dd.iter().enumerate() {
+            archive.insert(32 + i, *b);
+        }
+
+        let cdfh = 32 + dd.len();
+        let eocd = archive.len() - 22;
+
+        archive[6] = 0x08;          // LFH flags
+        archive[cdfh + 8] = 0x08;   // CDFH flags
+        archive[cdfh + 42] = 0;     // LFH offset
+        archive[eocd + 16] += dd.len() as u8;
+
+        assert_eq!(
+            diagnostics(&archive),
+            vec!(Diagnostic::new(
+                Code::FieldMismatch,
+                32 + 12,
+                Record::DataDescriptor,
+                "uncompressed_size"
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_through_copying_reader() {
+        let mut archive = test_zip64_archive(&[(b"a", b"a"), (b"b", b"")]);
+        archive[30] = b'c';
+
+        let reader = CopyingReader { data: &archive };
+        let mut scratch = [0; 256];
+        let mut result = vec!();
+
+        validate(&reader, &mut scratch, &mut |d| result.push(d)).unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result, diagnostics(&archive));
+    }
+
+    #[test]
+    fn validate_through_chunked_reader() {
+        let mut archive = test_zip64_archive(&[(b"a", b"a"), (b"b", b"")]);
+        archive[30] = b'c';
+
+        for chunk in 1..16 {
+            let reader = ChunkedReader { data: &archive, chunk };
+            let mut scratch = [0; 256];
+            let mut result = vec!();
+
+            validate(&reader, &mut scratch, &mut |d| result.push(d)).unwrap();
+
+            assert_eq!(result, diagnostics(&archive));
+        }
+    }
+
+    #[test]
+    fn validate_nothing_on_well_formed_zip64_archive() {
+        let archive = test_zip64_archive(TEST_ENTRIES);
+
+        assert_eq!(diagnostics(&archive), vec!());
+    }
+
+    #[test]
+    fn validate_zip64_record_count_mismatch() {
+        let mut archive = test_zip64_archive(&[(b"a", b"a")]);
+        let zip64 = archive.len() - 22 - 20 - 56;
+        archive[zip64 + 24] = 2;
+        archive[zip64 + 32] = 2;
+
+        assert_eq!(
+            diagnostics(&archive),
+            vec!(Diagnostic::new(
+                Code::RecordCountMismatch,
+                zip64 as u64 + 32,
+                Record::Zip64EndOfCentralDirectory,
+                "nb_central_directory_records"
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_unresolved_zip64_placeholders() {
+        //  Without the ZIP64 extra field, whose ID is altered, the placeholders
+        //  of the Central Directory File Header cannot be resolved.
+        let mut archive = test_zip64_archive(&[(b"a", b"a")]);
+        let cdfh = 30 + 1 + 20 + 1;
+        archive[cdfh + 47] = 0xff;
+
+        assert_eq!(
+            diagnostics(&archive),
+            vec!(
+                Diagnostic::new(
+                    Code::Zip64Placeholder,
+                    cdfh as u64 + 20,
+                    Record::CentralDirectoryFileHeader,
+                    "compressed_size"
+                ),
+                Diagnostic::new(
+                    Code::Zip64Placeholder,
+                    cdfh as u64 + 24,
+                    Record::CentralDirectoryFileHeader,
+                    "uncompressed_size"
+                ),
+                Diagnostic::new(
+                    Code::Zip64Placeholder,
+                    cdfh as u64 + 42,
+                    Record::CentralDirectoryFileHeader,
+                    "local_file_header_relative_offset"
+                ),
+            )
+        );
+    }
+
+    #[test]
+    fn validate_unresolved_eocd_placeholders() {
+        //  Without the ZIP64 End of Central Directory Locator record, the
+        //  placeholders of the End of Central Directory cannot be resolved.
+        let mut archive = test_zip64_archive(&[(b"a", b"a")]);
+        let locator = archive.len() - 22 - 20;
+        archive[locator] = 0;
+
+        let eocd = archive.len() as u64 - 22;
+
+        assert_eq!(
+            diagnostics(&archive),
+            vec!(
+                Diagnostic::new(
+                    Code::Zip64Placeholder,
+                    eocd + 10,
+                    Record::EndOfCentralDirectory,
+                    "nb_central_directory_records"
+                ),
+                Diagnostic::new(
+                    Code::Zip64Placeholder,
+                    eocd + 12,
+                    Record::EndOfCentralDirectory,
+                    "central_directory_size"
+                ),
+                Diagnostic::new(
+                    Code::Zip64Placeholder,
+                    eocd + 16,
+                    Record::EndOfCentralDirectory,
+                    "central_directory_offset"
+                ),
+            )
+        );
+    }
+
+    #[test]
+    fn validate_io_on_failing_reader() {
+        let archive = test_zip64_archive(&[(b"a", b"a"), (b"b", b"")]);
+
+        let reader = FailingReader::new(&archive, usize::MAX);
+        assert_eq!(validate(&reader, &mut [], &mut |_| ()), Ok(()));
+
+        for limit in 0..reader.accesses.get() {
+            let reader = FailingReader::new(&archive, limit);
+
+            assert_eq!(validate(&reader, &mut [], &mut |_| ()), Err(TestError));
+        }
+    }
+}
@@ -0,0 +1,389 @@
+//! # Low-level access to the ZIP64 records of a ZIP archive
+//!
+//! The 16-bits and 32-bits fields of the records are set to their maximum
+//! value when the actual value does not fit, the actual value being stored in
+//! a ZIP64 record, or ZIP64 extra field, instead.
+
+use api::{ReadError, Reader};
+use utils::read::{read_contiguous, read_exact};
+
+use zip::raw::{
+    CentralDirectoryFileHeaderReader,
+    LocalFileHeaderReader,
+    Zip64EndOfCentralDirectoryLocatorReader,
+    Zip64EndOfCentralDirectoryReader,
+    Zip64ExtendedInformationReader
+};
+
+use super::extra::ExtraFieldIterator;
+
+/// Returns the ZIP64 End of Central Directory record, alongside its offset,
+/// referenced by the ZIP64 End of Central Directory Locator record immediately
+/// preceding the End of Central Directory record located at `eocd_offset`.
+///
+/// The record is either borrowed from the reader or copied into the `scratch`
+/// buffer, in which case its extensible data sector is only available if the
+/// buffer is large enough.
+///
+/// Returns `None` if no locator, identified by its signature, precedes the End
+/// of Central Directory record.
+///
+/// Returns `Malformed` if the ZIP64 End of Central Directory record does not
+/// lie before its locator, or its signature or size does not match, and
+/// `Truncated` if it extends beyond its locator.
+pub fn locate_zip64_end_of_central_directory<'a, R>(
+    reader: &'a R,
+    eocd_offset: u64,
+    scratch: &'a mut [u8]
+)
+    -> Result<
+        Option<(u64, Zip64EndOfCentralDirectoryReader<'a>)>,
+        ReadError<R::Error>
+    >
+    where R: Reader + ?Sized + 'a
+{
+    type LocatorReader<'a> = Zip64EndOfCentralDirectoryLocatorReader<'a>;
+    type Zip64EocdReader<'a> = Zip64EndOfCentralDirectoryReader<'a>;
+
+    let locator_offset =
+        match eocd_offset.checked_sub(LocatorReader::min_size() as u64) {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+
+    let offset = {
+        let mut buffer = [0; 20];
+
+        let filled = read_exact(reader, locator_offset, &mut buffer)
+            .map_err(ReadError::Io)?;
+
+        let locator = LocatorReader::new(buffer.get(..filled).unwrap_or(b""))
+            .filter(|l| l.signature() == LocatorReader::expected_signature());
+
+        match locator {
+            Some(locator) => locator.zip64_end_of_central_directory_offset(),
+            None => return Ok(None),
+        }
+    };
+
+    if offset >= locator_offset {
+        return Err(ReadError::Malformed);
+    }
+
+    let slice = read_contiguous(reader, offset..locator_offset, scratch)
+        .map_err(ReadError::Io)?;
+
+    let eocd = match Zip64EocdReader::new(slice) {
+        Some(eocd) => eocd,
+        None => return Err(ReadError::Truncated),
+    };
+
+    if eocd.signature() != Zip64EocdReader::expected_signature() {
+        return Err(ReadError::Malformed);
+    }
+
+    let size = eocd.record_size().saturating_add(12);
+
+    if size < Zip64EocdReader::min_size() as u64 {
+        return Err(ReadError::Malformed);
+    }
+
+    if offset.saturating_add(size) > locator_offset {
+        return Err(ReadError::Truncated);
+    }
+
+    Ok(Some((offset, eocd)))
+}
+
+/// The sizes and offset of an entry, as announced by its Central Directory
+/// File Header record, superseded by its ZIP64 Extended Information extra field
+/// for the fields set to their maximum value.
+///
+/// A field set to its maximum value, which the extra field does not provide,
+/// is `None`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Zip64Fields {
+    uncompressed_size: Option<u64>,
+    compressed_size: Option<u64>,
+    local_file_header_relative_offset: Option<u64>,
+    zip64_sizes: bool,
+}
+
+impl Zip64Fields {
+    /// Returns the fields of the Central Directory File Header record.
+    pub fn from_central_directory_file_header(
+        cdfh: CentralDirectoryFileHeaderReader
+    )
+        -> Zip64Fields
+    {
+        let extra = cdfh.extra_field()
+            .and_then(find_zip64_data)
+            .map(|data| {
+                Zip64ExtendedInformationReader::
+                    from_central_directory_file_header(data, cdfh)
+            });
+
+        Zip64Fields::resolve(
+            [
+                cdfh.uncompressed_size(),
+                cdfh.compressed_size(),
+                cdfh.local_file_header_relative_offset(),
+            ],
+            extra
+        )
+    }
+
+    /// Returns the sizes of the Local File Header record; the offset is not
+    /// part of the record, and is always `None`.
+    pub fn from_local_file_header(lfh: LocalFileHeaderReader) -> Zip64Fields {
+        let extra = lfh.extra_field()
+            .and_then(find_zip64_data)
+            .map(|data| {
+                Zip64ExtendedInformationReader::
+                    from_local_file_header(data, lfh)
+            });
+
+        let fields = Zip64Fields::resolve(
+            [lfh.uncompressed_size(), lfh.compressed_size(), 0xffffffff],
+            extra
+        );
+
+        Zip64Fields {
+            local_file_header_relative_offset: None,
+            zip64_sizes: fields.zip64_sizes || extra.is_some(),
+            ..fields
+        }
+    }
+
+    /// Returns the uncompressed size of the file, or `None` if unresolved.
+    pub fn uncompressed_size(&self) -> Option<u64> { self.uncompressed_size }
+
+    /// Returns the compressed size of the file, or `None` if unresolved.
+    pub fn compressed_size(&self) -> Option<u64> { self.compressed_size }
+
+    /// Returns the relative offset of the Local File Header record, or `None`
+    /// if unresolved.
+    pub fn local_file_header_relative_offset(&self) -> Option<u64> {
+        self.local_file_header_relative_offset
+    }
+
+    /// Returns whether the sizes are stored in the ZIP64 Extended Information
+    /// extra field, in which case the Data Descriptor record of the entry, if
+    /// any, is expected to be a ZIP64 Data Descriptor record.
+    ///
+    /// This is the case if either size of the record is set to its maximum
+    /// value, or if a Local File Header record carries the extra field.
+    pub fn has_zip64_sizes(&self) -> bool { self.zip64_sizes }
+
+    /// Supersedes the `fields` set to their maximum value by the `extra`.
+    fn resolve(
+        fields: [u32; 3],
+        extra: Option<Zip64ExtendedInformationReader>
+    )
+        -> Zip64Fields
+    {
+        let resolve = |field: u32, zip64: Option<u64>| {
+            if field == 0xffffffff { zip64 } else { Some(u64::from(field)) }
+        };
+
+        let [uncompressed_size, compressed_size, offset] = fields;
+
+        Zip64Fields {
+            uncompressed_size: resolve(
+                uncompressed_size,
+                extra.and_then(|e| e.uncompressed_size())
+            ),
+            compressed_size: resolve(
+                compressed_size,
+                extra.and_then(|e| e.compressed_size())
+            ),
+            local_file_header_relative_offset: resolve(
+                offset,
+                extra.and_then(|e| e.local_file_header_relative_offset())
+            ),
+            zip64_sizes: uncompressed_size == 0xffffffff ||
+                         compressed_size == 0xffffffff,
+        }
+    }
+}
+
+/// Returns the data of the ZIP64 Extended Information block of the
+/// `extra_field`, if any.
+fn find_zip64_data(extra_field: &[u8]) -> Option<&[u8]> {
+    let id = Zip64ExtendedInformationReader::id();
+
+    ExtraFieldIterator::find_id(extra_field, id).and_then(|extra| extra.data())
+}
+
+#[cfg(test)]
+mod tests {
+    use zip::raw::{
+        CentralDirectoryFileHeaderReader,
+        CentralDirectoryFileHeaderWriter,
+        EndOfCentralDirectoryWriter,
+        LocalFileHeaderReader,
+        LocalFileHeaderWriter,
+        Zip64EndOfCentralDirectoryLocatorWriter,
+        Zip64EndOfCentralDirectoryWriter,
+        Zip64ExtendedInformationWriter
+    };
+    use api::ReadError;
+    use super::{Zip64Fields, locate_zip64_end_of_central_directory};
+
+    fn zip64_tail(offset: u64) -> Vec<u8> {
+        let mut v = vec!(0; 8);
+        let mut record = [0; 56];
+
+        Zip64EndOfCentralDirectoryWriter::new()
+            .with_central_directory_offset(0x1_0000_0000)
+            .write(&mut record)
+            .unwrap();
+        v.extend_from_slice(&record);
+
+        let mut locator = [0; 20];
+        Zip64EndOfCentralDirectoryLocatorWriter::new()
+            .with_zip64_end_of_central_directory_offset(offset)
+            .write(&mut locator)
+            .unwrap();
+        v.extend_from_slice(&locator);
+
+        let mut eocd = [0; 22];
+        EndOfCentralDirectoryWriter::new().write(&mut eocd).unwrap();
+        v.extend_from_slice(&eocd);
+
+        v
+    }
+
+    #[test]
+    fn locate_zip64_eocd_success() {
+        let v = zip64_tail(8);
+
+        let (offset, eocd64) =
+            locate_zip64_end_of_central_directory(&v[..], 84, &mut [])
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(offset, 8);
+        assert_eq!(eocd64.central_directory_offset(), 0x1_0000_0000);
+    }
+
+    #[test]
+    fn locate_zip64_eocd_none_on_invalid_locator() {
+        let mut v = zip64_tail(8);
+
+        assert_eq!(
+            locate_zip64_end_of_central_directory(&v[..], 83, &mut []),
+            Ok(None)
+        );
+        assert_eq!(
+            locate_zip64_end_of_central_directory(&v[..], 12, &mut []),
+            Ok(None)
+        );
+
+        v[64] = 0;
+
+        assert_eq!(
+            locate_zip64_end_of_central_directory(&v[..], 84, &mut []),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn locate_zip64_eocd_malformed_on_invalid_offset() {
+        for &offset in &[0, 64, 0xffff_ffff_ffff] {
+            let v = zip64_tail(offset);
+
+            assert_eq!(
+                locate_zip64_end_of_central_directory(&v[..], 84, &mut []),
+                Err(ReadError::Malformed)
+            );
+        }
+    }
+
+    #[test]
+    fn locate_zip64_eocd_truncated_on_short_record() {
+        for &offset in &[9, 12] {
+            let v = zip64_tail(offset);
+
+            assert_eq!(
+                locate_zip64_end_of_central_directory(&v[..], 84, &mut []),
+                Err(ReadError::Truncated)
+            );
+        }
+    }
+
+    #[test]
+    fn fields_from_cdfh_without_extra() {
+        let mut v = vec!(0; 64);
+        CentralDirectoryFileHeaderWriter::new()
+            .with_compressed_size(3)
+            .with_uncompressed_size(0xffffffff)
+            .with_local_file_header_relative_offset(7)
+            .write(&mut v)
+            .unwrap();
+
+        let cdfh = CentralDirectoryFileHeaderReader::new(&v).unwrap();
+        let fields = Zip64Fields::from_central_directory_file_header(cdfh);
+
+        assert_eq!(fields.uncompressed_size(), None);
+        assert_eq!(fields.compressed_size(), Some(3));
+        assert_eq!(fields.local_file_header_relative_offset(), Some(7));
+        assert!(fields.has_zip64_sizes());
+    }
+
+    #[test]
+    fn fields_from_cdfh_with_extra() {
+        let mut extra = [0; 20];
+        Zip64ExtendedInformationWriter::new()
+            .with_compressed_size(0x1_0000_0003)
+            .with_local_file_header_relative_offset(0x2_0000_0007)
+            .write(&mut extra)
+            .unwrap();
+
+        let mut v = vec!(0; 128);
+        CentralDirectoryFileHeaderWriter::new()
+            .with_compressed_size(0xffffffff)
+            .with_uncompressed_size(5)
+            .with_local_file_header_relative_offset(0xffffffff)
+            .with_extra_field(&extra)
+            .write(&mut v)
+            .unwrap();
+
+        let cdfh = CentralDirectoryFileHeaderReader::new(&v).unwrap();
+        let fields = Zip64Fields::from_central_directory_file_header(cdfh);
+
+        assert_eq!(fields.uncompressed_size(), Some(5));
+        assert_eq!(fields.compressed_size(), Some(0x1_0000_0003));
+        assert_eq!(
+            fields.local_file_header_relative_offset(),
+            Some(0x2_0000_0007)
+        );
+        assert!(fields.has_zip64_sizes());
+    }
+
+    #[test]
+    fn fields_from_lfh_with_extra() {
+        let mut extra = [0; 20];
+        Zip64ExtendedInformationWriter::new()
+            .with_uncompressed_size(5)
+            .with_compressed_size(3)
+            .write(&mut extra)
+            .unwrap();
+
+        let mut v = vec!(0; 64);
+        LocalFileHeaderWriter::new()
+            .with_compressed_size(0xffffffff)
+            .with_uncompressed_size(0xffffffff)
+            .with_extra_field(&extra)
+            .write(&mut v)
+            .unwrap();
+
+        let lfh = LocalFileHeaderReader::new(&v).unwrap();
+        let fields = Zip64Fields::from_local_file_header(lfh);
+
+        assert_eq!(fields.uncompressed_size(), Some(5));
+        assert_eq!(fields.compressed_size(), Some(3));
+        assert_eq!(fields.local_file_header_relative_offset(), None);
+        assert!(fields.has_zip64_sizes());
+    }
+}
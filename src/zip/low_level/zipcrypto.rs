@@ -0,0 +1,153 @@
+//! # Traditional PKWARE encryption, also known as ZipCrypto
+//!
+//! The encryption is a stream cipher, whose three 32 bits keys are initialized
+//! from the password, then updated with each plain text byte. The encrypted
+//! data of an entry starts with a 12 bytes encryption header, whose last byte
+//! allows checking the password.
+//!
+//! This encryption is weak, and only supported to read legacy archives.
+
+use checksum::update_crc32_register;
+
+use zip::raw::CentralDirectoryFileHeaderReader;
+
+/// The size of the encryption header, preceding the encrypted data.
+pub const ENCRYPTION_HEADER_SIZE: usize = 12;
+
+/// The key schedule of the traditional PKWARE encryption.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ZipCrypto {
+    keys: [u32; 3],
+}
+
+impl ZipCrypto {
+    /// Returns an instance whose keys are initialized from the `password`.
+    pub fn new(password: &[u8]) -> ZipCrypto {
+        let mut result =
+            ZipCrypto { keys: [0x12345678, 0x23456789, 0x34567890] };
+
+        for &byte in password {
+            result.update_keys(byte);
+        }
+
+        result
+    }
+
+    /// Decrypts the 12 bytes encryption `header`, in place, and returns
+    /// whether its last byte matches the `check` byte.
+    ///
+    /// A mismatch indicates an invalid password; a match does not guarantee
+    /// a valid password, as 1 in 256 invalid passwords match.
+    pub fn decrypt_header(
+        &mut self,
+        header: &mut [u8; ENCRYPTION_HEADER_SIZE],
+        check: u8
+    )
+        -> bool
+    {
+        self.decrypt(header);
+
+        header[ENCRYPTION_HEADER_SIZE - 1] == check
+    }
+
+    /// Decrypts the `data`, in place.
+    pub fn decrypt(&mut self, data: &mut [u8]) {
+        for byte in data {
+            *byte ^= self.stream_byte();
+            self.update_keys(*byte);
+        }
+    }
+
+    /// Encrypts the `data`, in place.
+    pub fn encrypt(&mut self, data: &mut [u8]) {
+        for byte in data {
+            let plain = *byte;
+            *byte ^= self.stream_byte();
+            self.update_keys(plain);
+        }
+    }
+
+    fn update_keys(&mut self, byte: u8) {
+        let [k0, k1, k2] = self.keys;
+
+        let k0 = update_crc32_register(k0, byte);
+        let k1 = k1
+            .wrapping_add(k0 & 0xff)
+            .wrapping_mul(134775813)
+            .wrapping_add(1);
+        let k2 = update_crc32_register(k2, (k1 >> 24) as u8);
+
+        self.keys = [k0, k1, k2];
+    }
+
+    fn stream_byte(&self) -> u8 {
+        let temp = (self.keys[2] | 2) as u16;
+
+        (temp.wrapping_mul(temp ^ 1) >> 8) as u8
+    }
+}
+
+/// Returns the password check byte of the entry.
+///
+/// The check byte is the high byte of the CRC-32 or, when the CRC-32 is
+/// deferred to a Data Descriptor record (bit 3), the high byte of the last
+/// modification time.
+pub fn check_byte(cdfh: CentralDirectoryFileHeaderReader) -> u8 {
+    if cdfh.general_purpose_bit_flag() & 0x08 != 0 {
+        (cdfh.file_last_modification_time() >> 8) as u8
+    } else {
+        (cdfh.crc32() >> 24) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ENCRYPTION_HEADER_SIZE, ZipCrypto};
+
+    //  Produced by `zip -0 -X -P secret`, on "Hello, World!", with a check
+    //  byte of 0 since the last modification time is 0.
+    const ENCRYPTED: [u8; 25] = [
+        0xcd, 0x86, 0x32, 0x3a, 0xcb, 0xb5, 0x66, 0x5e, 0xe5, 0xf6, 0x81, 0xd1,
+        0x37, 0x7f, 0xcd, 0x06, 0x58, 0xa6, 0x04, 0x57, 0x79, 0x62, 0x09, 0xbc,
+        0x6a,
+    ];
+
+    #[test]
+    fn decrypt_success() {
+        let mut header = [0; ENCRYPTION_HEADER_SIZE];
+        header.copy_from_slice(&ENCRYPTED[..ENCRYPTION_HEADER_SIZE]);
+        let mut data = ENCRYPTED[ENCRYPTION_HEADER_SIZE..].to_vec();
+
+        let mut cipher = ZipCrypto::new(b"secret");
+
+        assert!(cipher.decrypt_header(&mut header, 0));
+
+        //  Decrypting chunk by chunk is equivalent to decrypting at once.
+        for chunk in data.chunks_mut(5) {
+            cipher.decrypt(chunk);
+        }
+
+        assert_eq!(&data[..], b"Hello, World!");
+    }
+
+    #[test]
+    fn decrypt_header_failure_on_invalid_password() {
+        let mut header = [0; ENCRYPTION_HEADER_SIZE];
+        header.copy_from_slice(&ENCRYPTED[..ENCRYPTION_HEADER_SIZE]);
+
+        assert!(!ZipCrypto::new(b"Secret").decrypt_header(&mut header, 0));
+    }
+
+    #[test]
+    fn encrypt_round_trip() {
+        let mut data = *b"Hello, World!";
+
+        ZipCrypto::new(b"password").encrypt(&mut data);
+
+        assert_ne!(&data, b"Hello, World!");
+
+        ZipCrypto::new(b"password").decrypt(&mut data);
+
+        assert_eq!(&data, b"Hello, World!");
+    }
+}
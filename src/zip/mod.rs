@@ -11,7 +11,7 @@
 //! - may contain a non-file start (self-extracting archives, or steganography)
 //! - may contain up to 65,535 bytes of data after the central directory
 //! - has a central directory, describing the current set of files in the
-//! archive, located toward the end of the file
+//!   archive, located toward the end of the file
 //!
 //!
 //! ## Structural records
@@ -162,3 +162,11 @@
 
 pub mod low_level;
 pub mod raw;
+
+mod archive;
+mod entry;
+mod writer;
+
+pub use self::archive::ZipArchive;
+pub use self::entry::{ZipEntry, ZipText, ZipTextChars};
+pub use self::writer::{EntryOptions, ZipWriteError, ZipWriter};
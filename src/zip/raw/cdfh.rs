@@ -1,6 +1,8 @@
 //! # Central Directory File Header
 
-use utils::{Slice, LeFieldReader};
+use utils::{Slice, LeFieldReader, LeFieldWriter};
+
+use super::write::{WriteError, check_buffer, field_size};
 
 /// A Central Directory File Header
 ///
@@ -58,7 +60,7 @@ impl<'a> CentralDirectoryFileHeaderReader<'a> {
             };
 
             Some(CentralDirectoryFileHeaderReader {
-                data: Slice::new(slice).take(reader.len())
+                data: Slice::new(slice).take(reader.expected_size())
             })
         } else {
             None
@@ -205,8 +207,9 @@ impl<'a> CentralDirectoryFileHeaderReader<'a> {
         self.extra_field_size() as usize
     }
 
-    /// Returns the expected size of the record.
-    fn len(&self) -> usize {
+    /// Returns the expected size of the record, as announced by its fixed part,
+    /// which the underlying slice may fall short of.
+    pub fn expected_size(&self) -> usize {
         self.file_comment_position() + self.file_comment_size() as usize
     }
 }
@@ -217,13 +220,189 @@ impl<'a> LeFieldReader<'a> for CentralDirectoryFileHeaderReader<'a> {
     fn get_slice(&self) -> Slice<'a> { self.data }
 }
 
+/// A Central Directory File Header writer
+///
+/// The fields are set by the `with_*` methods, then serialized by `write`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CentralDirectoryFileHeaderWriter<'a> {
+    version_made_by: u16,
+    version_needed_to_extract: u16,
+    general_purpose_bit_flag: u16,
+    compression_method: u16,
+    file_last_modification_time: u16,
+    file_last_modification_date: u16,
+    crc32: u32,
+    compressed_size: u32,
+    uncompressed_size: u32,
+    file_start_disk: u16,
+    internal_file_attributes: u16,
+    external_file_attributes: u32,
+    local_file_header_relative_offset: u32,
+    file_name: &'a [u8],
+    extra_field: &'a [u8],
+    file_comment: &'a [u8],
+}
+
+impl<'a> CentralDirectoryFileHeaderWriter<'a> {
+    /// Returns a new instance, with all fields zeroed or empty.
+    pub fn new() -> CentralDirectoryFileHeaderWriter<'a> {
+        CentralDirectoryFileHeaderWriter::default()
+    }
+
+    /// Sets the version of the software that created the record, and its OS.
+    pub fn with_version_made_by(self, version: u16) -> Self {
+        CentralDirectoryFileHeaderWriter { version_made_by: version, ..self }
+    }
+
+    /// Sets the minimum ZIP version needed to extract.
+    pub fn with_version_needed_to_extract(self, version: u16) -> Self {
+        CentralDirectoryFileHeaderWriter {
+            version_needed_to_extract: version,
+            ..self
+        }
+    }
+
+    /// Sets the general purpose bit flags.
+    pub fn with_general_purpose_bit_flag(self, flag: u16) -> Self {
+        CentralDirectoryFileHeaderWriter {
+            general_purpose_bit_flag: flag,
+            ..self
+        }
+    }
+
+    /// Sets the compression method.
+    pub fn with_compression_method(self, method: u16) -> Self {
+        CentralDirectoryFileHeaderWriter { compression_method: method, ..self }
+    }
+
+    /// Sets the last modification time of the file, MS-DOS format.
+    pub fn with_file_last_modification_time(self, time: u16) -> Self {
+        CentralDirectoryFileHeaderWriter {
+            file_last_modification_time: time,
+            ..self
+        }
+    }
+
+    /// Sets the last modification date of the file, MS-DOS format.
+    pub fn with_file_last_modification_date(self, date: u16) -> Self {
+        CentralDirectoryFileHeaderWriter {
+            file_last_modification_date: date,
+            ..self
+        }
+    }
+
+    /// Sets the CRC-32 of the file.
+    pub fn with_crc32(self, crc32: u32) -> Self {
+        CentralDirectoryFileHeaderWriter { crc32, ..self }
+    }
+
+    /// Sets the compressed size of the file.
+    pub fn with_compressed_size(self, size: u32) -> Self {
+        CentralDirectoryFileHeaderWriter { compressed_size: size, ..self }
+    }
+
+    /// Sets the uncompressed size of the file.
+    pub fn with_uncompressed_size(self, size: u32) -> Self {
+        CentralDirectoryFileHeaderWriter { uncompressed_size: size, ..self }
+    }
+
+    /// Sets the number of the disk in which the file starts.
+    pub fn with_file_start_disk(self, disk: u16) -> Self {
+        CentralDirectoryFileHeaderWriter { file_start_disk: disk, ..self }
+    }
+
+    /// Sets the internal file attributes.
+    pub fn with_internal_file_attributes(self, attributes: u16) -> Self {
+        CentralDirectoryFileHeaderWriter {
+            internal_file_attributes: attributes,
+            ..self
+        }
+    }
+
+    /// Sets the external file attributes.
+    pub fn with_external_file_attributes(self, attributes: u32) -> Self {
+        CentralDirectoryFileHeaderWriter {
+            external_file_attributes: attributes,
+            ..self
+        }
+    }
+
+    /// Sets the relative offset of the Local File Header record.
+    pub fn with_local_file_header_relative_offset(self, offset: u32) -> Self {
+        CentralDirectoryFileHeaderWriter {
+            local_file_header_relative_offset: offset,
+            ..self
+        }
+    }
+
+    /// Sets the file name, of at most 65535 bytes.
+    pub fn with_file_name(self, name: &'a [u8]) -> Self {
+        CentralDirectoryFileHeaderWriter { file_name: name, ..self }
+    }
+
+    /// Sets the extra field, of at most 65535 bytes.
+    pub fn with_extra_field(self, extra: &'a [u8]) -> Self {
+        CentralDirectoryFileHeaderWriter { extra_field: extra, ..self }
+    }
+
+    /// Sets the file comment, of at most 65535 bytes.
+    pub fn with_file_comment(self, comment: &'a [u8]) -> Self {
+        CentralDirectoryFileHeaderWriter { file_comment: comment, ..self }
+    }
+
+    /// Returns the size of the record.
+    pub fn size(&self) -> usize {
+        CentralDirectoryFileHeaderReader::min_size()
+            .saturating_add(self.file_name.len())
+            .saturating_add(self.extra_field.len())
+            .saturating_add(self.file_comment.len())
+    }
+
+    /// Writes the record at the start of the `buffer`, returning the number of
+    /// bytes written.
+    pub fn write(&self, buffer: &mut [u8]) -> Result<usize, WriteError> {
+        let file_name_size = field_size(self.file_name)?;
+        let extra_field_size = field_size(self.extra_field)?;
+        let file_comment_size = field_size(self.file_comment)?;
+
+        check_buffer(buffer, self.size())?;
+
+        let mut writer = LeFieldWriter::new(buffer);
+
+        writer.write_u32(CentralDirectoryFileHeaderReader::expected_signature());
+        writer.write_u16(self.version_made_by);
+        writer.write_u16(self.version_needed_to_extract);
+        writer.write_u16(self.general_purpose_bit_flag);
+        writer.write_u16(self.compression_method);
+        writer.write_u16(self.file_last_modification_time);
+        writer.write_u16(self.file_last_modification_date);
+        writer.write_u32(self.crc32);
+        writer.write_u32(self.compressed_size);
+        writer.write_u32(self.uncompressed_size);
+        writer.write_u16(file_name_size);
+        writer.write_u16(extra_field_size);
+        writer.write_u16(file_comment_size);
+        writer.write_u16(self.file_start_disk);
+        writer.write_u16(self.internal_file_attributes);
+        writer.write_u32(self.external_file_attributes);
+        writer.write_u32(self.local_file_header_relative_offset);
+        writer.write(self.file_name);
+        writer.write(self.extra_field);
+        writer.write(self.file_comment);
+
+        Ok(writer.position())
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::identity_op)]
 mod tests {
-    use std;
-
     use utils::test::{test_all_u16_at, test_some_u32_at};
 
+    use zip::raw::WriteError;
+
     type Reader<'a> = super::CentralDirectoryFileHeaderReader<'a>;
+    type Writer<'a> = super::CentralDirectoryFileHeaderWriter<'a>;
 
     #[test]
     fn reader_new_failure_on_short_slice() {
@@ -269,6 +448,17 @@ mod tests {
         assert!(Reader::new(&v).is_some());
     }
 
+    #[test]
+    fn reader_expected_size_beyond_truncated_slice() {
+        let mut v = vec!(0; Reader::min_size());
+        v[28] = 0x01;   // 1 byte file name
+        v[30] = 0x02;   // 2 bytes extra field
+        v[32] = 0x03;   // 3 bytes file comment
+        let cdfh = Reader::new(&v).unwrap();
+        assert_eq!(cdfh.expected_size(), Reader::min_size() + 6);
+        assert_eq!(cdfh.raw().len(), Reader::min_size());
+    }
+
     #[test]
     fn reader_signature_success_with_expected_signature() {
         let mut v = vec!(0; Reader::min_size());
@@ -521,7 +711,7 @@ mod tests {
     #[test]
     fn reader_file_name_success_with_max_length() {
         let mut v = vec!(0; Reader::min_size());
-        v.resize(Reader::min_size() + std::u16::MAX as usize, 1);
+        v.resize(Reader::min_size() + u16::MAX as usize, 1);
 
         v[29] = 0xff;
         v[28] = 0xff;
@@ -534,7 +724,7 @@ mod tests {
     #[test]
     fn reader_extra_field_success_with_max_length() {
         let mut v = vec!(0; Reader::min_size());
-        v.resize(Reader::min_size() + std::u16::MAX as usize, 1);
+        v.resize(Reader::min_size() + u16::MAX as usize, 1);
 
         v[31] = 0xff;
         v[30] = 0xff;
@@ -547,7 +737,7 @@ mod tests {
     #[test]
     fn reader_file_comment_success_with_max_length() {
         let mut v = vec!(0; Reader::min_size());
-        v.resize(Reader::min_size() + std::u16::MAX as usize, 1);
+        v.resize(Reader::min_size() + u16::MAX as usize, 1);
 
         v[33] = 0xff;
         v[32] = 0xff;
@@ -560,9 +750,9 @@ mod tests {
     #[test]
     fn reader_file_name_failure_on_too_short_buffer() {
         let mut v = vec!(0; Reader::min_size());
-        v.resize(Reader::min_size() + std::u16::MAX as usize - 1, 1);
+        v.resize(Reader::min_size() + u16::MAX as usize - 1, 1);
 
-        for length in 1..(std::u16::MAX as usize + 1) {
+        for length in 1..(u16::MAX as usize + 1) {
             v[29] = (length >> 8) as u8;
             v[28] = (length >> 0) as u8;
 
@@ -577,9 +767,9 @@ mod tests {
     #[test]
     fn reader_extra_field_failure_on_too_short_buffer() {
         let mut v = vec!(0; Reader::min_size());
-        v.resize(Reader::min_size() + std::u16::MAX as usize - 1, 1);
+        v.resize(Reader::min_size() + u16::MAX as usize - 1, 1);
 
-        for length in 1..(std::u16::MAX as usize + 1) {
+        for length in 1..(u16::MAX as usize + 1) {
             v[31] = (length >> 8) as u8;
             v[30] = (length >> 0) as u8;
 
@@ -594,9 +784,9 @@ mod tests {
     #[test]
     fn reader_file_comment_failure_on_too_short_buffer() {
         let mut v = vec!(0; Reader::min_size());
-        v.resize(Reader::min_size() + std::u16::MAX as usize - 1, 1);
+        v.resize(Reader::min_size() + u16::MAX as usize - 1, 1);
 
-        for length in 1..(std::u16::MAX as usize + 1) {
+        for length in 1..(u16::MAX as usize + 1) {
             v[33] = (length >> 8) as u8;
             v[32] = (length >> 0) as u8;
 
@@ -629,4 +819,70 @@ mod tests {
         assert_eq!(cdfh.extra_field(), Some(hello_extra));
         assert_eq!(cdfh.file_comment(), Some(hello_comment));
     }
+
+    #[test]
+    fn writer_round_trip() {
+        let writer = Writer::new()
+            .with_version_made_by(0x031e)
+            .with_version_needed_to_extract(20)
+            .with_general_purpose_bit_flag(0x0808)
+            .with_compression_method(8)
+            .with_file_last_modification_time(0x6000)
+            .with_file_last_modification_date(0x5021)
+            .with_crc32(0xcbf43926)
+            .with_compressed_size(7)
+            .with_uncompressed_size(9)
+            .with_file_start_disk(1)
+            .with_internal_file_attributes(2)
+            .with_external_file_attributes(0x81a40000)
+            .with_local_file_header_relative_offset(0x12345678)
+            .with_file_name(b"a.txt")
+            .with_extra_field(b"\x01\x02\x03\x04")
+            .with_file_comment(b"Hi");
+
+        let mut v = vec!(0xff; 64);
+        assert_eq!(writer.write(&mut v), Ok(57));
+        assert_eq!(writer.size(), 57);
+
+        let cdfh = Reader::new(&v).unwrap();
+
+        assert_eq!(cdfh.raw().len(), 57);
+        assert_eq!(cdfh.signature(), Reader::expected_signature());
+        assert_eq!(cdfh.version_made_by(), 0x031e);
+        assert_eq!(cdfh.version_needed_to_extract(), 20);
+        assert_eq!(cdfh.general_purpose_bit_flag(), 0x0808);
+        assert_eq!(cdfh.compression_method(), 8);
+        assert_eq!(cdfh.file_last_modification_time(), 0x6000);
+        assert_eq!(cdfh.file_last_modification_date(), 0x5021);
+        assert_eq!(cdfh.crc32(), 0xcbf43926);
+        assert_eq!(cdfh.compressed_size(), 7);
+        assert_eq!(cdfh.uncompressed_size(), 9);
+        assert_eq!(cdfh.file_start_disk(), 1);
+        assert_eq!(cdfh.interal_file_attributes(), 2);
+        assert_eq!(cdfh.external_file_attributes(), 0x81a40000);
+        assert_eq!(cdfh.local_file_header_relative_offset(), 0x12345678);
+        assert_eq!(cdfh.file_name(), Some(&b"a.txt"[..]));
+        assert_eq!(cdfh.extra_field(), Some(&b"\x01\x02\x03\x04"[..]));
+        assert_eq!(cdfh.file_comment(), Some(&b"Hi"[..]));
+    }
+
+    #[test]
+    fn writer_failure_on_short_buffer() {
+        let writer = Writer::new().with_file_comment(b"Hi");
+        let mut v = vec!(0; 47);
+
+        assert_eq!(
+            writer.write(&mut v),
+            Err(WriteError::BufferTooSmall { required: 48 })
+        );
+    }
+
+    #[test]
+    fn writer_failure_on_too_large_file_comment() {
+        let comment = vec!(b'a'; 65536);
+        let writer = Writer::new().with_file_comment(&comment);
+        let mut v = vec!(0; 65536 + 46);
+
+        assert_eq!(writer.write(&mut v), Err(WriteError::FieldTooLarge));
+    }
 }
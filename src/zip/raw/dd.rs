@@ -1,8 +1,10 @@
 //! ## Data Descriptor
 
-use std::ops::Range;
+use core::ops::Range;
 
-use utils::{DEADBEEF, Slice, read_u32_le};
+use utils::{DEADBEEF, LeFieldWriter, Slice, read_u32_le};
+
+use super::write::{WriteError, check_buffer};
 
 /// A Local File Header
 ///
@@ -59,17 +61,17 @@ impl<'a> DataDescriptorReader<'a> {
     ///
     /// (see CentralDirectoryFileHeaderReader for more ample information)
     pub fn crc32(&self) -> u32 {
-        self.read_u32(if self.has_signature() { (4..8) } else { (0..4) })
+        self.read_u32(if self.has_signature() { 4..8 } else { 0..4 })
     }
 
     /// Returns the compressed size of the file.
     pub fn compressed_size(&self) -> u32 {
-        self.read_u32(if self.has_signature() { (8..12) } else { (4..8) })
+        self.read_u32(if self.has_signature() { 8..12 } else { 4..8 })
     }
 
     /// Returns the uncompressed size of the file.
     pub fn uncompressed_size(&self) -> u32 {
-        self.read_u32(if self.has_signature() { (12..16) } else { (8..12) })
+        self.read_u32(if self.has_signature() { 12..16 } else { 8..12 })
     }
 
     /// Interprets the 4 bytes as u32 (little-endian).
@@ -84,11 +86,91 @@ impl<'a> DataDescriptorReader<'a> {
     }
 }
 
+/// A Data Descriptor writer
+///
+/// The fields are set by the `with_*` methods, then serialized by `write`.
+///
+/// The optional signature is written by default, as recommended by the
+/// specification.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DataDescriptorWriter {
+    signature: bool,
+    crc32: u32,
+    compressed_size: u32,
+    uncompressed_size: u32,
+}
+
+impl DataDescriptorWriter {
+    /// Returns a new instance, with a signature and all fields zeroed.
+    pub fn new() -> DataDescriptorWriter {
+        DataDescriptorWriter {
+            signature: true,
+            crc32: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+        }
+    }
+
+    /// Sets whether the optional signature is written.
+    pub fn with_signature(self, signature: bool) -> Self {
+        DataDescriptorWriter { signature, ..self }
+    }
+
+    /// Sets the CRC-32 of the file.
+    pub fn with_crc32(self, crc32: u32) -> Self {
+        DataDescriptorWriter { crc32, ..self }
+    }
+
+    /// Sets the compressed size of the file.
+    pub fn with_compressed_size(self, size: u32) -> Self {
+        DataDescriptorWriter { compressed_size: size, ..self }
+    }
+
+    /// Sets the uncompressed size of the file.
+    pub fn with_uncompressed_size(self, size: u32) -> Self {
+        DataDescriptorWriter { uncompressed_size: size, ..self }
+    }
+
+    /// Returns the size of the record.
+    pub fn size(&self) -> usize {
+        if self.signature {
+            DataDescriptorReader::max_size()
+        } else {
+            DataDescriptorReader::min_size()
+        }
+    }
+
+    /// Writes the record at the start of the `buffer`, returning the number of
+    /// bytes written.
+    pub fn write(&self, buffer: &mut [u8]) -> Result<usize, WriteError> {
+        check_buffer(buffer, self.size())?;
+
+        let mut writer = LeFieldWriter::new(buffer);
+
+        if self.signature {
+            writer.write_u32(DataDescriptorReader::expected_signature());
+        }
+
+        writer.write_u32(self.crc32);
+        writer.write_u32(self.compressed_size);
+        writer.write_u32(self.uncompressed_size);
+
+        Ok(writer.position())
+    }
+}
+
+impl Default for DataDescriptorWriter {
+    fn default() -> DataDescriptorWriter { DataDescriptorWriter::new() }
+}
+
 #[cfg(test)]
 mod tests {
     use utils::test::{test_some_u32_at};
 
+    use zip::raw::WriteError;
+
     type Reader<'a> = super::DataDescriptorReader<'a>;
+    type Writer = super::DataDescriptorWriter;
 
     #[test]
     fn reader_new_failure_on_inexact_slice() {
@@ -104,14 +186,14 @@ mod tests {
     fn reader_new_success_on_12_bytes() {
         let v = vec!(0; Reader::min_size());
         assert!(Reader::new(&v).is_some());
-        assert_eq!(Reader::new(&v).unwrap().has_signature(), false);
+        assert!(!Reader::new(&v).unwrap().has_signature());
     }
 
     #[test]
     fn reader_new_success_on_16_bytes() {
         let v = vec!(0; Reader::max_size());
         assert!(Reader::new(&v).is_some());
-        assert_eq!(Reader::new(&v).unwrap().has_signature(), true);
+        assert!(Reader::new(&v).unwrap().has_signature());
     }
 
     #[test]
@@ -122,7 +204,7 @@ mod tests {
         v[1] = 0x4b;
         v[0] = 0x50;
         let dd = Reader::new(&v).unwrap();
-        assert_eq!(dd.has_signature(), false);
+        assert!(!dd.has_signature());
         assert_eq!(dd.signature(), None);
     }
 
@@ -130,7 +212,7 @@ mod tests {
     fn reader_signature_failure_on_12_bytes_with_unexpected_signature() {
         let v = vec!(0; Reader::min_size());
         let dd = Reader::new(&v).unwrap();
-        assert_eq!(dd.has_signature(), false);
+        assert!(!dd.has_signature());
         assert_eq!(dd.signature(), None);
     }
 
@@ -205,4 +287,51 @@ mod tests {
             assert_eq!(dd.uncompressed_size(), version);
         });
     }
+
+    #[test]
+    fn writer_round_trip_with_signature() {
+        let writer = Writer::new()
+            .with_crc32(0xcbf43926)
+            .with_compressed_size(7)
+            .with_uncompressed_size(9);
+
+        let mut v = vec!(0xff; 20);
+        assert_eq!(writer.write(&mut v), Ok(16));
+
+        let dd = Reader::new(&v[..16]).unwrap();
+
+        assert_eq!(dd.signature(), Some(Reader::expected_signature()));
+        assert_eq!(dd.crc32(), 0xcbf43926);
+        assert_eq!(dd.compressed_size(), 7);
+        assert_eq!(dd.uncompressed_size(), 9);
+    }
+
+    #[test]
+    fn writer_round_trip_without_signature() {
+        let writer = Writer::new()
+            .with_signature(false)
+            .with_crc32(0xcbf43926)
+            .with_compressed_size(7)
+            .with_uncompressed_size(9);
+
+        let mut v = vec!(0xff; 20);
+        assert_eq!(writer.write(&mut v), Ok(12));
+
+        let dd = Reader::new(&v[..12]).unwrap();
+
+        assert_eq!(dd.signature(), None);
+        assert_eq!(dd.crc32(), 0xcbf43926);
+        assert_eq!(dd.compressed_size(), 7);
+        assert_eq!(dd.uncompressed_size(), 9);
+    }
+
+    #[test]
+    fn writer_failure_on_short_buffer() {
+        let mut v = vec!(0; 15);
+
+        assert_eq!(
+            Writer::new().write(&mut v),
+            Err(WriteError::BufferTooSmall { required: 16 })
+        );
+    }
 }
@@ -1,6 +1,8 @@
 //! # End of Central Directory
 
-use utils::{Slice, LeFieldReader};
+use utils::{Slice, LeFieldReader, LeFieldWriter};
+
+use super::write::{WriteError, check_buffer, field_size};
 
 /// A End of Central Directory
 ///
@@ -102,11 +104,108 @@ impl<'a> LeFieldReader<'a> for EndOfCentralDirectoryReader<'a> {
     fn get_slice(&self) -> Slice<'a> { self.data }
 }
 
+/// A End of Central Directory writer
+///
+/// The fields are set by the `with_*` methods, then serialized by `write`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct EndOfCentralDirectoryWriter<'a> {
+    disk: u16,
+    central_directory_disk: u16,
+    nb_local_central_directory_records: u16,
+    nb_central_directory_records: u16,
+    central_directory_size: u32,
+    central_directory_offset: u32,
+    comment: &'a [u8],
+}
+
+impl<'a> EndOfCentralDirectoryWriter<'a> {
+    /// Returns a new instance, with all fields zeroed or empty.
+    pub fn new() -> EndOfCentralDirectoryWriter<'a> {
+        EndOfCentralDirectoryWriter::default()
+    }
+
+    /// Sets the number of the disk.
+    pub fn with_disk(self, disk: u16) -> Self {
+        EndOfCentralDirectoryWriter { disk, ..self }
+    }
+
+    /// Sets the number of the disk where the central directory starts.
+    pub fn with_central_directory_disk(self, disk: u16) -> Self {
+        EndOfCentralDirectoryWriter { central_directory_disk: disk, ..self }
+    }
+
+    /// Sets the number of central directory records on this disk.
+    pub fn with_nb_local_central_directory_records(self, nb: u16) -> Self {
+        EndOfCentralDirectoryWriter {
+            nb_local_central_directory_records: nb,
+            ..self
+        }
+    }
+
+    /// Sets the number of central directory records on all disks.
+    pub fn with_nb_central_directory_records(self, nb: u16) -> Self {
+        EndOfCentralDirectoryWriter {
+            nb_central_directory_records: nb,
+            ..self
+        }
+    }
+
+    /// Sets the size of the central directory (in bytes).
+    pub fn with_central_directory_size(self, size: u32) -> Self {
+        EndOfCentralDirectoryWriter { central_directory_size: size, ..self }
+    }
+
+    /// Sets the offset of the central directory, from start of archive.
+    pub fn with_central_directory_offset(self, offset: u32) -> Self {
+        EndOfCentralDirectoryWriter {
+            central_directory_offset: offset,
+            ..self
+        }
+    }
+
+    /// Sets the comment, of at most 65535 bytes.
+    pub fn with_comment(self, comment: &'a [u8]) -> Self {
+        EndOfCentralDirectoryWriter { comment, ..self }
+    }
+
+    /// Returns the size of the record.
+    pub fn size(&self) -> usize {
+        EndOfCentralDirectoryReader::min_size()
+            .saturating_add(self.comment.len())
+    }
+
+    /// Writes the record at the start of the `buffer`, returning the number of
+    /// bytes written.
+    pub fn write(&self, buffer: &mut [u8]) -> Result<usize, WriteError> {
+        let comment_size = field_size(self.comment)?;
+
+        check_buffer(buffer, self.size())?;
+
+        let mut writer = LeFieldWriter::new(buffer);
+
+        writer.write_u32(EndOfCentralDirectoryReader::expected_signature());
+        writer.write_u16(self.disk);
+        writer.write_u16(self.central_directory_disk);
+        writer.write_u16(self.nb_local_central_directory_records);
+        writer.write_u16(self.nb_central_directory_records);
+        writer.write_u32(self.central_directory_size);
+        writer.write_u32(self.central_directory_offset);
+        writer.write_u16(comment_size);
+        writer.write(self.comment);
+
+        Ok(writer.position())
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::identity_op)]
 mod tests {
     use utils::test::{test_all_u16_at, test_some_u32_at};
 
+    use zip::raw::WriteError;
+
     type Reader<'a> = super::EndOfCentralDirectoryReader<'a>;
+    type Writer<'a> = super::EndOfCentralDirectoryWriter<'a>;
 
     #[test]
     fn reader_new_failure_on_short_slice() {
@@ -261,4 +360,51 @@ mod tests {
             assert_eq!(eocd.comment(), None);
         }
     }
+
+    #[test]
+    fn writer_round_trip() {
+        let writer = Writer::new()
+            .with_disk(1)
+            .with_central_directory_disk(2)
+            .with_nb_local_central_directory_records(3)
+            .with_nb_central_directory_records(4)
+            .with_central_directory_size(0x12345678)
+            .with_central_directory_offset(0x9abcdef0)
+            .with_comment(b"Hello, World!");
+
+        let mut v = vec!(0xff; 64);
+        assert_eq!(writer.write(&mut v), Ok(35));
+        assert_eq!(writer.size(), 35);
+
+        let eocd = Reader::new(&v).unwrap();
+
+        assert_eq!(eocd.raw().len(), 35);
+        assert_eq!(eocd.signature(), Reader::expected_signature());
+        assert_eq!(eocd.disk(), 1);
+        assert_eq!(eocd.central_directory_disk(), 2);
+        assert_eq!(eocd.nb_local_central_directory_records(), 3);
+        assert_eq!(eocd.nb_central_directory_records(), 4);
+        assert_eq!(eocd.central_directory_size(), 0x12345678);
+        assert_eq!(eocd.central_directory_offset(), 0x9abcdef0);
+        assert_eq!(eocd.comment(), Some(&b"Hello, World!"[..]));
+    }
+
+    #[test]
+    fn writer_failure_on_short_buffer() {
+        let mut v = vec!(0; Reader::min_size() - 1);
+
+        assert_eq!(
+            Writer::new().write(&mut v),
+            Err(WriteError::BufferTooSmall { required: 22 })
+        );
+    }
+
+    #[test]
+    fn writer_failure_on_too_large_comment() {
+        let comment = vec!(b'a'; 65536);
+        let writer = Writer::new().with_comment(&comment);
+        let mut v = vec!(0; 65536 + 22);
+
+        assert_eq!(writer.write(&mut v), Err(WriteError::FieldTooLarge));
+    }
 }
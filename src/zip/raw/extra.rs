@@ -0,0 +1,139 @@
+//! # Extra Field
+
+use utils::{Slice, LeFieldReader};
+
+/// An Extra Field block, as found in the extra field of either a Local File
+/// Header or a Central Directory File Header.
+///
+/// This structure only guarantees that access to its various fields is safe, it
+/// does not guarantee their integrity.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ExtraFieldReader<'a> {
+    data: Slice<'a>,
+}
+
+// +------------------------------------------+
+// | Offset  | Bytes  |      Description      |
+// |---------+--------+-----------------------|
+// | 0       | 2      | Header ID             |
+// | 2       | 2      | Data size (n)         |
+// | 4       | n      | Data                  |
+// +------------------------------------------+
+impl<'a> ExtraFieldReader<'a> {
+    /// Returns the minimum size of the record.
+    pub fn min_size() -> usize { 4 }
+
+    /// Returns the maximum size of the record.
+    pub fn max_size() -> usize { Self::min_size() + 65535 }
+
+    /// Returns a new instance if the slice is sufficiently large (4 bytes),
+    /// otherwise returns `None`.
+    ///
+    /// Note that the size of the data field is not checked, this is so that
+    /// decoding potentially corrupted archives is still possible.
+    pub fn new(slice: &'a [u8]) -> Option<ExtraFieldReader<'a>> {
+        if slice.len() >= Self::min_size() {
+            let reader = ExtraFieldReader { data: Slice::new(slice) };
+
+            Some(ExtraFieldReader {
+                data: Slice::new(slice).take(reader.len())
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the underlying slice.
+    pub fn raw(&self) -> &'a [u8] { self.data.raw() }
+
+    /// Returns the header ID.
+    ///
+    /// Of particular interest:
+    /// - 0x0001: ZIP64 extended information
+    /// - 0x000a: NTFS
+    /// - 0x5455: Extended timestamp
+    /// - 0x7075: Info-ZIP Unicode Path
+    /// - 0x9901: AE-x encryption
+    pub fn id(&self) -> u16 { self.read_u16(0..2) }
+
+    /// Returns the size of the data.
+    pub fn data_size(&self) -> u16 { self.read_u16(2..4) }
+
+    /// Returns the data, possibly of length 0, or `None` if the slice is
+    /// truncated.
+    pub fn data(&self) -> Option<&'a [u8]> {
+        self.read_field(Self::min_size(), self.data_size() as usize)
+    }
+
+    /// Returns the expected size of the record.
+    fn len(&self) -> usize {
+        Self::min_size() + self.data_size() as usize
+    }
+}
+
+impl<'a> LeFieldReader<'a> for ExtraFieldReader<'a> {
+    fn min_size() -> usize { ExtraFieldReader::min_size() }
+
+    fn get_slice(&self) -> Slice<'a> { self.data }
+}
+
+#[cfg(test)]
+mod tests {
+    use utils::test::test_all_u16_at;
+
+    type Reader<'a> = super::ExtraFieldReader<'a>;
+
+    #[test]
+    fn reader_new_failure_on_short_slice() {
+        let v = vec!(0; Reader::min_size() - 1);
+        for length in 0..v.len() {
+            assert_eq!(Reader::new(&v[0..length]), None);
+        }
+    }
+
+    #[test]
+    fn reader_new_success_on_4_bytes_slice_with_nonzero_data_size() {
+        let mut v = vec!(0; Reader::min_size());
+        v[2] = 0x01;    // 1 byte data
+        assert!(Reader::new(&v).is_some());
+    }
+
+    #[test]
+    fn reader_id_success() {
+        let mut v = vec!(0; Reader::min_size());
+        test_all_u16_at(&mut v, 0, |v, id| {
+            let extra = Reader::new(v).unwrap();
+            assert_eq!(extra.id(), id);
+        });
+    }
+
+    #[test]
+    fn reader_data_size_success() {
+        let mut v = vec!(0; Reader::min_size());
+        test_all_u16_at(&mut v, 2, |v, size| {
+            let extra = Reader::new(v).unwrap();
+            assert_eq!(extra.data_size(), size);
+        });
+    }
+
+    #[test]
+    fn reader_data_success_with_hello_world() {
+        let mut v = vec!(0x75, 0x70, 13, 0);
+        v.extend_from_slice(b"Hello, World!");
+        v.extend_from_slice(b"Trailing");
+
+        let extra = Reader::new(&v).unwrap();
+
+        assert_eq!(extra.raw().len(), 17);
+        assert_eq!(extra.data(), Some(&b"Hello, World!"[..]));
+    }
+
+    #[test]
+    fn reader_data_failure_on_too_short_buffer() {
+        let v = vec!(0x75, 0x70, 13, 0, 1, 2, 3);
+
+        let extra = Reader::new(&v).unwrap();
+
+        assert_eq!(extra.data(), None);
+    }
+}
@@ -1,6 +1,8 @@
 //! ## Local File Header
 
-use utils::{Slice, LeFieldReader};
+use utils::{Slice, LeFieldReader, LeFieldWriter};
+
+use super::write::{WriteError, check_buffer, field_size};
 
 /// A Local File Header
 ///
@@ -49,7 +51,7 @@ impl<'a> LocalFileHeaderReader<'a> {
             let reader = LocalFileHeaderReader { data: Slice::new(slice) };
 
             Some(LocalFileHeaderReader {
-                data: Slice::new(slice).take(reader.len())
+                data: Slice::new(slice).take(reader.expected_size())
             })
         } else {
             None
@@ -121,8 +123,9 @@ impl<'a> LocalFileHeaderReader<'a> {
         self.file_name_size() as usize
     }
 
-    /// Returns the size of the record.
-    fn len(&self) -> usize {
+    /// Returns the expected size of the record, as announced by its fixed part,
+    /// which the underlying slice may fall short of.
+    pub fn expected_size(&self) -> usize {
         self.extra_field_position() + self.extra_field_size() as usize
     }
 }
@@ -133,13 +136,123 @@ impl<'a> LeFieldReader<'a> for LocalFileHeaderReader<'a> {
     fn get_slice(&self) -> Slice<'a> { self.data }
 }
 
+/// A Local File Header writer
+///
+/// The fields are set by the `with_*` methods, then serialized by `write`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct LocalFileHeaderWriter<'a> {
+    version_needed_to_extract: u16,
+    general_purpose_bit_flag: u16,
+    compression_method: u16,
+    file_last_modification_time: u16,
+    file_last_modification_date: u16,
+    crc32: u32,
+    compressed_size: u32,
+    uncompressed_size: u32,
+    file_name: &'a [u8],
+    extra_field: &'a [u8],
+}
+
+impl<'a> LocalFileHeaderWriter<'a> {
+    /// Returns a new instance, with all fields zeroed or empty.
+    pub fn new() -> LocalFileHeaderWriter<'a> {
+        LocalFileHeaderWriter::default()
+    }
+
+    /// Sets the minimum ZIP version needed to extract.
+    pub fn with_version_needed_to_extract(self, version: u16) -> Self {
+        LocalFileHeaderWriter { version_needed_to_extract: version, ..self }
+    }
+
+    /// Sets the general purpose bit flags.
+    pub fn with_general_purpose_bit_flag(self, flag: u16) -> Self {
+        LocalFileHeaderWriter { general_purpose_bit_flag: flag, ..self }
+    }
+
+    /// Sets the compression method.
+    pub fn with_compression_method(self, method: u16) -> Self {
+        LocalFileHeaderWriter { compression_method: method, ..self }
+    }
+
+    /// Sets the last modification time of the file, MS-DOS format.
+    pub fn with_file_last_modification_time(self, time: u16) -> Self {
+        LocalFileHeaderWriter { file_last_modification_time: time, ..self }
+    }
+
+    /// Sets the last modification date of the file, MS-DOS format.
+    pub fn with_file_last_modification_date(self, date: u16) -> Self {
+        LocalFileHeaderWriter { file_last_modification_date: date, ..self }
+    }
+
+    /// Sets the CRC-32 of the file.
+    pub fn with_crc32(self, crc32: u32) -> Self {
+        LocalFileHeaderWriter { crc32, ..self }
+    }
+
+    /// Sets the compressed size of the file.
+    pub fn with_compressed_size(self, size: u32) -> Self {
+        LocalFileHeaderWriter { compressed_size: size, ..self }
+    }
+
+    /// Sets the uncompressed size of the file.
+    pub fn with_uncompressed_size(self, size: u32) -> Self {
+        LocalFileHeaderWriter { uncompressed_size: size, ..self }
+    }
+
+    /// Sets the file name, of at most 65535 bytes.
+    pub fn with_file_name(self, name: &'a [u8]) -> Self {
+        LocalFileHeaderWriter { file_name: name, ..self }
+    }
+
+    /// Sets the extra field, of at most 65535 bytes.
+    pub fn with_extra_field(self, extra: &'a [u8]) -> Self {
+        LocalFileHeaderWriter { extra_field: extra, ..self }
+    }
+
+    /// Returns the size of the record.
+    pub fn size(&self) -> usize {
+        LocalFileHeaderReader::min_size()
+            .saturating_add(self.file_name.len())
+            .saturating_add(self.extra_field.len())
+    }
+
+    /// Writes the record at the start of the `buffer`, returning the number of
+    /// bytes written.
+    pub fn write(&self, buffer: &mut [u8]) -> Result<usize, WriteError> {
+        let file_name_size = field_size(self.file_name)?;
+        let extra_field_size = field_size(self.extra_field)?;
+
+        check_buffer(buffer, self.size())?;
+
+        let mut writer = LeFieldWriter::new(buffer);
+
+        writer.write_u32(LocalFileHeaderReader::expected_signature());
+        writer.write_u16(self.version_needed_to_extract);
+        writer.write_u16(self.general_purpose_bit_flag);
+        writer.write_u16(self.compression_method);
+        writer.write_u16(self.file_last_modification_time);
+        writer.write_u16(self.file_last_modification_date);
+        writer.write_u32(self.crc32);
+        writer.write_u32(self.compressed_size);
+        writer.write_u32(self.uncompressed_size);
+        writer.write_u16(file_name_size);
+        writer.write_u16(extra_field_size);
+        writer.write(self.file_name);
+        writer.write(self.extra_field);
+
+        Ok(writer.position())
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::identity_op)]
 mod tests {
-    use std;
-
     use utils::test::{test_all_u16_at, test_some_u32_at};
 
+    use zip::raw::WriteError;
+
     type Reader<'a> = super::LocalFileHeaderReader<'a>;
+    type Writer<'a> = super::LocalFileHeaderWriter<'a>;
 
     #[test]
     fn reader_new_failure_on_short_slice() {
@@ -177,6 +290,16 @@ mod tests {
         assert!(Reader::new(&v).is_some());
     }
 
+    #[test]
+    fn reader_expected_size_beyond_truncated_slice() {
+        let mut v = vec!(0; Reader::min_size());
+        v[26] = 0x02;   // 2 bytes file name
+        v[28] = 0x03;   // 3 bytes extra field
+        let lfh = Reader::new(&v).unwrap();
+        assert_eq!(lfh.expected_size(), Reader::min_size() + 5);
+        assert_eq!(lfh.raw().len(), Reader::min_size());
+    }
+
     #[test]
     fn reader_signature_success_with_expected_signature() {
         let mut v = vec!(0; Reader::min_size());
@@ -341,7 +464,7 @@ mod tests {
     #[test]
     fn reader_file_name_success_with_max_length() {
         let mut v = vec!(0; Reader::min_size());
-        v.resize(Reader::min_size() + std::u16::MAX as usize, 1);
+        v.resize(Reader::min_size() + u16::MAX as usize, 1);
 
         v[27] = 0xff;
         v[26] = 0xff;
@@ -354,7 +477,7 @@ mod tests {
     #[test]
     fn reader_extra_field_success_with_max_length() {
         let mut v = vec!(0; Reader::min_size());
-        v.resize(Reader::min_size() + std::u16::MAX as usize, 1);
+        v.resize(Reader::min_size() + u16::MAX as usize, 1);
 
         v[29] = 0xff;
         v[28] = 0xff;
@@ -367,9 +490,9 @@ mod tests {
     #[test]
     fn reader_file_name_failure_on_too_short_buffer() {
         let mut v = vec!(0; Reader::min_size());
-        v.resize(Reader::min_size() + std::u16::MAX as usize - 1, 1);
+        v.resize(Reader::min_size() + u16::MAX as usize - 1, 1);
 
-        for length in 1..(std::u16::MAX as usize + 1) {
+        for length in 1..(u16::MAX as usize + 1) {
             v[27] = (length >> 8) as u8;
             v[26] = (length >> 0) as u8;
 
@@ -384,9 +507,9 @@ mod tests {
     #[test]
     fn reader_extra_field_failure_on_too_short_buffer() {
         let mut v = vec!(0; Reader::min_size());
-        v.resize(Reader::min_size() + std::u16::MAX as usize - 1, 1);
+        v.resize(Reader::min_size() + u16::MAX as usize - 1, 1);
 
-        for length in 1..(std::u16::MAX as usize + 1) {
+        for length in 1..(u16::MAX as usize + 1) {
             v[29] = (length >> 8) as u8;
             v[28] = (length >> 0) as u8;
 
@@ -415,4 +538,60 @@ mod tests {
         assert_eq!(lfh.file_name(), Some(hello_file));
         assert_eq!(lfh.extra_field(), Some(hello_extra));
     }
+
+    #[test]
+    fn writer_round_trip() {
+        let writer = Writer::new()
+            .with_version_needed_to_extract(20)
+            .with_general_purpose_bit_flag(0x0808)
+            .with_compression_method(8)
+            .with_file_last_modification_time(0x6000)
+            .with_file_last_modification_date(0x5021)
+            .with_crc32(0xcbf43926)
+            .with_compressed_size(7)
+            .with_uncompressed_size(9)
+            .with_file_name(b"dir/a.txt")
+            .with_extra_field(b"\x01\x02\x03\x04");
+
+        let mut v = vec!(0xff; 64);
+        assert_eq!(writer.write(&mut v), Ok(43));
+        assert_eq!(writer.size(), 43);
+
+        let lfh = Reader::new(&v).unwrap();
+
+        assert_eq!(lfh.raw().len(), 43);
+        assert_eq!(lfh.signature(), Reader::expected_signature());
+        assert_eq!(lfh.version_needed_to_extract(), 20);
+        assert_eq!(lfh.general_purpose_bit_flag(), 0x0808);
+        assert_eq!(lfh.compression_method(), 8);
+        assert_eq!(lfh.file_last_modification_time(), 0x6000);
+        assert_eq!(lfh.file_last_modification_date(), 0x5021);
+        assert_eq!(lfh.crc32(), 0xcbf43926);
+        assert_eq!(lfh.compressed_size(), 7);
+        assert_eq!(lfh.uncompressed_size(), 9);
+        assert_eq!(lfh.file_name(), Some(&b"dir/a.txt"[..]));
+        assert_eq!(lfh.extra_field(), Some(&b"\x01\x02\x03\x04"[..]));
+        assert_eq!(v[43], 0xff);
+    }
+
+    #[test]
+    fn writer_failure_on_short_buffer() {
+        let writer = Writer::new().with_file_name(b"a.txt");
+        let mut v = vec!(0; 34);
+
+        assert_eq!(
+            writer.write(&mut v),
+            Err(WriteError::BufferTooSmall { required: 35 })
+        );
+        assert_eq!(v, vec!(0; 34));
+    }
+
+    #[test]
+    fn writer_failure_on_too_large_file_name() {
+        let name = vec!(b'a'; 65536);
+        let writer = Writer::new().with_file_name(&name);
+        let mut v = vec!(0; 65536 + 30);
+
+        assert_eq!(writer.write(&mut v), Err(WriteError::FieldTooLarge));
+    }
 }
@@ -1,14 +1,41 @@
 //! # Raw ZIP structures
 //!
 //! This module provides types to interpret raw bytes as particular structures
-//! of the ZIP specification.
+//! of the ZIP specification, and to serialize them into raw bytes.
 
 mod cdfh;
 mod dd;
 mod eocd;
+mod extra;
 mod lfh;
+mod write;
+mod zip64_dd;
+mod zip64_eocd;
+mod zip64_eocd_locator;
+mod zip64_extra;
 
-pub use self::cdfh::CentralDirectoryFileHeaderReader;
-pub use self::dd::DataDescriptorReader;
-pub use self::eocd::EndOfCentralDirectoryReader;
-pub use self::lfh::LocalFileHeaderReader;
+pub use self::cdfh::{
+    CentralDirectoryFileHeaderReader,
+    CentralDirectoryFileHeaderWriter
+};
+pub use self::dd::{DataDescriptorReader, DataDescriptorWriter};
+pub use self::eocd::{EndOfCentralDirectoryReader, EndOfCentralDirectoryWriter};
+pub use self::extra::ExtraFieldReader;
+pub use self::lfh::{LocalFileHeaderReader, LocalFileHeaderWriter};
+pub use self::write::WriteError;
+pub use self::zip64_dd::{
+    Zip64DataDescriptorReader,
+    Zip64DataDescriptorWriter
+};
+pub use self::zip64_eocd::{
+    Zip64EndOfCentralDirectoryReader,
+    Zip64EndOfCentralDirectoryWriter
+};
+pub use self::zip64_eocd_locator::{
+    Zip64EndOfCentralDirectoryLocatorReader,
+    Zip64EndOfCentralDirectoryLocatorWriter
+};
+pub use self::zip64_extra::{
+    Zip64ExtendedInformationReader,
+    Zip64ExtendedInformationWriter
+};
@@ -0,0 +1,32 @@
+//! ## Shared support for the writers
+
+/// The reason why a record could not be written.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum WriteError {
+    /// The buffer is too small to hold the record.
+    BufferTooSmall {
+        /// The number of bytes required to hold the record.
+        required: usize,
+    },
+    /// A variable-size field is larger than its size field can express.
+    FieldTooLarge,
+}
+
+/// Returns the size of the variable-size `field`, or `FieldTooLarge` if it
+/// exceeds 65535 bytes.
+pub fn field_size(field: &[u8]) -> Result<u16, WriteError> {
+    if field.len() <= 0xffff {
+        Ok(field.len() as u16)
+    } else {
+        Err(WriteError::FieldTooLarge)
+    }
+}
+
+/// Returns `BufferTooSmall` if the `buffer` cannot hold `required` bytes.
+pub fn check_buffer(buffer: &[u8], required: usize) -> Result<(), WriteError> {
+    if buffer.len() >= required {
+        Ok(())
+    } else {
+        Err(WriteError::BufferTooSmall { required })
+    }
+}
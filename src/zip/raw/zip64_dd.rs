@@ -0,0 +1,250 @@
+//! ## ZIP64 Data Descriptor
+
+use core::ops::Range;
+
+use utils::{
+    DEADBEEF,
+    DEADBEEFDEADBEEF,
+    LeFieldWriter,
+    Slice,
+    read_u32_le,
+    read_u64_le
+};
+
+use super::dd::DataDescriptorReader;
+use super::write::{WriteError, check_buffer};
+
+/// A ZIP64 Data Descriptor, whose sizes are 8 bytes wide
+///
+/// This structure only guarantees that access to its various fields is safe, it
+/// does not guarantee their integrity.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Zip64DataDescriptorReader<'a> {
+    data: Slice<'a>,
+}
+
+// +--------------------------------------------------------------------+
+// | Offset  | Bytes  |                 Description                     |
+// |---------+--------+-------------------------------------------------|
+// | 0       | 0/4    | Optional data descriptor signature = 0x08074b50 |
+// | 0/4     | 4      | CRC-32                                          |
+// | 4/8     | 8      | Compressed size                                 |
+// | 12/16   | 8      | Uncompressed size                               |
+// +--------------------------------------------------------------------+
+impl<'a> Zip64DataDescriptorReader<'a> {
+    /// Returns the minimum size of the record.
+    pub fn min_size() -> usize { 20 }
+
+    /// Returns the maximum size of the record.
+    pub fn max_size() -> usize { Self::min_size() + 4 }
+
+    /// Returns the expected signature.
+    pub fn expected_signature() -> u32 {
+        DataDescriptorReader::expected_signature()
+    }
+
+    /// Returns a new instance if the slice is either 20 bytes or 24 bytes,
+    /// otherwise returns `None`.
+    ///
+    /// Note that the signature is not checked, this is so that decoding
+    /// potentially corrupted archives is still possible.
+    pub fn new(slice: &'a [u8]) -> Option<Zip64DataDescriptorReader<'a>> {
+        if slice.len() == Self::min_size() || slice.len() == Self::max_size() {
+            Some(Zip64DataDescriptorReader { data: Slice::new(slice) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the underlying slice.
+    pub fn raw(&self) -> &'a [u8] { self.data.raw() }
+
+    /// Returns whether this instance has a signature field, or not.
+    pub fn has_signature(&self) -> bool { self.data.len() == Self::max_size() }
+
+    /// Returns the signature.
+    pub fn signature(&self) -> Option<u32> {
+        if self.has_signature() { Some(self.read_u32(0..4)) } else { None }
+    }
+
+    /// Returns the CRC-32 of the file.
+    pub fn crc32(&self) -> u32 {
+        self.read_u32(if self.has_signature() { 4..8 } else { 0..4 })
+    }
+
+    /// Returns the compressed size of the file.
+    pub fn compressed_size(&self) -> u64 {
+        self.read_u64(if self.has_signature() { 8..16 } else { 4..12 })
+    }
+
+    /// Returns the uncompressed size of the file.
+    pub fn uncompressed_size(&self) -> u64 {
+        self.read_u64(if self.has_signature() { 16..24 } else { 12..20 })
+    }
+
+    /// Interprets the 4 bytes as u32 (little-endian).
+    fn read_u32(&self, range: Range<usize>) -> u32 {
+        debug_assert!(range.len() == 4);
+        debug_assert!(range.end <= self.data.len());
+
+        self.data
+            .slice(range)
+            .and_then(read_u32_le)
+            .unwrap_or(DEADBEEF)
+    }
+
+    /// Interprets the 8 bytes as u64 (little-endian).
+    fn read_u64(&self, range: Range<usize>) -> u64 {
+        debug_assert!(range.len() == 8);
+        debug_assert!(range.end <= self.data.len());
+
+        self.data
+            .slice(range)
+            .and_then(read_u64_le)
+            .unwrap_or(DEADBEEFDEADBEEF)
+    }
+}
+
+/// A ZIP64 Data Descriptor writer
+///
+/// The fields are set by the `with_*` methods, then serialized by `write`.
+///
+/// The optional signature is written by default, as recommended by the
+/// specification.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Zip64DataDescriptorWriter {
+    signature: bool,
+    crc32: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+}
+
+impl Zip64DataDescriptorWriter {
+    /// Returns a new instance, with a signature and all fields zeroed.
+    pub fn new() -> Zip64DataDescriptorWriter {
+        Zip64DataDescriptorWriter {
+            signature: true,
+            crc32: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+        }
+    }
+
+    /// Sets whether the optional signature is written.
+    pub fn with_signature(self, signature: bool) -> Self {
+        Zip64DataDescriptorWriter { signature, ..self }
+    }
+
+    /// Sets the CRC-32 of the file.
+    pub fn with_crc32(self, crc32: u32) -> Self {
+        Zip64DataDescriptorWriter { crc32, ..self }
+    }
+
+    /// Sets the compressed size of the file.
+    pub fn with_compressed_size(self, size: u64) -> Self {
+        Zip64DataDescriptorWriter { compressed_size: size, ..self }
+    }
+
+    /// Sets the uncompressed size of the file.
+    pub fn with_uncompressed_size(self, size: u64) -> Self {
+        Zip64DataDescriptorWriter { uncompressed_size: size, ..self }
+    }
+
+    /// Returns the size of the record.
+    pub fn size(&self) -> usize {
+        if self.signature {
+            Zip64DataDescriptorReader::max_size()
+        } else {
+            Zip64DataDescriptorReader::min_size()
+        }
+    }
+
+    /// Writes the record at the start of the `buffer`, returning the number of
+    /// bytes written.
+    pub fn write(&self, buffer: &mut [u8]) -> Result<usize, WriteError> {
+        check_buffer(buffer, self.size())?;
+
+        let mut writer = LeFieldWriter::new(buffer);
+
+        if self.signature {
+            writer.write_u32(Zip64DataDescriptorReader::expected_signature());
+        }
+
+        writer.write_u32(self.crc32);
+        writer.write_u64(self.compressed_size);
+        writer.write_u64(self.uncompressed_size);
+
+        Ok(writer.position())
+    }
+}
+
+impl Default for Zip64DataDescriptorWriter {
+    fn default() -> Zip64DataDescriptorWriter {
+        Zip64DataDescriptorWriter::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zip::raw::WriteError;
+
+    type Reader<'a> = super::Zip64DataDescriptorReader<'a>;
+    type Writer = super::Zip64DataDescriptorWriter;
+
+    #[test]
+    fn reader_new_failure_on_inexact_slice() {
+        let v = [0; 64];
+        for length in 0..v.len() {
+            if length != Reader::min_size() && length != Reader::max_size() {
+                assert_eq!(Reader::new(&v[0..length]), None);
+            }
+        }
+    }
+
+    #[test]
+    fn writer_round_trip_with_signature() {
+        let writer = Writer::new()
+            .with_crc32(0xcbf43926)
+            .with_compressed_size(0x1_0000_0007)
+            .with_uncompressed_size(0x2_0000_0009);
+
+        let mut v = vec!(0xff; 32);
+        assert_eq!(writer.write(&mut v), Ok(24));
+
+        let dd = Reader::new(&v[..24]).unwrap();
+
+        assert_eq!(dd.signature(), Some(Reader::expected_signature()));
+        assert_eq!(dd.crc32(), 0xcbf43926);
+        assert_eq!(dd.compressed_size(), 0x1_0000_0007);
+        assert_eq!(dd.uncompressed_size(), 0x2_0000_0009);
+    }
+
+    #[test]
+    fn writer_round_trip_without_signature() {
+        let writer = Writer::new()
+            .with_signature(false)
+            .with_crc32(0xcbf43926)
+            .with_compressed_size(0x1_0000_0007)
+            .with_uncompressed_size(0x2_0000_0009);
+
+        let mut v = vec!(0xff; 32);
+        assert_eq!(writer.write(&mut v), Ok(20));
+
+        let dd = Reader::new(&v[..20]).unwrap();
+
+        assert_eq!(dd.signature(), None);
+        assert_eq!(dd.crc32(), 0xcbf43926);
+        assert_eq!(dd.compressed_size(), 0x1_0000_0007);
+        assert_eq!(dd.uncompressed_size(), 0x2_0000_0009);
+    }
+
+    #[test]
+    fn writer_failure_on_short_buffer() {
+        let mut v = vec!(0; 23);
+
+        assert_eq!(
+            Writer::new().write(&mut v),
+            Err(WriteError::BufferTooSmall { required: 24 })
+        );
+    }
+}
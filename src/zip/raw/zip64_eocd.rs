@@ -0,0 +1,324 @@
+//! # ZIP64 End of Central Directory
+
+use utils::{Slice, LeFieldReader, LeFieldWriter};
+
+use super::write::{WriteError, check_buffer};
+
+/// A ZIP64 End of Central Directory
+///
+/// This structure only guarantees that access to its various fields is safe, it
+/// does not guarantee their integrity.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Zip64EndOfCentralDirectoryReader<'a> {
+    data: Slice<'a>,
+}
+
+// +---------------------------------------------------------------------+
+// | Offset  | Bytes  |                          Description             |
+// |---------+--------+--------------------------------------------------|
+// |      0  | 4      | ZIP64 EOCD signature = 0x06064b50                |
+// |      4  | 8      | Size of the remaining record (12 + n)            |
+// |     12  | 2      | Version made by                                  |
+// |     14  | 2      | Version needed to extract (minimum)              |
+// |     16  | 4      | Number of this disk                              |
+// |     20  | 4      | Disk where central directory starts              |
+// |     24  | 8      | Number of central directory records on this disk |
+// |     32  | 8      | Total number of central directory records        |
+// |     40  | 8      | Size of central directory (bytes)                |
+// |     48  | 8      | Offset of start of central directory             |
+// |     56  | n      | Extensible data sector                           |
+// +---------------------------------------------------------------------+
+impl<'a> Zip64EndOfCentralDirectoryReader<'a> {
+    /// Returns the minimum size of the record.
+    pub fn min_size() -> usize { 56 }
+
+    /// Returns the expected signature.
+    pub fn expected_signature() -> u32 { 0x06064b50 }
+
+    /// Returns a new instance if the slice is sufficiently large (56 bytes),
+    /// otherwise returns `None`.
+    ///
+    /// Note that neither the signature nor the size of the extensible data
+    /// sector are checked, this is so that decoding potentially corrupted
+    /// archives is still possible.
+    pub fn new(slice: &'a [u8])
+        -> Option<Zip64EndOfCentralDirectoryReader<'a>>
+    {
+        if slice.len() >= Self::min_size() {
+            let reader =
+                Zip64EndOfCentralDirectoryReader { data: Slice::new(slice) };
+
+            Some(Zip64EndOfCentralDirectoryReader {
+                data: Slice::new(slice).take(reader.len())
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the underlying slice.
+    pub fn raw(&self) -> &'a [u8] { self.data.raw() }
+
+    /// Returns the signature.
+    pub fn signature(&self) -> u32 { self.read_u32(0..4) }
+
+    /// Returns the size of the record, minus its 12 first bytes.
+    pub fn record_size(&self) -> u64 { self.read_u64(4..12) }
+
+    /// Returns the version of the software that created the record, and its OS.
+    ///
+    /// (see CentralDirectoryFileHeaderReader for more ample information)
+    pub fn version_made_by(&self) -> u16 { self.read_u16(12..14) }
+
+    /// Returns the minimum ZIP version needed to extract.
+    ///
+    /// (see CentralDirectoryFileHeaderReader for more ample information)
+    pub fn version_needed_to_extract(&self) -> u16 { self.read_u16(14..16) }
+
+    /// Returns the number of the disk.
+    pub fn disk(&self) -> u32 { self.read_u32(16..20) }
+
+    /// Returns the number of the disk where the central directory starts.
+    pub fn central_directory_disk(&self) -> u32 { self.read_u32(20..24) }
+
+    /// Returns the number of central directory records on this disk.
+    pub fn nb_local_central_directory_records(&self) -> u64 {
+        self.read_u64(24..32)
+    }
+
+    /// Returns the number of central directory records on all disks.
+    pub fn nb_central_directory_records(&self) -> u64 { self.read_u64(32..40) }
+
+    /// Returns the size of the central directory (in bytes).
+    pub fn central_directory_size(&self) -> u64 { self.read_u64(40..48) }
+
+    /// Returns the offset of the central directory, from start of archive.
+    pub fn central_directory_offset(&self) -> u64 { self.read_u64(48..56) }
+
+    /// Returns the extensible data sector, possibly of length 0, or `None` if
+    /// the slice is truncated or the record size is lower than 44.
+    pub fn extensible_data(&self) -> Option<&'a [u8]> {
+        let size = self.record_size()
+            .checked_sub((Self::min_size() - 12) as u64)?;
+
+        if size > usize::MAX as u64 {
+            return None;
+        }
+
+        self.read_field(Self::min_size(), size as usize)
+    }
+
+    /// Returns the expected size of the record.
+    fn len(&self) -> usize {
+        let size = self.record_size();
+
+        if size > usize::MAX as u64 {
+            usize::MAX
+        } else {
+            (size as usize).saturating_add(12)
+        }
+    }
+}
+
+impl<'a> LeFieldReader<'a> for Zip64EndOfCentralDirectoryReader<'a> {
+    fn min_size() -> usize { Zip64EndOfCentralDirectoryReader::min_size() }
+
+    fn get_slice(&self) -> Slice<'a> { self.data }
+}
+
+/// A ZIP64 End of Central Directory writer
+///
+/// The fields are set by the `with_*` methods, then serialized by `write`; the
+/// size of the record is computed from the extensible data sector.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Zip64EndOfCentralDirectoryWriter<'a> {
+    version_made_by: u16,
+    version_needed_to_extract: u16,
+    disk: u32,
+    central_directory_disk: u32,
+    nb_local_central_directory_records: u64,
+    nb_central_directory_records: u64,
+    central_directory_size: u64,
+    central_directory_offset: u64,
+    extensible_data: &'a [u8],
+}
+
+impl<'a> Zip64EndOfCentralDirectoryWriter<'a> {
+    /// Returns a new instance, with all fields zeroed or empty.
+    pub fn new() -> Zip64EndOfCentralDirectoryWriter<'a> {
+        Zip64EndOfCentralDirectoryWriter::default()
+    }
+
+    /// Sets the version of the software that created the record, and its OS.
+    pub fn with_version_made_by(self, version: u16) -> Self {
+        Zip64EndOfCentralDirectoryWriter { version_made_by: version, ..self }
+    }
+
+    /// Sets the minimum ZIP version needed to extract.
+    pub fn with_version_needed_to_extract(self, version: u16) -> Self {
+        Zip64EndOfCentralDirectoryWriter {
+            version_needed_to_extract: version,
+            ..self
+        }
+    }
+
+    /// Sets the number of the disk.
+    pub fn with_disk(self, disk: u32) -> Self {
+        Zip64EndOfCentralDirectoryWriter { disk, ..self }
+    }
+
+    /// Sets the number of the disk where the central directory starts.
+    pub fn with_central_directory_disk(self, disk: u32) -> Self {
+        Zip64EndOfCentralDirectoryWriter {
+            central_directory_disk: disk,
+            ..self
+        }
+    }
+
+    /// Sets the number of central directory records on this disk.
+    pub fn with_nb_local_central_directory_records(self, nb: u64) -> Self {
+        Zip64EndOfCentralDirectoryWriter {
+            nb_local_central_directory_records: nb,
+            ..self
+        }
+    }
+
+    /// Sets the number of central directory records on all disks.
+    pub fn with_nb_central_directory_records(self, nb: u64) -> Self {
+        Zip64EndOfCentralDirectoryWriter {
+            nb_central_directory_records: nb,
+            ..self
+        }
+    }
+
+    /// Sets the size of the central directory (in bytes).
+    pub fn with_central_directory_size(self, size: u64) -> Self {
+        Zip64EndOfCentralDirectoryWriter {
+            central_directory_size: size,
+            ..self
+        }
+    }
+
+    /// Sets the offset of the central directory, from start of archive.
+    pub fn with_central_directory_offset(self, offset: u64) -> Self {
+        Zip64EndOfCentralDirectoryWriter {
+            central_directory_offset: offset,
+            ..self
+        }
+    }
+
+    /// Sets the extensible data sector.
+    pub fn with_extensible_data(self, data: &'a [u8]) -> Self {
+        Zip64EndOfCentralDirectoryWriter { extensible_data: data, ..self }
+    }
+
+    /// Returns the size of the record.
+    pub fn size(&self) -> usize {
+        Zip64EndOfCentralDirectoryReader::min_size()
+            .saturating_add(self.extensible_data.len())
+    }
+
+    /// Writes the record at the start of the `buffer`, returning the number of
+    /// bytes written.
+    pub fn write(&self, buffer: &mut [u8]) -> Result<usize, WriteError> {
+        type Reader<'a> = Zip64EndOfCentralDirectoryReader<'a>;
+
+        check_buffer(buffer, self.size())?;
+
+        let mut writer = LeFieldWriter::new(buffer);
+
+        writer.write_u32(Reader::expected_signature());
+        writer.write_u64(self.size().wrapping_sub(12) as u64);
+        writer.write_u16(self.version_made_by);
+        writer.write_u16(self.version_needed_to_extract);
+        writer.write_u32(self.disk);
+        writer.write_u32(self.central_directory_disk);
+        writer.write_u64(self.nb_local_central_directory_records);
+        writer.write_u64(self.nb_central_directory_records);
+        writer.write_u64(self.central_directory_size);
+        writer.write_u64(self.central_directory_offset);
+        writer.write(self.extensible_data);
+
+        Ok(writer.position())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zip::raw::WriteError;
+
+    type Reader<'a> = super::Zip64EndOfCentralDirectoryReader<'a>;
+    type Writer<'a> = super::Zip64EndOfCentralDirectoryWriter<'a>;
+
+    #[test]
+    fn reader_new_failure_on_short_slice() {
+        let v = vec!(0; Reader::min_size() - 1);
+        for length in 0..v.len() {
+            assert_eq!(Reader::new(&v[0..length]), None);
+        }
+    }
+
+    #[test]
+    fn reader_extensible_data_failure_on_too_small_record_size() {
+        let mut v = vec!(0; Reader::min_size());
+        v[4] = 43;
+
+        let eocd = Reader::new(&v).unwrap();
+
+        assert_eq!(eocd.extensible_data(), None);
+    }
+
+    #[test]
+    fn reader_extensible_data_failure_on_truncated_slice() {
+        let mut v = vec!(0; Reader::min_size() + 3);
+        v[4] = 48;
+
+        let eocd = Reader::new(&v).unwrap();
+
+        assert_eq!(eocd.raw().len(), Reader::min_size() + 3);
+        assert_eq!(eocd.extensible_data(), None);
+    }
+
+    #[test]
+    fn writer_round_trip() {
+        let writer = Writer::new()
+            .with_version_made_by(0x032d)
+            .with_version_needed_to_extract(45)
+            .with_disk(1)
+            .with_central_directory_disk(2)
+            .with_nb_local_central_directory_records(0x1_0000_0003)
+            .with_nb_central_directory_records(0x1_0000_0004)
+            .with_central_directory_size(0x1_2345_6789)
+            .with_central_directory_offset(0xfedc_ba98_7654_3210)
+            .with_extensible_data(b"data");
+
+        let mut v = vec!(0xff; 64);
+        assert_eq!(writer.write(&mut v), Ok(60));
+        assert_eq!(writer.size(), 60);
+
+        let eocd = Reader::new(&v).unwrap();
+
+        assert_eq!(eocd.raw().len(), 60);
+        assert_eq!(eocd.signature(), Reader::expected_signature());
+        assert_eq!(eocd.record_size(), 48);
+        assert_eq!(eocd.version_made_by(), 0x032d);
+        assert_eq!(eocd.version_needed_to_extract(), 45);
+        assert_eq!(eocd.disk(), 1);
+        assert_eq!(eocd.central_directory_disk(), 2);
+        assert_eq!(eocd.nb_local_central_directory_records(), 0x1_0000_0003);
+        assert_eq!(eocd.nb_central_directory_records(), 0x1_0000_0004);
+        assert_eq!(eocd.central_directory_size(), 0x1_2345_6789);
+        assert_eq!(eocd.central_directory_offset(), 0xfedc_ba98_7654_3210);
+        assert_eq!(eocd.extensible_data(), Some(&b"data"[..]));
+    }
+
+    #[test]
+    fn writer_failure_on_short_buffer() {
+        let mut v = vec!(0; Reader::min_size() - 1);
+
+        assert_eq!(
+            Writer::new().write(&mut v),
+            Err(WriteError::BufferTooSmall { required: 56 })
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! # ZIP64 End of Central Directory Locator
+
+use utils::{Slice, LeFieldReader, LeFieldWriter};
+
+use super::write::{WriteError, check_buffer};
+
+/// A ZIP64 End of Central Directory Locator
+///
+/// This structure only guarantees that access to its various fields is safe, it
+/// does not guarantee their integrity.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Zip64EndOfCentralDirectoryLocatorReader<'a> {
+    data: Slice<'a>,
+}
+
+// +---------------------------------------------------------------------+
+// | Offset  | Bytes  |                          Description             |
+// |---------+--------+--------------------------------------------------|
+// |      0  | 4      | ZIP64 EOCD locator signature = 0x07064b50        |
+// |      4  | 4      | Disk where the ZIP64 EOCD starts                 |
+// |      8  | 8      | Offset of the ZIP64 EOCD                         |
+// |     16  | 4      | Total number of disks                            |
+// +---------------------------------------------------------------------+
+impl<'a> Zip64EndOfCentralDirectoryLocatorReader<'a> {
+    /// Returns the size of the record.
+    pub fn min_size() -> usize { 20 }
+
+    /// Returns the size of the record.
+    pub fn max_size() -> usize { Self::min_size() }
+
+    /// Returns the expected signature.
+    pub fn expected_signature() -> u32 { 0x07064b50 }
+
+    /// Returns a new instance if the slice is sufficiently large (20 bytes),
+    /// otherwise returns `None`.
+    ///
+    /// Note that the signature is not checked, this is so that decoding
+    /// potentially corrupted archives is still possible.
+    pub fn new(slice: &'a [u8])
+        -> Option<Zip64EndOfCentralDirectoryLocatorReader<'a>>
+    {
+        if slice.len() >= Self::min_size() {
+            Some(Zip64EndOfCentralDirectoryLocatorReader {
+                data: Slice::new(slice).take(Self::min_size())
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the underlying slice.
+    pub fn raw(&self) -> &'a [u8] { self.data.raw() }
+
+    /// Returns the signature.
+    pub fn signature(&self) -> u32 { self.read_u32(0..4) }
+
+    /// Returns the number of the disk where the ZIP64 End of Central Directory
+    /// record starts.
+    pub fn zip64_end_of_central_directory_disk(&self) -> u32 {
+        self.read_u32(4..8)
+    }
+
+    /// Returns the offset of the ZIP64 End of Central Directory record, from
+    /// the start of the archive.
+    pub fn zip64_end_of_central_directory_offset(&self) -> u64 {
+        self.read_u64(8..16)
+    }
+
+    /// Returns the total number of disks.
+    pub fn nb_disks(&self) -> u32 { self.read_u32(16..20) }
+}
+
+impl<'a> LeFieldReader<'a> for Zip64EndOfCentralDirectoryLocatorReader<'a> {
+    fn min_size() -> usize {
+        Zip64EndOfCentralDirectoryLocatorReader::min_size()
+    }
+
+    fn get_slice(&self) -> Slice<'a> { self.data }
+}
+
+/// A ZIP64 End of Central Directory Locator writer
+///
+/// The fields are set by the `with_*` methods, then serialized by `write`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Zip64EndOfCentralDirectoryLocatorWriter {
+    zip64_end_of_central_directory_disk: u32,
+    zip64_end_of_central_directory_offset: u64,
+    nb_disks: u32,
+}
+
+impl Zip64EndOfCentralDirectoryLocatorWriter {
+    /// Returns a new instance, for a single disk archive.
+    pub fn new() -> Zip64EndOfCentralDirectoryLocatorWriter {
+        Zip64EndOfCentralDirectoryLocatorWriter {
+            zip64_end_of_central_directory_disk: 0,
+            zip64_end_of_central_directory_offset: 0,
+            nb_disks: 1,
+        }
+    }
+
+    /// Sets the number of the disk where the ZIP64 End of Central Directory
+    /// record starts.
+    pub fn with_zip64_end_of_central_directory_disk(self, disk: u32) -> Self {
+        Zip64EndOfCentralDirectoryLocatorWriter {
+            zip64_end_of_central_directory_disk: disk,
+            ..self
+        }
+    }
+
+    /// Sets the offset of the ZIP64 End of Central Directory record, from the
+    /// start of the archive.
+    pub fn with_zip64_end_of_central_directory_offset(self, offset: u64)
+        -> Self
+    {
+        Zip64EndOfCentralDirectoryLocatorWriter {
+            zip64_end_of_central_directory_offset: offset,
+            ..self
+        }
+    }
+
+    /// Sets the total number of disks.
+    pub fn with_nb_disks(self, nb: u32) -> Self {
+        Zip64EndOfCentralDirectoryLocatorWriter { nb_disks: nb, ..self }
+    }
+
+    /// Returns the size of the record.
+    pub fn size(&self) -> usize {
+        Zip64EndOfCentralDirectoryLocatorReader::min_size()
+    }
+
+    /// Writes the record at the start of the `buffer`, returning the number of
+    /// bytes written.
+    pub fn write(&self, buffer: &mut [u8]) -> Result<usize, WriteError> {
+        type Reader<'a> = Zip64EndOfCentralDirectoryLocatorReader<'a>;
+
+        check_buffer(buffer, self.size())?;
+
+        let mut writer = LeFieldWriter::new(buffer);
+
+        writer.write_u32(Reader::expected_signature());
+        writer.write_u32(self.zip64_end_of_central_directory_disk);
+        writer.write_u64(self.zip64_end_of_central_directory_offset);
+        writer.write_u32(self.nb_disks);
+
+        Ok(writer.position())
+    }
+}
+
+impl Default for Zip64EndOfCentralDirectoryLocatorWriter {
+    fn default() -> Zip64EndOfCentralDirectoryLocatorWriter {
+        Zip64EndOfCentralDirectoryLocatorWriter::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zip::raw::WriteError;
+
+    type Reader<'a> = super::Zip64EndOfCentralDirectoryLocatorReader<'a>;
+    type Writer = super::Zip64EndOfCentralDirectoryLocatorWriter;
+
+    #[test]
+    fn reader_new_failure_on_short_slice() {
+        let v = vec!(0; Reader::min_size() - 1);
+        for length in 0..v.len() {
+            assert_eq!(Reader::new(&v[0..length]), None);
+        }
+    }
+
+    #[test]
+    fn writer_round_trip() {
+        let writer = Writer::new()
+            .with_zip64_end_of_central_directory_disk(2)
+            .with_zip64_end_of_central_directory_offset(0x1_2345_6789)
+            .with_nb_disks(3);
+
+        let mut v = vec!(0xff; 32);
+        assert_eq!(writer.write(&mut v), Ok(20));
+
+        let locator = Reader::new(&v).unwrap();
+
+        assert_eq!(locator.raw().len(), 20);
+        assert_eq!(locator.signature(), Reader::expected_signature());
+        assert_eq!(locator.zip64_end_of_central_directory_disk(), 2);
+        assert_eq!(
+            locator.zip64_end_of_central_directory_offset(),
+            0x1_2345_6789
+        );
+        assert_eq!(locator.nb_disks(), 3);
+    }
+
+    #[test]
+    fn writer_failure_on_short_buffer() {
+        let mut v = vec!(0; 19);
+
+        assert_eq!(
+            Writer::new().write(&mut v),
+            Err(WriteError::BufferTooSmall { required: 20 })
+        );
+    }
+}
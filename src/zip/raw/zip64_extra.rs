@@ -0,0 +1,319 @@
+//! # ZIP64 Extended Information Extra Field
+
+use utils::{Slice, LeFieldWriter, read_u32_le, read_u64_le};
+
+use super::cdfh::CentralDirectoryFileHeaderReader;
+use super::lfh::LocalFileHeaderReader;
+use super::write::{WriteError, check_buffer};
+
+/// The data of a ZIP64 Extended Information extra field block.
+///
+/// Each field is only present if the corresponding field of the record is set
+/// to its maximum value (0xffffffff, or 0xffff for the disk), and the fields
+/// which are present are packed in order.
+///
+/// This structure only guarantees that access to its various fields is safe, it
+/// does not guarantee their integrity.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Zip64ExtendedInformationReader<'a> {
+    data: Slice<'a>,
+    presence: [bool; 4],
+}
+
+// +---------------------------------------------------------------------+
+// | Offset  | Bytes  |                          Description             |
+// |---------+--------+--------------------------------------------------|
+// |      -  | 0/8    | Uncompressed size                                |
+// |      -  | 0/8    | Compressed size                                  |
+// |      -  | 0/8    | Relative offset of the Local File Header         |
+// |      -  | 0/4    | Number of the disk in which the file starts      |
+// +---------------------------------------------------------------------+
+impl<'a> Zip64ExtendedInformationReader<'a> {
+    /// Returns the ID of the extra field block.
+    pub fn id() -> u16 { 0x0001 }
+
+    /// Returns a new instance interpreting the `data` of the extra field
+    /// block of the Local File Header record.
+    ///
+    /// The Local File Header only ever contains both sizes, which are present
+    /// if either size of the record is set to 0xffffffff.
+    pub fn from_local_file_header(
+        data: &'a [u8],
+        lfh: LocalFileHeaderReader
+    )
+        -> Zip64ExtendedInformationReader<'a>
+    {
+        let sizes = lfh.uncompressed_size() == 0xffffffff ||
+                    lfh.compressed_size() == 0xffffffff;
+
+        Zip64ExtendedInformationReader {
+            data: Slice::new(data),
+            presence: [sizes, sizes, false, false],
+        }
+    }
+
+    /// Returns a new instance interpreting the `data` of the extra field
+    /// block of the Central Directory File Header record.
+    pub fn from_central_directory_file_header(
+        data: &'a [u8],
+        cdfh: CentralDirectoryFileHeaderReader
+    )
+        -> Zip64ExtendedInformationReader<'a>
+    {
+        Zip64ExtendedInformationReader {
+            data: Slice::new(data),
+            presence: [
+                cdfh.uncompressed_size() == 0xffffffff,
+                cdfh.compressed_size() == 0xffffffff,
+                cdfh.local_file_header_relative_offset() == 0xffffffff,
+                cdfh.file_start_disk() == 0xffff,
+            ],
+        }
+    }
+
+    /// Returns the underlying slice.
+    pub fn raw(&self) -> &'a [u8] { self.data.raw() }
+
+    /// Returns the uncompressed size of the file, or `None` if absent or
+    /// truncated.
+    pub fn uncompressed_size(&self) -> Option<u64> {
+        self.field(0).and_then(read_u64_le)
+    }
+
+    /// Returns the compressed size of the file, or `None` if absent or
+    /// truncated.
+    pub fn compressed_size(&self) -> Option<u64> {
+        self.field(1).and_then(read_u64_le)
+    }
+
+    /// Returns the relative offset of the Local File Header record, or `None`
+    /// if absent or truncated.
+    pub fn local_file_header_relative_offset(&self) -> Option<u64> {
+        self.field(2).and_then(read_u64_le)
+    }
+
+    /// Returns the number of the disk in which the file starts, or `None` if
+    /// absent or truncated.
+    pub fn file_start_disk(&self) -> Option<u32> {
+        self.field(3).and_then(read_u32_le)
+    }
+
+    /// Returns the slice of the `index`-th field, if present.
+    fn field(&self, index: usize) -> Option<Slice<'a>> {
+        const SIZES: [usize; 4] = [8, 8, 8, 4];
+
+        let mut start = 0;
+
+        for (i, (&present, &size)) in
+            self.presence.iter().zip(SIZES.iter()).enumerate()
+        {
+            if i == index {
+                return if present {
+                    self.data.slice(start..(start + size))
+                } else {
+                    None
+                };
+            }
+
+            if present {
+                start += size;
+            }
+        }
+
+        None
+    }
+}
+
+/// A ZIP64 Extended Information extra field block writer
+///
+/// The fields are set by the `with_*` methods, then serialized by `write`,
+/// header included; only the fields which are set are written.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Zip64ExtendedInformationWriter {
+    uncompressed_size: Option<u64>,
+    compressed_size: Option<u64>,
+    local_file_header_relative_offset: Option<u64>,
+    file_start_disk: Option<u32>,
+}
+
+impl Zip64ExtendedInformationWriter {
+    /// Returns a new instance, with no field set.
+    pub fn new() -> Zip64ExtendedInformationWriter {
+        Zip64ExtendedInformationWriter::default()
+    }
+
+    /// Sets the uncompressed size of the file.
+    pub fn with_uncompressed_size(self, size: u64) -> Self {
+        Zip64ExtendedInformationWriter {
+            uncompressed_size: Some(size),
+            ..self
+        }
+    }
+
+    /// Sets the compressed size of the file.
+    pub fn with_compressed_size(self, size: u64) -> Self {
+        Zip64ExtendedInformationWriter { compressed_size: Some(size), ..self }
+    }
+
+    /// Sets the relative offset of the Local File Header record.
+    pub fn with_local_file_header_relative_offset(self, offset: u64) -> Self {
+        Zip64ExtendedInformationWriter {
+            local_file_header_relative_offset: Some(offset),
+            ..self
+        }
+    }
+
+    /// Sets the number of the disk in which the file starts.
+    pub fn with_file_start_disk(self, disk: u32) -> Self {
+        Zip64ExtendedInformationWriter { file_start_disk: Some(disk), ..self }
+    }
+
+    /// Returns the size of the extra field block, header included.
+    pub fn size(&self) -> usize { 4 + self.data_size() }
+
+    /// Writes the extra field block at the start of the `buffer`, returning
+    /// the number of bytes written.
+    pub fn write(&self, buffer: &mut [u8]) -> Result<usize, WriteError> {
+        check_buffer(buffer, self.size())?;
+
+        let mut writer = LeFieldWriter::new(buffer);
+
+        writer.write_u16(Zip64ExtendedInformationReader::id());
+        writer.write_u16(self.data_size() as u16);
+
+        let sizes = [
+            self.uncompressed_size,
+            self.compressed_size,
+            self.local_file_header_relative_offset,
+        ];
+
+        for &size in sizes.iter().flatten() {
+            writer.write_u64(size);
+        }
+
+        if let Some(disk) = self.file_start_disk {
+            writer.write_u32(disk);
+        }
+
+        Ok(writer.position())
+    }
+
+    /// Returns the size of the data of the extra field block.
+    fn data_size(&self) -> usize {
+        self.uncompressed_size.map_or(0, |_| 8) +
+        self.compressed_size.map_or(0, |_| 8) +
+        self.local_file_header_relative_offset.map_or(0, |_| 8) +
+        self.file_start_disk.map_or(0, |_| 4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zip::raw::{
+        CentralDirectoryFileHeaderReader,
+        CentralDirectoryFileHeaderWriter,
+        ExtraFieldReader,
+        LocalFileHeaderReader,
+        LocalFileHeaderWriter,
+        WriteError,
+    };
+
+    type Reader<'a> = super::Zip64ExtendedInformationReader<'a>;
+    type Writer = super::Zip64ExtendedInformationWriter;
+
+    #[test]
+    fn writer_round_trip_in_cdfh() {
+        let writer = Writer::new()
+            .with_compressed_size(0x1_0000_0000)
+            .with_file_start_disk(0x10000);
+
+        let mut extra = vec!(0; 32);
+        let written = writer.write(&mut extra).unwrap();
+        extra.truncate(written);
+
+        assert_eq!(written, 16);
+
+        let mut v = vec!(0; 64);
+        CentralDirectoryFileHeaderWriter::new()
+            .with_compressed_size(0xffffffff)
+            .with_uncompressed_size(7)
+            .with_file_start_disk(0xffff)
+            .with_extra_field(&extra)
+            .write(&mut v)
+            .unwrap();
+
+        let cdfh = CentralDirectoryFileHeaderReader::new(&v).unwrap();
+        let block = ExtraFieldReader::new(cdfh.extra_field().unwrap()).unwrap();
+
+        assert_eq!(block.id(), Reader::id());
+
+        let zip64 = Reader::from_central_directory_file_header(
+            block.data().unwrap(), cdfh);
+
+        assert_eq!(zip64.uncompressed_size(), None);
+        assert_eq!(zip64.compressed_size(), Some(0x1_0000_0000));
+        assert_eq!(zip64.local_file_header_relative_offset(), None);
+        assert_eq!(zip64.file_start_disk(), Some(0x10000));
+    }
+
+    #[test]
+    fn writer_round_trip_in_lfh() {
+        let writer = Writer::new()
+            .with_uncompressed_size(0x2_0000_0000)
+            .with_compressed_size(0x1_0000_0000);
+
+        let mut extra = vec!(0; 20);
+        assert_eq!(writer.write(&mut extra), Ok(20));
+
+        let mut v = vec!(0; 64);
+        LocalFileHeaderWriter::new()
+            .with_compressed_size(0xffffffff)
+            .with_uncompressed_size(0xffffffff)
+            .with_extra_field(&extra)
+            .write(&mut v)
+            .unwrap();
+
+        let lfh = LocalFileHeaderReader::new(&v).unwrap();
+        let block = ExtraFieldReader::new(lfh.extra_field().unwrap()).unwrap();
+
+        let zip64 = Reader::from_local_file_header(block.data().unwrap(), lfh);
+
+        assert_eq!(zip64.uncompressed_size(), Some(0x2_0000_0000));
+        assert_eq!(zip64.compressed_size(), Some(0x1_0000_0000));
+    }
+
+    #[test]
+    fn reader_failure_on_truncated_data() {
+        let v = vec!(0; 64);
+        let cdfh = CentralDirectoryFileHeaderReader::new(&v).unwrap();
+        let lfh = LocalFileHeaderReader::new(&v).unwrap();
+
+        let zip64 = Reader::from_central_directory_file_header(&v[..4], cdfh);
+
+        assert_eq!(zip64.uncompressed_size(), None);
+
+        let mut lfh_bytes = v.clone();
+        lfh_bytes[18..26].copy_from_slice(&[0xff; 8]);
+        let lfh_max = LocalFileHeaderReader::new(&lfh_bytes).unwrap();
+
+        let zip64 = Reader::from_local_file_header(&v[..12], lfh_max);
+
+        assert_eq!(zip64.uncompressed_size(), Some(0));
+        assert_eq!(zip64.compressed_size(), None);
+
+        let zip64 = Reader::from_local_file_header(&v[..16], lfh);
+
+        assert_eq!(zip64.uncompressed_size(), None);
+    }
+
+    #[test]
+    fn writer_failure_on_short_buffer() {
+        let writer = Writer::new().with_uncompressed_size(1);
+        let mut v = vec!(0; 11);
+
+        assert_eq!(
+            writer.write(&mut v),
+            Err(WriteError::BufferTooSmall { required: 12 })
+        );
+    }
+}
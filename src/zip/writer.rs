@@ -0,0 +1,1078 @@
+//! # Streaming writer of ZIP archives
+//!
+//! A `ZipWriter` writes the entries of an archive one after the other, each as
+//! a Local File Header record followed by the (already compressed) data of the
+//! entry, and possibly a Data Descriptor record. The Central Directory File
+//! Header records are accumulated in caller-provided storage, possibly spilled
+//! to a caller-provided sink, and written along with the End of Central
+//! Directory record once the archive is finished.
+//!
+//! ZIP64 records and extra fields are only used when a size, an offset or a
+//! count does not fit in the regular records.
+
+use core::cmp::min;
+
+use api::{Reader, Writer};
+
+use zip::raw::{
+    CentralDirectoryFileHeaderWriter,
+    DataDescriptorWriter,
+    EndOfCentralDirectoryWriter,
+    LocalFileHeaderWriter,
+    WriteError,
+    Zip64DataDescriptorWriter,
+    Zip64EndOfCentralDirectoryLocatorWriter,
+    Zip64EndOfCentralDirectoryWriter,
+    Zip64ExtendedInformationWriter,
+};
+
+/// The version of the specification needed for ZIP64 extensions, 4.5.
+const ZIP64_VERSION: u16 = 45;
+
+/// The maximum value of a 32 bits field, used as a ZIP64 placeholder.
+const MAX_U32: u64 = 0xffffffff;
+
+/// The maximum value of a 16 bits field, used as a ZIP64 placeholder.
+const MAX_U16: u64 = 0xffff;
+
+/// The reason why writing an archive failed.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ZipWriteError<E> {
+    /// The `Writer` failed.
+    Io(E),
+    /// The operation is not permitted in the current state, such as starting
+    /// an entry while another is in progress.
+    InvalidState,
+    /// A file name, extra field or comment is larger than 65535 bytes.
+    FieldTooLarge,
+    /// The storage cannot hold the Central Directory File Header record; it
+    /// should be spilled before retrying.
+    CentralDirectoryFull,
+    /// The data of the entry does not match the sizes announced up front.
+    SizeMismatch,
+    /// The spilled Central Directory cannot be read back in full.
+    SpillTruncated,
+}
+
+impl<E> From<WriteError> for ZipWriteError<E> {
+    fn from(error: WriteError) -> ZipWriteError<E> {
+        match error {
+            WriteError::BufferTooSmall { .. } =>
+                ZipWriteError::CentralDirectoryFull,
+            WriteError::FieldTooLarge => ZipWriteError::FieldTooLarge,
+        }
+    }
+}
+
+/// The description of an entry.
+///
+/// The fields are set by the `with_*` methods; by default, an entry is stored
+/// (compression method 0) and dated 1980-01-01 00:00:00.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct EntryOptions<'a> {
+    name: &'a [u8],
+    extra_field: &'a [u8],
+    comment: &'a [u8],
+    version_made_by: u16,
+    version_needed_to_extract: u16,
+    general_purpose_bit_flag: u16,
+    compression_method: u16,
+    time: u16,
+    date: u16,
+    internal_file_attributes: u16,
+    external_file_attributes: u32,
+    sizes: Option<(u32, u64, u64)>,
+}
+
+impl<'a> EntryOptions<'a> {
+    /// Returns a new instance, for an entry named `name`.
+    pub fn new(name: &'a [u8]) -> EntryOptions<'a> {
+        EntryOptions {
+            name,
+            extra_field: b"",
+            comment: b"",
+            version_made_by: 20,
+            version_needed_to_extract: 10,
+            general_purpose_bit_flag: 0,
+            compression_method: 0,
+            time: 0,
+            date: 0x21,
+            internal_file_attributes: 0,
+            external_file_attributes: 0,
+            sizes: None,
+        }
+    }
+
+    /// Sets the extra field, written in both the Local File Header and the
+    /// Central Directory File Header records.
+    pub fn with_extra_field(self, extra: &'a [u8]) -> Self {
+        EntryOptions { extra_field: extra, ..self }
+    }
+
+    /// Sets the file comment.
+    pub fn with_comment(self, comment: &'a [u8]) -> Self {
+        EntryOptions { comment, ..self }
+    }
+
+    /// Sets the version of the software that created the record, and its OS.
+    pub fn with_version_made_by(self, version: u16) -> Self {
+        EntryOptions { version_made_by: version, ..self }
+    }
+
+    /// Sets the minimum ZIP version needed to extract, raised to 4.5 if ZIP64
+    /// extensions are used.
+    pub fn with_version_needed_to_extract(self, version: u16) -> Self {
+        EntryOptions { version_needed_to_extract: version, ..self }
+    }
+
+    /// Sets the general purpose bit flags.
+    ///
+    /// The bit 3, indicating a Data Descriptor, is managed by the writer.
+    pub fn with_general_purpose_bit_flag(self, flag: u16) -> Self {
+        EntryOptions { general_purpose_bit_flag: flag & !0x08, ..self }
+    }
+
+    /// Sets the compression method of the data.
+    pub fn with_compression_method(self, method: u16) -> Self {
+        EntryOptions { compression_method: method, ..self }
+    }
+
+    /// Sets the last modification time and date, MS-DOS format.
+    pub fn with_last_modification(self, time: u16, date: u16) -> Self {
+        EntryOptions { time, date, ..self }
+    }
+
+    /// Sets the internal file attributes.
+    pub fn with_internal_file_attributes(self, attributes: u16) -> Self {
+        EntryOptions { internal_file_attributes: attributes, ..self }
+    }
+
+    /// Sets the external file attributes.
+    pub fn with_external_file_attributes(self, attributes: u32) -> Self {
+        EntryOptions { external_file_attributes: attributes, ..self }
+    }
+
+    /// Sets the CRC-32, compressed size and uncompressed size, known up front.
+    ///
+    /// Otherwise, they are written in a Data Descriptor record after the data.
+    pub fn with_sizes(self, crc32: u32, compressed: u64, uncompressed: u64)
+        -> Self
+    {
+        EntryOptions { sizes: Some((crc32, compressed, uncompressed)), ..self }
+    }
+}
+
+/// The state of the entry in progress.
+///
+/// The fields of the Local File Header record are kept, so that the Central
+/// Directory File Header record matches it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Current {
+    offset: u64,
+    written: u64,
+    version_needed_to_extract: u16,
+    general_purpose_bit_flag: u16,
+    compression_method: u16,
+    time: u16,
+    date: u16,
+    sizes: Option<(u32, u64, u64)>,
+}
+
+/// A streaming writer of ZIP archives.
+///
+/// For each entry, `start_entry` writes the Local File Header record, then
+/// `write_data` writes the already compressed data, in as many chunks as
+/// necessary, and `finish_entry` completes the entry. Once all entries are
+/// written, `finish` writes the Central Directory and the End of Central
+/// Directory record.
+///
+/// The Central Directory File Header records are accumulated in the storage
+/// provided on construction. Whenever it is full, `finish_entry` returns
+/// `CentralDirectoryFull` without side-effect, and the storage can be drained
+/// into an external sink by `spill`; the spilled records are then read back by
+/// `finish_with_spill`.
+#[derive(Debug, Eq, PartialEq)]
+pub struct ZipWriter<'s> {
+    storage: &'s mut [u8],
+    stored: usize,
+    spilled: u64,
+    offset: u64,
+    entries: u64,
+    current: Option<Current>,
+    finished: bool,
+}
+
+impl<'s> ZipWriter<'s> {
+    /// Returns an instance accumulating the Central Directory in `storage`.
+    pub fn new(storage: &'s mut [u8]) -> ZipWriter<'s> {
+        ZipWriter::with_offset(storage, 0)
+    }
+
+    /// Returns an instance accumulating the Central Directory in `storage`,
+    /// whose archive starts after `offset` bytes of unrelated data, such as a
+    /// self-extracting stub.
+    pub fn with_offset(storage: &'s mut [u8], offset: u64) -> ZipWriter<'s> {
+        ZipWriter {
+            storage,
+            stored: 0,
+            spilled: 0,
+            offset,
+            entries: 0,
+            current: None,
+            finished: false,
+        }
+    }
+
+    /// Returns the number of bytes written so far, including the initial
+    /// offset.
+    pub fn offset(&self) -> u64 { self.offset }
+
+    /// Returns the number of entries completed so far.
+    pub fn nb_entries(&self) -> u64 { self.entries }
+
+    /// Returns the number of bytes of the Central Directory held in storage.
+    pub fn stored(&self) -> usize { self.stored }
+
+    /// Returns the number of bytes of the Central Directory spilled so far.
+    pub fn spilled(&self) -> u64 { self.spilled }
+
+    /// Returns whether the archive is finished.
+    pub fn is_finished(&self) -> bool { self.finished }
+
+    /// Writes the Local File Header record of the entry.
+    ///
+    /// If the sizes of the entry are known up front and do not fit in 32 bits,
+    /// they are written in a ZIP64 extra field. Otherwise, should the sizes
+    /// turn out not to fit, a ZIP64 Data Descriptor record is written instead.
+    ///
+    /// The version needed to extract is raised to 4.5 if the sizes known up
+    /// front, or the offset of the record, do not fit in 32 bits.
+    pub fn start_entry<W>(&mut self, writer: &mut W, entry: &EntryOptions)
+        -> Result<(), ZipWriteError<W::Error>>
+        where W: Writer + ?Sized
+    {
+        if self.finished || self.current.is_some() {
+            return Err(ZipWriteError::InvalidState);
+        }
+
+        let large_sizes = match entry.sizes {
+            Some((_, compressed, uncompressed)) =>
+                compressed >= MAX_U32 || uncompressed >= MAX_U32,
+            None => false,
+        };
+
+        let zip64 = large_sizes || self.offset >= MAX_U32;
+
+        let current = Current {
+            offset: self.offset,
+            written: 0,
+            version_needed_to_extract:
+                version_needed(entry.version_needed_to_extract, zip64),
+            general_purpose_bit_flag: entry.general_purpose_bit_flag |
+                if entry.sizes.is_none() { 0x08 } else { 0 },
+            compression_method: entry.compression_method,
+            time: entry.time,
+            date: entry.date,
+            sizes: entry.sizes,
+        };
+
+        let mut extended = [0; 32];
+        let extended_size = match entry.sizes {
+            Some((_, compressed, uncompressed)) if large_sizes =>
+                Zip64ExtendedInformationWriter::new()
+                    .with_uncompressed_size(uncompressed)
+                    .with_compressed_size(compressed)
+                    .write(&mut extended)?,
+            _ => 0,
+        };
+        let extended = extended.get(..extended_size).unwrap_or(b"");
+
+        let mut header = LocalFileHeaderWriter::new()
+            .with_version_needed_to_extract(current.version_needed_to_extract)
+            .with_general_purpose_bit_flag(current.general_purpose_bit_flag)
+            .with_compression_method(current.compression_method)
+            .with_file_last_modification_time(current.time)
+            .with_file_last_modification_date(current.date);
+
+        //  With ZIP64, both sizes are only present in the extra field.
+        if let Some((crc32, compressed, uncompressed)) = current.sizes {
+            let (compressed, uncompressed) = if large_sizes {
+                (MAX_U32, MAX_U32)
+            } else {
+                (compressed, uncompressed)
+            };
+
+            header = header
+                .with_crc32(crc32)
+                .with_compressed_size(compressed as u32)
+                .with_uncompressed_size(uncompressed as u32);
+        }
+
+        let mut fixed = [0; 30];
+        header.write(&mut fixed)?;
+        patch_sizes(
+            &mut fixed,
+            26,
+            &[entry.name.len(), extended.len() + entry.extra_field.len()]
+        )?;
+
+        self.append(writer, &fixed)?;
+        self.append(writer, entry.name)?;
+        self.append(writer, extended)?;
+        self.append(writer, entry.extra_field)?;
+
+        self.current = Some(current);
+
+        Ok(())
+    }
+
+    /// Writes the next chunk of (already compressed) data of the entry.
+    pub fn write_data<W>(&mut self, writer: &mut W, data: &[u8])
+        -> Result<(), ZipWriteError<W::Error>>
+        where W: Writer + ?Sized
+    {
+        let written = match self.current {
+            Some(current) => current.written,
+            None => return Err(ZipWriteError::InvalidState),
+        };
+
+        self.append(writer, data)?;
+
+        self.current = self.current.map(|current| Current {
+            written: written.saturating_add(data.len() as u64),
+            ..current
+        });
+
+        Ok(())
+    }
+
+    /// Completes the entry, writing its Data Descriptor record if its sizes
+    /// were not known up front, and storing its Central Directory File Header
+    /// record.
+    ///
+    /// The `entry` must be the description passed to `start_entry`, while
+    /// `crc32` and `uncompressed_size` describe the data prior to compression.
+    ///
+    /// The fields shared with the Local File Header record, such as the flags
+    /// or the sizes known up front, are those written by `start_entry`; only
+    /// the name, the extra field, and the fields specific to the Central
+    /// Directory File Header record are taken from the `entry`.
+    pub fn finish_entry<W>(
+        &mut self,
+        writer: &mut W,
+        entry: &EntryOptions,
+        crc32: u32,
+        uncompressed_size: u64
+    )
+        -> Result<(), ZipWriteError<W::Error>>
+        where W: Writer + ?Sized
+    {
+        let current = match self.current {
+            Some(current) => current,
+            None => return Err(ZipWriteError::InvalidState),
+        };
+
+        let compressed_size = current.written;
+
+        if let Some(sizes) = current.sizes {
+            if sizes != (crc32, compressed_size, uncompressed_size) {
+                return Err(ZipWriteError::SizeMismatch);
+            }
+        }
+
+        let large_sizes =
+            compressed_size >= MAX_U32 || uncompressed_size >= MAX_U32;
+        let large_offset = current.offset >= MAX_U32;
+
+        let mut extended = [0; 32];
+        let extended_size = if large_sizes || large_offset {
+            let mut writer = Zip64ExtendedInformationWriter::new();
+
+            if large_sizes {
+                writer = writer
+                    .with_uncompressed_size(uncompressed_size)
+                    .with_compressed_size(compressed_size);
+            }
+
+            if large_offset {
+                writer = writer
+                    .with_local_file_header_relative_offset(current.offset);
+            }
+
+            writer.write(&mut extended)?
+        } else {
+            0
+        };
+        let extended = extended.get(..extended_size).unwrap_or(b"");
+
+        let header = CentralDirectoryFileHeaderWriter::new()
+            .with_version_made_by(entry.version_made_by)
+            .with_version_needed_to_extract(version_needed(
+                current.version_needed_to_extract,
+                large_sizes || large_offset
+            ))
+            .with_general_purpose_bit_flag(current.general_purpose_bit_flag)
+            .with_compression_method(current.compression_method)
+            .with_file_last_modification_time(current.time)
+            .with_file_last_modification_date(current.date)
+            .with_crc32(crc32)
+            .with_compressed_size(min(compressed_size, MAX_U32) as u32)
+            .with_uncompressed_size(min(uncompressed_size, MAX_U32) as u32)
+            .with_internal_file_attributes(entry.internal_file_attributes)
+            .with_external_file_attributes(entry.external_file_attributes)
+            .with_local_file_header_relative_offset(
+                min(current.offset, MAX_U32) as u32);
+
+        let mut fixed = [0; 46];
+        header.write(&mut fixed)?;
+        patch_sizes(
+            &mut fixed,
+            28,
+            &[
+                entry.name.len(),
+                extended.len() + entry.extra_field.len(),
+                entry.comment.len(),
+            ]
+        )?;
+
+        let fields = [
+            &fixed[..], entry.name, extended, entry.extra_field, entry.comment
+        ];
+
+        let required = fields.iter().map(|field| field.len()).sum::<usize>();
+
+        if self.storage.len().saturating_sub(self.stored) < required {
+            return Err(ZipWriteError::CentralDirectoryFull);
+        }
+
+        if current.sizes.is_none() {
+            let mut dd = [0; 24];
+
+            let written = if large_sizes {
+                Zip64DataDescriptorWriter::new()
+                    .with_crc32(crc32)
+                    .with_compressed_size(compressed_size)
+                    .with_uncompressed_size(uncompressed_size)
+                    .write(&mut dd)?
+            } else {
+                DataDescriptorWriter::new()
+                    .with_crc32(crc32)
+                    .with_compressed_size(compressed_size as u32)
+                    .with_uncompressed_size(uncompressed_size as u32)
+                    .write(&mut dd)?
+            };
+
+            self.append(writer, dd.get(..written).unwrap_or(b""))?;
+        }
+
+        for field in &fields {
+            let end = self.stored.saturating_add(field.len());
+
+            if let Some(target) = self.storage.get_mut(self.stored..end) {
+                target.copy_from_slice(field);
+                self.stored = end;
+            }
+        }
+
+        self.entries = self.entries.saturating_add(1);
+        self.current = None;
+
+        Ok(())
+    }
+
+    /// Appends the Central Directory held in storage to the `sink`, freeing
+    /// the storage.
+    pub fn spill<S>(&mut self, sink: &mut S) -> Result<(), S::Error>
+        where S: Writer + ?Sized
+    {
+        let stored = self.storage.get(..self.stored).unwrap_or(b"");
+
+        sink.append(stored)?;
+
+        self.spilled = self.spilled.saturating_add(self.stored as u64);
+        self.stored = 0;
+
+        Ok(())
+    }
+
+    /// Writes the Central Directory and the End of Central Directory record,
+    /// with the archive `comment`.
+    ///
+    /// Returns `SpillTruncated` if part of the Central Directory was spilled,
+    /// in which case `finish_with_spill` should be used instead.
+    pub fn finish<W>(&mut self, writer: &mut W, comment: &[u8])
+        -> Result<(), ZipWriteError<W::Error>>
+        where W: Writer + ?Sized
+    {
+        self.check_finish(comment)?;
+
+        if self.spilled > 0 {
+            return Err(ZipWriteError::SpillTruncated);
+        }
+
+        let cd_offset = self.offset;
+
+        self.finish_stored(writer, cd_offset, comment)
+    }
+
+    /// Writes the Central Directory, reading back its spilled part from
+    /// `spilled`, and the End of Central Directory record, with the archive
+    /// `comment`.
+    ///
+    /// A failure of the `spilled` reader is converted into an `Io` error.
+    pub fn finish_with_spill<W, R>(
+        &mut self,
+        writer: &mut W,
+        spilled: &R,
+        comment: &[u8]
+    )
+        -> Result<(), ZipWriteError<W::Error>>
+        where W: Writer + ?Sized,
+              R: Reader + ?Sized,
+              W::Error: From<R::Error>
+    {
+        self.check_finish(comment)?;
+
+        let read_error = |error| ZipWriteError::Io(W::Error::from(error));
+
+        if spilled.size().map_err(read_error)? < self.spilled {
+            return Err(ZipWriteError::SpillTruncated);
+        }
+
+        let cd_offset = self.offset;
+
+        let spill_size = self.spilled;
+        let mut position = 0;
+
+        //  Only used by readers which copy, the spill is then copied over in
+        //  chunks.
+        let mut scratch = [0; 512];
+
+        while position < spill_size {
+            let chunk = spilled
+                .get(position..spill_size, &mut scratch)
+                .map_err(read_error)?;
+
+            if chunk.is_empty() {
+                return Err(ZipWriteError::SpillTruncated);
+            }
+
+            self.append(writer, chunk)?;
+
+            position += chunk.len() as u64;
+        }
+
+        self.finish_stored(writer, cd_offset, comment)
+    }
+
+    /// Checks that the archive can be finished with the `comment`.
+    fn check_finish<E>(&self, comment: &[u8]) -> Result<(), ZipWriteError<E>> {
+        if self.finished || self.current.is_some() {
+            return Err(ZipWriteError::InvalidState);
+        }
+
+        if comment.len() > 0xffff {
+            return Err(ZipWriteError::FieldTooLarge);
+        }
+
+        Ok(())
+    }
+
+    /// Writes the part of the Central Directory held in storage, following
+    /// its spilled part, if any, then the End of Central Directory records.
+    fn finish_stored<W>(
+        &mut self,
+        writer: &mut W,
+        cd_offset: u64,
+        comment: &[u8]
+    )
+        -> Result<(), ZipWriteError<W::Error>>
+        where W: Writer + ?Sized
+    {
+        let stored = self.stored;
+        let storage = self.storage.get(..stored).unwrap_or(b"");
+
+        writer.append(storage).map_err(ZipWriteError::Io)?;
+        self.offset = self.offset.saturating_add(stored as u64);
+
+        let cd_size = self.offset - cd_offset;
+
+        let zip64 = self.entries >= MAX_U16 ||
+                    cd_size >= MAX_U32 ||
+                    cd_offset >= MAX_U32;
+
+        if zip64 {
+            let eocd64_offset = self.offset;
+
+            let mut records = [0; 76];
+
+            let eocd64 = Zip64EndOfCentralDirectoryWriter::new()
+                .with_version_made_by(ZIP64_VERSION)
+                .with_version_needed_to_extract(ZIP64_VERSION)
+                .with_nb_local_central_directory_records(self.entries)
+                .with_nb_central_directory_records(self.entries)
+                .with_central_directory_size(cd_size)
+                .with_central_directory_offset(cd_offset)
+                .write(&mut records)?;
+
+            let locator = Zip64EndOfCentralDirectoryLocatorWriter::new()
+                .with_zip64_end_of_central_directory_offset(eocd64_offset)
+                .write(records.get_mut(eocd64..).unwrap_or(&mut []))?;
+
+            let records = records.get(..(eocd64 + locator)).unwrap_or(b"");
+
+            self.append(writer, records)?;
+        }
+
+        let entries = min(self.entries, MAX_U16) as u16;
+
+        let mut eocd = [0; 22];
+
+        EndOfCentralDirectoryWriter::new()
+            .with_nb_local_central_directory_records(entries)
+            .with_nb_central_directory_records(entries)
+            .with_central_directory_size(min(cd_size, MAX_U32) as u32)
+            .with_central_directory_offset(min(cd_offset, MAX_U32) as u32)
+            .write(&mut eocd)?;
+
+        patch_sizes(&mut eocd, 20, &[comment.len()])?;
+
+        self.append(writer, &eocd)?;
+        self.append(writer, comment)?;
+
+        self.finished = true;
+
+        Ok(())
+    }
+
+    /// Appends the `data` to the `writer`, keeping track of the offset.
+    fn append<W>(&mut self, writer: &mut W, data: &[u8])
+        -> Result<(), ZipWriteError<W::Error>>
+        where W: Writer + ?Sized
+    {
+        writer.append(data).map_err(ZipWriteError::Io)?;
+
+        self.offset = self.offset.saturating_add(data.len() as u64);
+
+        Ok(())
+    }
+}
+
+/// Returns the version needed to extract, raised to 4.5 for ZIP64.
+fn version_needed(version: u16, zip64: bool) -> u16 {
+    if zip64 && version < ZIP64_VERSION { ZIP64_VERSION } else { version }
+}
+
+/// Overwrites the sizes of the variable-size fields of a record, starting at
+/// `offset` in its `fixed` part.
+///
+/// The fixed part is written with empty variable-size fields, which are then
+/// written separately, to avoid assembling them in a buffer.
+fn patch_sizes(fixed: &mut [u8], offset: usize, sizes: &[usize])
+    -> Result<(), WriteError>
+{
+    for (index, &size) in sizes.iter().enumerate() {
+        if size > 0xffff {
+            return Err(WriteError::FieldTooLarge);
+        }
+
+        let start = offset + 2 * index;
+
+        if let Some(target) = fixed.get_mut(start..(start + 2)) {
+            target.copy_from_slice(&(size as u16).to_le_bytes());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use api::ChainReader;
+    use checksum::Crc32;
+    use utils::test::{CountingWriter, TestError, TestWriter, ZeroReader};
+    use zip::low_level::{
+        CentralDirectoryFileHeaderIterator,
+        Diagnostic,
+        Extractor,
+        locate_end_of_central_directory,
+        validate,
+    };
+    use zip::raw::{
+        CentralDirectoryFileHeaderReader,
+        EndOfCentralDirectoryReader,
+        ExtraFieldReader,
+        Zip64EndOfCentralDirectoryLocatorReader,
+        Zip64EndOfCentralDirectoryReader,
+        Zip64ExtendedInformationReader,
+    };
+    use super::{EntryOptions, ZipWriteError, ZipWriter};
+
+    fn write_entry(
+        zip: &mut ZipWriter,
+        writer: &mut TestWriter,
+        entry: &EntryOptions,
+        data: &[u8]
+    )
+        -> Result<(), ZipWriteError<TestError>>
+    {
+        zip.start_entry(writer, entry)?;
+
+        for chunk in data.chunks(4) {
+            zip.write_data(writer, chunk)?;
+        }
+
+        zip.finish_entry(
+            writer, entry, Crc32::checksum(data), data.len() as u64)
+    }
+
+    fn entries(archive: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let eocd = locate_end_of_central_directory(archive, &mut []).unwrap();
+        let cd = eocd.central_directory_offset() as usize;
+        let nb = u64::from(eocd.nb_central_directory_records());
+
+        CentralDirectoryFileHeaderIterator::new(&archive[cd..], nb)
+            .map(|cdfh| {
+                let mut extractor = Extractor::new(archive, cdfh).unwrap();
+                let data = extractor.read_stored(archive, &mut []).unwrap();
+
+                assert!(extractor.is_finished());
+
+                (cdfh.file_name().unwrap().to_vec(), data.to_vec())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let known = EntryOptions::new(b"a.txt")
+            .with_sizes(Crc32::checksum(b"Hello, World!"), 13, 13);
+        let deferred = EntryOptions::new(b"dir/b.txt")
+            .with_comment(b"Deferred");
+
+        let mut storage = [0; 256];
+        let mut zip = ZipWriter::new(&mut storage);
+        let mut writer = TestWriter::default();
+
+        write_entry(&mut zip, &mut writer, &known, b"Hello, World!").unwrap();
+        write_entry(&mut zip, &mut writer, &deferred, b"Streamed").unwrap();
+        zip.finish(&mut writer, b"Comment").unwrap();
+
+        assert!(zip.is_finished());
+        assert_eq!(zip.nb_entries(), 2);
+        assert_eq!(zip.offset(), writer.data.len() as u64);
+
+        let archive = &writer.data[..];
+
+        let mut diagnostics: Vec<Diagnostic> = vec!();
+        validate(archive, &mut [], &mut |d| diagnostics.push(d)).unwrap();
+
+        assert_eq!(diagnostics, vec!());
+        assert_eq!(
+            entries(archive),
+            vec!(
+                (b"a.txt".to_vec(), b"Hello, World!".to_vec()),
+                (b"dir/b.txt".to_vec(), b"Streamed".to_vec()),
+            )
+        );
+
+        let eocd = locate_end_of_central_directory(archive, &mut []).unwrap();
+
+        assert_eq!(eocd.comment(), Some(&b"Comment"[..]));
+    }
+
+    #[test]
+    fn write_round_trip_with_spill() {
+        let first = EntryOptions::new(b"a.txt");
+        let second = EntryOptions::new(b"b.txt");
+
+        //  Only a single Central Directory File Header record fits.
+        let mut storage = [0; 60];
+        let mut zip = ZipWriter::new(&mut storage);
+        let mut writer = TestWriter::default();
+        let mut spill = TestWriter::default();
+
+        write_entry(&mut zip, &mut writer, &first, b"1").unwrap();
+
+        assert_eq!(
+            write_entry(&mut zip, &mut writer, &second, b"22"),
+            Err(ZipWriteError::CentralDirectoryFull)
+        );
+
+        zip.spill(&mut spill).unwrap();
+
+        assert_eq!(zip.stored(), 0);
+        assert_eq!(zip.spilled(), 51);
+
+        zip.finish_entry(&mut writer, &second, Crc32::checksum(b"22"), 2)
+            .unwrap();
+
+        assert_eq!(
+            zip.finish(&mut TestWriter::default(), b""),
+            Err(ZipWriteError::SpillTruncated)
+        );
+
+        zip.finish_with_spill(&mut writer, &spill.data[..], b"").unwrap();
+
+        assert_eq!(
+            entries(&writer.data),
+            vec!((b"a.txt".to_vec(), b"1".to_vec()),
+                 (b"b.txt".to_vec(), b"22".to_vec()))
+        );
+    }
+
+    #[test]
+    fn write_entry_as_started() {
+        let started = EntryOptions::new(b"a.txt")
+            .with_compression_method(8)
+            .with_sizes(Crc32::checksum(b"1"), 1, 1);
+        let finished = EntryOptions::new(b"a.txt");
+
+        let mut storage = [0; 64];
+        let mut zip = ZipWriter::new(&mut storage);
+        let mut writer = TestWriter::default();
+
+        zip.start_entry(&mut writer, &started).unwrap();
+        zip.write_data(&mut writer, b"1").unwrap();
+        zip.finish_entry(&mut writer, &finished, Crc32::checksum(b"1"), 1)
+            .unwrap();
+        zip.finish(&mut writer, b"").unwrap();
+
+        //  No Data Descriptor record follows the data.
+        let archive = &writer.data[..];
+        let cdfh = CentralDirectoryFileHeaderReader::new(&archive[36..])
+            .unwrap();
+
+        assert_eq!(cdfh.signature(), 0x02014b50);
+        assert_eq!(cdfh.general_purpose_bit_flag(), 0);
+        assert_eq!(cdfh.compression_method(), 8);
+
+        let mut diagnostics: Vec<Diagnostic> = vec!();
+        validate(archive, &mut [], &mut |d| diagnostics.push(d)).unwrap();
+
+        assert_eq!(diagnostics, vec!());
+    }
+
+    #[test]
+    fn write_failure_on_invalid_state() {
+        let entry = EntryOptions::new(b"a");
+
+        let mut storage = [0; 64];
+        let mut zip = ZipWriter::new(&mut storage);
+        let mut writer = TestWriter::default();
+
+        assert_eq!(
+            zip.write_data(&mut writer, b"1"),
+            Err(ZipWriteError::InvalidState)
+        );
+        assert_eq!(
+            zip.finish_entry(&mut writer, &entry, 0, 0),
+            Err(ZipWriteError::InvalidState)
+        );
+
+        zip.start_entry(&mut writer, &entry).unwrap();
+
+        assert_eq!(
+            zip.start_entry(&mut writer, &entry),
+            Err(ZipWriteError::InvalidState)
+        );
+        assert_eq!(
+            zip.finish(&mut writer, b""),
+            Err(ZipWriteError::InvalidState)
+        );
+
+        zip.finish_entry(&mut writer, &entry, 0, 0).unwrap();
+        zip.finish(&mut writer, b"").unwrap();
+
+        assert_eq!(
+            zip.start_entry(&mut writer, &entry),
+            Err(ZipWriteError::InvalidState)
+        );
+    }
+
+    #[test]
+    fn write_failure_on_size_mismatch() {
+        let entry = EntryOptions::new(b"a").with_sizes(0, 2, 2);
+
+        let mut storage = [0; 64];
+        let mut zip = ZipWriter::new(&mut storage);
+        let mut writer = TestWriter::default();
+
+        assert_eq!(
+            write_entry(&mut zip, &mut writer, &entry, b"1"),
+            Err(ZipWriteError::SizeMismatch)
+        );
+    }
+
+    #[test]
+    fn write_failure_on_too_large_name() {
+        let name = vec!(b'a'; 65536);
+        let entry = EntryOptions::new(&name);
+
+        let mut storage = [0; 64];
+        let mut zip = ZipWriter::new(&mut storage);
+        let mut writer = TestWriter::default();
+
+        assert_eq!(
+            zip.start_entry(&mut writer, &entry),
+            Err(ZipWriteError::FieldTooLarge)
+        );
+        assert_eq!(writer.data, vec!());
+    }
+
+    #[test]
+    fn write_failure_on_writer_failure() {
+        let entry = EntryOptions::new(b"a");
+
+        let mut storage = [0; 64];
+        let mut zip = ZipWriter::new(&mut storage);
+        let mut writer = TestWriter { data: vec!(), limit: Some(16) };
+
+        assert_eq!(
+            zip.start_entry(&mut writer, &entry),
+            Err(ZipWriteError::Io(TestError))
+        );
+    }
+
+    #[test]
+    fn write_zip64_on_large_offset() {
+        const OFFSET: u64 = 0x1_0000_0000;
+
+        let entry = EntryOptions::new(b"a");
+
+        let mut storage = [0; 128];
+        let mut zip = ZipWriter::with_offset(&mut storage, OFFSET);
+        let mut writer = TestWriter::default();
+
+        write_entry(&mut zip, &mut writer, &entry, b"1").unwrap();
+        zip.finish(&mut writer, b"").unwrap();
+
+        //  LFH (31), data (1), DD (16), then CDFH (47 + 12 of ZIP64 extra).
+        let archive = &writer.data[..];
+        let cdfh = CentralDirectoryFileHeaderReader::new(&archive[48..])
+            .unwrap();
+
+        assert_eq!(cdfh.raw().len(), 59);
+        assert_eq!(cdfh.version_needed_to_extract(), 45);
+        assert_eq!(cdfh.local_file_header_relative_offset(), 0xffffffff);
+
+        let extra = ExtraFieldReader::new(cdfh.extra_field().unwrap()).unwrap();
+        let zip64 = Zip64ExtendedInformationReader::
+            from_central_directory_file_header(extra.data().unwrap(), cdfh);
+
+        assert_eq!(zip64.local_file_header_relative_offset(), Some(OFFSET));
+
+        let eocd64 = Zip64EndOfCentralDirectoryReader::new(&archive[107..])
+            .unwrap();
+
+        assert_eq!(eocd64.signature(), 0x06064b50);
+        assert_eq!(eocd64.nb_central_directory_records(), 1);
+        assert_eq!(eocd64.central_directory_size(), 59);
+        assert_eq!(eocd64.central_directory_offset(), OFFSET + 48);
+
+        let locator =
+            Zip64EndOfCentralDirectoryLocatorReader::new(&archive[163..])
+                .unwrap();
+
+        assert_eq!(locator.signature(), 0x07064b50);
+        assert_eq!(
+            locator.zip64_end_of_central_directory_offset(),
+            OFFSET + 107
+        );
+
+        let eocd = EndOfCentralDirectoryReader::new(&archive[183..]).unwrap();
+
+        assert_eq!(eocd.raw().len(), archive.len() - 183);
+        assert_eq!(eocd.nb_central_directory_records(), 1);
+        assert_eq!(eocd.central_directory_offset(), 0xffffffff);
+
+        //  The Local File Header record announces ZIP64 as well.
+        assert_eq!(&archive[4..6], &[45, 0]);
+
+        //  The End of Central Directory record is assembled, past the padding,
+        //  into the scratch buffer.
+        let padded = ChainReader::new(ZeroReader { size: OFFSET }, archive);
+        let mut scratch = [0; 1024];
+
+        let mut diagnostics: Vec<Diagnostic> = vec!();
+        validate(&padded, &mut scratch, &mut |d| diagnostics.push(d)).unwrap();
+
+        assert_eq!(diagnostics, vec!());
+    }
+
+    #[test]
+    fn write_zip64_on_large_sizes_known_up_front() {
+        let entry = EntryOptions::new(b"a").with_sizes(0, 1, 0x1_0000_0000);
+
+        let mut storage = [0; 128];
+        let mut zip = ZipWriter::new(&mut storage);
+        let mut writer = TestWriter::default();
+
+        zip.start_entry(&mut writer, &entry).unwrap();
+
+        //  LFH (31), then ZIP64 extra field (20).
+        assert_eq!(writer.data.len(), 51);
+        assert_eq!(&writer.data[18..26], &[0xff; 8]);
+        assert_eq!(&writer.data[31..35], &[0x01, 0x00, 16, 0]);
+    }
+
+    #[test]
+    fn write_zip64_on_large_streamed_sizes() {
+        const SIZE: u64 = 0x1_0000_0000;
+
+        let entry = EntryOptions::new(b"a");
+        let chunk = vec!(0; 1 << 20);
+
+        let mut storage = [0; 128];
+        let mut zip = ZipWriter::new(&mut storage);
+        let mut writer = CountingWriter::default();
+
+        zip.start_entry(&mut writer, &entry).unwrap();
+
+        for _ in 0..SIZE / chunk.len() as u64 {
+            zip.write_data(&mut writer, &chunk).unwrap();
+        }
+
+        zip.finish_entry(&mut writer, &entry, 0, SIZE).unwrap();
+
+        //  LFH (31), data, then ZIP64 Data Descriptor (24).
+        assert_eq!(writer.size, 31 + SIZE + 24);
+
+        //  CDFH (47), requiring version 4.5, then ZIP64 extra field (20).
+        assert_eq!(&storage[6..8], &[45, 0]);
+        assert_eq!(&storage[20..28], &[0xff; 8]);
+        assert_eq!(&storage[47..51], &[0x01, 0x00, 16, 0]);
+    }
+
+    #[test]
+    fn write_zip64_on_many_entries() {
+        let entry = EntryOptions::new(b"a");
+
+        let mut storage = vec!(0; 47 * 65535);
+        let mut zip = ZipWriter::new(&mut storage);
+        let mut writer = TestWriter::default();
+
+        for _ in 0..65535 {
+            write_entry(&mut zip, &mut writer, &entry, b"").unwrap();
+        }
+
+        zip.finish(&mut writer, b"").unwrap();
+
+        let archive = &writer.data[..];
+        let eocd = locate_end_of_central_directory(archive, &mut []).unwrap();
+
+        assert_eq!(eocd.nb_central_directory_records(), 0xffff);
+
+        let eocd64 = archive.len() - 22 - 20 - 56;
+        let eocd64 = Zip64EndOfCentralDirectoryReader::new(&archive[eocd64..])
+            .unwrap();
+
+        assert_eq!(eocd64.signature(), 0x06064b50);
+        assert_eq!(eocd64.nb_central_directory_records(), 65535);
+
+        let mut diagnostics = vec!();
+        validate(archive, &mut [], &mut |d| diagnostics.push(d)).unwrap();
+
+        assert_eq!(diagnostics, vec!());
+    }
+}
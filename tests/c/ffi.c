@@ -0,0 +1,211 @@
+/*
+ * Lists the archive given as argument through the C FFI, checking it holds
+ * "hello.txt", "empty", "dir/" and "dir/nested.txt", as created by the test
+ * suite, both from memory and through an ArkivReader over the file.
+ *
+ * Beforehand, checks the layout of the structures of "arkiv.h" against the
+ * sizes and offsets computed by Rust, which the test suite writes into
+ * "layout.h".
+ *
+ * Exits with 0 on success.
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "arkiv.h"
+
+#define CHECK(condition)                                                      \
+    do {                                                                      \
+        if (!(condition)) {                                                   \
+            fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #condition);   \
+            exit(1);                                                          \
+        }                                                                     \
+    } while (0)
+
+#define CHECK_SIZE(type, size) CHECK(sizeof(type) == (size))
+
+#define CHECK_OFFSET(type, field, offset)                                     \
+    CHECK(offsetof(type, field) == (offset))
+
+static void check_layout(void) {
+#include "layout.h"
+}
+
+static int equals(ArkivBytes bytes, const char *expected) {
+    return bytes.size == strlen(expected)
+        && memcmp(bytes.data, expected, bytes.size) == 0;
+}
+
+static int32_t file_size(void *user_data, uint64_t *size) {
+    FILE *file = user_data;
+
+    if (fseek(file, 0, SEEK_END) != 0) {
+        return 1;
+    }
+
+    long end = ftell(file);
+
+    if (end < 0) {
+        return 2;
+    }
+
+    *size = (uint64_t) end;
+    return 0;
+}
+
+static int32_t file_fail(void *user_data, uint64_t *size) {
+    (void) user_data;
+    (void) size;
+
+    return 42;
+}
+
+static int32_t file_read(
+    void *user_data,
+    uint64_t offset,
+    uint8_t *buffer,
+    size_t length,
+    size_t *read)
+{
+    FILE *file = user_data;
+
+    if (fseek(file, (long) offset, SEEK_SET) != 0) {
+        return 1;
+    }
+
+    *read = fread(buffer, 1, length, file);
+    return ferror(file) ? 2 : 0;
+}
+
+static void check_reader(FILE *file) {
+    ArkivReader reader = { file, file_size, file_read, 0 };
+    uint8_t scratch[1024];
+
+    ArkivZipEndOfCentralDirectory eocd;
+
+    CHECK(arkiv_zip_reader_locate_end_of_central_directory(
+        &reader, scratch, sizeof(scratch), &eocd) == ARKIV_STATUS_OK);
+    CHECK(eocd.nb_central_directory_records == 4);
+
+    ArkivZipRawCentralDirectoryFileHeader cdfh;
+    uint64_t offset = eocd.central_directory_offset;
+
+    CHECK(arkiv_zip_reader_read_central_directory_file_header(
+        &reader, offset, eocd.offset, scratch, sizeof(scratch), &cdfh)
+        == ARKIV_STATUS_OK);
+    CHECK(equals(cdfh.file_name, "hello.txt"));
+
+    offset += 46u + cdfh.file_name_size + cdfh.extra_field_size
+        + cdfh.file_comment_size;
+
+    CHECK(arkiv_zip_reader_read_central_directory_file_header(
+        &reader, offset, eocd.offset, scratch, sizeof(scratch), &cdfh)
+        == ARKIV_STATUS_OK);
+    CHECK(equals(cdfh.file_name, "empty"));
+
+    ArkivZipRawLocalFileHeader lfh;
+
+    CHECK(arkiv_zip_reader_read_local_file_header(
+        &reader, cdfh.local_file_header_relative_offset, eocd.offset,
+        scratch, sizeof(scratch), &lfh) == ARKIV_STATUS_OK);
+    CHECK(equals(lfh.file_name, "empty"));
+
+    /* A null callback is reported, not called. */
+    ArkivReader broken = { file, file_size, NULL, 0 };
+
+    CHECK(arkiv_zip_reader_locate_end_of_central_directory(
+        &broken, scratch, sizeof(scratch), &eocd)
+        == ARKIV_STATUS_NULL_POINTER);
+
+    /* The error code of a failing callback is handed back. */
+    ArkivReader failing = { file, file_fail, file_read, 0 };
+
+    CHECK(arkiv_zip_reader_locate_end_of_central_directory(
+        &failing, scratch, sizeof(scratch), &eocd) == ARKIV_STATUS_IO);
+    CHECK(failing.last_error == 42);
+}
+
+int main(int argc, char **argv) {
+    static uint8_t archive[1 << 16];
+
+    CHECK(argc == 2);
+
+    check_layout();
+
+    FILE *file = fopen(argv[1], "rb");
+    CHECK(file != NULL);
+
+    size_t size = fread(archive, 1, sizeof(archive), file);
+    CHECK(size > 0 && feof(file));
+
+    check_reader(file);
+    fclose(file);
+
+    ArkivZipEndOfCentralDirectory eocd;
+
+    CHECK(arkiv_zip_locate_end_of_central_directory(archive, size, &eocd)
+        == ARKIV_STATUS_OK);
+    CHECK(!eocd.is_zip64);
+    CHECK(eocd.nb_central_directory_records == 4);
+    CHECK(eocd.central_directory_offset + eocd.central_directory_size
+        <= eocd.offset);
+
+    ArkivZipRawEndOfCentralDirectory raw;
+
+    CHECK(arkiv_zip_raw_read_end_of_central_directory(
+        archive + eocd.offset, size - eocd.offset, &raw) == ARKIV_STATUS_OK);
+    CHECK(raw.signature == 0x06054b50);
+    CHECK(raw.comment.size == 0);
+
+    ArkivZipCentralDirectoryIterator iterator;
+    ArkivZipRawCentralDirectoryFileHeader cdfh;
+    const char *names[] = { "hello.txt", "empty", "dir/", "dir/nested.txt" };
+    const uint32_t sizes[] = { 13, 0, 0, 6 };
+    int count = 0;
+
+    CHECK(arkiv_zip_central_directory_iterator_init(
+        &iterator,
+        archive + eocd.central_directory_offset,
+        eocd.central_directory_size,
+        eocd.nb_central_directory_records) == ARKIV_STATUS_OK);
+
+    ArkivStatus status;
+
+    while ((status = arkiv_zip_central_directory_iterator_next(
+        &iterator, &cdfh)) == ARKIV_STATUS_OK)
+    {
+        CHECK(count < 4);
+        CHECK(cdfh.signature == 0x02014b50);
+        CHECK(equals(cdfh.file_name, names[count]));
+        CHECK(cdfh.uncompressed_size == sizes[count]);
+
+        uint32_t offset = cdfh.local_file_header_relative_offset;
+        ArkivZipRawLocalFileHeader lfh;
+
+        CHECK(offset < size);
+        CHECK(arkiv_zip_raw_read_local_file_header(
+            archive + offset, size - offset, &lfh) == ARKIV_STATUS_OK);
+        CHECK(lfh.signature == 0x04034b50);
+        CHECK(equals(lfh.file_name, names[count]));
+        CHECK(lfh.crc32 == cdfh.crc32);
+
+        count += 1;
+    }
+
+    CHECK(status == ARKIV_STATUS_DONE);
+    CHECK(count == 4);
+
+    CHECK(arkiv_zip_raw_read_central_directory_file_header(NULL, 46, &cdfh)
+        == ARKIV_STATUS_NULL_POINTER);
+    CHECK(arkiv_zip_raw_read_central_directory_file_header(archive, 45, &cdfh)
+        == ARKIV_STATUS_TRUNCATED);
+
+    ArkivZipRawDataDescriptor dd;
+
+    CHECK(arkiv_zip_raw_read_data_descriptor(archive, 13, &dd)
+        == ARKIV_STATUS_MALFORMED);
+
+    return 0;
+}